@@ -46,14 +46,100 @@ export const DatasetEvents = () => {
     );
   };
 
-  // Start the interval when the component mounts
-  const intervalId = setInterval(() => {
-    getEvents();
-  }, 5000); // 5000 milliseconds = 5 seconds
+  // Reads the stream until it closes. Returns whether the server accepted the connection.
+  const readEventStream = async (
+    datasetId: string,
+    signal: AbortSignal,
+    lastEventId: string | undefined,
+    onEvent: (id: string | undefined, event: Event) => void,
+  ) => {
+    const headers: Record<string, string> = { "TR-Dataset": datasetId };
+    if (lastEventId) {
+      headers["Last-Event-ID"] = lastEventId;
+    }
+
+    const res = await fetch(`${api_host}/events/stream`, {
+      method: "GET",
+      credentials: "include",
+      headers,
+      signal,
+    });
+    const reader = res.body?.getReader();
+    if (!res.ok || !reader) return false;
+
+    const decoder = new TextDecoder();
+    let buffer = "";
+    for (;;) {
+      const { done, value } = await reader.read();
+      if (done) return true;
+
+      buffer += decoder.decode(value, { stream: true });
+      const messages = buffer.split("\n\n");
+      buffer = messages.pop() ?? "";
+
+      for (const message of messages) {
+        const lines = message.split("\n");
+        const id = lines
+          .find((line) => line.startsWith("id: "))
+          ?.slice("id: ".length);
+        const data = lines
+          .find((line) => line.startsWith("data: "))
+          ?.slice("data: ".length);
+        if (!data) continue;
+
+        const event: unknown = JSON.parse(data);
+        if (isEvent(event)) {
+          onEvent(id, event);
+        }
+      }
+    }
+  };
+
+  // Prepend events from the server as they are created while the first page is shown. Reconnects with backoff when the stream drops and resumes after the last event received, so that no event is missed.
+  const streamEvents = async (datasetId: string, signal: AbortSignal) => {
+    let lastEventId: string | undefined;
+    let backoff = 1000;
+
+    while (!signal.aborted) {
+      try {
+        const accepted = await readEventStream(
+          datasetId,
+          signal,
+          lastEventId,
+          (id, event) => {
+            lastEventId = id ?? lastEventId;
+            backoff = 1000;
+            if (page() === 1) {
+              setEvents((prev) => [event, ...prev].slice(0, 10));
+            }
+          },
+        );
+        // The server rejects a Last-Event-ID it does not know, so the next attempt starts over without it
+        if (!accepted) {
+          lastEventId = undefined;
+        }
+      } catch (err) {
+        if (signal.aborted) return;
+        console.error("Event stream failed", err);
+      }
+
+      await new Promise((resolve) => setTimeout(resolve, backoff));
+      backoff = Math.min(backoff * 2, 30000);
+    }
+  };
+
+  createEffect(() => {
+    const datasetId = datasetContext.dataset?.()?.id;
+    if (!datasetId) return;
+
+    const streamAbortController = new AbortController();
+    streamEvents(datasetId, streamAbortController.signal).catch((err) => {
+      console.error("Failed to stream events", err);
+    });
 
-  // Cleanup function to clear the interval when the component unmounts
-  onCleanup(() => {
-    clearInterval(intervalId);
+    onCleanup(() => {
+      streamAbortController.abort();
+    });
   });
 
   createEffect(() => {
@@ -71,7 +157,7 @@ export const DatasetEvents = () => {
                   Events
                 </h1>
                 <p class="mt-2 text-sm text-gray-700">
-                  Event Log from the server (Refreshes every 5 seconds)
+                  Event Log from the server (Updates live)
                 </p>
               </div>
             </div>
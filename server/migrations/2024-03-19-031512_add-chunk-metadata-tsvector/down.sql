@@ -0,0 +1,15 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER IF EXISTS request_dataset_fulltext_reindex_trigger ON datasets;
+
+DROP FUNCTION IF EXISTS request_dataset_fulltext_reindex();
+
+DROP TABLE IF EXISTS dataset_fulltext_reindexes;
+
+DROP TRIGGER IF EXISTS update_chunk_metadata_tsvector_trigger ON chunk_metadata;
+
+DROP FUNCTION IF EXISTS update_chunk_metadata_tsvector();
+
+DROP FUNCTION IF EXISTS dataset_fulltext_language(UUID);
+
+ALTER TABLE chunk_metadata
+DROP COLUMN IF EXISTS chunk_metadata_tsvector;
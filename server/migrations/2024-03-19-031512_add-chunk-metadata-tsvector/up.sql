@@ -0,0 +1,58 @@
+-- Your SQL goes here
+ALTER TABLE chunk_metadata
+ADD COLUMN chunk_metadata_tsvector TSVECTOR;
+
+-- Text search configuration for a dataset. Defaults to english when FULLTEXT_LANGUAGE is unset and falls back to simple when
+-- it is not a known configuration, so neither indexing nor searching fails on a bad value
+CREATE FUNCTION dataset_fulltext_language(target_dataset_id UUID) RETURNS REGCONFIG AS $$
+DECLARE
+    language TEXT;
+BEGIN
+    SELECT server_configuration->>'FULLTEXT_LANGUAGE' INTO language FROM datasets WHERE id = target_dataset_id;
+    IF language IS NULL OR language = '' THEN
+        RETURN 'english'::regconfig;
+    END IF;
+    IF EXISTS (SELECT 1 FROM pg_ts_config WHERE cfgname = language) THEN
+        RETURN language::regconfig;
+    END IF;
+    RETURN 'simple'::regconfig;
+END;
+$$ LANGUAGE plpgsql STABLE;
+
+CREATE FUNCTION update_chunk_metadata_tsvector() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.chunk_metadata_tsvector := to_tsvector(dataset_fulltext_language(NEW.dataset_id), NEW.content);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER update_chunk_metadata_tsvector_trigger
+BEFORE INSERT OR UPDATE OF content, dataset_id ON chunk_metadata
+FOR EACH ROW
+EXECUTE FUNCTION update_chunk_metadata_tsvector();
+
+-- Datasets whose chunks need their tsvector recomputed, either because FULLTEXT_LANGUAGE changed or because they existed
+-- before the column was added. The server reindexes them in batches in the background so that neither this migration nor
+-- a settings change rewrites every chunk in one transaction
+CREATE TABLE dataset_fulltext_reindexes (
+    dataset_id UUID PRIMARY KEY REFERENCES datasets(id) ON DELETE CASCADE,
+    requested_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE FUNCTION request_dataset_fulltext_reindex() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO dataset_fulltext_reindexes (dataset_id, requested_at)
+    VALUES (NEW.id, clock_timestamp())
+    ON CONFLICT (dataset_id) DO UPDATE SET requested_at = EXCLUDED.requested_at;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER request_dataset_fulltext_reindex_trigger
+AFTER UPDATE OF server_configuration ON datasets
+FOR EACH ROW
+WHEN (OLD.server_configuration->>'FULLTEXT_LANGUAGE' IS DISTINCT FROM NEW.server_configuration->>'FULLTEXT_LANGUAGE')
+EXECUTE FUNCTION request_dataset_fulltext_reindex();
+
+INSERT INTO dataset_fulltext_reindexes (dataset_id)
+SELECT id FROM datasets;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_search_rules_dataset_id;
+
+DROP TABLE IF EXISTS search_rules;
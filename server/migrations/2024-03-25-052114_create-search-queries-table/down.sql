@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_search_queries_dataset_id_created_at;
+
+DROP TABLE IF EXISTS search_queries;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS search_queries (
+    id UUID PRIMARY KEY,
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    search_type TEXT NOT NULL,
+    query TEXT NOT NULL,
+    filters_hash TEXT,
+    result_count INTEGER NOT NULL,
+    top_score DOUBLE PRECISION,
+    latency_ms INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_search_queries_dataset_id_created_at ON search_queries(dataset_id, created_at);
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_search_feedback_dataset_id_chunk_id;
+DROP INDEX IF EXISTS idx_search_feedback_search_id;
+
+DROP TABLE IF EXISTS search_feedback;
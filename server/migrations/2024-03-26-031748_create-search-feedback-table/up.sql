@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS search_feedback (
+    id UUID PRIMARY KEY,
+    search_id UUID NOT NULL REFERENCES search_queries(id) ON DELETE CASCADE,
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    chunk_id UUID NOT NULL,
+    position INTEGER NOT NULL,
+    feedback_type TEXT NOT NULL,
+    group_id UUID,
+    rating INTEGER,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_search_feedback_search_id ON search_feedback(search_id);
+CREATE INDEX idx_search_feedback_dataset_id_chunk_id ON search_feedback(dataset_id, chunk_id);
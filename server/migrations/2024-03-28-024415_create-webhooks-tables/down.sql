@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_webhook_deliveries_webhook_id_created_at;
+DROP TABLE IF EXISTS webhook_deliveries;
+
+DROP INDEX IF EXISTS idx_webhooks_dataset_id;
+DROP TABLE IF EXISTS webhooks;
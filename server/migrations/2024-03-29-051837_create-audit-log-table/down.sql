@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_audit_log_organization_id_created_at;
+
+DROP TABLE IF EXISTS audit_log;
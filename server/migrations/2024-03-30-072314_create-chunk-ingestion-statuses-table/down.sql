@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_chunk_ingestion_statuses_dataset_id_tracking_id;
+DROP INDEX IF EXISTS idx_chunk_ingestion_statuses_dataset_id_batch_id;
+DROP TABLE IF EXISTS chunk_ingestion_statuses;
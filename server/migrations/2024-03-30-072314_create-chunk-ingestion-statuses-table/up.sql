@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS chunk_ingestion_statuses (
+    chunk_id UUID PRIMARY KEY,
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    batch_id UUID NOT NULL,
+    tracking_id TEXT,
+    status TEXT NOT NULL,
+    error TEXT,
+    collision_chunk_id UUID,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_chunk_ingestion_statuses_dataset_id_batch_id ON chunk_ingestion_statuses(dataset_id, batch_id);
+CREATE INDEX idx_chunk_ingestion_statuses_dataset_id_tracking_id ON chunk_ingestion_statuses(dataset_id, tracking_id);
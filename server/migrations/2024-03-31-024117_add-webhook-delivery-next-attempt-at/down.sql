@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS idx_webhook_deliveries_pending_next_attempt_at;
+
+ALTER TABLE webhook_deliveries DROP COLUMN IF EXISTS next_attempt_at;
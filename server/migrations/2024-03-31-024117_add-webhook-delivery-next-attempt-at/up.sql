@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE webhook_deliveries ADD COLUMN IF NOT EXISTS next_attempt_at TIMESTAMP;
+
+UPDATE webhook_deliveries SET next_attempt_at = NOW() WHERE status = 'pending';
+
+CREATE INDEX idx_webhook_deliveries_pending_next_attempt_at ON webhook_deliveries(next_attempt_at) WHERE status = 'pending';
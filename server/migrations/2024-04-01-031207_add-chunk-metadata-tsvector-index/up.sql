@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Built concurrently so that chunks can still be written while the index is built
+CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_chunk_metadata_tsvector ON chunk_metadata USING GIN(chunk_metadata_tsvector);
@@ -1,7 +1,7 @@
 use crate::{
     data::models::{DatasetAndOrgWithSubAndPlan, Pool, RedisPool, UserRole},
     errors::ServiceError,
-    handlers::auth_handler::{LoggedUser, OrganizationRole},
+    handlers::auth_handler::{AuthenticatedApiKey, LoggedUser, OrganizationRole},
     operators::{
         dataset_operator::get_dataset_by_id_query,
         organization_operator::get_organization_by_key_query,
@@ -203,7 +203,9 @@ async fn get_user(req: &HttpRequest, pl: &mut Payload) -> Option<LoggedUser> {
     if let Some(authen_header) = req.headers().get("Authorization") {
         if let Ok(authen_header) = authen_header.to_str() {
             if let Some(pool) = req.app_data::<web::Data<Pool>>() {
-                if let Ok(user) = get_user_from_api_key_query(authen_header, pool).await {
+                if let Ok((user, api_key)) = get_user_from_api_key_query(authen_header, pool).await
+                {
+                    req.extensions_mut().insert(AuthenticatedApiKey(api_key.id));
                     return Some(user);
                 }
             }
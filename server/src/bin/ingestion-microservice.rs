@@ -1,19 +1,42 @@
 use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
 use sentry::{Hub, SentryFutureExt};
+use std::collections::HashMap;
 use tracing_subscriber::{prelude::*, EnvFilter, Layer};
-use trieve_server::data::models::{self, Event, ServerDatasetConfiguration};
+use trieve_server::data::models::{
+    self, Event, FullTextBackend, IngestionStatus, ServerDatasetConfiguration,
+};
 use trieve_server::errors::ServiceError;
 use trieve_server::handlers::chunk_handler::{UpdateIngestionMessage, UploadIngestionMessage};
 use trieve_server::handlers::group_handler::dataset_owns_group;
+use trieve_server::operators::cache_operator::bump_search_cache_generation;
 use trieve_server::operators::chunk_operator::{
-    get_metadata_from_point_ids, get_qdrant_id_from_chunk_id_query, insert_chunk_metadata_query,
-    insert_duplicate_chunk_metadata_query, update_chunk_metadata_query,
+    get_metadata_from_id_query, get_metadata_from_point_ids, get_qdrant_id_from_chunk_id_query,
+    insert_chunk_metadata_query, insert_duplicate_chunk_metadata_query,
+    update_chunk_metadata_query,
 };
 use trieve_server::operators::event_operator::create_event_query;
-use trieve_server::operators::model_operator::{create_embeddings, get_splade_embedding};
-use trieve_server::operators::parse_operator::{average_embeddings, coarse_doc_chunker};
+use trieve_server::operators::ingestion_queue_operator::{
+    ack_ingestion_message, claim_ingestion_messages, extend_ingestion_leases,
+    fail_ingestion_message, get_ingestion_batch_size, get_ingestion_lease_renewal_interval,
+    get_ingestion_message_chunk_id, get_ingestion_message_dataset_id,
+    requeue_stalled_ingestion_messages, IngestionFailure, INGESTION_QUEUE,
+    STALLED_INGESTION_MESSAGE_ERROR,
+};
+use trieve_server::operators::ingestion_status_operator::update_chunk_ingestion_statuses_query;
+use trieve_server::operators::math_operator::cosine_similarity;
+use trieve_server::operators::metrics_operator::{
+    gather_metrics, metrics_content_type, observe_ingestion_batch, observe_ingestion_message,
+    set_ingestion_queue_depth,
+};
+use trieve_server::operators::model_operator::{
+    create_embeddings, get_splade_embedding, get_splade_embeddings,
+};
+use trieve_server::operators::parse_operator::{
+    get_batched_upload_embedding_vectors, get_upload_embedding_texts, get_upload_embedding_vector,
+};
 use trieve_server::operators::qdrant_operator::{
-    create_new_qdrant_point_query, update_qdrant_point_query,
+    bulk_create_new_qdrant_points_query, create_new_qdrant_point_query, update_qdrant_point_query,
+    NewQdrantPoint,
 };
 use trieve_server::operators::search_operator::global_unfiltered_top_match_query;
 use trieve_server::{establish_connection, get_env};
@@ -74,6 +97,12 @@ fn main() {
             * 2
     };
 
+    let metrics_port: u16 = std::env::var("METRICS_PORT")
+        .unwrap_or("9091".to_string())
+        .parse()
+        .unwrap_or(9091);
+    std::thread::spawn(move || serve_metrics(metrics_port));
+
     let database_url = get_env!("DATABASE_URL", "DATABASE_URL is not set");
 
     let mut config = ManagerConfig::default();
@@ -115,6 +144,12 @@ fn main() {
 
                 let web_redis_pool = actix_web::web::Data::new(redis_pool);
 
+                tokio::spawn(poll_ingestion_queue_depth(web_redis_pool.clone()));
+                tokio::spawn(requeue_stalled_messages(
+                    web_redis_pool.clone(),
+                    web_pool.clone(),
+                ));
+
                 let threads: Vec<_> = (0..thread_num)
                     .map(|i| {
                         let web_pool = web_pool.clone();
@@ -131,6 +166,202 @@ fn main() {
         );
 }
 
+/// Serves the metrics of the microservice in the Prometheus text format on every path of the port. Runs its own actix system on the thread, apart from the runtime of the workers.
+fn serve_metrics(port: u16) {
+    let served = actix_web::rt::System::new().block_on(async move {
+        let server = actix_web::HttpServer::new(|| {
+            actix_web::App::new().default_service(actix_web::web::to(|| async {
+                actix_web::HttpResponse::Ok()
+                    .content_type(metrics_content_type())
+                    .body(gather_metrics())
+            }))
+        })
+        .workers(1)
+        .bind(("0.0.0.0", port))?;
+        log::info!("Serving metrics on port {}", port);
+
+        server.run().await
+    });
+
+    if let Err(err) = served {
+        log::error!("Failed to serve metrics on port {}: {:?}", port, err);
+    }
+}
+
+#[tracing::instrument(skip(redis_pool))]
+async fn poll_ingestion_queue_depth(redis_pool: actix_web::web::Data<models::RedisPool>) {
+    loop {
+        if let Ok(mut redis_connection) = redis_pool.get().await {
+            let depth: Result<i64, redis::RedisError> = redis::cmd("llen")
+                .arg(INGESTION_QUEUE)
+                .query_async(&mut *redis_connection)
+                .await;
+
+            match depth {
+                Ok(depth) => set_ingestion_queue_depth(depth),
+                Err(err) => log::error!("Failed to get ingestion queue depth: {:?}", err),
+            }
+        }
+
+        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+    }
+}
+
+#[tracing::instrument(skip(redis_pool, web_pool))]
+async fn requeue_stalled_messages(
+    redis_pool: actix_web::web::Data<models::RedisPool>,
+    web_pool: actix_web::web::Data<models::Pool>,
+) {
+    loop {
+        match requeue_stalled_ingestion_messages(redis_pool.clone()).await {
+            Ok(stalled_messages) => {
+                for (payload, failure) in stalled_messages {
+                    record_ingestion_failure(
+                        &payload,
+                        failure,
+                        STALLED_INGESTION_MESSAGE_ERROR.to_string(),
+                        web_pool.clone(),
+                        redis_pool.clone(),
+                    )
+                    .await;
+                }
+            }
+            Err(err) => log::error!("Failed to requeue stalled ingestion messages: {:?}", err),
+        }
+
+        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+    }
+}
+
+/// Status updates are informational, so a failure to record one never fails the message.
+async fn set_ingestion_status(
+    chunk_id: uuid::Uuid,
+    status: IngestionStatus,
+    error: Option<String>,
+    collision_chunk_id: Option<uuid::Uuid>,
+    web_pool: actix_web::web::Data<models::Pool>,
+) {
+    let _ = update_chunk_ingestion_statuses_query(
+        vec![chunk_id],
+        status,
+        error,
+        collision_chunk_id,
+        web_pool,
+    )
+    .await
+    .map_err(|err| {
+        log::error!(
+            "Failed to set ingestion status of chunk {:?}: {:?}",
+            chunk_id,
+            err
+        );
+    });
+}
+
+/// Retries the message if the error is transient and it has attempts left. Otherwise the message is dead-lettered and a card_action_failed event is created for it.
+async fn handle_failed_message(
+    payload: &str,
+    err: ServiceError,
+    web_pool: actix_web::web::Data<models::Pool>,
+    redis_pool: actix_web::web::Data<models::RedisPool>,
+) {
+    // Embedding, Qdrant, and database failures surface as internal errors and may succeed on a later attempt
+    let retryable = matches!(err, ServiceError::InternalServerError(_));
+
+    match fail_ingestion_message(payload, err.to_string(), retryable, redis_pool.clone()).await {
+        Ok(failure) => {
+            record_ingestion_failure(payload, failure, err.to_string(), web_pool, redis_pool).await
+        }
+        Err(fail_err) => {
+            // The message stays on the processing list and is retried once its visibility timeout expires
+            log::error!(
+                "Failed to record failed attempt of chunk {:?}: {:?} {:?}",
+                get_ingestion_message_chunk_id(payload),
+                fail_err,
+                err
+            );
+        }
+    }
+}
+
+/// Sets the ingestion status of the chunk of a failed message to queued if it is retried or to failed if it was dead-lettered, in which case a card_action_failed event is also created. The ids are read from the payload so that messages which could not be parsed are recorded too.
+async fn record_ingestion_failure(
+    payload: &str,
+    failure: IngestionFailure,
+    error: String,
+    web_pool: actix_web::web::Data<models::Pool>,
+    redis_pool: actix_web::web::Data<models::RedisPool>,
+) {
+    let chunk_id = match get_ingestion_message_chunk_id(payload) {
+        Some(chunk_id) => chunk_id,
+        None => {
+            log::error!(
+                "Failed ingestion message has no chunk id, {:?}: {:?}",
+                failure,
+                error
+            );
+            return;
+        }
+    };
+
+    match failure {
+        IngestionFailure::Retrying {
+            attempt_count,
+            delay,
+        } => {
+            log::warn!(
+                "Attempt {} of chunk {:?} failed, retrying in {:?}: {:?}",
+                attempt_count,
+                chunk_id,
+                delay,
+                error
+            );
+            set_ingestion_status(
+                chunk_id,
+                IngestionStatus::Queued,
+                Some(error),
+                None,
+                web_pool,
+            )
+            .await;
+        }
+        IngestionFailure::DeadLettered { attempt_count } => {
+            log::error!(
+                "Dead-lettered chunk {:?} after {} attempts: {:?}",
+                chunk_id,
+                attempt_count,
+                error
+            );
+            set_ingestion_status(
+                chunk_id,
+                IngestionStatus::Failed,
+                Some(error.clone()),
+                None,
+                web_pool.clone(),
+            )
+            .await;
+
+            if let Some(dataset_id) = get_ingestion_message_dataset_id(payload) {
+                let _ = create_event_query(
+                    Event::from_details(
+                        dataset_id,
+                        models::EventType::CardActionFailed {
+                            chunk_id,
+                            error: format!("Failed after {} attempts: {}", attempt_count, error),
+                        },
+                    ),
+                    web_pool,
+                    redis_pool,
+                )
+                .await
+                .map_err(|err| {
+                    log::error!("Failed to create event: {:?}", err);
+                });
+            }
+        }
+    }
+}
+
 #[tracing::instrument(skip(web_pool, redis_pool))]
 async fn ingestion_service(
     thread: usize,
@@ -139,6 +370,8 @@ async fn ingestion_service(
 ) {
     log::info!("Starting ingestion service thread");
 
+    let batch_size = get_ingestion_batch_size();
+
     let mut redis_connection = match redis_pool.get().await {
         Ok(redis_connection) => redis_connection,
         Err(err) => {
@@ -148,189 +381,570 @@ async fn ingestion_service(
     };
 
     loop {
-        let payload_result: Result<Vec<String>, redis::RedisError> = redis::cmd("brpop")
-            .arg("ingestion")
-            .arg(1.0)
-            .query_async(&mut *redis_connection)
-            .await;
-
-        let payload = if let Ok(payload) = payload_result {
-            if payload.len() < 2 {
+        let payloads = match claim_ingestion_messages(&mut *redis_connection, batch_size).await {
+            Ok(payloads) if payloads.is_empty() => continue,
+            Ok(payloads) => payloads,
+            Err(err) => {
+                log::error!("Unable to claim ingestion messages: {:?}", err);
                 continue;
             }
-
-            payload
-        } else {
-            log::error!("Unable to process {:?}", payload_result);
-            continue;
         };
 
-        let ctx = sentry::TransactionContext::new("Processing chunk", "Processing chunk");
+        let ctx = sentry::TransactionContext::new("Processing chunks", "Processing chunks");
         let transaction = sentry::start_transaction(ctx);
 
-        let payload: IngestionMessage =
-            serde_json::from_str(&payload[1]).expect("Failed to parse ingestion message");
-        match payload {
-            IngestionMessage::Upload(payload) => {
-                match upload_chunk(payload.clone(), web_pool.clone(), payload.dataset_config).await
-                {
-                    Ok(_) => {
-                        log::info!("Uploaded chunk: {:?}", payload.chunk_metadata.id);
-                        let _ = create_event_query(
-                            Event::from_details(
-                                payload.chunk_metadata.dataset_id,
-                                models::EventType::CardUploaded {
-                                    chunk_id: payload.chunk_metadata.id,
-                                },
-                            ),
-                            web_pool.clone(),
-                        )
-                        .await
-                        .map_err(|err| {
-                            log::error!("Failed to create event: {:?}", err);
-                        });
-                    }
-                    Err(err) => {
-                        log::error!("Failed to upload chunk: {:?}", err);
-                        let _ = create_event_query(
-                            Event::from_details(
-                                payload.chunk_metadata.dataset_id,
-                                models::EventType::CardActionFailed {
-                                    chunk_id: payload.chunk_metadata.id,
-                                    error: format!("Failed to upload chunk: {:?}", err),
-                                },
-                            ),
-                            web_pool.clone(),
-                        )
+        let lease_keeper =
+            tokio::spawn(keep_ingestion_leases(payloads.clone(), redis_pool.clone()));
+
+        let mut upload_messages: Vec<(String, UploadIngestionMessage)> = vec![];
+        let mut update_messages: Vec<(String, UpdateIngestionMessage)> = vec![];
+        for payload in payloads {
+            match serde_json::from_str::<IngestionMessage>(&payload) {
+                Ok(IngestionMessage::Upload(message)) => upload_messages.push((payload, message)),
+                Ok(IngestionMessage::Update(message)) => update_messages.push((payload, message)),
+                Err(err) => {
+                    log::error!("Failed to parse ingestion message: {:?}", err);
+                    let error = format!("Failed to parse ingestion message: {}", err);
+                    match fail_ingestion_message(&payload, error.clone(), false, redis_pool.clone())
                         .await
-                        .map_err(|err| {
-                            log::error!("Failed to create event: {:?}", err);
-                        });
+                    {
+                        Ok(failure) => {
+                            record_ingestion_failure(
+                                &payload,
+                                failure,
+                                error,
+                                web_pool.clone(),
+                                redis_pool.clone(),
+                            )
+                            .await
+                        }
+                        Err(err) => {
+                            log::error!("Failed to dead-letter ingestion message: {:?}", err)
+                        }
                     }
                 }
             }
+        }
 
-            IngestionMessage::Update(payload) => {
-                match update_chunk(
-                    payload.clone(),
-                    web_pool.clone(),
-                    payload.server_dataset_config.clone(),
+        let _ = update_chunk_ingestion_statuses_query(
+            upload_messages
+                .iter()
+                .map(|(_, message)| message.chunk_metadata.id)
+                .chain(
+                    update_messages
+                        .iter()
+                        .map(|(_, message)| message.chunk_metadata.id),
                 )
-                .await
-                {
-                    Ok(_) => {
-                        log::info!("Updated chunk: {:?}", payload.chunk_metadata.id);
-                        let _ = create_event_query(
-                            Event::from_details(
-                                payload.dataset_id,
-                                models::EventType::CardUpdated {
-                                    chunk_id: payload.chunk_metadata.id,
-                                },
-                            ),
-                            web_pool.clone(),
-                        )
-                        .await
-                        .map_err(|err| {
-                            log::error!("Failed to create event: {:?}", err);
-                        });
-                    }
-                    Err(err) => {
-                        log::error!("Failed to upload chunk: {:?}", err);
-                        let _ = create_event_query(
-                            Event::from_details(
-                                payload.dataset_id,
-                                models::EventType::CardActionFailed {
-                                    chunk_id: payload.chunk_metadata.id,
-                                    error: format!("Failed to upload chunk: {:?}", err),
-                                },
-                            ),
-                            web_pool.clone(),
-                        )
-                        .await
-                        .map_err(|err| {
-                            log::error!("Failed to create event: {:?}", err);
-                        });
-                    }
-                }
-            }
+                .collect(),
+            IngestionStatus::Processing,
+            None,
+            None,
+            web_pool.clone(),
+        )
+        .await
+        .map_err(|err| {
+            log::error!("Failed to set ingestion status of chunks: {:?}", err);
+        });
+
+        // The dataset configuration picks the embedding model and the Qdrant collection, so only messages which share one can be embedded and upserted together
+        let mut upload_batches: HashMap<String, Vec<(String, UploadIngestionMessage)>> =
+            HashMap::new();
+        for (payload, message) in upload_messages {
+            let dataset_config = serde_json::to_string(&message.dataset_config)
+                .expect("ServerDatasetConfiguration must serialize");
+            upload_batches
+                .entry(dataset_config)
+                .or_default()
+                .push((payload, message));
+        }
+
+        for (_, upload_batch) in upload_batches {
+            upload_chunks(thread, upload_batch, web_pool.clone(), redis_pool.clone()).await;
         }
+
+        for (payload, message) in update_messages {
+            process_update_message(
+                thread,
+                payload,
+                message,
+                web_pool.clone(),
+                redis_pool.clone(),
+            )
+            .await;
+        }
+
+        lease_keeper.abort();
         transaction.finish();
     }
 }
 
-#[tracing::instrument(skip(payload, web_pool, dataset_config))]
-async fn upload_chunk(
-    mut payload: UploadIngestionMessage,
+/// Extends the leases of a claimed batch until it is aborted once the batch is processed.
+async fn keep_ingestion_leases(
+    payloads: Vec<String>,
+    redis_pool: actix_web::web::Data<models::RedisPool>,
+) {
+    let renewal_interval = get_ingestion_lease_renewal_interval();
+
+    loop {
+        tokio::time::sleep(renewal_interval).await;
+
+        let _ = extend_ingestion_leases(&payloads, redis_pool.clone())
+            .await
+            .map_err(|err| {
+                log::error!("Failed to extend ingestion leases: {:?}", err);
+            });
+    }
+}
+
+/// Uploads messages which share a dataset configuration. Their dense and sparse embeddings are each created in one request and their new points are upserted to Qdrant in one request, unless a chunk resembles one of the batch whose point is not upserted yet, in which case the points so far are upserted first so that its collision check can find them. When a batched request fails, its work is redone message by message so that one bad message does not fail the others.
+async fn upload_chunks(
+    thread: usize,
+    messages: Vec<(String, UploadIngestionMessage)>,
     web_pool: actix_web::web::Data<models::Pool>,
-    dataset_config: ServerDatasetConfiguration,
-) -> Result<(), ServiceError> {
-    let tx_ctx = sentry::TransactionContext::new("upload_chunk", "Uploading Chunk");
-    let transaction = sentry::start_transaction(tx_ctx);
-    sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+    redis_pool: actix_web::web::Data<models::RedisPool>,
+) {
+    let processing_started_at = std::time::Instant::now();
 
-    let mut qdrant_point_id = payload
-        .chunk_metadata
-        .qdrant_point_id
-        .unwrap_or(uuid::Uuid::new_v4());
+    let dataset_config = match messages.first() {
+        Some((_, message)) => message.dataset_config.clone(),
+        None => return,
+    };
 
-    let embedding_vector = if let Some(embedding_vector) = payload.chunk.chunk_vector.clone() {
-        embedding_vector
-    } else {
-        match payload.chunk.split_avg.unwrap_or(false) {
-            true => {
-                let chunks = coarse_doc_chunker(payload.chunk_metadata.content.clone());
+    let embedding_vectors = create_upload_embeddings(
+        messages.iter().map(|(_, message)| message).collect(),
+        dataset_config.clone(),
+    )
+    .await;
+    let splade_vectors = create_upload_splade_embeddings(
+        messages.iter().map(|(_, message)| message).collect(),
+        dataset_config.clone(),
+    )
+    .await;
+
+    let collisions_checked =
+        dataset_config.DUPLICATE_DISTANCE_THRESHOLD < 1.0 || dataset_config.COLLISIONS_ENABLED;
+
+    let mut results: Vec<Result<UploadedChunk, ServiceError>> = vec![];
+    let mut durations: Vec<std::time::Duration> = vec![];
+    // Points before this index have been upserted already
+    let mut flushed_until = 0;
+    for (((_, message), embedding_vector), splade_vector) in
+        messages.iter().zip(embedding_vectors).zip(splade_vectors)
+    {
+        let message_started_at = std::time::Instant::now();
+        let result = match embedding_vector {
+            Ok(embedding_vector) => {
+                // Points of the batch are not in Qdrant until they are upserted, so a chunk which duplicates an earlier one of the batch is only found after they are
+                if collisions_checked
+                    && results[flushed_until..].iter().any(|result| match result {
+                        Ok(UploadedChunk::New(new_point)) => {
+                            cosine_similarity(&new_point.embedding_vector, &embedding_vector)
+                                >= dataset_config.DUPLICATE_DISTANCE_THRESHOLD
+                        }
+                        _ => false,
+                    })
+                {
+                    create_new_points(&mut results[flushed_until..], dataset_config.clone()).await;
+                    flushed_until = results.len();
+                }
+
+                upload_chunk(
+                    message.clone(),
+                    embedding_vector,
+                    splade_vector,
+                    web_pool.clone(),
+                    dataset_config.clone(),
+                )
+                .await
+            }
+            Err(err) => Err(err),
+        };
+        results.push(result);
+        durations.push(message_started_at.elapsed());
+    }
+
+    create_new_points(&mut results[flushed_until..], dataset_config.clone()).await;
+    observe_ingestion_batch("upload", processing_started_at.elapsed());
+
+    for (((payload, message), result), duration) in messages.into_iter().zip(results).zip(durations)
+    {
+        observe_ingestion_message(
+            thread,
+            "upload",
+            if result.is_ok() {
+                "succeeded"
+            } else {
+                "failed"
+            },
+            duration,
+        );
+
+        match result {
+            Ok(uploaded_chunk) => {
+                let collision_chunk_id = match uploaded_chunk {
+                    UploadedChunk::Collided(collision_chunk_id) => Some(collision_chunk_id),
+                    UploadedChunk::New(_) => None,
+                };
 
-                let embeddings = create_embeddings(chunks, "doc", dataset_config.clone())
+                log::info!("Uploaded chunk: {:?}", message.chunk_metadata.id);
+                set_ingestion_status(
+                    message.chunk_metadata.id,
+                    if collision_chunk_id.is_some() {
+                        IngestionStatus::Collided
+                    } else {
+                        IngestionStatus::Indexed
+                    },
+                    None,
+                    collision_chunk_id,
+                    web_pool.clone(),
+                )
+                .await;
+                let _ = ack_ingestion_message(&payload, redis_pool.clone())
                     .await
                     .map_err(|err| {
-                        ServiceError::InternalServerError(format!(
-                            "Failed to create embedding: {:?}",
-                            err
-                        ))
-                    })?;
-
-                average_embeddings(embeddings).map_err(|err| {
-                    ServiceError::InternalServerError(format!(
-                        "Failed to average embeddings: {:?}",
-                        err.message
-                    ))
-                })?
+                        log::error!("Failed to ack ingestion message: {:?}", err);
+                    });
+                let _ = bump_search_cache_generation(
+                    message.chunk_metadata.dataset_id,
+                    redis_pool.clone(),
+                )
+                .await;
+                let _ = create_event_query(
+                    Event::from_details(
+                        message.chunk_metadata.dataset_id,
+                        models::EventType::CardUploaded {
+                            chunk_id: message.chunk_metadata.id,
+                        },
+                    ),
+                    web_pool.clone(),
+                    redis_pool.clone(),
+                )
+                .await
+                .map_err(|err| {
+                    log::error!("Failed to create event: {:?}", err);
+                });
+            }
+            Err(err) => {
+                handle_failed_message(&payload, err, web_pool.clone(), redis_pool.clone()).await;
             }
-            false => {
-                let embedding_vectors = create_embeddings(
-                    vec![payload.chunk_metadata.content.clone()],
-                    "doc",
+        }
+    }
+}
+
+/// Upserts the new points of the results in one request, falling back to one request per point if it fails. Results whose point could not be created become errors.
+async fn create_new_points(
+    results: &mut [Result<UploadedChunk, ServiceError>],
+    dataset_config: ServerDatasetConfiguration,
+) {
+    let new_points: Vec<NewQdrantPoint> = results
+        .iter()
+        .filter_map(|result| match result {
+            Ok(UploadedChunk::New(new_point)) => Some(new_point.clone()),
+            _ => None,
+        })
+        .collect();
+
+    if new_points.is_empty() {
+        return;
+    }
+
+    if let Err(err) = bulk_create_new_qdrant_points_query(new_points, dataset_config.clone()).await
+    {
+        log::error!(
+            "Failed to bulk create qdrant points, creating them one at a time: {:?}",
+            err
+        );
+
+        for result in results.iter_mut() {
+            if let Ok(UploadedChunk::New(new_point)) = result {
+                if let Err(err) = create_new_qdrant_point_query(
+                    new_point.point_id,
+                    new_point.embedding_vector.clone(),
+                    new_point.chunk_metadata.clone(),
+                    new_point.splade_vector.clone(),
+                    new_point.group_ids.clone(),
                     dataset_config.clone(),
                 )
                 .await
-                .map_err(|err| {
-                    ServiceError::InternalServerError(format!(
-                        "Failed to create embedding: {:?}",
+                {
+                    *result = Err(ServiceError::InternalServerError(format!(
+                        "Failed to create new qdrant point: {:?}",
                         err
-                    ))
-                })?;
+                    )));
+                }
+            }
+        }
+    }
+}
 
-                embedding_vectors
-                    .first()
-                    .ok_or(ServiceError::InternalServerError(
-                        "Failed to get first embedding".into(),
-                    ))?
-                    .clone()
+async fn process_update_message(
+    thread: usize,
+    payload: String,
+    message: UpdateIngestionMessage,
+    web_pool: actix_web::web::Data<models::Pool>,
+    redis_pool: actix_web::web::Data<models::RedisPool>,
+) {
+    let processing_started_at = std::time::Instant::now();
+    let update_result = update_chunk(
+        message.clone(),
+        web_pool.clone(),
+        message.server_dataset_config.clone(),
+    )
+    .await;
+    observe_ingestion_message(
+        thread,
+        "update",
+        if update_result.is_ok() {
+            "succeeded"
+        } else {
+            "failed"
+        },
+        processing_started_at.elapsed(),
+    );
+
+    match update_result {
+        Ok(_) => {
+            log::info!("Updated chunk: {:?}", message.chunk_metadata.id);
+            set_ingestion_status(
+                message.chunk_metadata.id,
+                IngestionStatus::Indexed,
+                None,
+                None,
+                web_pool.clone(),
+            )
+            .await;
+            let _ = ack_ingestion_message(&payload, redis_pool.clone())
+                .await
+                .map_err(|err| {
+                    log::error!("Failed to ack ingestion message: {:?}", err);
+                });
+            let _ = bump_search_cache_generation(message.dataset_id, redis_pool.clone()).await;
+            let _ = create_event_query(
+                Event::from_details(
+                    message.dataset_id,
+                    models::EventType::CardUpdated {
+                        chunk_id: message.chunk_metadata.id,
+                    },
+                ),
+                web_pool.clone(),
+                redis_pool.clone(),
+            )
+            .await
+            .map_err(|err| {
+                log::error!("Failed to create event: {:?}", err);
+            });
+        }
+        Err(err) => {
+            handle_failed_message(&payload, err, web_pool.clone(), redis_pool.clone()).await;
+        }
+    }
+}
+
+/// Creates the dense embedding of every message which did not come with a chunk_vector in one request. Falls back to one request per message if the batched request fails.
+async fn create_upload_embeddings(
+    messages: Vec<&UploadIngestionMessage>,
+    dataset_config: ServerDatasetConfiguration,
+) -> Vec<Result<Vec<f32>, ServiceError>> {
+    let texts: Vec<Vec<String>> = messages
+        .iter()
+        .map(|message| match message.chunk.chunk_vector {
+            Some(_) => vec![],
+            None => get_upload_embedding_texts(
+                &message.chunk_metadata.content,
+                message.chunk.split_avg.unwrap_or(false),
+            ),
+        })
+        .collect();
+    let text_count: usize = texts.iter().map(|texts| texts.len()).sum();
+
+    if text_count > 0 {
+        match create_embeddings(texts.concat(), "doc", dataset_config.clone()).await {
+            Ok(embeddings) if embeddings.len() == text_count => {
+                return get_batched_upload_embedding_vectors(
+                    messages
+                        .iter()
+                        .zip(texts.iter())
+                        .map(|(message, texts)| {
+                            (
+                                message.chunk.chunk_vector.clone(),
+                                message.chunk.split_avg.unwrap_or(false),
+                                texts.len(),
+                            )
+                        })
+                        .collect(),
+                    embeddings,
+                );
+            }
+            Ok(embeddings) => {
+                log::error!(
+                    "Embedding server returned {} embeddings for {} texts, embedding one message at a time",
+                    embeddings.len(),
+                    text_count
+                );
+            }
+            Err(err) => {
+                log::error!(
+                    "Failed to create batched embeddings, embedding one message at a time: {:?}",
+                    err
+                );
             }
         }
-    };
+    }
 
-    let splade_vector = if dataset_config.FULLTEXT_ENABLED {
-        match get_splade_embedding(&payload.chunk_metadata.content, "doc").await {
-            Ok(v) => v,
-            Err(_) => vec![(0, 0.0)],
+    let mut embedding_vectors = vec![];
+    for (message, texts) in messages.into_iter().zip(texts) {
+        if let Some(chunk_vector) = message.chunk.chunk_vector.clone() {
+            embedding_vectors.push(Ok(chunk_vector));
+            continue;
         }
-    } else {
-        vec![(0, 0.0)]
-    };
-    // let splade_vector = vec![(0, 0.0)];
+
+        let embeddings = create_embeddings(texts, "doc", dataset_config.clone())
+            .await
+            .map_err(|err| {
+                ServiceError::InternalServerError(format!("Failed to create embedding: {:?}", err))
+            });
+
+        embedding_vectors.push(embeddings.and_then(|embeddings| {
+            get_upload_embedding_vector(embeddings, message.chunk.split_avg.unwrap_or(false))
+        }));
+    }
+
+    embedding_vectors
+}
+
+/// Creates the SPLADE embedding of every message in one request when the dataset uses the SPLADE full-text backend. Falls back to one request per message if the batched request fails. Messages whose sparse embedding fails are indexed without one, as they were before batching.
+async fn create_upload_splade_embeddings(
+    messages: Vec<&UploadIngestionMessage>,
+    dataset_config: ServerDatasetConfiguration,
+) -> Vec<Vec<(u32, f32)>> {
+    // The Postgres full-text backend is indexed by a trigger on chunk_metadata, so only SPLADE needs a sparse vector
+    if !dataset_config.FULLTEXT_ENABLED
+        || dataset_config.FULLTEXT_BACKEND != FullTextBackend::Splade
+    {
+        return vec![vec![(0, 0.0)]; messages.len()];
+    }
+
+    match get_splade_embeddings(
+        messages
+            .iter()
+            .map(|message| message.chunk_metadata.content.clone())
+            .collect(),
+        "doc",
+    )
+    .await
+    {
+        Ok(splade_vectors) => splade_vectors,
+        Err(err) => {
+            log::error!(
+                "Failed to create batched splade embeddings, embedding one message at a time: {:?}",
+                err
+            );
+
+            let mut splade_vectors = vec![];
+            for message in messages {
+                splade_vectors.push(
+                    get_splade_embedding(&message.chunk_metadata.content, "doc")
+                        .await
+                        .unwrap_or(vec![(0, 0.0)]),
+                );
+            }
+            splade_vectors
+        }
+    }
+}
+
+/// What is left to do for a chunk once its metadata is stored.
+enum UploadedChunk {
+    /// The chunk collided with the existing chunk, whose point was updated instead of creating a new one.
+    Collided(uuid::Uuid),
+    /// The chunk needs a new point, which is upserted together with the rest of the batch.
+    New(NewQdrantPoint),
+}
+
+/// What is left to do for a chunk which an earlier attempt of its message already stored. A chunk with a point of its own may still need it upserted, which is harmless to repeat. A chunk without one collided, and the collided point was updated before the chunk was stored.
+async fn get_stored_uploaded_chunk(
+    mut payload: UploadIngestionMessage,
+    stored_chunk: models::ChunkMetadata,
+    embedding_vector: Vec<f32>,
+    splade_vector: Vec<(u32, f32)>,
+    web_pool: actix_web::web::Data<models::Pool>,
+) -> Result<UploadedChunk, ServiceError> {
+    if let Some(qdrant_point_id) = stored_chunk.qdrant_point_id {
+        payload.chunk_metadata.qdrant_point_id = Some(qdrant_point_id);
+
+        return Ok(UploadedChunk::New(NewQdrantPoint {
+            point_id: qdrant_point_id,
+            embedding_vector,
+            chunk_metadata: payload.chunk_metadata,
+            splade_vector,
+            group_ids: payload.chunk.group_ids,
+        }));
+    }
+
+    let collision_qdrant_id = get_qdrant_id_from_chunk_id_query(stored_chunk.id, web_pool.clone())
+        .await
+        .map_err(|err| {
+            ServiceError::InternalServerError(format!(
+                "Failed to get collided point of stored chunk: {:?}",
+                err
+            ))
+        })?;
+    let collided_chunk = get_metadata_from_point_ids(vec![collision_qdrant_id], web_pool)
+        .await
+        .map_err(|err| {
+            ServiceError::InternalServerError(format!(
+                "Failed to get collided chunk of stored chunk: {:?}",
+                err
+            ))
+        })?
+        .into_iter()
+        .next()
+        .ok_or(ServiceError::InternalServerError(
+            "Collided chunk of stored chunk not found".into(),
+        ))?;
+
+    Ok(UploadedChunk::Collided(collided_chunk.id))
+}
+
+#[tracing::instrument(skip(payload, embedding_vector, splade_vector, web_pool, dataset_config))]
+async fn upload_chunk(
+    mut payload: UploadIngestionMessage,
+    embedding_vector: Vec<f32>,
+    splade_vector: Vec<(u32, f32)>,
+    web_pool: actix_web::web::Data<models::Pool>,
+    dataset_config: ServerDatasetConfiguration,
+) -> Result<UploadedChunk, ServiceError> {
+    let tx_ctx = sentry::TransactionContext::new("upload_chunk", "Uploading Chunk");
+    let transaction = sentry::start_transaction(tx_ctx);
+    sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+
+    // A redelivered message, or a retry of an attempt which failed after storing the chunk, only finishes what the earlier attempt started
+    if let Ok(stored_chunk) = get_metadata_from_id_query(
+        payload.chunk_metadata.id,
+        payload.dataset_id,
+        web_pool.clone(),
+    )
+    .await
+    {
+        let uploaded_chunk = get_stored_uploaded_chunk(
+            payload,
+            stored_chunk,
+            embedding_vector,
+            splade_vector,
+            web_pool,
+        )
+        .await;
+        transaction.finish();
+        return uploaded_chunk;
+    }
+
+    let mut qdrant_point_id = payload
+        .chunk_metadata
+        .qdrant_point_id
+        .unwrap_or(uuid::Uuid::new_v4());
 
     let mut collision: Option<uuid::Uuid> = None;
+    let mut collision_chunk_id: Option<uuid::Uuid> = None;
 
     let duplicate_distance_threshold = dataset_config.DUPLICATE_DISTANCE_THRESHOLD;
 
@@ -350,7 +964,10 @@ async fn upload_chunk(
             ServiceError::InternalServerError(format!("Failed to get top match: {:?}", err))
         })?;
 
-        if first_semantic_result.score >= duplicate_distance_threshold as f32 {
+        // The chunk never collides with its own point
+        if first_semantic_result.score >= duplicate_distance_threshold as f32
+            && first_semantic_result.point_id != qdrant_point_id
+        {
             //Sets collision to collided chunk id
             collision = Some(first_semantic_result.point_id);
 
@@ -358,7 +975,7 @@ async fn upload_chunk(
                 get_metadata_from_point_ids(vec![first_semantic_result.point_id], web_pool.clone())
                     .await;
 
-            match score_chunk_result {
+            let collided_chunk = match score_chunk_result {
                 Ok(chunk_results) => chunk_results
                     .first()
                     .expect("First chunk must exist on collision check")
@@ -370,12 +987,13 @@ async fn upload_chunk(
                     )))
                 }
             };
+            collision_chunk_id = Some(collided_chunk.id);
         }
         collision_detection_span.finish();
     }
 
     //if collision is not nil, insert chunk with collision
-    if collision.is_some() {
+    let uploaded_chunk = if collision.is_some() {
         let update_collision_span = transaction.start_child(
             "update_collision",
             "update_qdrant_point_query and insert_duplicate_chunk_metadata_query",
@@ -411,6 +1029,8 @@ async fn upload_chunk(
         })?;
 
         update_collision_span.finish();
+
+        UploadedChunk::Collided(collision_chunk_id.expect("Collided chunk must be some"))
     }
     //if collision is nil and embedding vector is some, insert chunk with no collision
     else {
@@ -421,7 +1041,7 @@ async fn upload_chunk(
             "calling_insert_chunk_metadata_query",
         );
 
-        let inserted_chunk = insert_chunk_metadata_query(
+        let inserted_chunk = match insert_chunk_metadata_query(
             payload.chunk_metadata.clone(),
             payload.chunk.file_id,
             payload.chunk.group_ids.clone(),
@@ -430,38 +1050,41 @@ async fn upload_chunk(
             web_pool.clone(),
         )
         .await
-        .map_err(|err| {
-            ServiceError::InternalServerError(format!("Failed to insert chunk metadata: {:?}", err))
-        })?;
+        {
+            Ok(inserted_chunk) => inserted_chunk,
+            // A previous attempt of the message may have inserted the chunk before failing
+            Err(err) => get_metadata_from_id_query(
+                payload.chunk_metadata.id,
+                payload.dataset_id,
+                web_pool.clone(),
+            )
+            .await
+            .map_err(|_| match err.message {
+                "Duplicate tracking_id" => {
+                    ServiceError::BadRequest(format!("Failed to insert chunk metadata: {:?}", err))
+                }
+                _ => ServiceError::InternalServerError(format!(
+                    "Failed to insert chunk metadata: {:?}",
+                    err
+                )),
+            })?,
+        };
 
         insert_tx.finish();
 
         qdrant_point_id = inserted_chunk.qdrant_point_id.unwrap_or(qdrant_point_id);
 
-        let insert_tx =
-            transaction.start_child("calling_create_qdrant_point", "calling_create_qdrant_point");
-
-        create_new_qdrant_point_query(
-            qdrant_point_id,
+        UploadedChunk::New(NewQdrantPoint {
+            point_id: qdrant_point_id,
             embedding_vector,
-            payload.chunk_metadata.clone(),
+            chunk_metadata: payload.chunk_metadata.clone(),
             splade_vector,
-            payload.chunk.group_ids,
-            dataset_config.clone(),
-        )
-        .await
-        .map_err(|err| {
-            ServiceError::InternalServerError(format!(
-                "Failed to create new qdrant point: {:?}",
-                err
-            ))
-        })?;
-
-        insert_tx.finish();
-    }
+            group_ids: payload.chunk.group_ids,
+        })
+    };
 
     transaction.finish();
-    Ok(())
+    Ok(uploaded_chunk)
 }
 
 #[tracing::instrument(skip(web_pool))]
@@ -476,7 +1099,7 @@ async fn update_chunk(
         server_dataset_config.clone(),
     )
     .await
-    .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+    .map_err(|err| ServiceError::InternalServerError(err.to_string()))?;
     let embedding_vector = embedding_vectors
         .first()
         .ok_or(ServiceError::BadRequest(
@@ -489,7 +1112,10 @@ async fn update_chunk(
             .await
             .map_err(|_| ServiceError::BadRequest("chunk not found".into()))?;
 
-    let splade_vector = if server_dataset_config.FULLTEXT_ENABLED {
+    // The Postgres full-text backend is indexed by a trigger on chunk_metadata, so only SPLADE needs a sparse vector
+    let splade_vector = if server_dataset_config.FULLTEXT_ENABLED
+        && server_dataset_config.FULLTEXT_BACKEND == FullTextBackend::Splade
+    {
         match get_splade_embedding(&payload.chunk_metadata.content, "doc").await {
             Ok(v) => v,
             Err(_) => vec![(0, 0.0)],
@@ -515,7 +1141,7 @@ async fn update_chunk(
             web_pool.clone(),
         )
         .await
-        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+        .map_err(|err| ServiceError::InternalServerError(err.message.into()))?;
 
         if let Some(qdrant_point_id) = chunk.qdrant_point_id {
             update_qdrant_point_query(
@@ -533,7 +1159,7 @@ async fn update_chunk(
                 server_dataset_config,
             )
             .await
-            .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+            .map_err(|err| ServiceError::InternalServerError(err.to_string()))?;
         }
     } else {
         update_chunk_metadata_query(
@@ -544,7 +1170,7 @@ async fn update_chunk(
             web_pool.clone(),
         )
         .await
-        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+        .map_err(|err| ServiceError::InternalServerError(err.message.into()))?;
 
         update_qdrant_point_query(
             // If the chunk is a collision, we don't want to update the qdrant point
@@ -561,7 +1187,7 @@ async fn update_chunk(
             server_dataset_config,
         )
         .await
-        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+        .map_err(|err| ServiceError::InternalServerError(err.to_string()))?;
     }
 
     Ok(())
@@ -1063,6 +1063,63 @@ impl DatasetAndUsage {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FullTextBackend {
+    /// SPLADE sparse vectors stored in Qdrant. Requires SPARSE_SERVER_QUERY_ORIGIN and SPARSE_SERVER_DOC_ORIGIN or GPU_SERVER_ORIGIN.
+    Splade,
+    /// A tsvector column on chunk_metadata ranked with ts_rank_cd. Runs on any Postgres, no GPU required.
+    Postgres,
+}
+
+impl FullTextBackend {
+    /// SPLADE when both queries and documents can be sparse embedded, otherwise Postgres. Each of
+    /// SPARSE_SERVER_QUERY_ORIGIN and SPARSE_SERVER_DOC_ORIGIN falls back to GPU_SERVER_ORIGIN when unset.
+    pub fn default_for_env() -> Self {
+        let is_set = |key: &str| std::env::var(key).map(|s| !s.is_empty()).unwrap_or(false);
+        let has_sparse_server = is_set("GPU_SERVER_ORIGIN")
+            || (is_set("SPARSE_SERVER_QUERY_ORIGIN") && is_set("SPARSE_SERVER_DOC_ORIGIN"));
+
+        if has_sparse_server {
+            FullTextBackend::Splade
+        } else {
+            FullTextBackend::Postgres
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DecayFunction {
+    /// The score multiplier shrinks by the decay factor over every scale_days. It never reaches zero.
+    Exponential,
+    /// The score multiplier drops by a constant amount every day until it reaches zero.
+    Linear,
+    /// The score multiplier stays close to 1 for recent chunks then falls off quickly around scale_days.
+    Gaussian,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+#[schema(example=json!({
+    "function": "exponential",
+    "scale_days": 365.0,
+    "origin": "2024-01-01",
+    "decay": 0.5,
+    "weight": 0.5
+}))]
+pub struct RecencyDecay {
+    /// The shape of the decay curve. Can be "exponential", "linear", or "gaussian". If not specified, this defaults to "exponential".
+    pub function: Option<DecayFunction>,
+    /// Age in days at which the decay curve reaches the decay factor. Must be greater than 0. If not specified, this defaults to 365.
+    pub scale_days: Option<f64>,
+    /// Date that the age of chunks is measured from. Accepts the same formats as the time_stamp of a chunk. If not specified, this defaults to the current time.
+    pub origin: Option<String>,
+    /// Value of the decay curve for a chunk which is scale_days old. Must be between 0 and 1, exclusive. If not specified, this defaults to 0.5.
+    pub decay: Option<f64>,
+    /// Share of the score which is subject to decay. Scores lose weight * (1 - decay_curve) of their magnitude, so a weight of 0.3 means a very old chunk keeps 70% of its score. Negative scores move further below zero. Must be between 0 and 1. If not specified, this defaults to 0.5.
+    pub weight: Option<f64>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 #[schema(example=json!({
     "DOCUMENT_UPLOAD_FEATURE": true,
@@ -1080,7 +1137,17 @@ impl DatasetAndUsage {
     "EMBEDDING_SIZE": 1536,
     "LLM_DEFAULT_MODEL": "gpt-3.5-turbo-1106",
     "FULLTEXT_ENABLED": true,
+    "FULLTEXT_BACKEND": "postgres",
+    "FULLTEXT_LANGUAGE": "english",
     "EMBEDDING_QUERY_PREFIX": "Search for",
+    "RECENCY_DECAY": {
+        "function": "exponential",
+        "scale_days": 365.0,
+        "decay": 0.5,
+        "weight": 0.5
+    },
+    "SEARCH_CACHE_ENABLED": false,
+    "SEARCH_CACHE_TTL": 300,
 }))]
 #[allow(non_snake_case)]
 pub struct ServerDatasetConfiguration {
@@ -1099,7 +1166,12 @@ pub struct ServerDatasetConfiguration {
     pub EMBEDDING_SIZE: usize,
     pub LLM_DEFAULT_MODEL: String,
     pub FULLTEXT_ENABLED: bool,
+    pub FULLTEXT_BACKEND: FullTextBackend,
+    pub FULLTEXT_LANGUAGE: String,
     pub EMBEDDING_QUERY_PREFIX: String,
+    pub RECENCY_DECAY: Option<RecencyDecay>,
+    pub SEARCH_CACHE_ENABLED: bool,
+    pub SEARCH_CACHE_TTL: u64,
 }
 
 impl ServerDatasetConfiguration {
@@ -1205,6 +1277,22 @@ impl ServerDatasetConfiguration {
                 .unwrap_or(&json!(true))
                 .as_bool()
                 .unwrap_or(true),
+            FULLTEXT_BACKEND: configuration
+                .get("FULLTEXT_BACKEND")
+                .and_then(|backend| serde_json::from_value(backend.clone()).ok())
+                .unwrap_or_else(FullTextBackend::default_for_env),
+            FULLTEXT_LANGUAGE: configuration
+                .get("FULLTEXT_LANGUAGE")
+                .unwrap_or(&json!("english"))
+                .as_str()
+                .map(|s| {
+                    if s.is_empty() {
+                        "english".to_string()
+                    } else {
+                        s.to_string()
+                    }
+                })
+                .unwrap_or("english".to_string()),
             QDRANT_URL: configuration
                 .get("QDRANT_URL")
                 .unwrap_or(&json!(get_env!("QDRANT_URL", "Must provide QDRANT_URL")))
@@ -1246,7 +1334,20 @@ impl ServerDatasetConfiguration {
                 .unwrap_or(&json!(""))
                 .as_str()
                 .map(|s| s.to_string())
-                .unwrap_or("".to_string())
+                .unwrap_or("".to_string()),
+            RECENCY_DECAY: configuration
+                .get("RECENCY_DECAY")
+                .and_then(|decay| serde_json::from_value(decay.clone()).ok()),
+            SEARCH_CACHE_ENABLED: configuration
+                .get("SEARCH_CACHE_ENABLED")
+                .unwrap_or(&json!(false))
+                .as_bool()
+                .unwrap_or(false),
+            SEARCH_CACHE_TTL: configuration
+                .get("SEARCH_CACHE_TTL")
+                .unwrap_or(&json!(300))
+                .as_u64()
+                .unwrap_or(300),
         }
     }
 }
@@ -1843,3 +1944,593 @@ impl From<String> for UnifiedId {
         UnifiedId::TrackingId(tracking_id)
     }
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchRuleMatchType {
+    /// The query must equal the pattern.
+    Exact,
+    /// The query must contain the pattern as whole words.
+    Contains,
+    /// The query must match the pattern as a regular expression.
+    Regex,
+}
+
+impl SearchRuleMatchType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchRuleMatchType::Exact => "exact",
+            SearchRuleMatchType::Contains => "contains",
+            SearchRuleMatchType::Regex => "regex",
+        }
+    }
+
+    pub fn parse(match_type: &str) -> Option<Self> {
+        match match_type {
+            "exact" => Some(SearchRuleMatchType::Exact),
+            "contains" => Some(SearchRuleMatchType::Contains),
+            "regex" => Some(SearchRuleMatchType::Regex),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+#[schema(example = json!({
+    "tag": "canonical",
+    "factor": 2.0
+}))]
+pub struct TagBoost {
+    /// The tag in the tag_set of a chunk which gets boosted.
+    pub tag: String,
+    /// The magnitude of the score of matching chunks is multiplied by this factor, so negative scores rise too. Must be greater than 0, factors below 1 demote chunks.
+    pub factor: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, ToSchema)]
+#[schema(example = json!({
+    "pin": ["d290f1ee-6c54-4b01-90e6-d701748f0851"],
+    "bury": ["e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3"],
+    "boost": [{"tag": "canonical", "factor": 2.0}]
+}))]
+pub struct SearchRuleActions {
+    /// Ids of chunks to show at the top of the first page, in this order. Pinned chunks which were not found by the search are added as long as they match its filters.
+    #[serde(default)]
+    pub pin: Vec<uuid::Uuid>,
+    /// Ids of chunks to move below every other result of the search.
+    #[serde(default)]
+    pub bury: Vec<uuid::Uuid>,
+    /// Tags whose chunks are boosted, which moves them up or down the whole ranking.
+    #[serde(default)]
+    pub boost: Vec<TagBoost>,
+}
+
+impl SearchRuleActions {
+    pub fn from_json(actions: serde_json::Value) -> Self {
+        serde_json::from_value(actions).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Insertable, Selectable, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "query_pattern": "carbon tax",
+    "match_type": "contains",
+    "actions": {
+        "pin": ["d290f1ee-6c54-4b01-90e6-d701748f0851"],
+        "bury": [],
+        "boost": [{"tag": "canonical", "factor": 2.0}]
+    },
+    "created_at": "2021-01-01T00:00:00",
+    "updated_at": "2021-01-01T00:00:00",
+}))]
+#[diesel(table_name = search_rules)]
+pub struct SearchRule {
+    pub id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+    pub query_pattern: String,
+    pub match_type: String,
+    pub actions: serde_json::Value,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl SearchRule {
+    pub fn from_details(
+        dataset_id: uuid::Uuid,
+        query_pattern: String,
+        match_type: SearchRuleMatchType,
+        actions: SearchRuleActions,
+    ) -> Self {
+        SearchRule {
+            id: uuid::Uuid::new_v4(),
+            dataset_id,
+            query_pattern,
+            match_type: match_type.as_str().to_string(),
+            actions: json!(actions),
+            created_at: chrono::Utc::now().naive_local(),
+            updated_at: chrono::Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Insertable, Selectable, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "search_type": "hybrid",
+    "query": "carbon tax",
+    "filters_hash": null,
+    "result_count": 10,
+    "top_score": 0.87,
+    "latency_ms": 112,
+    "created_at": "2021-01-01T00:00:00",
+}))]
+#[diesel(table_name = search_queries)]
+pub struct SearchQuery {
+    pub id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+    pub search_type: String,
+    pub query: String,
+    /// Sha256 of the filters of the search. Null if the search had no filters.
+    pub filters_hash: Option<String>,
+    /// Number of results on the page which was returned.
+    pub result_count: i32,
+    pub top_score: Option<f64>,
+    pub latency_ms: i32,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl SearchQuery {
+    pub fn from_details(
+        dataset_id: uuid::Uuid,
+        search_type: String,
+        query: String,
+        filters_hash: Option<String>,
+        result_count: i32,
+        top_score: Option<f64>,
+        latency_ms: i32,
+    ) -> Self {
+        SearchQuery {
+            id: uuid::Uuid::new_v4(),
+            dataset_id,
+            search_type,
+            query,
+            filters_hash,
+            result_count,
+            top_score,
+            latency_ms,
+            created_at: chrono::Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchFeedbackType {
+    /// The user opened the chunk.
+    Click,
+    /// The user bookmarked the chunk into a group.
+    Bookmark,
+    /// The user rated the chunk.
+    Rating,
+}
+
+impl SearchFeedbackType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchFeedbackType::Click => "click",
+            SearchFeedbackType::Bookmark => "bookmark",
+            SearchFeedbackType::Rating => "rating",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Insertable, Selectable, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "search_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "chunk_id": "d290f1ee-6c54-4b01-90e6-d701748f0851",
+    "position": 1,
+    "feedback_type": "click",
+    "group_id": null,
+    "rating": null,
+    "created_at": "2021-01-01T00:00:00",
+}))]
+#[diesel(table_name = search_feedback)]
+pub struct SearchFeedback {
+    pub id: uuid::Uuid,
+    /// Id of the search in the search log which returned the chunk.
+    pub search_id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+    pub chunk_id: uuid::Uuid,
+    /// Position of the chunk in the results of the search, starting at 1.
+    pub position: i32,
+    /// Can be "click", "bookmark", or "rating".
+    pub feedback_type: String,
+    /// The group the chunk was bookmarked into. Only set for bookmarks.
+    pub group_id: Option<uuid::Uuid>,
+    /// Only set for ratings.
+    pub rating: Option<i32>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl SearchFeedback {
+    pub fn from_details(
+        search_query: &SearchQuery,
+        chunk_id: uuid::Uuid,
+        position: i32,
+        feedback_type: SearchFeedbackType,
+        group_id: Option<uuid::Uuid>,
+        rating: Option<i32>,
+    ) -> Self {
+        SearchFeedback {
+            id: uuid::Uuid::new_v4(),
+            search_id: search_query.id,
+            dataset_id: search_query.dataset_id,
+            chunk_id,
+            position,
+            feedback_type: feedback_type.as_str().to_string(),
+            group_id,
+            rating,
+            created_at: chrono::Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Insertable, Selectable, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "url": "https://example.com/trieve/webhook",
+    "secret": "whsec-3W9tHcXJ4dWm7Lz2qBvRkP8sYnE5aF6u",
+    "event_types": ["card_action_failed"],
+    "created_at": "2021-01-01T00:00:00",
+    "updated_at": "2021-01-01T00:00:00",
+}))]
+#[diesel(table_name = webhooks)]
+pub struct Webhook {
+    pub id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+    /// The url events are POSTed to.
+    pub url: String,
+    /// Key of the HMAC-SHA256 signature in the X-Trieve-Signature header of each delivery.
+    pub secret: String,
+    /// The event types which are delivered. Every event type is delivered if empty.
+    pub event_types: serde_json::Value,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl Webhook {
+    pub fn from_details(dataset_id: uuid::Uuid, url: String, event_types: Vec<String>) -> Self {
+        Webhook {
+            id: uuid::Uuid::new_v4(),
+            dataset_id,
+            url,
+            secret: Webhook::generate_secret(),
+            event_types: json!(event_types),
+            created_at: chrono::Utc::now().naive_local(),
+            updated_at: chrono::Utc::now().naive_local(),
+        }
+    }
+
+    pub fn generate_secret() -> String {
+        use rand::{distributions::Alphanumeric, Rng};
+
+        format!(
+            "whsec-{}",
+            rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(32)
+                .map(char::from)
+                .collect::<String>()
+        )
+    }
+
+    pub fn subscribes_to(&self, event_type: &str) -> bool {
+        let event_types: Vec<String> =
+            serde_json::from_value(self.event_types.clone()).unwrap_or_default();
+
+        event_types.is_empty() || event_types.iter().any(|e| e == event_type)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "url": "https://example.com/trieve/webhook",
+    "event_types": ["card_action_failed"],
+    "created_at": "2021-01-01T00:00:00",
+    "updated_at": "2021-01-01T00:00:00",
+}))]
+pub struct WebhookDTO {
+    pub id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+    pub url: String,
+    pub event_types: serde_json::Value,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl From<Webhook> for WebhookDTO {
+    fn from(webhook: Webhook) -> Self {
+        WebhookDTO {
+            id: webhook.id,
+            dataset_id: webhook.dataset_id,
+            url: webhook.url,
+            event_types: webhook.event_types,
+            created_at: webhook.created_at,
+            updated_at: webhook.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookDeliveryStatus {
+    /// The event is being delivered or is waiting for a retry.
+    Pending,
+    /// The webhook url responded with a 2xx status.
+    Succeeded,
+    /// Every attempt to deliver the event failed.
+    Failed,
+}
+
+impl WebhookDeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookDeliveryStatus::Pending => "pending",
+            WebhookDeliveryStatus::Succeeded => "succeeded",
+            WebhookDeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Insertable, Selectable, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "webhook_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "event_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "status": "failed",
+    "attempt_count": 5,
+    "response_status": 502,
+    "last_error": "Webhook url responded with 502 Bad Gateway",
+    "created_at": "2021-01-01T00:00:00",
+    "updated_at": "2021-01-01T00:00:00",
+    "next_attempt_at": null,
+}))]
+#[diesel(table_name = webhook_deliveries)]
+pub struct WebhookDelivery {
+    pub id: uuid::Uuid,
+    pub webhook_id: uuid::Uuid,
+    pub event_id: uuid::Uuid,
+    /// Can be "pending", "succeeded", or "failed".
+    pub status: String,
+    /// Number of attempts made so far.
+    pub attempt_count: i32,
+    /// Status code of the response to the last attempt. Null if the url could not be reached.
+    pub response_status: Option<i32>,
+    /// Why the last attempt failed. Null if it succeeded.
+    pub last_error: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+    /// When the next attempt is due. While an attempt is in flight this is when its claim expires. Null once the delivery succeeded or failed.
+    pub next_attempt_at: Option<chrono::NaiveDateTime>,
+}
+
+impl WebhookDelivery {
+    pub fn from_details(webhook_id: uuid::Uuid, event_id: uuid::Uuid) -> Self {
+        WebhookDelivery {
+            id: uuid::Uuid::new_v4(),
+            webhook_id,
+            event_id,
+            status: WebhookDeliveryStatus::Pending.as_str().to_string(),
+            attempt_count: 0,
+            response_status: None,
+            last_error: None,
+            created_at: chrono::Utc::now().naive_local(),
+            updated_at: chrono::Utc::now().naive_local(),
+            next_attempt_at: Some(chrono::Utc::now().naive_local()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    ApiKeyCreated,
+    ApiKeyDeleted,
+    DatasetCreated,
+    DatasetUpdated,
+    DatasetDeleted,
+    OrganizationUpdated,
+    UserRoleChanged,
+    InvitationSent,
+    FileDeleted,
+    GroupDeleted,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::ApiKeyCreated => "api_key_created",
+            AuditAction::ApiKeyDeleted => "api_key_deleted",
+            AuditAction::DatasetCreated => "dataset_created",
+            AuditAction::DatasetUpdated => "dataset_updated",
+            AuditAction::DatasetDeleted => "dataset_deleted",
+            AuditAction::OrganizationUpdated => "organization_updated",
+            AuditAction::UserRoleChanged => "user_role_changed",
+            AuditAction::InvitationSent => "invitation_sent",
+            AuditAction::FileDeleted => "file_deleted",
+            AuditAction::GroupDeleted => "group_deleted",
+        }
+    }
+
+    /// The kind of resource the action was taken on.
+    pub fn resource_type(&self) -> &'static str {
+        match self {
+            AuditAction::ApiKeyCreated | AuditAction::ApiKeyDeleted => "api_key",
+            AuditAction::DatasetCreated
+            | AuditAction::DatasetUpdated
+            | AuditAction::DatasetDeleted => "dataset",
+            AuditAction::OrganizationUpdated => "organization",
+            AuditAction::UserRoleChanged => "user",
+            AuditAction::InvitationSent => "invitation",
+            AuditAction::FileDeleted => "file",
+            AuditAction::GroupDeleted => "group",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Insertable, Selectable, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "organization_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "user_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "api_key_id": null,
+    "action": "dataset_deleted",
+    "resource_type": "dataset",
+    "resource_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "details": {"dataset_name": "Evidence"},
+    "created_at": "2021-01-01T00:00:00",
+}))]
+#[diesel(table_name = audit_log)]
+pub struct AuditLog {
+    pub id: uuid::Uuid,
+    pub organization_id: uuid::Uuid,
+    /// The dataset the action was taken in, if any.
+    pub dataset_id: Option<uuid::Uuid>,
+    /// The user who took the action. Null if the request was not made by a user. The id is kept even if the user has since been deleted.
+    pub user_id: Option<uuid::Uuid>,
+    /// The api key the action was taken with. Null if it was taken from a logged in session.
+    pub api_key_id: Option<uuid::Uuid>,
+    /// What was done, e.g. "dataset_deleted".
+    pub action: String,
+    /// The kind of resource the action was taken on, e.g. "dataset".
+    pub resource_type: String,
+    /// Id of the resource the action was taken on. For invitations this is the invited email.
+    pub resource_id: Option<String>,
+    /// Action specific details, such as the name of a deleted dataset or the new role of a user.
+    pub details: serde_json::Value,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl AuditLog {
+    pub fn from_details(
+        organization_id: uuid::Uuid,
+        dataset_id: Option<uuid::Uuid>,
+        user_id: Option<uuid::Uuid>,
+        api_key_id: Option<uuid::Uuid>,
+        action: AuditAction,
+        resource_id: Option<String>,
+        details: serde_json::Value,
+    ) -> Self {
+        AuditLog {
+            id: uuid::Uuid::new_v4(),
+            organization_id,
+            dataset_id,
+            user_id,
+            api_key_id,
+            action: action.as_str().to_string(),
+            resource_type: action.resource_type().to_string(),
+            resource_id,
+            details,
+            created_at: chrono::Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IngestionStatus {
+    /// The chunk is waiting for a worker, either for the first time or for a retry.
+    Queued,
+    /// A worker is embedding and indexing the chunk.
+    Processing,
+    /// The chunk is searchable.
+    Indexed,
+    /// The chunk collided with an existing chunk and was stored as a duplicate of it.
+    Collided,
+    /// The chunk failed permanently or exhausted its retries and was dead-lettered.
+    Failed,
+}
+
+impl IngestionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IngestionStatus::Queued => "queued",
+            IngestionStatus::Processing => "processing",
+            IngestionStatus::Indexed => "indexed",
+            IngestionStatus::Collided => "collided",
+            IngestionStatus::Failed => "failed",
+        }
+    }
+}
+
+impl From<String> for IngestionStatus {
+    fn from(status: String) -> Self {
+        match status.as_str() {
+            "processing" => IngestionStatus::Processing,
+            "indexed" => IngestionStatus::Indexed,
+            "collided" => IngestionStatus::Collided,
+            "failed" => IngestionStatus::Failed,
+            _ => IngestionStatus::Queued,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable, Insertable, Selectable, ToSchema)]
+#[schema(example = json!({
+    "chunk_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "batch_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "tracking_id": "evidence-1234",
+    "status": "indexed",
+    "error": null,
+    "collision_chunk_id": null,
+    "created_at": "2021-01-01T00:00:00",
+    "updated_at": "2021-01-01T00:00:00",
+}))]
+#[diesel(table_name = chunk_ingestion_statuses)]
+pub struct ChunkIngestionStatus {
+    /// Id of the chunk as returned when it was queued.
+    pub chunk_id: uuid::Uuid,
+    pub dataset_id: uuid::Uuid,
+    /// Id of the request which last queued the chunk.
+    pub batch_id: uuid::Uuid,
+    pub tracking_id: Option<String>,
+    /// Can be "queued", "processing", "indexed", "collided", or "failed".
+    pub status: String,
+    /// Why the last attempt failed. Set while a failed chunk waits for a retry and once it has failed.
+    pub error: Option<String>,
+    /// Id of the chunk a collided chunk was stored as a duplicate of.
+    pub collision_chunk_id: Option<uuid::Uuid>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl ChunkIngestionStatus {
+    pub fn from_details(
+        chunk_id: uuid::Uuid,
+        dataset_id: uuid::Uuid,
+        batch_id: uuid::Uuid,
+        tracking_id: Option<String>,
+    ) -> Self {
+        ChunkIngestionStatus {
+            chunk_id,
+            dataset_id,
+            batch_id,
+            tracking_id,
+            status: IngestionStatus::Queued.as_str().to_string(),
+            error: None,
+            collision_chunk_id: None,
+            created_at: chrono::Utc::now().naive_local(),
+            updated_at: chrono::Utc::now().naive_local(),
+        }
+    }
+}
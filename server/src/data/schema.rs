@@ -1,5 +1,26 @@
 // @generated automatically by Diesel CLI.
 
+pub mod sql_types {
+    #[derive(diesel::query_builder::QueryId, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "tsvector", schema = "pg_catalog"))]
+    pub struct Tsvector;
+}
+
+diesel::table! {
+    audit_log (id) {
+        id -> Uuid,
+        organization_id -> Uuid,
+        dataset_id -> Nullable<Uuid>,
+        user_id -> Nullable<Uuid>,
+        api_key_id -> Nullable<Uuid>,
+        action -> Text,
+        resource_type -> Text,
+        resource_id -> Nullable<Text>,
+        details -> Jsonb,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     chunk_collisions (id) {
         id -> Uuid,
@@ -43,6 +64,23 @@ diesel::table! {
 }
 
 diesel::table! {
+    chunk_ingestion_statuses (chunk_id) {
+        chunk_id -> Uuid,
+        dataset_id -> Uuid,
+        batch_id -> Uuid,
+        tracking_id -> Nullable<Text>,
+        status -> Text,
+        error -> Nullable<Text>,
+        collision_chunk_id -> Nullable<Uuid>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Tsvector;
+
     chunk_metadata (id) {
         id -> Uuid,
         content -> Text,
@@ -57,6 +95,7 @@ diesel::table! {
         time_stamp -> Nullable<Timestamp>,
         dataset_id -> Uuid,
         weight -> Float8,
+        chunk_metadata_tsvector -> Nullable<Tsvector>,
     }
 }
 
@@ -68,6 +107,13 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    dataset_fulltext_reindexes (dataset_id) {
+        dataset_id -> Uuid,
+        requested_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     dataset_group_counts (id) {
         id -> Uuid,
@@ -185,6 +231,46 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    search_feedback (id) {
+        id -> Uuid,
+        search_id -> Uuid,
+        dataset_id -> Uuid,
+        chunk_id -> Uuid,
+        position -> Int4,
+        feedback_type -> Text,
+        group_id -> Nullable<Uuid>,
+        rating -> Nullable<Int4>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    search_queries (id) {
+        id -> Uuid,
+        dataset_id -> Uuid,
+        search_type -> Text,
+        query -> Text,
+        filters_hash -> Nullable<Text>,
+        result_count -> Int4,
+        top_score -> Nullable<Float8>,
+        latency_ms -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    search_rules (id) {
+        id -> Uuid,
+        dataset_id -> Uuid,
+        query_pattern -> Text,
+        match_type -> Text,
+        actions -> Jsonb,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     stripe_plans (id) {
         id -> Uuid,
@@ -261,13 +347,43 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    webhook_deliveries (id) {
+        id -> Uuid,
+        webhook_id -> Uuid,
+        event_id -> Uuid,
+        status -> Text,
+        attempt_count -> Int4,
+        response_status -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        next_attempt_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    webhooks (id) {
+        id -> Uuid,
+        dataset_id -> Uuid,
+        url -> Text,
+        secret -> Text,
+        event_types -> Jsonb,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::joinable!(audit_log -> organizations (organization_id));
 diesel::joinable!(chunk_files -> chunk_metadata (chunk_id));
 diesel::joinable!(chunk_files -> files (file_id));
 diesel::joinable!(chunk_group -> datasets (dataset_id));
 diesel::joinable!(chunk_group_bookmarks -> chunk_group (group_id));
 diesel::joinable!(chunk_group_bookmarks -> chunk_metadata (chunk_metadata_id));
+diesel::joinable!(chunk_ingestion_statuses -> datasets (dataset_id));
 diesel::joinable!(chunk_metadata -> datasets (dataset_id));
 diesel::joinable!(dataset_event_counts -> datasets (dataset_uuid));
+diesel::joinable!(dataset_fulltext_reindexes -> datasets (dataset_id));
 diesel::joinable!(dataset_usage_counts -> datasets (dataset_id));
 diesel::joinable!(datasets -> organizations (organization_id));
 diesel::joinable!(events -> datasets (dataset_id));
@@ -277,6 +393,10 @@ diesel::joinable!(groups_from_files -> files (file_id));
 diesel::joinable!(messages -> datasets (dataset_id));
 diesel::joinable!(messages -> topics (topic_id));
 diesel::joinable!(organization_usage_counts -> organizations (org_id));
+diesel::joinable!(search_feedback -> datasets (dataset_id));
+diesel::joinable!(search_feedback -> search_queries (search_id));
+diesel::joinable!(search_queries -> datasets (dataset_id));
+diesel::joinable!(search_rules -> datasets (dataset_id));
 diesel::joinable!(stripe_subscriptions -> organizations (organization_id));
 diesel::joinable!(stripe_subscriptions -> stripe_plans (plan_id));
 diesel::joinable!(topics -> datasets (dataset_id));
@@ -284,14 +404,20 @@ diesel::joinable!(topics -> users (user_id));
 diesel::joinable!(user_api_key -> users (user_id));
 diesel::joinable!(user_organizations -> organizations (organization_id));
 diesel::joinable!(user_organizations -> users (user_id));
+diesel::joinable!(webhook_deliveries -> events (event_id));
+diesel::joinable!(webhook_deliveries -> webhooks (webhook_id));
+diesel::joinable!(webhooks -> datasets (dataset_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    audit_log,
     chunk_collisions,
     chunk_files,
     chunk_group,
     chunk_group_bookmarks,
+    chunk_ingestion_statuses,
     chunk_metadata,
     dataset_event_counts,
+    dataset_fulltext_reindexes,
     dataset_group_counts,
     dataset_usage_counts,
     datasets,
@@ -302,10 +428,15 @@ diesel::allow_tables_to_appear_in_same_query!(
     messages,
     organization_usage_counts,
     organizations,
+    search_feedback,
+    search_queries,
+    search_rules,
     stripe_plans,
     stripe_subscriptions,
     topics,
     user_api_key,
     user_organizations,
     users,
+    webhook_deliveries,
+    webhooks,
 );
@@ -0,0 +1,355 @@
+use super::auth_handler::{AdminOnly, LoggedUser};
+use crate::{
+    data::models::{DatasetAndOrgWithSubAndPlan, Pool, SearchFeedback, SearchFeedbackType},
+    errors::ServiceError,
+    operators::{
+        analytics_operator::{
+            create_search_feedback_query, get_query_counts_query, get_search_latency_query,
+            get_search_query_by_id_query,
+        },
+        group_operator::get_group_by_id_query,
+    },
+};
+use actix_web::{web, HttpResponse};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct SearchAnalyticsQuery {
+    /// Start of the time range, e.g. 2024-03-01T00:00:00. Defaults to 7 days before to.
+    pub from: Option<NaiveDateTime>,
+    /// End of the time range. Defaults to now.
+    pub to: Option<NaiveDateTime>,
+    /// Maximum number of queries to return. Defaults to 10 and cannot exceed 100.
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct SearchLatencyQuery {
+    /// Start of the time range, e.g. 2024-03-01T00:00:00. Defaults to 7 days before to.
+    pub from: Option<NaiveDateTime>,
+    /// End of the time range. Defaults to now.
+    pub to: Option<NaiveDateTime>,
+    /// Only include searches of this type, e.g. "semantic", "fulltext", or "hybrid". Includes every search type if not specified.
+    pub search_type: Option<String>,
+}
+
+fn get_time_range(
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+) -> Result<(NaiveDateTime, NaiveDateTime), ServiceError> {
+    let to = to.unwrap_or_else(|| chrono::Utc::now().naive_utc());
+    let from = from.unwrap_or(to - chrono::Duration::days(7));
+
+    if from > to {
+        return Err(ServiceError::BadRequest(
+            "from must be before to".to_string(),
+        ));
+    }
+
+    Ok((from, to))
+}
+
+fn get_query_limit(limit: Option<i64>) -> Result<i64, ServiceError> {
+    match limit.unwrap_or(10) {
+        limit if (1..=100).contains(&limit) => Ok(limit),
+        _ => Err(ServiceError::BadRequest(
+            "limit must be between 1 and 100".to_string(),
+        )),
+    }
+}
+
+/// Get Top Queries
+///
+/// Get the most frequent search queries of the dataset over a time range. Every chunk and group search is recorded. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    get,
+    path = "/analytics/top_queries",
+    context_path = "/api",
+    tag = "analytics",
+    responses(
+        (status = 200, description = "The most frequent queries along with how often they were searched", body = Vec<SearchQueryCount>),
+        (status = 400, description = "Service error relating to getting the top queries", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+        ("from" = Option<NaiveDateTime>, Query, description = "Start of the time range. Defaults to 7 days before to."),
+        ("to" = Option<NaiveDateTime>, Query, description = "End of the time range. Defaults to now."),
+        ("limit" = Option<i64>, Query, description = "Maximum number of queries to return. Defaults to 10 and cannot exceed 100."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn get_top_queries(
+    data: web::Query<SearchAnalyticsQuery>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (from, to) = get_time_range(data.from, data.to)?;
+    let limit = get_query_limit(data.limit)?;
+
+    let query_counts = get_query_counts_query(
+        dataset_org_plan_sub.dataset.id,
+        from,
+        to,
+        false,
+        limit,
+        pool,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(query_counts))
+}
+
+/// Get Zero Result Queries
+///
+/// Get the most frequent search queries of the dataset which returned no results over a time range. These are usually gaps in the content of the dataset. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    get,
+    path = "/analytics/zero_result_queries",
+    context_path = "/api",
+    tag = "analytics",
+    responses(
+        (status = 200, description = "The most frequent queries without results along with how often they were searched", body = Vec<SearchQueryCount>),
+        (status = 400, description = "Service error relating to getting the zero result queries", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+        ("from" = Option<NaiveDateTime>, Query, description = "Start of the time range. Defaults to 7 days before to."),
+        ("to" = Option<NaiveDateTime>, Query, description = "End of the time range. Defaults to now."),
+        ("limit" = Option<i64>, Query, description = "Maximum number of queries to return. Defaults to 10 and cannot exceed 100."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn get_zero_result_queries(
+    data: web::Query<SearchAnalyticsQuery>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (from, to) = get_time_range(data.from, data.to)?;
+    let limit = get_query_limit(data.limit)?;
+
+    let query_counts =
+        get_query_counts_query(dataset_org_plan_sub.dataset.id, from, to, true, limit, pool)
+            .await
+            .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(query_counts))
+}
+
+/// Get Search Latency
+///
+/// Get the number of searches of the dataset and their p50 and p95 latency in milliseconds over a time range. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    get,
+    path = "/analytics/latency",
+    context_path = "/api",
+    tag = "analytics",
+    responses(
+        (status = 200, description = "Latency percentiles of the searches in the time range", body = SearchLatencyStats),
+        (status = 400, description = "Service error relating to getting the search latency", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+        ("from" = Option<NaiveDateTime>, Query, description = "Start of the time range. Defaults to 7 days before to."),
+        ("to" = Option<NaiveDateTime>, Query, description = "End of the time range. Defaults to now."),
+        ("search_type" = Option<String>, Query, description = "Only include searches of this type. Includes every search type if not specified."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn get_search_latency(
+    data: web::Query<SearchLatencyQuery>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (from, to) = get_time_range(data.from, data.to)?;
+
+    let latency_stats = get_search_latency_query(
+        dataset_org_plan_sub.dataset.id,
+        from,
+        to,
+        data.search_type.clone(),
+        pool,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(latency_stats))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[schema(example = json!({
+    "search_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "chunk_id": "d290f1ee-6c54-4b01-90e6-d701748f0851",
+    "position": 3,
+    "group_id": null
+}))]
+pub struct SearchClickData {
+    /// The search_id of the search response the chunk was in.
+    pub search_id: uuid::Uuid,
+    /// Id of the chunk which was clicked.
+    pub chunk_id: uuid::Uuid,
+    /// Position of the chunk in the results of the search, starting at 1 for the first result of the first page.
+    pub position: i32,
+    /// Set group_id to the group the chunk was bookmarked into to record a bookmark instead of an open.
+    pub group_id: Option<uuid::Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[schema(example = json!({
+    "search_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "chunk_id": "d290f1ee-6c54-4b01-90e6-d701748f0851",
+    "position": 3,
+    "rating": 1
+}))]
+pub struct SearchFeedbackData {
+    /// The search_id of the search response the chunk was in.
+    pub search_id: uuid::Uuid,
+    /// Id of the chunk which was rated.
+    pub chunk_id: uuid::Uuid,
+    /// Position of the chunk in the results of the search, starting at 1 for the first result of the first page.
+    pub position: i32,
+    /// 1 if the chunk was a good result for the search and -1 if it was not.
+    pub rating: i32,
+}
+
+fn validate_position(position: i32) -> Result<(), ServiceError> {
+    if position < 1 {
+        return Err(ServiceError::BadRequest(
+            "position must be at least 1".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Record Click
+///
+/// Record that a user opened a chunk from the results of a search, or bookmarked it into a group if group_id is set. Clicks are stored with the search_id so they can be joined with the search log to get click-through rates per query and per chunk.
+#[utoipa::path(
+    post,
+    path = "/analytics/click",
+    context_path = "/api",
+    tag = "analytics",
+    request_body(content = SearchClickData, description = "JSON request payload to record a click", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The recorded click", body = SearchFeedback),
+        (status = 400, description = "Service error relating to recording the click", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+    ),
+    security(
+        ("ApiKey" = ["readonly"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn record_search_click(
+    data: web::Json<SearchClickData>,
+    _user: LoggedUser,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    validate_position(data.position)?;
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+
+    let search_query = get_search_query_by_id_query(data.search_id, dataset_id, pool.clone())
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    let feedback_type = match data.group_id {
+        Some(group_id) => {
+            get_group_by_id_query(group_id, dataset_id, pool.clone())
+                .await
+                .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+            SearchFeedbackType::Bookmark
+        }
+        None => SearchFeedbackType::Click,
+    };
+
+    let search_feedback = create_search_feedback_query(
+        SearchFeedback::from_details(
+            &search_query,
+            data.chunk_id,
+            data.position,
+            feedback_type,
+            data.group_id,
+            None,
+        ),
+        pool,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(search_feedback))
+}
+
+/// Record Feedback
+///
+/// Record a rating a user gave to a chunk from the results of a search. Ratings are stored with the search_id so they can be joined with the search log.
+#[utoipa::path(
+    post,
+    path = "/analytics/feedback",
+    context_path = "/api",
+    tag = "analytics",
+    request_body(content = SearchFeedbackData, description = "JSON request payload to record a rating", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The recorded rating", body = SearchFeedback),
+        (status = 400, description = "Service error relating to recording the rating", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+    ),
+    security(
+        ("ApiKey" = ["readonly"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn record_search_feedback(
+    data: web::Json<SearchFeedbackData>,
+    _user: LoggedUser,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    validate_position(data.position)?;
+    if data.rating != 1 && data.rating != -1 {
+        return Err(ServiceError::BadRequest("rating must be 1 or -1".to_string()).into());
+    }
+
+    let search_query = get_search_query_by_id_query(
+        data.search_id,
+        dataset_org_plan_sub.dataset.id,
+        pool.clone(),
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    let search_feedback = create_search_feedback_query(
+        SearchFeedback::from_details(
+            &search_query,
+            data.chunk_id,
+            data.position,
+            SearchFeedbackType::Rating,
+            None,
+            Some(data.rating),
+        ),
+        pool,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(search_feedback))
+}
@@ -0,0 +1,99 @@
+use super::auth_handler::OwnerOnly;
+use crate::{
+    data::models::{Pool, UserRole},
+    errors::ServiceError,
+    operators::audit_operator::{get_audit_logs_query, AuditLogFilter},
+};
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema, IntoParams)]
+#[into_params(style = Form, parameter_in = Query)]
+pub struct GetAuditLogQuery {
+    /// Only include entries with this action. One of api_key_created, api_key_deleted, dataset_created, dataset_updated, dataset_deleted, organization_updated, user_role_changed, invitation_sent, file_deleted, or group_deleted.
+    pub action: Option<String>,
+    /// Only include entries for this kind of resource. One of api_key, dataset, organization, user, invitation, file, or group.
+    pub resource_type: Option<String>,
+    /// Only include actions taken by this user.
+    pub user_id: Option<uuid::Uuid>,
+    /// Only include actions taken with this api key.
+    pub api_key_id: Option<uuid::Uuid>,
+    /// Only include actions taken in this dataset.
+    pub dataset_id: Option<uuid::Uuid>,
+    /// Only include actions taken at or after this time, e.g. 2024-03-01T00:00:00.
+    pub from: Option<chrono::NaiveDateTime>,
+    /// Only include actions taken at or before this time.
+    pub to: Option<chrono::NaiveDateTime>,
+    /// The page number to get. Default is 1.
+    pub page: Option<i64>,
+    /// The number of items per page. Default is 10 and cannot exceed 100.
+    pub page_size: Option<i64>,
+}
+
+/// Get Audit Log
+///
+/// Get the audit log of an organization, newest first. The log records who created and deleted api keys, created, updated, and deleted datasets, updated the organization, changed roles, sent invitations, and deleted files and groups. The auth'ed user must be an owner of the organization.
+#[utoipa::path(
+    get,
+    path = "/organization/audit_log/{organization_id}",
+    context_path = "/api",
+    tag = "organization",
+    responses(
+        (status = 200, description = "The page of audit log entries along with the number of pages", body = AuditLogPage),
+        (status = 400, description = "Service error relating to getting the audit log", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Organization" = String, Header, description = "The organization id to use for the request"),
+        ("organization_id" = uuid::Uuid, Path, description = "The id of the organization you want to fetch the audit log of."),
+        GetAuditLogQuery,
+    ),
+    security(
+        ("ApiKey" = ["owner"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn get_audit_logs(
+    organization_id: web::Path<uuid::Uuid>,
+    data: web::Query<GetAuditLogQuery>,
+    user: OwnerOnly,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let organization_id = organization_id.into_inner();
+    let data = data.into_inner();
+
+    if !user.0.user_orgs.iter().any(|user_org| {
+        user_org.organization_id == organization_id
+            && UserRole::from(user_org.role) == UserRole::Owner
+    }) {
+        return Err(ServiceError::Forbidden.into());
+    }
+
+    let page = data.page.unwrap_or(1).max(1);
+    let page_size = data.page_size.unwrap_or(10);
+    if !(1..=100).contains(&page_size) {
+        return Err(
+            ServiceError::BadRequest("page_size must be between 1 and 100".to_string()).into(),
+        );
+    }
+
+    let audit_log_page = get_audit_logs_query(
+        organization_id,
+        AuditLogFilter {
+            action: data.action,
+            resource_type: data.resource_type,
+            user_id: data.user_id,
+            api_key_id: data.api_key_id,
+            dataset_id: data.dataset_id,
+            from: data.from,
+            to: data.to,
+        },
+        page,
+        page_size,
+        pool,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(audit_log_page))
+}
@@ -56,6 +56,32 @@ impl FromRequest for LoggedUser {
     }
 }
 
+/// Id of the api key a request was authenticated with. Only set by the auth middleware for requests with an Authorization header.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthenticatedApiKey(pub uuid::Uuid);
+
+/// Who is making the request, for the audit log. Both ids are None for unauthenticated requests.
+#[derive(Debug, Clone, Copy)]
+pub struct AuditActor {
+    pub user_id: Option<uuid::Uuid>,
+    pub api_key_id: Option<uuid::Uuid>,
+}
+
+impl FromRequest for AuditActor {
+    type Error = Error;
+    type Future = Ready<Result<AuditActor, Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let ext = req.extensions();
+
+        ready(Ok(AuditActor {
+            user_id: ext.get::<LoggedUser>().map(|user| user.id),
+            api_key_id: ext.get::<AuthenticatedApiKey>().map(|api_key| api_key.0),
+        }))
+    }
+}
+
 #[derive(Debug)]
 pub struct OrganizationRole {
     pub user: SlimUser,
@@ -1,17 +1,42 @@
 use super::auth_handler::{AdminOnly, LoggedUser};
 use crate::data::models::{
-    ChatMessageProxy, ChunkMetadata, ChunkMetadataWithFileData, DatasetAndOrgWithSubAndPlan, Pool,
-    RedisPool, ServerDatasetConfiguration, UnifiedId,
+    ChatMessageProxy, ChunkIngestionStatus, ChunkMetadata, ChunkMetadataWithFileData, Dataset,
+    DatasetAndOrgWithSubAndPlan, IngestionStatus, Pool, RecencyDecay, RedisPool,
+    ServerDatasetConfiguration, UnifiedId, UserRole,
 };
 use crate::errors::ServiceError;
 use crate::get_env;
+use crate::operators::analytics_operator::log_search_query;
+use crate::operators::cache_operator::{
+    bump_search_cache_generation, create_query_embeddings_with_cache, get_cached_search_response,
+    get_search_cache_key, set_cached_search_response,
+};
 use crate::operators::chunk_operator::get_metadata_from_id_query;
 use crate::operators::chunk_operator::*;
+use crate::operators::dataset_operator::get_dataset_by_id_query;
+use crate::operators::diversity_operator::{
+    get_diversity_candidate_limit, get_diversity_total_pages,
+};
+use crate::operators::facet_operator::{get_facet_counts_query, FacetResult};
 use crate::operators::group_operator::get_groups_from_tracking_ids_query;
+use crate::operators::highlight_operator::ChunkHighlights;
+use crate::operators::ingestion_queue_operator::queue_ingestion_messages;
+use crate::operators::ingestion_status_operator::{
+    create_chunk_ingestion_statuses_query, update_chunk_ingestion_statuses_query,
+};
+use crate::operators::llm_operator::{rewrite_query, DatasetLlmProvider};
 use crate::operators::parse_operator::convert_html_to_text;
 use crate::operators::qdrant_operator::recommend_qdrant_query;
+use crate::operators::query_operator::{compile_query, parse_query_ast, TextFilter};
 use crate::operators::search_operator::{
-    search_full_text_chunks, search_hybrid_chunks, search_semantic_chunks,
+    decode_search_cursor, finish_explanations, get_federated_candidate_limit,
+    get_federated_total_pages, get_fused_ranking_window, get_fused_total_pages, get_query_vector,
+    get_rank_offset, normalize_dataset_scores, page_fused_results, search_full_text_chunks,
+    search_hybrid_chunks, search_semantic_chunks,
+};
+use crate::operators::search_rule_operator::{
+    apply_search_rules, diversify_search_rule_page, get_matching_search_rules_query,
+    get_missing_pinned_chunks_query,
 };
 use actix_web::web::Bytes;
 use actix_web::{web, HttpResponse};
@@ -22,10 +47,10 @@ use openai_dive::v1::api::Client;
 use openai_dive::v1::resources::chat::{
     ChatCompletionParameters, ChatMessage, ChatMessageContent, Role,
 };
-use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use simple_server_timing_header::Timer;
+use std::collections::HashMap;
 use tokio_stream::StreamExt;
 use utoipa::ToSchema;
 
@@ -101,13 +126,16 @@ pub enum ReturnQueuedChunk {
         "time_stamp": "2021-01-01T00:00:00",
         "weight": 0.5
     }],
-    "pos_in_queue": 1
+    "pos_in_queue": 1,
+    "batch_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3"
 }))]
 pub struct SingleQueuedChunkResponse {
     /// The chunk that got queue'd
     pub chunk_metadata: ChunkMetadata,
     /// The current position the last access item is in the queue
     pub pos_in_queue: i32,
+    /// Id of the batch the chunk was queued in. The ingestion status of the chunk can be fetched by its id or by this batch id.
+    pub batch_id: uuid::Uuid,
 }
 
 #[derive(Serialize, Deserialize, Clone, ToSchema)]
@@ -134,13 +162,16 @@ pub struct SingleQueuedChunkResponse {
         "time_stamp": "2021-01-01T00:00:00",
         "weight": 0.5
     }],
-    "pos_in_queue": 2
+    "pos_in_queue": 2,
+    "batch_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3"
 }))]
 pub struct BatchQueuedChunkResponse {
     // All the chunks that got queue'd
     pub chunk_metadata: Vec<ChunkMetadata>,
     /// The current position the last access item is in the queue
     pub pos_in_queue: i32,
+    /// Id of the batch the chunks were queued in. Fetch the ingestion status of the batch with it to know when every chunk is searchable.
+    pub batch_id: uuid::Uuid,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -210,6 +241,30 @@ pub enum CreateChunkData {
     Batch(CreateBatchChunkData),
 }
 
+/// Queues the messages of chunks whose ingestion statuses were already recorded, so that workers always find the statuses. If the messages cannot be queued the statuses are failed rather than left queued forever. Returns the length of the queue after the push.
+async fn queue_ingestion_messages_or_fail_statuses(
+    chunk_ids: Vec<uuid::Uuid>,
+    payloads: &[String],
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<i32, ServiceError> {
+    match queue_ingestion_messages(payloads, redis_pool).await {
+        Ok(pos_in_queue) => Ok(pos_in_queue),
+        Err(err) => {
+            let _ = update_chunk_ingestion_statuses_query(
+                chunk_ids,
+                IngestionStatus::Failed,
+                Some(err.message.to_string()),
+                None,
+                pool,
+            )
+            .await;
+
+            Err(ServiceError::BadRequest(err.message.into()))
+        }
+    }
+}
+
 /// Create or Upsert Chunk or Chunks
 ///
 /// Create a new chunk. If the chunk has the same tracking_id as an existing chunk, the request will fail. Once a chunk is created, it can be searched for using the search endpoint.
@@ -347,23 +402,41 @@ pub async fn create_chunk(
         ingestion_messages.push(upload_message);
     }
 
-    let mut redis_conn = redis_pool
-        .get()
-        .await
-        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
-    timer.add("got redis connection");
+    let batch_id = uuid::Uuid::new_v4();
+    create_chunk_ingestion_statuses_query(
+        ingestion_messages
+            .iter()
+            .map(|message| {
+                ChunkIngestionStatus::from_details(
+                    message.chunk_metadata.id,
+                    message.dataset_id,
+                    batch_id,
+                    message.chunk_metadata.tracking_id.clone(),
+                )
+            })
+            .collect(),
+        pool.clone(),
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+    timer.add("created ingestion statuses");
 
     let serialized_messages: Vec<String> = ingestion_messages
         .iter()
         .filter_map(|msg| serde_json::to_string(&msg).ok())
         .collect();
 
-    let pos_in_queue = redis::cmd("lpush")
-        .arg("ingestion")
-        .arg(&serialized_messages)
-        .query_async(&mut *redis_conn)
-        .await
-        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+    let pos_in_queue = queue_ingestion_messages_or_fail_statuses(
+        ingestion_messages
+            .iter()
+            .map(|message| message.chunk_metadata.id)
+            .collect(),
+        &serialized_messages,
+        pool,
+        redis_pool,
+    )
+    .await?;
+    timer.add("queued ingestion messages");
 
     let chunk_metadatas: Vec<ChunkMetadata> = ingestion_messages
         .iter()
@@ -379,10 +452,12 @@ pub async fn create_chunk(
                 ))?
                 .clone(),
             pos_in_queue,
+            batch_id,
         }),
         CreateChunkData::Batch(_) => ReturnQueuedChunk::Batch(BatchQueuedChunkResponse {
             chunk_metadata: chunk_metadatas,
             pos_in_queue,
+            batch_id,
         }),
     };
 
@@ -411,10 +486,11 @@ pub async fn create_chunk(
         ("ApiKey" = ["admin"]),
     )
 )]
-#[tracing::instrument(skip(pool))]
+#[tracing::instrument(skip(pool, redis_pool))]
 pub async fn delete_chunk(
     chunk_id: web::Path<uuid::Uuid>,
     pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
     _user: AdminOnly,
     dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
 ) -> Result<HttpResponse, actix_web::Error> {
@@ -423,6 +499,7 @@ pub async fn delete_chunk(
     );
 
     let chunk_id = chunk_id.into_inner();
+    let dataset_id = dataset_org_plan_sub.dataset.id;
 
     delete_chunk_metadata_query(
         chunk_id,
@@ -433,6 +510,8 @@ pub async fn delete_chunk(
     .await
     .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
 
+    let _ = bump_search_cache_generation(dataset_id, redis_pool).await;
+
     Ok(HttpResponse::NoContent().finish())
 }
 
@@ -456,10 +535,11 @@ pub async fn delete_chunk(
         ("ApiKey" = ["admin"]),
     )
 )]
-#[tracing::instrument(skip(pool))]
+#[tracing::instrument(skip(pool, redis_pool))]
 pub async fn delete_chunk_by_tracking_id(
     tracking_id: web::Path<String>,
     pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
     _user: AdminOnly,
     dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
 ) -> Result<HttpResponse, actix_web::Error> {
@@ -483,6 +563,8 @@ pub async fn delete_chunk_by_tracking_id(
     .await
     .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
 
+    let _ = bump_search_cache_generation(dataset_id, redis_pool).await;
+
     Ok(HttpResponse::NoContent().finish())
 }
 
@@ -561,11 +643,11 @@ pub async fn update_chunk(
     let chunk_id = chunk.chunk_id;
 
     let chunk_metadata = if let Some(chunk_id) = chunk_id {
-        get_metadata_from_id_query(chunk_id, dataset_id, pool)
+        get_metadata_from_id_query(chunk_id, dataset_id, pool.clone())
             .await
             .map_err(|err| ServiceError::BadRequest(err.message.into()))?
     } else if let Some(tracking_id) = chunk.tracking_id.clone() {
-        get_metadata_from_tracking_id_query(tracking_id.clone(), dataset_id, pool)
+        get_metadata_from_tracking_id_query(tracking_id.clone(), dataset_id, pool.clone())
             .await
             .map_err(|err| ServiceError::BadRequest(err.message.into()))?
     } else {
@@ -643,17 +725,25 @@ pub async fn update_chunk(
         group_ids,
     };
 
-    let mut redis_conn = redis_pool
-        .get()
-        .await
-        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+    create_chunk_ingestion_statuses_query(
+        vec![ChunkIngestionStatus::from_details(
+            message.chunk_metadata.id,
+            dataset_id,
+            uuid::Uuid::new_v4(),
+            message.chunk_metadata.tracking_id.clone(),
+        )],
+        pool.clone(),
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
 
-    redis::cmd("lpush")
-        .arg("ingestion")
-        .arg(serde_json::to_string(&message)?)
-        .query_async(&mut *redis_conn)
-        .await
-        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+    queue_ingestion_messages_or_fail_statuses(
+        vec![message.chunk_metadata.id],
+        &[serde_json::to_string(&message)?],
+        pool,
+        redis_pool,
+    )
+    .await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
@@ -787,17 +877,25 @@ pub async fn update_chunk_by_tracking_id(
         group_ids,
     };
 
-    let mut redis_conn = redis_pool
-        .get()
-        .await
-        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+    create_chunk_ingestion_statuses_query(
+        vec![ChunkIngestionStatus::from_details(
+            message.chunk_metadata.id,
+            dataset_id,
+            uuid::Uuid::new_v4(),
+            message.chunk_metadata.tracking_id.clone(),
+        )],
+        pool.clone(),
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
 
-    redis::cmd("lpush")
-        .arg("ingestion")
-        .arg(serde_json::to_string(&message)?)
-        .query_async(&mut *redis_conn)
-        .await
-        .map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+    queue_ingestion_messages_or_fail_statuses(
+        vec![message.chunk_metadata.id],
+        &[serde_json::to_string(&message)?],
+        pool,
+        redis_pool,
+    )
+    .await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
@@ -912,6 +1010,70 @@ pub struct ChunkFilter {
     pub must_not: Option<Vec<FieldCondition>>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FusionStrategy {
+    /// Reciprocal rank fusion. Each result is scored by the sum of 1 / (60 + rank) over the semantic and full-text result lists.
+    Rrf,
+    /// Min-max normalize each result list then blend them as alpha * semantic + (1 - alpha) * full-text.
+    WeightedLinear,
+    /// Re-rank the union of both result lists with the cross encoder model. Falls back to rrf if the reranker server cannot be reached.
+    CrossEncoder,
+}
+
+impl FusionStrategy {
+    /// Resolves the strategy to use when the request does not specify one. The cross encoder is only the default when a reranker server is configured.
+    pub fn resolve(requested: Option<FusionStrategy>) -> FusionStrategy {
+        match requested {
+            Some(strategy) => strategy,
+            None => {
+                let reranker_configured = ["RERANKER_SERVER_ORIGIN", "GPU_SERVER_ORIGIN"]
+                    .iter()
+                    .any(|name| std::env::var(name).is_ok_and(|origin| !origin.is_empty()));
+
+                if reranker_configured {
+                    FusionStrategy::CrossEncoder
+                } else {
+                    FusionStrategy::Rrf
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RewriteQueryMode {
+    /// Hypothetical document embeddings. The LLM writes a passage answering the query with the RAG_PROMPT of the dataset and the passage is embedded instead of the query.
+    Hyde,
+    /// The LLM writes up to 3 alternative phrasings of the query and they are embedded together with the query.
+    Expand,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreNormalization {
+    /// Min-max normalize the scores of each dataset to between 0 and 1 before merging.
+    #[default]
+    MinMax,
+    /// Score each result by 1 / (60 + rank) within its own dataset. Ignores the raw scores entirely, which suits datasets using different embedding models.
+    Rrf,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[schema(example = json!({
+    "mmr_lambda": 0.7,
+    "max_per_link": 2
+}))]
+pub struct DiversifyOptions {
+    /// Mmr_lambda re-selects results with maximal marginal relevance, using the dense vectors of the chunks to penalize results which are similar to ones already picked. 1 keeps the relevance order and 0 always picks the result least like those already picked. Must be between 0 and 1. If not specified, results are only capped.
+    pub mmr_lambda: Option<f32>,
+    /// Max_per_link is the most results which can share the same link. Results past the cap are dropped.
+    pub max_per_link: Option<u32>,
+    /// Max_per_file is the most results which can come from the same file. Results past the cap are dropped.
+    pub max_per_file: Option<u32>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 #[schema(example = json!({
     "search_type": "semantic",
@@ -961,31 +1123,114 @@ pub struct ChunkFilter {
     "get_collisions": true,
     "highlight_results": true,
     "highlight_delimiters": ["?", ",", ".", "!"],
-    "score_threshold": 0.5
+    "highlight_window": 10,
+    "highlight_max_snippets": 3,
+    "score_threshold": 0.5,
+    "fusion_strategy": "weighted_linear",
+    "fusion_alpha": 0.7,
+    "facets": ["tag_set", "metadata.author", "time_stamp"]
 }))]
 pub struct SearchChunkData {
-    /// Can be either "semantic", "fulltext", or "hybrid". "hybrid" will pull in one page (10 chunks) of both semantic and full-text results then re-rank them using BAAI/bge-reranker-large. "semantic" will pull in one page (10 chunks) of the nearest cosine distant vectors. "fulltext" will pull in one page (10 chunks) of full-text results based on SPLADE.
+    /// Can be either "semantic", "fulltext", or "hybrid". "hybrid" will pull in one page (10 chunks) of both semantic and full-text results then combine them using the fusion_strategy. "semantic" will pull in one page (10 chunks) of the nearest cosine distant vectors. "fulltext" will pull in one page (10 chunks) of full-text results based on SPLADE.
     pub search_type: String,
-    /// Query is the search query. This can be any string. The query will be used to create an embedding vector and/or SPLADE vector which will be used to find the result set.
+    /// Query is the search query. This can be any string. The query will be used to create an embedding vector and/or SPLADE vector which will be used to find the result set. Terms can be combined with AND, OR, NOT (or a leading -), and parentheses. Use "double quotes" for phrases, a trailing * for prefixes, and field:value to filter on tag_set, link, time_stamp, or metadata.<key> (e.g. tag_set:news, metadata.price:>=10, time_stamp:2024-01). Bare words separated by spaces are only used for similarity; quoted, prefixed, and operator-joined terms must appear in the chunk. Negated terms and field filters are not embedded. Malformed queries are rejected with a syntax error. Can be left out when query_vector, query_chunk_id, or query_tracking_id is set.
+    #[serde(default)]
     pub query: String,
     /// Page of chunks to fetch. Each page is 10 chunks. Support for custom page size is coming soon.
     pub page: Option<u64>,
     /// Page size is the number of chunks to fetch. This can be used to fetch more than 10 chunks at a time.
     pub page_size: Option<u64>,
+    /// Cursor is the value returned in the cursor field of a previous search response. If specified, page is ignored and results continue after the last chunk of that response. Use this to page deterministically through large result sets. Hybrid search pages through a fixed ranking of its top results, at most 1000, so its cursors end there.
+    pub cursor: Option<String>,
     /// Filters is a JSON object which can be used to filter chunks. This is useful for when you want to filter chunks by arbitrary metadata. Unlike with tag filtering, there is a performance hit for filtering on metadata.
     pub filters: Option<ChunkFilter>,
-    /// Set date_bias to true to bias search results towards more recent chunks. This will work best in hybrid search mode.
+    /// Set date_bias to true to boost more recent chunks with the recency decay of the dataset, or the default decay if the dataset does not set one. Set it to false to turn recency decay off.
     pub date_bias: Option<bool>,
+    /// Recency_decay blends the age of each chunk into its score. Older chunks are multiplied by a decay curve so recent chunks rank higher without burying relevant older ones. Overrides the RECENCY_DECAY of the dataset and applies unless date_bias is false.
+    pub recency_decay: Option<RecencyDecay>,
     /// Set use_weights to true to use the weights of the chunks in the result set in order to sort them. If not specified, this defaults to true.
     pub use_weights: Option<bool>,
     /// Set get_collisions to true to get the collisions for each chunk. This will only apply if environment variable COLLISIONS_ENABLED is set to true.
     pub get_collisions: Option<bool>,
-    /// Set highlight_results to true to highlight the results. If not specified, this defaults to true.
+    /// Set highlight_results to true to return the highlights field on each score chunk with the spans of content that matched the query and snippets around them. Chunk_html is never modified. If not specified, this defaults to true.
     pub highlight_results: Option<bool>,
-    /// Set highlight_delimiters to a list of strings to use as delimiters for highlighting. If not specified, this defaults to ["?", ",", ".", "!"].
+    /// Set highlight_delimiters to a list of strings which end a sentence. Only used when none of the query terms appear in a chunk, in which case the sentences most similar to the query are highlighted instead. If not specified, this defaults to [".", "!", "?", "\n", "\t", ","].
     pub highlight_delimiters: Option<Vec<String>>,
+    /// Highlight_window is the number of words of context to keep on each side of a match in a snippet. If not specified, this defaults to 10.
+    pub highlight_window: Option<u32>,
+    /// Highlight_max_snippets is the maximum number of snippets returned for each chunk. Snippets covering the most distinct query terms are kept. If not specified, this defaults to 3.
+    pub highlight_max_snippets: Option<u32>,
     /// Set score_threshold to a float to filter out chunks with a score below the threshold.
     pub score_threshold: Option<f32>,
+    /// Fusion_strategy controls how semantic and full-text results are combined in hybrid search. Can be "rrf", "weighted_linear", or "cross_encoder". If not specified, this defaults to "cross_encoder" when a reranker server is configured and "rrf" otherwise.
+    pub fusion_strategy: Option<FusionStrategy>,
+    /// Fusion_alpha is the weight given to the semantic scores when fusion_strategy is "weighted_linear". Must be between 0 and 1. If not specified, this defaults to 0.5.
+    pub fusion_alpha: Option<f32>,
+    /// Facets is a list of fields to count values for over every chunk matching the filters. Can be "tag_set", "link", "metadata.<key>", or "time_stamp". Time stamps are bucketed by month by default, use "time_stamp:day" or "time_stamp:year" for other intervals.
+    pub facets: Option<Vec<String>>,
+    /// Query_vector is a dense vector to search with instead of the embedding of the query. It must have the same number of dimensions as the embeddings of the dataset. Use this to search with vectors from your own embedding model. If a text query is also given, it is still used for the full-text results of hybrid search and for field filters.
+    pub query_vector: Option<Vec<f32>>,
+    /// Query_chunk_id is the id of a chunk in the dataset whose vector is used as the query vector. The chunk itself will usually be the top result.
+    pub query_chunk_id: Option<uuid::Uuid>,
+    /// Query_tracking_id is the tracking_id of a chunk in the dataset whose vector is used as the query vector. The chunk itself will usually be the top result.
+    pub query_tracking_id: Option<String>,
+    /// Set explain to true to return every score which shaped the rank of each chunk in the explain field of the score chunks. If not specified, this defaults to false.
+    pub explain: Option<bool>,
+    /// Diversify keeps one page of results from being filled with near-identical chunks. Candidates are fetched several pages deep, then re-selected with maximal marginal relevance and per-link or per-file caps.
+    pub diversify: Option<DiversifyOptions>,
+    /// Rewrite_query has the LLM of the dataset rewrite the query before it is embedded. Can be "hyde" to embed a hypothetical passage answering the query, or "expand" to embed the query along with alternative phrasings of it. Full-text results still use the query as written. Only applies to semantic and hybrid searches with a text query. The rewritten text is returned in the rewritten_query field of the response.
+    pub rewrite_query: Option<RewriteQueryMode>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RetrievalSource {
+    /// Nearest neighbours of the dense embedding of the query.
+    Semantic,
+    /// Matches from the full-text backend of the dataset, either SPLADE or Postgres.
+    FullText,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, ToSchema)]
+#[schema(example = json!({
+    "retrieved_from": ["semantic", "full_text"],
+    "semantic_score": 0.82,
+    "semantic_rank": 3,
+    "full_text_score": 12.4,
+    "full_text_rank": 1,
+    "fusion_strategy": "rrf",
+    "fusion_score": 0.032,
+    "cross_encoder_score": null,
+    "weight": 1.5,
+    "recency_adjustment": null,
+    "search_rules": null,
+    "final_rank": 1
+}))]
+pub struct ScoreExplanation {
+    /// The retrieval lists the chunk was found in.
+    pub retrieved_from: Vec<RetrievalSource>,
+    /// Raw similarity of the chunk to the dense embedding of the query.
+    pub semantic_score: Option<f64>,
+    /// Position of the chunk in the semantic result list, starting at 1.
+    pub semantic_rank: Option<u64>,
+    /// Raw score from the full-text backend. This is the SPLADE dot product or the Postgres ts_rank_cd of the chunk.
+    pub full_text_score: Option<f64>,
+    /// Position of the chunk in the full-text result list, starting at 1.
+    pub full_text_rank: Option<u64>,
+    /// The strategy which combined the result lists in hybrid search. This is "rrf" if the cross encoder failed and search fell back to it.
+    pub fusion_strategy: Option<FusionStrategy>,
+    /// Score assigned by rrf or weighted_linear fusion.
+    pub fusion_score: Option<f64>,
+    /// Relevance score assigned by the cross encoder.
+    pub cross_encoder_score: Option<f64>,
+    /// Weight of the chunk which the score was multiplied by. Null if use_weights was false.
+    pub weight: Option<f64>,
+    /// Multiplier applied to the score for the age of the chunk. Null if no recency adjustment was applied.
+    pub recency_adjustment: Option<f64>,
+    /// Ids of the search rules of the dataset which pinned, buried, or boosted the chunk. Null if no rule applied to it.
+    pub search_rules: Option<Vec<uuid::Uuid>>,
+    /// Position of the chunk in the final ranking, starting at 1 and counting the chunks on previous pages.
+    pub final_rank: u64,
 }
 
 #[derive(Serialize, Deserialize, Debug, ToSchema, Clone)]
@@ -1006,56 +1251,80 @@ pub struct SearchChunkData {
 pub struct ScoreChunkDTO {
     pub metadata: Vec<ChunkMetadataWithFileData>,
     pub score: f64,
+    /// Every score which shaped the rank of the chunk. Only returned when explain is set to true on the search request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explain: Option<ScoreExplanation>,
+    /// Where the query matched the content of the first chunk in metadata. Only returned when highlight_results is not false on the search request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlights: Option<ChunkHighlights>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema, Debug)]
 pub struct SearchChunkQueryResponseBody {
     pub score_chunks: Vec<ScoreChunkDTO>,
     pub total_chunk_pages: i64,
+    /// Opaque cursor for the next page of chunks. Pass it back in the cursor field of the request to continue from where this page ended. Null when there are no more results.
+    pub cursor: Option<String>,
+    /// Value counts for each facet requested in the facets field of the request. Null if no facets were requested.
+    pub facets: Option<Vec<FacetResult>>,
+    /// The text which was embedded in place of the query when rewrite_query was set. Null otherwise.
+    pub rewritten_query: Option<String>,
+    /// Id of the search in the search log. Send it to /analytics/click and /analytics/feedback so clicks and ratings can be attributed to this search.
+    pub search_id: Option<uuid::Uuid>,
 }
 
 #[derive(Clone, Debug)]
 pub struct ParsedQuery {
+    /// The text to embed. Field filters and negated terms are removed.
     pub query: String,
-    pub quote_words: Option<Vec<String>>,
-    pub negated_words: Option<Vec<String>>,
+    /// Filters compiled from `field:value` terms. Every one of them has to match.
+    pub filters: Vec<ChunkFilter>,
+    /// Phrase, prefix, and boolean constraints on the text of the chunks.
+    pub text_filter: Option<TextFilter>,
 }
-pub fn parse_query(query: String) -> ParsedQuery {
-    let re = Regex::new(r#""(?:[^"\\]|\\.)*""#).expect("Regex pattern is always valid");
-    let quote_words: Vec<String> = re
-        .captures_iter(&query)
-        .map(|capture| capture[0].to_string())
-        .filter(|word| !word.is_empty())
-        .collect::<Vec<String>>();
-
-    let quote_words = if quote_words.is_empty() {
-        None
-    } else {
-        Some(quote_words)
-    };
 
-    let negated_words: Vec<String> = query
-        .split_whitespace()
-        .filter(|word| word.starts_with('-'))
-        .map(|word| word.strip_prefix('-').unwrap().to_string())
-        .collect::<Vec<String>>();
+impl ParsedQuery {
+    pub fn from_text(query: String) -> Self {
+        ParsedQuery {
+            query,
+            filters: vec![],
+            text_filter: None,
+        }
+    }
 
-    let negated_words = if negated_words.is_empty() {
-        None
-    } else {
-        Some(negated_words)
-    };
+    /// Rejects a query made only of field filters and negations, such as `tag_set:news -draft`, when its text has to be embedded or matched.
+    pub fn ensure_has_terms(&self) -> Result<(), ServiceError> {
+        if self.query.trim().is_empty() && (!self.filters.is_empty() || self.text_filter.is_some())
+        {
+            return Err(ServiceError::BadRequest(
+                "The query only has field filters or negated terms, so there is nothing to search for. Add a search term, or search with a query_vector and pass the filters in the filters field".to_string(),
+            ));
+        }
 
-    ParsedQuery {
-        query,
-        quote_words,
-        negated_words,
+        Ok(())
     }
 }
 
+pub fn parse_query(query: String) -> Result<ParsedQuery, ServiceError> {
+    let root =
+        match parse_query_ast(&query).map_err(|err| ServiceError::BadRequest(err.to_string()))? {
+            Some(root) => root,
+            None => return Ok(ParsedQuery::from_text(query)),
+        };
+
+    let compiled_query =
+        compile_query(root).map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+
+    Ok(ParsedQuery {
+        query: compiled_query.text,
+        filters: compiled_query.filters,
+        text_filter: compiled_query.text_filter,
+    })
+}
+
 /// Search
 ///
-/// This route provides the primary search functionality for the API. It can be used to search for chunks by semantic similarity, full-text similarity, or a combination of both. Results' `chunk_html` values will be modified with `<b>` tags for sub-sentence highlighting.
+/// This route provides the primary search functionality for the API. It can be used to search for chunks by semantic similarity, full-text similarity, or a combination of both. Each result carries highlight spans and snippets as character offsets into its `content`; `chunk_html` is returned unmodified.
 #[utoipa::path(
     post,
     path = "/chunk/search",
@@ -1073,26 +1342,360 @@ pub fn parse_query(query: String) -> ParsedQuery {
         ("ApiKey" = ["readonly"]),
     )
 )]
-#[tracing::instrument(skip(pool))]
+#[tracing::instrument(skip(pool, redis_pool))]
 pub async fn search_chunk(
     data: web::Json<SearchChunkData>,
     _user: LoggedUser,
     pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
     dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
 ) -> Result<HttpResponse, actix_web::Error> {
     let server_dataset_config = ServerDatasetConfiguration::from_json(
         dataset_org_plan_sub.dataset.server_configuration.clone(),
     );
 
-    let page = data.page.unwrap_or(1);
-    let parsed_query = parse_query(data.query.clone());
+    let parsed_query = parse_query(data.query.clone())?;
 
     let tx_ctx = sentry::TransactionContext::new("search", "search_chunks");
     let transaction = sentry::start_transaction(tx_ctx);
     sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
     let mut timer = Timer::new();
 
-    let result_chunks = match data.search_type.as_str() {
+    let cache_key = get_search_cache_key(
+        "chunk_search",
+        &data.0,
+        dataset_org_plan_sub.dataset.id,
+        &server_dataset_config,
+        redis_pool.clone(),
+    )
+    .await;
+    if let Some(cache_key) = cache_key.as_ref() {
+        if let Some(cached_response) =
+            get_cached_search_response(cache_key, redis_pool.clone()).await
+        {
+            timer.add("Read cached response");
+            transaction.finish();
+
+            // The cached response carries the search_id of the search which cached it, so it gets a fresh one
+            return match serde_json::from_str::<SearchChunkQueryResponseBody>(&cached_response) {
+                Ok(mut cached_chunks) => {
+                    cached_chunks.search_id = Some(
+                        log_search_query(
+                            dataset_org_plan_sub.dataset.id,
+                            &data.search_type,
+                            &data.query,
+                            &data.filters,
+                            cached_chunks
+                                .score_chunks
+                                .iter()
+                                .map(|score_chunk| score_chunk.score),
+                            &timer,
+                            pool,
+                        )
+                        .await,
+                    );
+
+                    Ok(HttpResponse::Ok()
+                        .insert_header((Timer::header_key(), timer.header_value()))
+                        .json(cached_chunks))
+                }
+                Err(_) => Ok(HttpResponse::Ok()
+                    .insert_header((Timer::header_key(), timer.header_value()))
+                    .content_type("application/json")
+                    .body(cached_response)),
+            };
+        }
+    }
+
+    let embedding_vector = get_query_vector(
+        &data.search_type,
+        &parsed_query,
+        data.query_vector.clone(),
+        data.query_chunk_id,
+        data.query_tracking_id.clone(),
+        dataset_org_plan_sub.dataset.id,
+        pool.clone(),
+        &server_dataset_config,
+    )
+    .await?;
+    let rewritten_query = get_rewritten_query(&data, &parsed_query, &server_dataset_config).await?;
+    if rewritten_query.is_some() {
+        timer.add("Rewrote query");
+    }
+
+    let embedding_vector = match embedding_vector {
+        None if data.search_type != "fulltext" => create_query_embeddings_with_cache(
+            vec![rewritten_query
+                .clone()
+                .unwrap_or_else(|| parsed_query.query.clone())],
+            &server_dataset_config,
+            redis_pool.clone(),
+        )
+        .await?
+        .pop(),
+        embedding_vector => embedding_vector,
+    };
+
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+    let search_type = data.search_type.clone();
+    let query = data.query.clone();
+    let filters = data.filters.clone();
+    let analytics_pool = pool.clone();
+
+    let mut result_chunks = run_chunk_search(
+        data,
+        parsed_query,
+        embedding_vector,
+        pool,
+        dataset_org_plan_sub.dataset,
+        server_dataset_config.clone(),
+        &mut timer,
+    )
+    .await?;
+    result_chunks.rewritten_query = rewritten_query;
+    timer.add("Ran search");
+
+    let search_id = log_search_query(
+        dataset_id,
+        &search_type,
+        &query,
+        &filters,
+        result_chunks
+            .score_chunks
+            .iter()
+            .map(|score_chunk| score_chunk.score),
+        &timer,
+        analytics_pool,
+    )
+    .await;
+
+    if let Some(cache_key) = cache_key.as_ref() {
+        set_cached_search_response(
+            cache_key,
+            &result_chunks,
+            &server_dataset_config,
+            redis_pool,
+        )
+        .await;
+    }
+    result_chunks.search_id = Some(search_id);
+
+    transaction.finish();
+
+    Ok(HttpResponse::Ok()
+        .insert_header((Timer::header_key(), timer.header_value()))
+        .json(result_chunks))
+}
+
+/// Has the LLM of the dataset rewrite the query if rewrite_query is set. The rewritten text is embedded in place of the query.
+async fn get_rewritten_query(
+    data: &SearchChunkData,
+    parsed_query: &ParsedQuery,
+    server_dataset_config: &ServerDatasetConfiguration,
+) -> Result<Option<String>, ServiceError> {
+    let mode = match data.rewrite_query {
+        Some(mode) => mode,
+        None => return Ok(None),
+    };
+
+    if data.search_type == "fulltext" {
+        return Err(ServiceError::BadRequest(
+            "rewrite_query only applies to semantic and hybrid searches".to_string(),
+        ));
+    }
+    if data.query_vector.is_some()
+        || data.query_chunk_id.is_some()
+        || data.query_tracking_id.is_some()
+    {
+        return Err(ServiceError::BadRequest(
+            "rewrite_query cannot be combined with query_vector, query_chunk_id, or query_tracking_id".to_string(),
+        ));
+    }
+
+    rewrite_query(
+        &DatasetLlmProvider::from_dataset_config(server_dataset_config),
+        &parsed_query.query,
+        mode,
+        &server_dataset_config.RAG_PROMPT,
+    )
+    .await
+    .map(Some)
+}
+
+/// Runs one search along with its facet counts. If no embedding vector is passed in, semantic and hybrid searches embed the query themselves.
+/// Search rules reorder a ranking which starts at the first result, so when any rule matches the query the search reads a window of
+/// candidates from the first result and pages through it after applying the rules.
+async fn run_chunk_search(
+    data: web::Json<SearchChunkData>,
+    parsed_query: ParsedQuery,
+    embedding_vector: Option<Vec<f32>>,
+    pool: web::Data<Pool>,
+    dataset: Dataset,
+    server_dataset_config: ServerDatasetConfiguration,
+    timer: &mut Timer,
+) -> Result<SearchChunkQueryResponseBody, actix_web::Error> {
+    let page = data.page.unwrap_or(1);
+    let limit = data.page_size.unwrap_or(10);
+    let cursor = decode_search_cursor(&data.cursor)?;
+    let rank_offset = get_rank_offset(page, limit, &cursor);
+    let dataset_id = dataset.id;
+    let rules_pool = pool.clone();
+    let rules_query = parsed_query.clone();
+    let rules_config = server_dataset_config.clone();
+
+    let matching_rules =
+        get_matching_search_rules_query(&data.query, dataset.id, pool.clone()).await?;
+
+    let facets_future = get_facet_counts_query(
+        data.facets.clone(),
+        data.filters.clone(),
+        parsed_query.clone(),
+        dataset.id,
+        pool.clone(),
+        server_dataset_config.clone(),
+    );
+
+    let search_future = async {
+        match data.diversify.clone() {
+            Some(diversify) => {
+                let candidate_limit = get_diversity_candidate_limit(page, limit, &cursor)?;
+
+                let mut candidate_data = data.clone();
+                candidate_data.page = Some(1);
+                candidate_data.page_size = Some(candidate_limit);
+                candidate_data.cursor = None;
+
+                let candidates = search_chunks_by_type(
+                    web::Json(candidate_data),
+                    parsed_query,
+                    embedding_vector,
+                    pool,
+                    dataset,
+                    server_dataset_config.clone(),
+                    timer,
+                )
+                .await?;
+
+                let missing_pinned_chunks = get_missing_pinned_chunks_query(
+                    &candidates.score_chunks,
+                    &matching_rules,
+                    data.filters.clone(),
+                    rules_query,
+                    dataset_id,
+                    rules_pool,
+                    rules_config,
+                )
+                .await?;
+
+                let (score_chunks, next_cursor) = diversify_search_rule_page(
+                    candidates.score_chunks,
+                    candidates.cursor.is_some(),
+                    &diversify,
+                    &matching_rules,
+                    missing_pinned_chunks,
+                    page,
+                    limit,
+                    &cursor,
+                    server_dataset_config,
+                )
+                .await?;
+
+                Ok(SearchChunkQueryResponseBody {
+                    score_chunks,
+                    total_chunk_pages: get_diversity_total_pages(
+                        candidates.total_chunk_pages,
+                        candidate_limit,
+                        limit,
+                    ),
+                    cursor: next_cursor.map(|cursor| cursor.encode()),
+                    facets: None,
+                    rewritten_query: None,
+                    search_id: None,
+                })
+            }
+            None if !matching_rules.is_empty() => {
+                let window = get_fused_ranking_window(page, limit, &cursor)?;
+
+                let mut candidate_data = data.clone();
+                candidate_data.page = Some(1);
+                candidate_data.page_size = Some(window);
+                candidate_data.cursor = None;
+
+                let candidates = search_chunks_by_type(
+                    web::Json(candidate_data),
+                    parsed_query,
+                    embedding_vector,
+                    pool,
+                    dataset,
+                    server_dataset_config,
+                    timer,
+                )
+                .await?;
+
+                let missing_pinned_chunks = get_missing_pinned_chunks_query(
+                    &candidates.score_chunks,
+                    &matching_rules,
+                    data.filters.clone(),
+                    rules_query,
+                    dataset_id,
+                    rules_pool,
+                    rules_config,
+                )
+                .await?;
+
+                let ranking = apply_search_rules(
+                    candidates.score_chunks,
+                    &matching_rules,
+                    missing_pinned_chunks,
+                );
+                let total_chunk_pages = get_fused_total_pages(ranking.len(), limit);
+                let (score_chunks, next_cursor) =
+                    page_fused_results(ranking, page, limit, &cursor, false);
+
+                Ok(SearchChunkQueryResponseBody {
+                    score_chunks,
+                    total_chunk_pages,
+                    cursor: next_cursor.map(|cursor| cursor.with_window(window).encode()),
+                    facets: None,
+                    rewritten_query: None,
+                    search_id: None,
+                })
+            }
+            None => {
+                search_chunks_by_type(
+                    data,
+                    parsed_query,
+                    embedding_vector,
+                    pool,
+                    dataset,
+                    server_dataset_config,
+                    timer,
+                )
+                .await
+            }
+        }
+    };
+
+    let (result_chunks, facets) = futures::join!(search_future, facets_future);
+    let mut result_chunks: SearchChunkQueryResponseBody = result_chunks?;
+    result_chunks.facets = facets?;
+    finish_explanations(&mut result_chunks.score_chunks, rank_offset);
+
+    Ok(result_chunks)
+}
+
+async fn search_chunks_by_type(
+    data: web::Json<SearchChunkData>,
+    parsed_query: ParsedQuery,
+    embedding_vector: Option<Vec<f32>>,
+    pool: web::Data<Pool>,
+    dataset: Dataset,
+    server_dataset_config: ServerDatasetConfiguration,
+    timer: &mut Timer,
+) -> Result<SearchChunkQueryResponseBody, actix_web::Error> {
+    let page = data.page.unwrap_or(1);
+
+    match data.search_type.as_str() {
         "fulltext" => {
             if !server_dataset_config.FULLTEXT_ENABLED {
                 return Err(ServiceError::BadRequest(
@@ -1106,41 +1709,559 @@ pub async fn search_chunk(
                 parsed_query,
                 page,
                 pool,
-                dataset_org_plan_sub.dataset,
+                dataset,
                 server_dataset_config,
             )
-            .await?
+            .await
         }
         "hybrid" => {
             search_hybrid_chunks(
                 data,
                 parsed_query,
+                embedding_vector,
                 page,
                 pool,
-                dataset_org_plan_sub.dataset,
+                dataset,
                 server_dataset_config,
             )
-            .await?
+            .await
         }
         _ => {
             search_semantic_chunks(
                 data,
                 parsed_query,
+                embedding_vector,
                 page,
                 pool,
-                dataset_org_plan_sub.dataset,
-                &mut timer,
+                dataset,
+                timer,
                 server_dataset_config,
             )
+            .await
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[schema(example = json!({
+    "searches": [
+        {
+            "search_type": "hybrid",
+            "query": "nuclear deterrence fails",
+            "page_size": 10
+        },
+        {
+            "search_type": "semantic",
+            "query": "deterrence stability",
+            "filters": {
+                "must": [
+                    {
+                        "field": "tag_set",
+                        "match": ["2024"]
+                    }
+                ]
+            }
+        }
+    ]
+}))]
+pub struct SearchChunkBatchData {
+    /// The searches to run. Each one accepts the same fields as a request to /chunk/search. The number of searches is limited by SEARCH_BATCH_LIMIT, which defaults to 100.
+    pub searches: Vec<SearchChunkData>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct SearchChunkBatchResponseBody {
+    /// The results of each search, in the same order as the searches in the request.
+    pub results: Vec<SearchChunkQueryResponseBody>,
+}
+
+/// Batch Search
+///
+/// Runs several searches against the same dataset in one request. The text queries of all semantic and hybrid searches are embedded together in a single call to the embedding server and the searches then run concurrently. The request fails if any of the searches fails.
+#[utoipa::path(
+    post,
+    path = "/chunk/search/batch",
+    context_path = "/api",
+    tag = "chunk",
+    request_body(content = SearchChunkBatchData, description = "JSON request payload with the list of searches to run", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The results of each search in the order they were requested", body = SearchChunkBatchResponseBody),
+        (status = 400, description = "Service error relating to searching", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+    ),
+    security(
+        ("ApiKey" = ["readonly"]),
+    )
+)]
+#[tracing::instrument(skip(pool, redis_pool))]
+pub async fn search_chunk_batch(
+    data: web::Json<SearchChunkBatchData>,
+    _user: LoggedUser,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+) -> Result<HttpResponse, actix_web::Error> {
+    let batch_limit: usize = std::env::var("SEARCH_BATCH_LIMIT")
+        .unwrap_or("100".to_string())
+        .parse()
+        .unwrap_or(100);
+
+    let searches = data.into_inner().searches;
+    if searches.len() > batch_limit {
+        return Err(ServiceError::BadRequest(format!(
+            "Cannot run more than {} searches in one batch",
+            batch_limit
+        ))
+        .into());
+    }
+
+    let server_dataset_config = ServerDatasetConfiguration::from_json(
+        dataset_org_plan_sub.dataset.server_configuration.clone(),
+    );
+
+    let parsed_queries = searches
+        .iter()
+        .map(|search| parse_query(search.query.clone()))
+        .collect::<Result<Vec<ParsedQuery>, ServiceError>>()?;
+
+    let tx_ctx = sentry::TransactionContext::new("batch search", "search_chunks_batch");
+    let transaction = sentry::start_transaction(tx_ctx);
+    sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+    let mut timer = Timer::new();
+
+    let mut embedding_vectors =
+        futures::future::try_join_all(searches.iter().zip(parsed_queries.iter()).map(
+            |(search, parsed_query)| {
+                get_query_vector(
+                    &search.search_type,
+                    parsed_query,
+                    search.query_vector.clone(),
+                    search.query_chunk_id,
+                    search.query_tracking_id.clone(),
+                    dataset_org_plan_sub.dataset.id,
+                    pool.clone(),
+                    &server_dataset_config,
+                )
+            },
+        ))
+        .await?;
+
+    let rewritten_queries =
+        futures::future::try_join_all(searches.iter().zip(parsed_queries.iter()).map(
+            |(search, parsed_query)| {
+                get_rewritten_query(search, parsed_query, &server_dataset_config)
+            },
+        ))
+        .await?;
+    if rewritten_queries.iter().any(|query| query.is_some()) {
+        timer.add("Rewrote queries");
+    }
+
+    let embedded_positions = searches
+        .iter()
+        .zip(embedding_vectors.iter())
+        .positions(|(search, embedding_vector)| {
+            search.search_type != "fulltext" && embedding_vector.is_none()
+        })
+        .collect_vec();
+
+    if !embedded_positions.is_empty() {
+        let embeddings = create_query_embeddings_with_cache(
+            embedded_positions
+                .iter()
+                .map(|position| {
+                    rewritten_queries[*position]
+                        .clone()
+                        .unwrap_or_else(|| parsed_queries[*position].query.clone())
+                })
+                .collect(),
+            &server_dataset_config,
+            redis_pool,
+        )
+        .await?;
+
+        if embeddings.len() != embedded_positions.len() {
+            return Err(ServiceError::BadRequest(
+                "Failed to get an embedding vector for every query in the batch".to_string(),
+            )
+            .into());
+        }
+
+        for (position, embedding) in embedded_positions.into_iter().zip(embeddings) {
+            embedding_vectors[position] = Some(embedding);
+        }
+    }
+    timer.add("Created embedding vectors");
+
+    let logged_searches = searches
+        .iter()
+        .map(|search| {
+            (
+                search.search_type.clone(),
+                search.query.clone(),
+                search.filters.clone(),
+            )
+        })
+        .collect_vec();
+    let analytics_pool = pool.clone();
+
+    let search_futures = searches
+        .into_iter()
+        .zip(parsed_queries)
+        .zip(embedding_vectors)
+        .map(|((search, parsed_query), embedding_vector)| {
+            let pool = pool.clone();
+            let dataset = dataset_org_plan_sub.dataset.clone();
+            let server_dataset_config = server_dataset_config.clone();
+
+            async move {
+                run_chunk_search(
+                    web::Json(search),
+                    parsed_query,
+                    embedding_vector,
+                    pool,
+                    dataset,
+                    server_dataset_config,
+                    &mut Timer::new(),
+                )
+                .await
+            }
+        });
+
+    let mut results = futures::future::try_join_all(search_futures).await?;
+    for (result, rewritten_query) in results.iter_mut().zip(rewritten_queries) {
+        result.rewritten_query = rewritten_query;
+    }
+    timer.add("Ran searches");
+
+    for (result, (search_type, query, filters)) in results.iter_mut().zip(logged_searches) {
+        result.search_id = Some(
+            log_search_query(
+                dataset_org_plan_sub.dataset.id,
+                &search_type,
+                &query,
+                &filters,
+                result
+                    .score_chunks
+                    .iter()
+                    .map(|score_chunk| score_chunk.score),
+                &timer,
+                analytics_pool.clone(),
+            )
+            .await,
+        );
+    }
+
+    transaction.finish();
+
+    Ok(HttpResponse::Ok()
+        .insert_header((Timer::header_key(), timer.header_value()))
+        .json(SearchChunkBatchResponseBody { results }))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[schema(example = json!({
+    "dataset_ids": ["d290f1ee-6c54-4b01-90e6-d701748f0851", "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3"],
+    "score_normalization": "min_max",
+    "search": {
+        "search_type": "hybrid",
+        "query": "nuclear deterrence fails",
+        "page": 1,
+        "page_size": 10
+    }
+}))]
+pub struct FederatedSearchData {
+    /// The datasets to search. The user must belong to the organization of every dataset. The number of datasets is limited by FEDERATED_SEARCH_DATASET_LIMIT, which defaults to 10.
+    pub dataset_ids: Vec<uuid::Uuid>,
+    /// How the scores of each dataset are put on a common scale before the results are merged. Defaults to min_max.
+    pub score_normalization: Option<ScoreNormalization>,
+    /// The search to run against every dataset. Accepts the same fields as a request to /chunk/search except for cursor, facets, query_chunk_id, query_tracking_id, and rewrite_query.
+    pub search: SearchChunkData,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct FederatedScoreChunkDTO {
+    /// The dataset the chunk was found in.
+    pub dataset_id: uuid::Uuid,
+    /// The normalized score the results were merged by.
+    pub score: f64,
+    /// The result as returned by the search of its own dataset, with its original score.
+    pub score_chunk: ScoreChunkDTO,
+    /// Id of the search of the dataset in the search log. Clicks and ratings of the chunk are attributed to this search.
+    pub search_id: uuid::Uuid,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct FederatedSearchResponseBody {
+    pub score_chunks: Vec<FederatedScoreChunkDTO>,
+    /// Number of pages of the merged results. Exact when every dataset returned fewer results than the requested pages cover, otherwise an upper bound. Pages past the top 1000 results are not counted since they cannot be requested.
+    pub total_chunk_pages: i64,
+}
+
+/// Federated Search
+///
+/// Runs one search against several datasets and merges the results into a single ranked list. Each dataset is searched with its own configuration, so datasets with different embedding models can be searched together; the query is embedded once per distinct embedding model. Scores are normalized per dataset before merging since raw scores are not comparable across datasets. Every dataset is searched deep enough to fill the requested page on its own, so only the top 1000 merged results can be paged through. No TR-Dataset header is needed.
+#[utoipa::path(
+    post,
+    path = "/chunk/search/federated",
+    context_path = "/api",
+    tag = "chunk",
+    request_body(content = FederatedSearchData, description = "JSON request payload with the datasets and the search to run against them", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The merged results of every dataset", body = FederatedSearchResponseBody),
+        (status = 400, description = "Service error relating to searching", body = ErrorResponseBody),
+        (status = 403, description = "The user does not belong to the organization of one of the datasets", body = ErrorResponseBody),
+    ),
+    security(
+        ("ApiKey" = ["readonly"]),
+    )
+)]
+#[tracing::instrument(skip(pool, redis_pool))]
+pub async fn search_chunks_federated(
+    data: web::Json<FederatedSearchData>,
+    user: LoggedUser,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let dataset_limit: usize = std::env::var("FEDERATED_SEARCH_DATASET_LIMIT")
+        .unwrap_or("10".to_string())
+        .parse()
+        .unwrap_or(10);
+
+    let FederatedSearchData {
+        dataset_ids,
+        score_normalization,
+        search,
+    } = data.into_inner();
+
+    if dataset_ids.is_empty() {
+        return Err(ServiceError::BadRequest("dataset_ids must not be empty".to_string()).into());
+    }
+    if dataset_ids.len() > dataset_limit {
+        return Err(ServiceError::BadRequest(format!(
+            "Cannot search more than {} datasets at once",
+            dataset_limit
+        ))
+        .into());
+    }
+    if !dataset_ids.iter().all_unique() {
+        return Err(ServiceError::BadRequest(
+            "dataset_ids must not contain duplicates".to_string(),
+        )
+        .into());
+    }
+    if search.cursor.is_some() || search.facets.is_some() {
+        return Err(ServiceError::BadRequest(
+            "cursor and facets are not supported for federated search, use page instead"
+                .to_string(),
+        )
+        .into());
+    }
+    if search.query_chunk_id.is_some() || search.query_tracking_id.is_some() {
+        return Err(ServiceError::BadRequest(
+            "query_chunk_id and query_tracking_id are not supported for federated search since chunks belong to a single dataset".to_string(),
+        )
+        .into());
+    }
+    if search.rewrite_query.is_some() {
+        return Err(ServiceError::BadRequest(
+            "rewrite_query is not supported for federated search since each dataset has its own LLM".to_string(),
+        )
+        .into());
+    }
+
+    let datasets =
+        futures::future::try_join_all(dataset_ids.iter().map(|dataset_id| {
+            get_dataset_by_id_query(*dataset_id, redis_pool.clone(), pool.clone())
+        }))
+        .await?;
+
+    for dataset in datasets.iter() {
+        user.user_orgs
+            .iter()
+            .find(|org| {
+                org.organization_id == dataset.organization_id && org.role >= UserRole::User.into()
+            })
+            .ok_or(ServiceError::Forbidden)?;
+    }
+
+    let server_dataset_configs = datasets
+        .iter()
+        .map(|dataset| ServerDatasetConfiguration::from_json(dataset.server_configuration.clone()))
+        .collect_vec();
+
+    let parsed_query = parse_query(search.query.clone())?;
+
+    let tx_ctx = sentry::TransactionContext::new("federated search", "search_chunks_federated");
+    let transaction = sentry::start_transaction(tx_ctx);
+    sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone().into())));
+    let mut timer = Timer::new();
+
+    let mut embedding_vectors =
+        futures::future::try_join_all(datasets.iter().zip(server_dataset_configs.iter()).map(
+            |(dataset, server_dataset_config)| {
+                get_query_vector(
+                    &search.search_type,
+                    &parsed_query,
+                    search.query_vector.clone(),
+                    None,
+                    None,
+                    dataset.id,
+                    pool.clone(),
+                    server_dataset_config,
+                )
+            },
+        ))
+        .await?;
+
+    // Datasets sharing an embedding model share one embedding of the query
+    if search.search_type != "fulltext" {
+        let models = server_dataset_configs
+            .iter()
+            .zip(embedding_vectors.iter())
+            .filter(|(_, embedding_vector)| embedding_vector.is_none())
+            .map(|(config, _)| config)
+            .unique_by(|config| {
+                (
+                    config.EMBEDDING_BASE_URL.clone(),
+                    config.EMBEDDING_MODEL_NAME.clone(),
+                    config.EMBEDDING_QUERY_PREFIX.clone(),
+                )
+            })
+            .cloned()
+            .collect_vec();
+
+        for model_config in models {
+            let embedding = create_query_embeddings_with_cache(
+                vec![parsed_query.query.clone()],
+                &model_config,
+                redis_pool.clone(),
+            )
             .await?
+            .into_iter()
+            .next()
+            .ok_or(ServiceError::BadRequest(
+                "Failed to get embedding vector for the query".to_string(),
+            ))?;
+
+            for (config, embedding_vector) in server_dataset_configs
+                .iter()
+                .zip(embedding_vectors.iter_mut())
+            {
+                if embedding_vector.is_none()
+                    && config.EMBEDDING_BASE_URL == model_config.EMBEDDING_BASE_URL
+                    && config.EMBEDDING_MODEL_NAME == model_config.EMBEDDING_MODEL_NAME
+                    && config.EMBEDDING_QUERY_PREFIX == model_config.EMBEDDING_QUERY_PREFIX
+                {
+                    *embedding_vector = Some(embedding.clone());
+                }
+            }
         }
-    };
+    }
+    timer.add("Created embedding vectors");
+
+    // Every dataset returns enough results to fill the requested page on its own
+    let page = search.page.unwrap_or(1).max(1);
+    let limit = search.page_size.unwrap_or(10);
+    let candidate_limit = get_federated_candidate_limit(page, limit)?;
+
+    let search_futures = datasets
+        .into_iter()
+        .zip(server_dataset_configs)
+        .zip(embedding_vectors)
+        .map(|((dataset, server_dataset_config), embedding_vector)| {
+            let mut dataset_search = search.clone();
+            dataset_search.page = Some(1);
+            dataset_search.page_size = Some(candidate_limit);
+            let parsed_query = parsed_query.clone();
+            let pool = pool.clone();
+
+            async move {
+                let dataset_id = dataset.id;
+                run_chunk_search(
+                    web::Json(dataset_search),
+                    parsed_query,
+                    embedding_vector,
+                    pool,
+                    dataset,
+                    server_dataset_config,
+                    &mut Timer::new(),
+                )
+                .await
+                .map(|result| (dataset_id, result))
+            }
+        });
+
+    let results = futures::future::try_join_all(search_futures).await?;
+    timer.add("Ran searches");
+
+    // Every dataset records the search with its own top results, before they are merged with the other datasets
+    let search_ids = futures::future::join_all(results.iter().map(|(dataset_id, result)| async {
+        let search_id = log_search_query(
+            *dataset_id,
+            &search.search_type,
+            &search.query,
+            &search.filters,
+            result
+                .score_chunks
+                .iter()
+                .take(limit as usize)
+                .map(|score_chunk| score_chunk.score),
+            &timer,
+            pool.clone(),
+        )
+        .await;
+
+        (*dataset_id, search_id)
+    }))
+    .await
+    .into_iter()
+    .collect::<HashMap<uuid::Uuid, uuid::Uuid>>();
+
+    let total_chunk_pages = get_federated_total_pages(
+        results
+            .iter()
+            .map(|(_, result)| (result.total_chunk_pages, result.score_chunks.len())),
+        candidate_limit,
+        limit,
+    );
+
+    let normalization = score_normalization.unwrap_or_default();
+    let score_chunks = results
+        .into_iter()
+        .flat_map(|(dataset_id, result)| {
+            let search_id = search_ids[&dataset_id];
+            let scores = result
+                .score_chunks
+                .iter()
+                .map(|score_chunk| score_chunk.score)
+                .collect_vec();
+
+            normalize_dataset_scores(&scores, normalization)
+                .into_iter()
+                .zip(result.score_chunks)
+                .map(move |(score, score_chunk)| FederatedScoreChunkDTO {
+                    dataset_id,
+                    score,
+                    score_chunk,
+                    search_id,
+                })
+        })
+        .sorted_by(|a, b| b.score.total_cmp(&a.score))
+        .skip(((page - 1) * limit) as usize)
+        .take(limit as usize)
+        .collect_vec();
 
     transaction.finish();
 
     Ok(HttpResponse::Ok()
         .insert_header((Timer::header_key(), timer.header_value()))
-        .json(result_chunks))
+        .json(FederatedSearchResponseBody {
+            score_chunks,
+            total_chunk_pages,
+        }))
 }
 
 /// Get Chunk By Id
@@ -1,14 +1,17 @@
-use super::auth_handler::{AdminOnly, LoggedUser, OwnerOnly};
+use super::auth_handler::{AdminOnly, AuditActor, LoggedUser, OwnerOnly};
 use crate::{
     data::models::{
-        ClientDatasetConfiguration, Dataset, DatasetAndOrgWithSubAndPlan, Pool, RedisPool,
-        ServerDatasetConfiguration, StripePlan,
+        AuditAction, AuditLog, ClientDatasetConfiguration, Dataset, DatasetAndOrgWithSubAndPlan,
+        Pool, RedisPool, ServerDatasetConfiguration, StripePlan,
     },
     errors::ServiceError,
     operators::{
+        audit_operator::log_audit_actions,
+        cache_operator::bump_search_cache_generation,
         dataset_operator::{
             create_dataset_query, delete_dataset_by_id_query, get_dataset_by_id_query,
             get_datasets_by_organization_id, update_dataset_query,
+            validate_server_configuration_query,
         },
         organization_operator::{get_org_dataset_count, get_organization_by_key_query},
         stripe_operator::refresh_redis_org_plan_sub,
@@ -83,6 +86,7 @@ pub async fn create_dataset(
     redis_pool: web::Data<RedisPool>,
     pool: web::Data<Pool>,
     _user: OwnerOnly,
+    actor: AuditActor,
 ) -> Result<HttpResponse, ServiceError> {
     let org_id = data.organization_id;
 
@@ -105,6 +109,8 @@ pub async fn create_dataset(
             .json(json!({"message": "Your plan must be upgraded to create additional datasets"})));
     }
 
+    validate_server_configuration_query(&data.server_configuration, pool.clone()).await?;
+
     let dataset = Dataset::from_details(
         data.dataset_name.clone(),
         data.organization_id,
@@ -112,7 +118,22 @@ pub async fn create_dataset(
         data.client_configuration.clone(),
     );
 
-    let d = create_dataset_query(dataset, redis_pool, pool).await?;
+    let d = create_dataset_query(dataset, redis_pool, pool.clone()).await?;
+
+    log_audit_actions(
+        vec![AuditLog::from_details(
+            d.organization_id,
+            Some(d.id),
+            actor.user_id,
+            actor.api_key_id,
+            AuditAction::DatasetCreated,
+            Some(d.id.to_string()),
+            json!({ "dataset_name": d.name }),
+        )],
+        pool,
+    )
+    .await;
+
     Ok(HttpResponse::Ok().json(d))
 }
 
@@ -160,9 +181,13 @@ pub async fn update_dataset(
     redis_pool: web::Data<RedisPool>,
     pool: web::Data<Pool>,
     _user: OwnerOnly,
+    actor: AuditActor,
 ) -> Result<HttpResponse, ServiceError> {
     let curr_dataset =
         get_dataset_by_id_query(data.dataset_id, redis_pool.clone(), pool.clone()).await?;
+    if let Some(server_configuration) = &data.server_configuration {
+        validate_server_configuration_query(server_configuration, pool.clone()).await?;
+    }
     let d = update_dataset_query(
         data.dataset_id,
         data.dataset_name.clone().unwrap_or(curr_dataset.name),
@@ -184,6 +209,26 @@ pub async fn update_dataset(
                 err
             ))
         });
+    let _ = bump_search_cache_generation(d.id, redis_pool).await;
+
+    log_audit_actions(
+        vec![AuditLog::from_details(
+            d.organization_id,
+            Some(d.id),
+            actor.user_id,
+            actor.api_key_id,
+            AuditAction::DatasetUpdated,
+            Some(d.id.to_string()),
+            json!({
+                "dataset_name": d.name,
+                "server_configuration_updated": data.server_configuration.is_some(),
+                "client_configuration_updated": data.client_configuration.is_some(),
+            }),
+        )],
+        pool,
+    )
+    .await;
+
     Ok(HttpResponse::Ok().json(d))
 }
 
@@ -218,13 +263,38 @@ pub async fn delete_dataset(
     data: web::Json<DeleteDatasetRequest>,
     pool: web::Data<Pool>,
     redis_pool: web::Data<RedisPool>,
-    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    _dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
     _user: OwnerOnly,
+    actor: AuditActor,
 ) -> Result<HttpResponse, ServiceError> {
-    let server_dataset_config = ServerDatasetConfiguration::from_json(
-        dataset_org_plan_sub.dataset.server_configuration.clone(),
-    );
-    delete_dataset_by_id_query(data.dataset_id, pool, redis_pool, server_dataset_config).await?;
+    // The deleted dataset is not necessarily the one of the TR-Dataset header, so it is loaded to log it under its own organization
+    let dataset =
+        get_dataset_by_id_query(data.dataset_id, redis_pool.clone(), pool.clone()).await?;
+
+    let server_dataset_config =
+        ServerDatasetConfiguration::from_json(dataset.server_configuration.clone());
+    delete_dataset_by_id_query(
+        data.dataset_id,
+        pool.clone(),
+        redis_pool,
+        server_dataset_config,
+    )
+    .await?;
+
+    log_audit_actions(
+        vec![AuditLog::from_details(
+            dataset.organization_id,
+            Some(dataset.id),
+            actor.user_id,
+            actor.api_key_id,
+            AuditAction::DatasetDeleted,
+            Some(dataset.id.to_string()),
+            json!({ "dataset_name": dataset.name }),
+        )],
+        pool,
+    )
+    .await;
+
     Ok(HttpResponse::NoContent().finish())
 }
 
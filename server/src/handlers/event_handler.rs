@@ -1,11 +1,17 @@
 use super::auth_handler::LoggedUser;
 use crate::{
-    data::models::{DatasetAndOrgWithSubAndPlan, EventType, Pool},
+    data::models::{DatasetAndOrgWithSubAndPlan, Event, EventType, Pool},
     errors::ServiceError,
-    operators::event_operator::get_events_query,
+    operators::event_operator::{
+        get_event_created_at_query, get_events_after_query, get_events_query,
+        subscribe_to_dataset_events,
+    },
 };
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
+use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
 use utoipa::{schema, ToSchema};
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
@@ -80,3 +86,164 @@ pub struct EventId {
     /// Id of the notification to target.
     pub notification_id: uuid::Uuid,
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct StreamEventsQuery {
+    /// Comma separated types of events to stream. Any combination of file_uploaded, card_uploaded, card_action_failed, or card_updated. Leave undefined to stream all events.
+    pub event_types: Option<String>,
+}
+
+fn format_server_sent_event(event: &Event) -> web::Bytes {
+    web::Bytes::from(format!(
+        "id: {}\nevent: {}\ndata: {}\n\n",
+        event.id,
+        event.event_type,
+        serde_json::to_string(event).unwrap_or_default()
+    ))
+}
+
+/// Number of missed events loaded at a time when an event stream is resumed.
+const MISSED_EVENTS_PAGE_SIZE: i64 = 1000;
+
+/// Stream events for the dataset
+///
+/// Stream the events of the dataset as Server-Sent Events as they are created. Each message has the id of the event as its id, the event type as its event, and the event as JSON as its data. Send the id of the last event received in the Last-Event-ID header to first receive every event which was created since. A comment is sent every 15 seconds to keep the connection open.
+#[utoipa::path(
+    get,
+    path = "/events/stream",
+    context_path = "/api",
+    tag = "events",
+    responses(
+        (status = 200, description = "A text/event-stream of the events of the dataset"),
+        (status = 400, description = "Service error relating to streaming events for the dataset", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+        ("Last-Event-ID" = Option<String>, Header, description = "Id of the last event received. Events created after it are sent first."),
+        ("event_types" = Option<String>, Query, description = "Comma separated types of events to stream. Leave undefined to stream all events."),
+    ),
+    security(
+        ("ApiKey" = ["readonly"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn stream_events(
+    req: HttpRequest,
+    _user: LoggedUser,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    data: web::Query<StreamEventsQuery>,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+    let event_types = match &data.event_types {
+        Some(event_types) if !event_types.trim().is_empty() => event_types
+            .split(',')
+            .map(|event_type| event_type.trim().to_string())
+            .collect::<Vec<String>>(),
+        _ => EventType::get_all_event_types(),
+    };
+
+    let last_event_id = match req.headers().get("Last-Event-ID") {
+        Some(last_event_id) => Some(
+            last_event_id
+                .to_str()
+                .ok()
+                .and_then(|last_event_id| last_event_id.parse::<uuid::Uuid>().ok())
+                .ok_or(ServiceError::BadRequest(
+                    "Last-Event-ID must be a valid UUID".to_string(),
+                ))?,
+        ),
+        None => None,
+    };
+
+    // Subscribe before loading the missed events so that nothing created in between is lost
+    let pubsub = subscribe_to_dataset_events(dataset_id)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    let missed_events_cursor = match last_event_id {
+        Some(last_event_id) => {
+            let last_event_created_at =
+                get_event_created_at_query(dataset_id, last_event_id, pool.clone())
+                    .await
+                    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+            Some((last_event_created_at, last_event_id))
+        }
+        None => None,
+    };
+
+    // The missed events are sent before any live event, so every one of them is known by the time live events are filtered
+    let sent_event_ids = Arc::new(Mutex::new(HashSet::<uuid::Uuid>::new()));
+
+    let missed_event_types = event_types.clone();
+    let missed_event_ids = sent_event_ids.clone();
+    let missed_events = futures::stream::unfold(missed_events_cursor, move |cursor| {
+        let event_types = missed_event_types.clone();
+        let sent_event_ids = missed_event_ids.clone();
+        let pool = pool.clone();
+
+        async move {
+            let (last_event_created_at, last_event_id) = cursor?;
+            let events = get_events_after_query(
+                dataset_id,
+                last_event_created_at,
+                last_event_id,
+                event_types,
+                MISSED_EVENTS_PAGE_SIZE,
+                pool,
+            )
+            .await
+            .ok()?;
+
+            let next_cursor = match events.last() {
+                Some(event) if events.len() as i64 == MISSED_EVENTS_PAGE_SIZE => {
+                    Some((event.created_at, event.id))
+                }
+                _ => None,
+            };
+            sent_event_ids
+                .lock()
+                .unwrap()
+                .extend(events.iter().map(|event| event.id));
+
+            Some((
+                futures::stream::iter(
+                    events
+                        .iter()
+                        .map(format_server_sent_event)
+                        .collect::<Vec<web::Bytes>>(),
+                ),
+                next_cursor,
+            ))
+        }
+    })
+    .flatten();
+
+    let live_events = pubsub.into_on_message().filter_map(move |message| {
+        let event = message
+            .get_payload::<String>()
+            .ok()
+            .and_then(|payload| serde_json::from_str::<Event>(&payload).ok())
+            .filter(|event| {
+                event_types.contains(&event.event_type)
+                    && !sent_event_ids.lock().unwrap().contains(&event.id)
+            });
+
+        futures::future::ready(event.map(|event| format_server_sent_event(&event)))
+    });
+    let keep_alive = tokio_stream::wrappers::IntervalStream::new(tokio::time::interval(
+        std::time::Duration::from_secs(15),
+    ))
+    .map(|_| web::Bytes::from_static(b": keep-alive\n\n"));
+
+    let stream = missed_events
+        .chain(futures::stream::select(live_events, keep_alive))
+        .map(Ok::<web::Bytes, actix_web::Error>);
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Content-Type", "text/event-stream"))
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(stream))
+}
@@ -1,11 +1,13 @@
-use super::auth_handler::{AdminOnly, LoggedUser};
+use super::auth_handler::{AdminOnly, AuditActor, LoggedUser};
 use crate::{
     data::models::{
-        DatasetAndOrgWithSubAndPlan, File, FileAndGroupId, Pool, RedisPool,
+        AuditAction, AuditLog, DatasetAndOrgWithSubAndPlan, File, FileAndGroupId, Pool, RedisPool,
         ServerDatasetConfiguration,
     },
     errors::ServiceError,
     operators::{
+        audit_operator::log_audit_actions,
+        cache_operator::bump_search_cache_generation,
         file_operator::{
             convert_doc_to_html_query, delete_file_query, get_aws_bucket, get_dataset_file_query,
             get_file_query,
@@ -313,26 +315,48 @@ pub struct DeleteFileQueryParams {
         ("ApiKey" = ["admin"]),
     )
 )]
-#[tracing::instrument(skip(pool))]
+#[tracing::instrument(skip(pool, redis_pool))]
 pub async fn delete_file_handler(
     file_id: web::Path<uuid::Uuid>,
     query_params: web::Query<DeleteFileQueryParams>,
     pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
     _user: AdminOnly,
+    actor: AuditActor,
     dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
 ) -> Result<HttpResponse, actix_web::Error> {
     let server_dataset_config = ServerDatasetConfiguration::from_json(
         dataset_org_plan_sub.dataset.server_configuration.clone(),
     );
+    let file_id = file_id.into_inner();
+    let dataset_id = dataset_org_plan_sub.dataset.id;
     delete_file_query(
-        file_id.into_inner(),
+        file_id,
         dataset_org_plan_sub.dataset,
         query_params.delete_chunks,
-        pool,
+        pool.clone(),
         server_dataset_config,
     )
     .await?;
 
+    if query_params.delete_chunks.unwrap_or(false) {
+        let _ = bump_search_cache_generation(dataset_id, redis_pool).await;
+    }
+
+    log_audit_actions(
+        vec![AuditLog::from_details(
+            dataset_org_plan_sub.organization.id,
+            Some(dataset_id),
+            actor.user_id,
+            actor.api_key_id,
+            AuditAction::FileDeleted,
+            Some(file_id.to_string()),
+            serde_json::json!({ "delete_chunks": query_params.delete_chunks.unwrap_or(false) }),
+        )],
+        pool,
+    )
+    .await;
+
     Ok(HttpResponse::NoContent().finish())
 }
 
@@ -1,28 +1,44 @@
 use super::{
-    auth_handler::{AdminOnly, LoggedUser},
-    chunk_handler::{parse_query, ChunkFilter, ScoreChunkDTO, SearchChunkData},
+    auth_handler::{AdminOnly, AuditActor, LoggedUser},
+    chunk_handler::{
+        parse_query, ChunkFilter, DiversifyOptions, FusionStrategy, ParsedQuery, ScoreChunkDTO,
+        SearchChunkData,
+    },
 };
 use crate::{
     data::models::{
-        ChunkGroup, ChunkGroupAndFile, ChunkGroupBookmark, ChunkMetadataWithFileData,
-        DatasetAndOrgWithSubAndPlan, Pool, ServerDatasetConfiguration, UnifiedId,
+        AuditAction, AuditLog, ChunkGroup, ChunkGroupAndFile, ChunkGroupBookmark,
+        ChunkMetadataWithFileData, Dataset, DatasetAndOrgWithSubAndPlan, Pool, RecencyDecay,
+        RedisPool, ServerDatasetConfiguration, UnifiedId,
     },
     errors::ServiceError,
     operators::{
+        analytics_operator::log_search_query,
+        audit_operator::log_audit_actions,
+        cache_operator::bump_search_cache_generation,
+        diversity_operator::{get_diversity_candidate_limit, get_diversity_total_pages},
+        facet_operator::get_facet_counts_query,
         group_operator::*,
         qdrant_operator::{
             add_bookmark_to_qdrant_query, recommend_qdrant_groups_query,
             remove_bookmark_from_qdrant_query,
         },
         search_operator::{
-            full_text_search_over_groups, get_metadata_from_groups, hybrid_search_over_groups,
+            decode_search_cursor, finish_explanations, full_text_search_over_groups,
+            get_fused_ranking_window, get_fused_total_pages, get_metadata_from_groups,
+            get_query_vector, get_rank_offset, hybrid_search_over_groups, page_fused_results,
             search_full_text_groups, search_hybrid_groups, search_semantic_groups,
-            semantic_search_over_groups, SearchOverGroupsQueryResult,
+            semantic_search_over_groups, SearchOverGroupsQueryResult, SearchOverGroupsResponseBody,
+        },
+        search_rule_operator::{
+            apply_search_rules, apply_search_rules_to_groups, diversify_search_rule_group_page,
+            diversify_search_rule_page, get_matching_search_rules_query,
         },
     },
 };
 use actix_web::{web, HttpResponse};
 use serde::{Deserialize, Serialize};
+use simple_server_timing_header::Timer;
 use utoipa::{IntoParams, ToSchema};
 
 #[tracing::instrument(skip(pool))]
@@ -342,13 +358,15 @@ pub struct DeleteGroupByTrackingIDData {
         ("ApiKey" = ["admin"]),
     )
 )]
-#[tracing::instrument(skip(pool))]
+#[tracing::instrument(skip(pool, redis_pool))]
 pub async fn delete_group_by_tracking_id(
     tracking_id: web::Path<String>,
     data: web::Query<DeleteGroupByTrackingIDData>,
     pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
     dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
     _user: AdminOnly,
+    actor: AuditActor,
 ) -> Result<HttpResponse, actix_web::Error> {
     let delete_group_pool = pool.clone();
     let server_dataset_config = ServerDatasetConfiguration::from_json(
@@ -359,10 +377,11 @@ pub async fn delete_group_by_tracking_id(
     let group = dataset_owns_group(
         UnifiedId::TrackingId(tracking_id),
         dataset_org_plan_sub.dataset.id,
-        pool,
+        pool.clone(),
     )
     .await?;
 
+    let dataset_id = dataset_org_plan_sub.dataset.id;
     delete_group_by_id_query(
         group.id,
         dataset_org_plan_sub.dataset,
@@ -373,6 +392,28 @@ pub async fn delete_group_by_tracking_id(
     .await
     .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
 
+    if data.delete_chunks.unwrap_or(false) {
+        let _ = bump_search_cache_generation(dataset_id, redis_pool).await;
+    }
+
+    log_audit_actions(
+        vec![AuditLog::from_details(
+            dataset_org_plan_sub.organization.id,
+            Some(dataset_id),
+            actor.user_id,
+            actor.api_key_id,
+            AuditAction::GroupDeleted,
+            Some(group.id.to_string()),
+            serde_json::json!({
+                "group_name": group.name,
+                "tracking_id": group.tracking_id,
+                "delete_chunks": data.delete_chunks.unwrap_or(false),
+            }),
+        )],
+        pool,
+    )
+    .await;
+
     Ok(HttpResponse::NoContent().finish())
 }
 
@@ -402,13 +443,15 @@ pub struct DeleteGroupData {
         ("ApiKey" = ["admin"]),
     )
 )]
-#[tracing::instrument(skip(pool))]
+#[tracing::instrument(skip(pool, redis_pool))]
 pub async fn delete_chunk_group(
     group_id: web::Path<uuid::Uuid>,
     data: web::Query<DeleteGroupData>,
     pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
     dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
     _user: AdminOnly,
+    actor: AuditActor,
 ) -> Result<HttpResponse, actix_web::Error> {
     let delete_group_pool = pool.clone();
     let server_dataset_config = ServerDatasetConfiguration::from_json(
@@ -417,13 +460,14 @@ pub async fn delete_chunk_group(
 
     let group_id = group_id.into_inner();
 
-    dataset_owns_group(
+    let group = dataset_owns_group(
         UnifiedId::TrieveUuid(group_id),
         dataset_org_plan_sub.dataset.id,
         pool.clone(),
     )
     .await?;
 
+    let dataset_id = dataset_org_plan_sub.dataset.id;
     delete_group_by_id_query(
         group_id,
         dataset_org_plan_sub.dataset,
@@ -434,6 +478,28 @@ pub async fn delete_chunk_group(
     .await
     .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
 
+    if data.delete_chunks.unwrap_or(false) {
+        let _ = bump_search_cache_generation(dataset_id, redis_pool).await;
+    }
+
+    log_audit_actions(
+        vec![AuditLog::from_details(
+            dataset_org_plan_sub.organization.id,
+            Some(dataset_id),
+            actor.user_id,
+            actor.api_key_id,
+            AuditAction::GroupDeleted,
+            Some(group.id.to_string()),
+            serde_json::json!({
+                "group_name": group.name,
+                "tracking_id": group.tracking_id,
+                "delete_chunks": data.delete_chunks.unwrap_or(false),
+            }),
+        )],
+        pool,
+    )
+    .await;
+
     Ok(HttpResponse::NoContent().finish())
 }
 
@@ -1010,6 +1076,7 @@ pub async fn get_recommended_groups(
     let group_query_result = SearchOverGroupsQueryResult {
         search_results: recommended_qdrant_point_ids.clone(),
         total_chunk_pages: (recommended_qdrant_point_ids.len() as f64 / 10.0).ceil() as i64,
+        next_cursor: None,
     };
 
     let recommended_chunk_metadatas =
@@ -1021,7 +1088,7 @@ pub async fn get_recommended_groups(
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema, IntoParams)]
 #[into_params(style = Form, parameter_in = Query)]
 pub struct SearchWithinGroupData {
-    /// The query is the search query. This can be any string. The query will be used to create an embedding vector and/or SPLADE vector which will be used to find the result set.
+    /// The query is the search query. This can be any string. The query will be used to create an embedding vector and/or SPLADE vector which will be used to find the result set. Terms can be combined with AND, OR, NOT (or a leading -), and parentheses. Use "double quotes" for phrases, a trailing * for prefixes, and field:value to filter on tag_set, link, time_stamp, or metadata.<key> (e.g. tag_set:news, metadata.price:>=10, time_stamp:2024-01). Bare words separated by spaces are only used for similarity; quoted, prefixed, and operator-joined terms must appear in the chunk. Negated terms and field filters are not embedded. Malformed queries are rejected with a syntax error.
     pub query: String,
     /// The page of chunks to fetch. Each page is 10 chunks. Support for custom page size is coming soon.
     pub page: Option<u64>,
@@ -1033,18 +1100,30 @@ pub struct SearchWithinGroupData {
     pub group_id: Option<uuid::Uuid>,
     /// Group_tracking_id specifies the group to search within by tracking id. Results will only consist of chunks which are bookmarks within the specified group. If both group_id and group_tracking_id are provided, group_id will be used.
     pub group_tracking_id: Option<String>,
-    /// Search_type can be either "semantic", "fulltext", or "hybrid". "hybrid" will pull in one page (10 chunks) of both semantic and full-text results then re-rank them using BAAI/bge-reranker-large. "semantic" will pull in one page (10 chunks) of the nearest cosine distant vectors. "fulltext" will pull in one page (10 chunks) of full-text results based on SPLADE.
+    /// Search_type can be either "semantic", "fulltext", or "hybrid". "hybrid" will pull in one page (10 chunks) of both semantic and full-text results then combine them using the fusion_strategy. "semantic" will pull in one page (10 chunks) of the nearest cosine distant vectors. "fulltext" will pull in one page (10 chunks) of full-text results based on SPLADE.
     pub search_type: String,
-    /// Set date_bias to true to bias search results towards more recent chunks. This will work best in hybrid search mode.
+    /// Set date_bias to true to boost more recent chunks with the recency decay of the dataset, or the default decay if the dataset does not set one. Set it to false to turn recency decay off.
     pub date_bias: Option<bool>,
+    /// Recency_decay blends the age of each chunk into its score. Older chunks are multiplied by a decay curve so recent chunks rank higher without burying relevant older ones. Overrides the RECENCY_DECAY of the dataset and applies unless date_bias is false.
+    pub recency_decay: Option<RecencyDecay>,
     /// Set use_weights to true to use the weights of the chunks in the result set in order to sort them. If not specified, this defaults to true.
     pub use_weights: Option<bool>,
-    /// Set highlight_results to true to highlight the results. If not specified, this defaults to true.
+    /// Set highlight_results to true to return the highlights field on each score chunk with the spans of content that matched the query and snippets around them. Chunk_html is never modified. If not specified, this defaults to true.
     pub highlight_results: Option<bool>,
-    /// Set highlight_delimiters to a list of strings to use as delimiters for highlighting. If not specified, this defaults to ["?", ",", ".", "!"].
+    /// Set highlight_delimiters to a list of strings which end a sentence. Only used when none of the query terms appear in a chunk, in which case the sentences most similar to the query are highlighted instead. If not specified, this defaults to [".", "!", "?", "\n", "\t", ","].
     pub highlight_delimiters: Option<Vec<String>>,
+    /// Highlight_window is the number of words of context to keep on each side of a match in a snippet. If not specified, this defaults to 10.
+    pub highlight_window: Option<u32>,
+    /// Highlight_max_snippets is the maximum number of snippets returned for each chunk. Snippets covering the most distinct query terms are kept. If not specified, this defaults to 3.
+    pub highlight_max_snippets: Option<u32>,
     /// Set score_threshold to a float to filter out chunks with a score below the threshold.
     pub score_threshold: Option<f32>,
+    /// Fusion_strategy controls how semantic and full-text results are combined in hybrid search. Can be "rrf", "weighted_linear", or "cross_encoder". If not specified, this defaults to "cross_encoder" when a reranker server is configured and "rrf" otherwise.
+    pub fusion_strategy: Option<FusionStrategy>,
+    /// Fusion_alpha is the weight given to the semantic scores when fusion_strategy is "weighted_linear". Must be between 0 and 1. If not specified, this defaults to 0.5.
+    pub fusion_alpha: Option<f32>,
+    /// Diversify keeps one page of results from being filled with near-identical chunks. Candidates are fetched several pages deep, then re-selected with maximal marginal relevance and per-link or per-file caps.
+    pub diversify: Option<DiversifyOptions>,
 }
 
 impl From<SearchWithinGroupData> for SearchChunkData {
@@ -1053,14 +1132,27 @@ impl From<SearchWithinGroupData> for SearchChunkData {
             query: data.query,
             page: data.page,
             page_size: data.page_size,
+            cursor: None,
             filters: data.filters,
             search_type: data.search_type,
             date_bias: data.date_bias,
+            recency_decay: data.recency_decay,
             use_weights: data.use_weights,
             get_collisions: Some(false),
             highlight_results: data.highlight_results,
             highlight_delimiters: data.highlight_delimiters,
+            highlight_window: data.highlight_window,
+            highlight_max_snippets: data.highlight_max_snippets,
             score_threshold: data.score_threshold,
+            fusion_strategy: data.fusion_strategy,
+            fusion_alpha: data.fusion_alpha,
+            facets: None,
+            explain: None,
+            query_vector: None,
+            query_chunk_id: None,
+            query_tracking_id: None,
+            diversify: data.diversify,
+            rewrite_query: None,
         }
     }
 }
@@ -1070,6 +1162,8 @@ pub struct SearchGroupsResult {
     pub bookmarks: Vec<ScoreChunkDTO>,
     pub group: ChunkGroup,
     pub total_pages: i64,
+    /// Id of the search in the search log. Send it to /analytics/click and /analytics/feedback so clicks and ratings can be attributed to this search.
+    pub search_id: Option<uuid::Uuid>,
 }
 
 /// Search Within Group
@@ -1103,12 +1197,19 @@ pub async fn search_within_group(
     let server_dataset_config = ServerDatasetConfiguration::from_json(
         dataset_org_plan_sub.dataset.server_configuration.clone(),
     );
+    let mut timer = Timer::new();
 
     //search over the links as well
     let page = data.page.unwrap_or(1);
     let group_id = data.group_id;
     let dataset_id = dataset_org_plan_sub.dataset.id;
     let search_pool = pool.clone();
+    let rules_pool = pool.clone();
+    let analytics_pool = pool.clone();
+    let query = data.query.clone();
+    let search_type = data.search_type.clone();
+    let filters = data.filters.clone();
+    let rank_offset = get_rank_offset(page, data.page_size.unwrap_or(10), &None);
 
     let group = {
         if let Some(group_id) = group_id {
@@ -1127,9 +1228,129 @@ pub async fn search_within_group(
         }
     };
 
-    let parsed_query = parse_query(data.query.clone());
+    let parsed_query = parse_query(data.query.clone())?;
+    parsed_query.ensure_has_terms()?;
+
+    // Chunks outside of the group are never pinned into its results
+    let matching_rules = get_matching_search_rules_query(&query, dataset_id, rules_pool).await?;
+    let limit = data.page_size.unwrap_or(10);
+
+    let mut result_chunks = match data.diversify.clone() {
+        Some(diversify) => {
+            let candidate_limit = get_diversity_candidate_limit(page, limit, &None)?;
+
+            let mut candidate_data = data.clone();
+            candidate_data.page_size = Some(candidate_limit);
+
+            let candidates = search_within_group_by_type(
+                web::Json(candidate_data),
+                parsed_query,
+                group,
+                1,
+                search_pool,
+                dataset_org_plan_sub.dataset,
+                server_dataset_config.clone(),
+            )
+            .await?;
+
+            let (bookmarks, _) = diversify_search_rule_page(
+                candidates.bookmarks,
+                false,
+                &diversify,
+                &matching_rules,
+                vec![],
+                page,
+                limit,
+                &None,
+                server_dataset_config,
+            )
+            .await?;
+
+            SearchGroupsResult {
+                bookmarks,
+                group: candidates.group,
+                total_pages: get_diversity_total_pages(
+                    candidates.total_pages,
+                    candidate_limit,
+                    limit,
+                ),
+                search_id: None,
+            }
+        }
+        None if !matching_rules.is_empty() => {
+            let window = get_fused_ranking_window(page, limit, &None)?;
+
+            let mut candidate_data = data.clone();
+            candidate_data.page_size = Some(window);
+
+            let candidates = search_within_group_by_type(
+                web::Json(candidate_data),
+                parsed_query,
+                group,
+                1,
+                search_pool,
+                dataset_org_plan_sub.dataset,
+                server_dataset_config,
+            )
+            .await?;
+
+            let ranking = apply_search_rules(candidates.bookmarks, &matching_rules, vec![]);
+            let total_pages = get_fused_total_pages(ranking.len(), limit);
+            let (bookmarks, _) = page_fused_results(ranking, page, limit, &None, false);
+
+            SearchGroupsResult {
+                bookmarks,
+                group: candidates.group,
+                total_pages,
+                search_id: None,
+            }
+        }
+        None => {
+            search_within_group_by_type(
+                data,
+                parsed_query,
+                group,
+                page,
+                search_pool,
+                dataset_org_plan_sub.dataset,
+                server_dataset_config,
+            )
+            .await?
+        }
+    };
+
+    finish_explanations(&mut result_chunks.bookmarks, rank_offset);
+    timer.add("Ran search");
+
+    result_chunks.search_id = Some(
+        log_search_query(
+            dataset_id,
+            &search_type,
+            &query,
+            &filters,
+            result_chunks
+                .bookmarks
+                .iter()
+                .map(|score_chunk| score_chunk.score),
+            &timer,
+            analytics_pool,
+        )
+        .await,
+    );
+
+    Ok(HttpResponse::Ok().json(result_chunks))
+}
 
-    let result_chunks = match data.search_type.as_str() {
+async fn search_within_group_by_type(
+    data: web::Json<SearchWithinGroupData>,
+    parsed_query: ParsedQuery,
+    group: ChunkGroup,
+    page: u64,
+    search_pool: web::Data<Pool>,
+    dataset: Dataset,
+    server_dataset_config: ServerDatasetConfiguration,
+) -> Result<SearchGroupsResult, actix_web::Error> {
+    match data.search_type.as_str() {
         "fulltext" => {
             if !server_dataset_config.FULLTEXT_ENABLED {
                 return Err(ServiceError::BadRequest(
@@ -1144,10 +1365,10 @@ pub async fn search_within_group(
                 group,
                 page,
                 search_pool,
-                dataset_org_plan_sub.dataset,
+                dataset,
                 server_dataset_config,
             )
-            .await?
+            .await
         }
         "hybrid" => {
             search_hybrid_groups(
@@ -1156,10 +1377,10 @@ pub async fn search_within_group(
                 group,
                 page,
                 search_pool,
-                dataset_org_plan_sub.dataset,
+                dataset,
                 server_dataset_config,
             )
-            .await?
+            .await
         }
         _ => {
             search_semantic_groups(
@@ -1168,43 +1389,154 @@ pub async fn search_within_group(
                 group,
                 page,
                 search_pool,
-                dataset_org_plan_sub.dataset,
+                dataset,
                 server_dataset_config,
             )
-            .await?
+            .await
         }
-    };
+    }
+}
 
-    Ok(HttpResponse::Ok().json(result_chunks))
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupScoreAggregation {
+    /// The score of the best matching chunk in the group. This is how groups are ranked when group_score is not specified.
+    Max,
+    /// The mean score of the k best matching chunks in the group. Groups with fewer than k matching chunks are averaged over the chunks they have.
+    MeanTopK,
+    /// The sum of the scores of the matching chunks in the group.
+    Sum,
+    /// The number of matching chunks in the group scoring at or above the threshold. Ties are broken by the score of the best matching chunk.
+    CountAboveThreshold,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[schema(example = json!({
+    "aggregation": "count_above_threshold",
+    "threshold": 0.6
+}))]
+pub struct GroupScoreOptions {
+    /// How the scores of the matching chunks in a group are combined into the score the group is ranked by. Only the group_size best matching chunks of each group are counted, so raise group_size to count more of them.
+    pub aggregation: GroupScoreAggregation,
+    /// K is the number of chunks averaged by mean_top_k. Groups are fetched with at least k chunks but only group_size chunks are returned. If not specified, this defaults to 3.
+    pub k: Option<u32>,
+    /// Threshold is the score a chunk needs to be counted by count_above_threshold. Scores are on the scale of the search type, so a good threshold for semantic search is not a good one for fulltext search. Required for count_above_threshold.
+    pub threshold: Option<f32>,
+}
+
+impl GroupScoreOptions {
+    /// Validates the options of a request. Returns None when groups are ranked by their best chunk, which needs no aggregation.
+    pub fn resolve(
+        group_score: &Option<GroupScoreOptions>,
+    ) -> Result<Option<GroupScoreOptions>, ServiceError> {
+        let group_score = match group_score {
+            Some(group_score) if group_score.aggregation != GroupScoreAggregation::Max => {
+                group_score.clone()
+            }
+            _ => return Ok(None),
+        };
+
+        if group_score.k == Some(0) {
+            return Err(ServiceError::BadRequest(
+                "group_score.k must be greater than 0".to_string(),
+            ));
+        }
+        if group_score.aggregation == GroupScoreAggregation::CountAboveThreshold
+            && group_score.threshold.is_none()
+        {
+            return Err(ServiceError::BadRequest(
+                "group_score.threshold must be set for count_above_threshold".to_string(),
+            ));
+        }
+
+        Ok(Some(group_score))
+    }
+
+    /// Number of chunks to fetch for each group so that the aggregation sees every chunk it needs.
+    pub fn hits_per_group(&self, group_size: u32) -> u32 {
+        match self.aggregation {
+            GroupScoreAggregation::MeanTopK => group_size.max(self.k.unwrap_or(3)),
+            _ => group_size,
+        }
+    }
+
+    /// Combines the scores of the chunks of a group.
+    pub fn aggregate(&self, scores: &[f64]) -> f64 {
+        let mut scores = scores.to_vec();
+        scores.sort_by(|a, b| b.total_cmp(a));
+
+        match self.aggregation {
+            GroupScoreAggregation::Max => scores.first().copied().unwrap_or(0.0),
+            GroupScoreAggregation::MeanTopK => {
+                let top_k = scores
+                    .iter()
+                    .take(self.k.unwrap_or(3) as usize)
+                    .collect::<Vec<&f64>>();
+                if top_k.is_empty() {
+                    0.0
+                } else {
+                    top_k.iter().copied().sum::<f64>() / top_k.len() as f64
+                }
+            }
+            GroupScoreAggregation::Sum => scores.iter().sum(),
+            GroupScoreAggregation::CountAboveThreshold => {
+                let threshold = self.threshold.unwrap_or(0.0) as f64;
+                scores.iter().filter(|score| **score >= threshold).count() as f64
+            }
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct SearchOverGroupsData {
-    /// Can be either "semantic", "fulltext", or "hybrid". "hybrid" will pull in one page (10 chunks) of both semantic and full-text results then re-rank them using BAAI/bge-reranker-large. "semantic" will pull in one page (10 chunks) of the nearest cosine distant vectors. "fulltext" will pull in one page (10 chunks) of full-text results based on SPLADE.
+    /// Can be either "semantic", "fulltext", or "hybrid". "hybrid" will pull in one page (10 chunks) of both semantic and full-text results then combine them using the fusion_strategy. "semantic" will pull in one page (10 chunks) of the nearest cosine distant vectors. "fulltext" will pull in one page (10 chunks) of full-text results based on SPLADE.
     pub search_type: String,
-    /// Query is the search query. This can be any string. The query will be used to create an embedding vector and/or SPLADE vector which will be used to find the result set.
+    /// Query is the search query. This can be any string. The query will be used to create an embedding vector and/or SPLADE vector which will be used to find the result set. Terms can be combined with AND, OR, NOT (or a leading -), and parentheses. Use "double quotes" for phrases, a trailing * for prefixes, and field:value to filter on tag_set, link, time_stamp, or metadata.<key> (e.g. tag_set:news, metadata.price:>=10, time_stamp:2024-01). Bare words separated by spaces are only used for similarity; quoted, prefixed, and operator-joined terms must appear in the chunk. Negated terms and field filters are not embedded. Malformed queries are rejected with a syntax error. Can be left out when query_vector, query_chunk_id, or query_tracking_id is set.
+    #[serde(default)]
     pub query: String,
     /// Page of chunks to fetch. Each page is 10 chunks. Support for custom page size is coming soon.
     pub page: Option<u64>,
     /// Page size is the number of chunks to fetch. This can be used to fetch more than 10 chunks at a time.
     pub page_size: Option<u32>,
+    /// Cursor is the value returned in the cursor field of a previous search response. If specified, page is ignored and results continue after the last group of that response. Use this to page deterministically through large result sets. Hybrid search pages through a fixed ranking of its top results, at most 1000, so its cursors end there.
+    pub cursor: Option<String>,
     /// Filters is a JSON object which can be used to filter chunks. The values on each key in the object will be used to check for an exact substring match on the metadata values for each existing chunk. This is useful for when you want to filter chunks by arbitrary metadata. Unlike with tag filtering, there is a performance hit for filtering on metadata.
     pub filters: Option<ChunkFilter>,
     /// Set get_collisions to true to get the collisions for each chunk. This will only apply if environment variable COLLISIONS_ENABLED is set to true.
     pub get_collisions: Option<bool>,
-    /// Set highlight_results to true to highlight the results. If not specified, this defaults to true.
+    /// Set highlight_results to true to return the highlights field on each score chunk with the spans of content that matched the query and snippets around them. Chunk_html is never modified. If not specified, this defaults to true.
     pub highlight_results: Option<bool>,
-    /// Set highlight_delimiters to a list of strings to use as delimiters for highlighting. If not specified, this defaults to ["?", ",", ".", "!"].
+    /// Set highlight_delimiters to a list of strings which end a sentence. Only used when none of the query terms appear in a chunk, in which case the sentences most similar to the query are highlighted instead. If not specified, this defaults to [".", "!", "?", "\n", "\t", ","].
     pub highlight_delimiters: Option<Vec<String>>,
+    /// Highlight_window is the number of words of context to keep on each side of a match in a snippet. If not specified, this defaults to 10.
+    pub highlight_window: Option<u32>,
+    /// Highlight_max_snippets is the maximum number of snippets returned for each chunk. Snippets covering the most distinct query terms are kept. If not specified, this defaults to 3.
+    pub highlight_max_snippets: Option<u32>,
     /// Set score_threshold to a float to filter out chunks with a score below the threshold.
     pub score_threshold: Option<f32>,
     // Group_size is the number of chunks to fetch for each group.
     pub group_size: Option<u32>,
+    /// Facets is a list of fields to count values for over every chunk matching the filters. Can be "tag_set", "link", "metadata.<key>", or "time_stamp". Time stamps are bucketed by month by default, use "time_stamp:day" or "time_stamp:year" for other intervals.
+    pub facets: Option<Vec<String>>,
+    /// Query_vector is a dense vector to search with instead of the embedding of the query. It must have the same number of dimensions as the embeddings of the dataset. Use this to search with vectors from your own embedding model. If a text query is also given, it is still used for the full-text results of hybrid search and for field filters.
+    pub query_vector: Option<Vec<f32>>,
+    /// Query_chunk_id is the id of a chunk in the dataset whose vector is used as the query vector. The chunk itself will usually be the top result.
+    pub query_chunk_id: Option<uuid::Uuid>,
+    /// Query_tracking_id is the tracking_id of a chunk in the dataset whose vector is used as the query vector. The chunk itself will usually be the top result.
+    pub query_tracking_id: Option<String>,
+    /// Fusion_strategy controls how semantic and full-text results are combined in hybrid search. Can be "rrf", "weighted_linear", or "cross_encoder". If not specified, this defaults to "cross_encoder" when a reranker server is configured and "rrf" otherwise.
+    pub fusion_strategy: Option<FusionStrategy>,
+    /// Fusion_alpha is the weight given to the semantic scores when fusion_strategy is "weighted_linear". Must be between 0 and 1. If not specified, this defaults to 0.5.
+    pub fusion_alpha: Option<f32>,
+    /// Diversify keeps one page of results from being filled with near-identical groups. Each group is represented by its best matching chunk. Candidate groups are fetched several pages deep, then re-selected with maximal marginal relevance and per-link or per-file caps on those chunks.
+    pub diversify: Option<DiversifyOptions>,
+    /// Group_score controls how groups are ranked from the scores of their matching chunks, e.g. to rank files by how many strong chunks they contain. Applies to semantic, fulltext, and hybrid search. For hybrid search the groups of each retriever are scored before the results are fused. If not specified, groups are ranked by their best matching chunk.
+    pub group_score: Option<GroupScoreOptions>,
 }
 
 /// Search Over Groups
 ///
-/// This route allows you to get groups as results instead of chunks. Each group returned will have the matching chunks sorted by similarity within the group. This is useful for when you want to get groups of chunks which are similar to the search query. If choosing hybrid search, the results will be re-ranked using BAAI/bge-reranker-large. Compatible with semantic, fulltext, or hybrid search modes.
+/// This route allows you to get groups as results instead of chunks. Each group returned will have the matching chunks sorted by similarity within the group. This is useful for when you want to get groups of chunks which are similar to the search query. If choosing hybrid search, the results will be re-ranked using BAAI/bge-reranker-large. Compatible with semantic, fulltext, or hybrid search modes. Search rules matching the query apply to the groups: groups holding a pinned chunk lead the results in pin order, groups whose chunks are all buried follow every other group, and boosts raise the groups of the boosted chunks. Pins only lift groups the search found.
 #[utoipa::path(
     post,
     path = "/chunk_group/group_oriented_search",
@@ -1227,12 +1559,169 @@ pub async fn search_over_groups(
         dataset_org_plan_sub.dataset.server_configuration.clone(),
     );
 
+    let mut timer = Timer::new();
+
     //search over the links as well
     let page = data.page.unwrap_or(1);
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+    let analytics_pool = pool.clone();
+    let query = data.query.clone();
+    let search_type = data.search_type.clone();
+    let filters = data.filters.clone();
+
+    let parsed_query = parse_query(data.query.clone())?;
+
+    let embedding_vector = get_query_vector(
+        &data.search_type,
+        &parsed_query,
+        data.query_vector.clone(),
+        data.query_chunk_id,
+        data.query_tracking_id.clone(),
+        dataset_org_plan_sub.dataset.id,
+        pool.clone(),
+        &server_dataset_config,
+    )
+    .await?;
 
-    let parsed_query = parse_query(data.query.clone());
+    let facets_future = get_facet_counts_query(
+        data.facets.clone(),
+        data.filters.clone(),
+        parsed_query.clone(),
+        dataset_org_plan_sub.dataset.id,
+        pool.clone(),
+        server_dataset_config.clone(),
+    );
+
+    let matching_rules = get_matching_search_rules_query(&query, dataset_id, pool.clone()).await?;
+    let limit: u64 = data.page_size.unwrap_or(10).into();
+    let cursor = decode_search_cursor(&data.cursor)?;
+
+    let search_future = async {
+        match data.diversify.clone() {
+            Some(diversify) => {
+                let candidate_limit = get_diversity_candidate_limit(page, limit, &cursor)?;
+
+                let mut candidate_data = data.clone();
+                candidate_data.page_size = Some(candidate_limit as u32);
+                candidate_data.cursor = None;
+
+                let candidates = search_over_groups_by_type(
+                    web::Json(candidate_data),
+                    parsed_query,
+                    embedding_vector,
+                    1,
+                    pool,
+                    dataset_org_plan_sub.dataset,
+                    server_dataset_config.clone(),
+                )
+                .await?;
+
+                let (group_chunks, next_cursor) = diversify_search_rule_group_page(
+                    candidates.group_chunks,
+                    candidates.cursor.is_some(),
+                    &diversify,
+                    &matching_rules,
+                    page,
+                    limit,
+                    &cursor,
+                    server_dataset_config.clone(),
+                )
+                .await?;
+
+                Ok(SearchOverGroupsResponseBody {
+                    group_chunks,
+                    total_chunk_pages: get_diversity_total_pages(
+                        candidates.total_chunk_pages,
+                        candidate_limit,
+                        limit,
+                    ),
+                    cursor: next_cursor.map(|cursor| cursor.encode()),
+                    facets: None,
+                    search_id: None,
+                })
+            }
+            None if !matching_rules.is_empty() => {
+                let window = get_fused_ranking_window(page, limit, &cursor)?;
+
+                let mut candidate_data = data.clone();
+                candidate_data.page_size = Some(window as u32);
+                candidate_data.cursor = None;
+
+                let candidates = search_over_groups_by_type(
+                    web::Json(candidate_data),
+                    parsed_query,
+                    embedding_vector,
+                    1,
+                    pool,
+                    dataset_org_plan_sub.dataset,
+                    server_dataset_config.clone(),
+                )
+                .await?;
+
+                let ranking =
+                    apply_search_rules_to_groups(candidates.group_chunks, &matching_rules);
+                let total_chunk_pages = get_fused_total_pages(ranking.len(), limit);
+                let (group_chunks, next_cursor) =
+                    page_fused_results(ranking, page, limit, &cursor, false);
+
+                Ok(SearchOverGroupsResponseBody {
+                    group_chunks,
+                    total_chunk_pages,
+                    cursor: next_cursor.map(|cursor| cursor.with_window(window).encode()),
+                    facets: None,
+                    search_id: None,
+                })
+            }
+            None => {
+                search_over_groups_by_type(
+                    data,
+                    parsed_query,
+                    embedding_vector,
+                    page,
+                    pool,
+                    dataset_org_plan_sub.dataset,
+                    server_dataset_config.clone(),
+                )
+                .await
+            }
+        }
+    };
 
-    let result_chunks = match data.search_type.as_str() {
+    let (result_chunks, facets) = futures::join!(search_future, facets_future);
+    let mut result_chunks: SearchOverGroupsResponseBody = result_chunks?;
+    result_chunks.facets = facets?;
+    timer.add("Ran search");
+
+    result_chunks.search_id = Some(
+        log_search_query(
+            dataset_id,
+            &search_type,
+            &query,
+            &filters,
+            result_chunks
+                .group_chunks
+                .iter()
+                .flat_map(|group_chunk| group_chunk.metadata.iter())
+                .map(|score_chunk| score_chunk.score),
+            &timer,
+            analytics_pool,
+        )
+        .await,
+    );
+
+    Ok(HttpResponse::Ok().json(result_chunks))
+}
+
+async fn search_over_groups_by_type(
+    data: web::Json<SearchOverGroupsData>,
+    parsed_query: ParsedQuery,
+    embedding_vector: Option<Vec<f32>>,
+    page: u64,
+    pool: web::Data<Pool>,
+    dataset: Dataset,
+    server_dataset_config: ServerDatasetConfiguration,
+) -> Result<SearchOverGroupsResponseBody, actix_web::Error> {
+    match data.search_type.as_str() {
         "fulltext" => {
             if !server_dataset_config.FULLTEXT_ENABLED {
                 return Err(ServiceError::BadRequest(
@@ -1246,34 +1735,34 @@ pub async fn search_over_groups(
                 parsed_query,
                 page,
                 pool,
-                dataset_org_plan_sub.dataset,
+                dataset,
                 server_dataset_config,
             )
-            .await?
+            .await
         }
         "hybrid" => {
             hybrid_search_over_groups(
                 data,
                 parsed_query,
+                embedding_vector,
                 page,
                 pool,
-                dataset_org_plan_sub.dataset,
+                dataset,
                 server_dataset_config,
             )
-            .await?
+            .await
         }
         _ => {
             semantic_search_over_groups(
                 data,
                 parsed_query,
+                embedding_vector,
                 page,
                 pool,
-                dataset_org_plan_sub.dataset,
+                dataset,
                 server_dataset_config,
             )
-            .await?
+            .await
         }
-    };
-
-    Ok(HttpResponse::Ok().json(result_chunks))
+    }
 }
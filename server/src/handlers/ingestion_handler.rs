@@ -0,0 +1,299 @@
+use super::auth_handler::AdminOnly;
+use crate::{
+    data::models::{DatasetAndOrgWithSubAndPlan, IngestionStatus, Pool, RedisPool},
+    errors::ServiceError,
+    operators::{
+        ingestion_queue_operator::{
+            get_dead_lettered_ingestion_messages_query, get_ingestion_message_chunk_id,
+            purge_dead_lettered_ingestion_messages_query,
+            requeue_dead_lettered_ingestion_messages_query,
+        },
+        ingestion_status_operator::{
+            get_batch_ingestion_status_query, get_chunk_ingestion_status_by_tracking_id_query,
+            get_chunk_ingestion_status_query, update_chunk_ingestion_statuses_query,
+        },
+    },
+};
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct GetDeadLetteredMessagesQuery {
+    /// The page number to get. Default is 1.
+    pub page: Option<i64>,
+    /// The number of items per page. Default is 10 and cannot exceed 100.
+    pub page_size: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[schema(example = json!({
+    "message_ids": ["e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3"]
+}))]
+pub struct DeadLetteredMessagesData {
+    /// Ids of the dead-lettered messages to act on. Leave undefined to act on every dead-lettered message of the dataset.
+    pub message_ids: Option<Vec<uuid::Uuid>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct DeadLetteredMessagesCount {
+    /// How many dead-lettered messages were acted on.
+    pub count: usize,
+}
+
+/// Get Dead-Lettered Ingestion Messages
+///
+/// Get the ingestion messages of the dataset which failed permanently or exhausted their retries, newest first. Each message includes the error of its last attempt. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    get,
+    path = "/ingestion/dead_letter",
+    context_path = "/api",
+    tag = "ingestion",
+    responses(
+        (status = 200, description = "The dead-lettered messages of the dataset", body = Vec<DeadLetteredIngestionMessage>),
+        (status = 400, description = "Service error relating to getting the dead-lettered messages", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+        ("page" = Option<i64>, Query, description = "The page number to get. Default is 1."),
+        ("page_size" = Option<i64>, Query, description = "The number of items per page. Default is 10 and cannot exceed 100."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(redis_pool))]
+pub async fn get_dead_lettered_messages(
+    data: web::Query<GetDeadLetteredMessagesQuery>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let page = data.page.unwrap_or(1).max(1);
+    let page_size = data.page_size.unwrap_or(10);
+    if !(1..=100).contains(&page_size) {
+        return Err(
+            ServiceError::BadRequest("page_size must be between 1 and 100".to_string()).into(),
+        );
+    }
+
+    let dead_lettered_messages = get_dead_lettered_ingestion_messages_query(
+        dataset_org_plan_sub.dataset.id,
+        page,
+        page_size,
+        redis_pool,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(dead_lettered_messages))
+}
+
+/// Requeue Dead-Lettered Ingestion Messages
+///
+/// Queue dead-lettered messages of the dataset for ingestion again, with a fresh set of retries. They are processed ahead of the rest of the queue. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    post,
+    path = "/ingestion/dead_letter/requeue",
+    context_path = "/api",
+    tag = "ingestion",
+    request_body(content = DeadLetteredMessagesData, description = "JSON request payload to pick the dead-lettered messages to requeue", content_type = "application/json"),
+    responses(
+        (status = 200, description = "How many messages were requeued", body = DeadLetteredMessagesCount),
+        (status = 400, description = "Service error relating to requeuing the dead-lettered messages", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool, redis_pool))]
+pub async fn requeue_dead_lettered_messages(
+    data: web::Json<DeadLetteredMessagesData>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let requeued_messages = requeue_dead_lettered_ingestion_messages_query(
+        dataset_org_plan_sub.dataset.id,
+        data.into_inner().message_ids,
+        redis_pool,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    // The messages are already queued, so a failure to update their statuses is only logged
+    let _ = update_chunk_ingestion_statuses_query(
+        requeued_messages
+            .iter()
+            .filter_map(|message| get_ingestion_message_chunk_id(&message.payload))
+            .collect(),
+        IngestionStatus::Queued,
+        None,
+        None,
+        pool,
+    )
+    .await
+    .map_err(|err| {
+        log::error!("Failed to mark requeued chunks as queued {:?}", err);
+    });
+
+    Ok(HttpResponse::Ok().json(DeadLetteredMessagesCount {
+        count: requeued_messages.len(),
+    }))
+}
+
+/// Purge Dead-Lettered Ingestion Messages
+///
+/// Delete dead-lettered messages of the dataset without processing them. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    delete,
+    path = "/ingestion/dead_letter",
+    context_path = "/api",
+    tag = "ingestion",
+    request_body(content = DeadLetteredMessagesData, description = "JSON request payload to pick the dead-lettered messages to purge", content_type = "application/json"),
+    responses(
+        (status = 200, description = "How many messages were purged", body = DeadLetteredMessagesCount),
+        (status = 400, description = "Service error relating to purging the dead-lettered messages", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(redis_pool))]
+pub async fn purge_dead_lettered_messages(
+    data: web::Json<DeadLetteredMessagesData>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let count = purge_dead_lettered_ingestion_messages_query(
+        dataset_org_plan_sub.dataset.id,
+        data.into_inner().message_ids,
+        redis_pool,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(DeadLetteredMessagesCount { count }))
+}
+
+/// Get Chunk Ingestion Status
+///
+/// Get whether a queued chunk is still queued, being processed, indexed, collided with an existing chunk, or failed. The id is the one returned when the chunk was queued. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    get,
+    path = "/ingestion/status/{chunk_id}",
+    context_path = "/api",
+    tag = "ingestion",
+    responses(
+        (status = 200, description = "The ingestion status of the chunk", body = ChunkIngestionStatus),
+        (status = 400, description = "Service error relating to getting the ingestion status", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+        ("chunk_id" = uuid::Uuid, Path, description = "Id of the chunk to get the ingestion status of"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn get_chunk_ingestion_status(
+    chunk_id: web::Path<uuid::Uuid>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let chunk_ingestion_status = get_chunk_ingestion_status_query(
+        chunk_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(chunk_ingestion_status))
+}
+
+/// Get Chunk Ingestion Status by Tracking ID
+///
+/// Get the ingestion status of the chunk with the tracking_id which was queued most recently. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    get,
+    path = "/ingestion/status/tracking_id/{tracking_id}",
+    context_path = "/api",
+    tag = "ingestion",
+    responses(
+        (status = 200, description = "The ingestion status of the chunk", body = ChunkIngestionStatus),
+        (status = 400, description = "Service error relating to getting the ingestion status", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+        ("tracking_id" = String, Path, description = "Tracking id of the chunk to get the ingestion status of"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn get_chunk_ingestion_status_by_tracking_id(
+    tracking_id: web::Path<String>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let chunk_ingestion_status = get_chunk_ingestion_status_by_tracking_id_query(
+        tracking_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(chunk_ingestion_status))
+}
+
+/// Get Batch Ingestion Status
+///
+/// Get the ingestion status of every chunk queued in a batch along with how many chunks are in each status. The batch is finished, and every chunk of it which did not fail is searchable, once no chunk is queued or processing. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    get,
+    path = "/ingestion/status/batch/{batch_id}",
+    context_path = "/api",
+    tag = "ingestion",
+    responses(
+        (status = 200, description = "The ingestion status of the batch", body = BatchIngestionStatus),
+        (status = 400, description = "Service error relating to getting the ingestion status", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+        ("batch_id" = uuid::Uuid, Path, description = "Id of the batch returned when the chunks were queued"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn get_batch_ingestion_status(
+    batch_id: web::Path<uuid::Uuid>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let batch_ingestion_status = get_batch_ingestion_status_query(
+        batch_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(batch_ingestion_status))
+}
@@ -1,8 +1,9 @@
-use super::auth_handler::AdminOnly;
+use super::auth_handler::{AdminOnly, AuditActor};
 use crate::{
-    data::models::{Invitation, Pool, RedisPool},
+    data::models::{AuditAction, AuditLog, Invitation, Pool, RedisPool},
     errors::{DefaultError, ServiceError},
     operators::{
+        audit_operator::log_audit_actions,
         invitation_operator::{create_invitation_query, send_invitation},
         user_operator::add_existing_user_to_org,
     },
@@ -62,6 +63,7 @@ pub async fn post_invitation(
     redis_pool: web::Data<RedisPool>,
     pool: web::Data<Pool>,
     user: AdminOnly,
+    actor: AuditActor,
 ) -> Result<HttpResponse, actix_web::Error> {
     let invitation_data = invitation_data.into_inner();
     let email = invitation_data.email;
@@ -100,7 +102,21 @@ pub async fn post_invitation(
     .await
     .map_err(|e| ServiceError::BadRequest(e.message.to_string()))?;
 
+    let audit_log = AuditLog::from_details(
+        invitation_data.organization_id,
+        None,
+        actor.user_id,
+        actor.api_key_id,
+        AuditAction::InvitationSent,
+        Some(email.clone()),
+        serde_json::json!({
+            "role": invitation_data.user_role,
+            "added_existing_user": added_user_to_org,
+        }),
+    );
+
     if added_user_to_org {
+        log_audit_actions(vec![audit_log], pool).await;
         return Ok(HttpResponse::NoContent().finish());
     }
 
@@ -110,7 +126,7 @@ pub async fn post_invitation(
         invitation_data.organization_id,
         invitation_data.redirect_uri,
         invitation_data.user_role,
-        pool,
+        pool.clone(),
     )
     .await
     .map_err(|e| ServiceError::BadRequest(e.message.to_string()))?;
@@ -121,6 +137,8 @@ pub async fn post_invitation(
             ServiceError::BadRequest(format!("Could not send invitation: {}", e.message))
         })?;
 
+    log_audit_actions(vec![audit_log], pool).await;
+
     Ok(HttpResponse::NoContent().finish())
 }
 
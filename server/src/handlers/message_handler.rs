@@ -5,9 +5,8 @@ use crate::{
     errors::{DefaultError, ServiceError},
     get_env,
     operators::{
-        chunk_operator::{
-            find_relevant_sentence, get_metadata_and_collided_chunks_from_point_ids_query,
-        },
+        chunk_operator::get_metadata_and_collided_chunks_from_point_ids_query,
+        highlight_operator::{ChunkHighlights, Highlighter},
         message_operator::{
             create_message_query, create_topic_message_query, delete_message_query,
             get_message_by_sort_for_topic_query, get_messages_for_topic_query, get_topic_messages,
@@ -45,9 +44,9 @@ pub struct CreateMessageData {
     pub topic_id: uuid::Uuid,
     /// Whether or not to stream the response. If this is set to true or not included, the response will be a stream. If this is set to false, the response will be a normal JSON response. Default is true.
     pub stream_response: Option<bool>,
-    /// Whether or not to highlight the citations in the response. If this is set to true or not included, the citations will be highlighted. If this is set to false, the citations will not be highlighted. Default is true.
+    /// Whether or not to highlight the citations in the response. If this is set to true or not included, each citation will have a highlights field with the spans of its content which matched the search query and snippets around them. Chunk_html is never modified. If this is set to false, the citations will not be highlighted. Default is true.
     pub highlight_citations: Option<bool>,
-    /// The delimiters to use for highlighting the citations. If this is not included, the default delimiters will be used. Default is `[".", "!", "?", "\n", "\t", ","]`.
+    /// The delimiters which end a sentence in a citation. They are only used when none of the search terms appear in a citation, in which case the sentences most similar to the search query are highlighted instead. Default is `[".", "!", "?", "\n", "\t", ","]`.
     pub highlight_delimiters: Option<Vec<String>>,
 }
 
@@ -235,9 +234,9 @@ pub struct RegenerateMessageData {
     topic_id: uuid::Uuid,
     /// Whether or not to stream the response. If this is set to true or not included, the response will be a stream. If this is set to false, the response will be a normal JSON response. Default is true.
     pub stream_response: Option<bool>,
-    /// Whether or not to highlight the citations in the response. If this is set to true or not included, the citations will be highlighted. If this is set to false, the citations will not be highlighted. Default is true.
+    /// Whether or not to highlight the citations in the response. If this is set to true or not included, each citation will have a highlights field with the spans of its content which matched the search query and snippets around them. Chunk_html is never modified. If this is set to false, the citations will not be highlighted. Default is true.
     pub highlight_citations: Option<bool>,
-    /// The delimiters to use for highlighting the citations. If this is not included, the default delimiters will be used. Default is `[".", "!", "?", "\n", "\t", ","]`.  
+    /// The delimiters which end a sentence in a citation. They are only used when none of the search terms appear in a citation, in which case the sentences most similar to the search query are highlighted instead. Default is `[".", "!", "?", "\n", "\t", ","]`.
     pub highlight_delimiters: Option<Vec<String>>,
 }
 
@@ -253,9 +252,9 @@ pub struct EditMessageData {
     new_message_content: String,
     /// Whether or not to stream the response. If this is set to true or not included, the response will be a stream. If this is set to false, the response will be a normal JSON response. Default is true.
     pub stream_response: Option<bool>,
-    /// Whether or not to highlight the citations in the response. If this is set to true or not included, the citations will be highlighted. If this is set to false, the citations will not be highlighted. Default is true.
+    /// Whether or not to highlight the citations in the response. If this is set to true or not included, each citation will have a highlights field with the spans of its content which matched the search query and snippets around them. Chunk_html is never modified. If this is set to false, the citations will not be highlighted. Default is true.
     pub highlight_citations: Option<bool>,
-    /// The delimiters to use for highlighting the citations. If this is not included, the default delimiters will be used. Default is `[".", "!", "?", "\n", "\t", ","]`.
+    /// The delimiters which end a sentence in a citation. They are only used when none of the search terms appear in a citation, in which case the sentences most similar to the search query are highlighted instead. Default is `[".", "!", "?", "\n", "\t", ","]`.
     pub highlight_delimiters: Option<Vec<String>>,
 }
 
@@ -552,6 +551,16 @@ pub async fn get_topic_string(
     Ok(topic)
 }
 
+/// A chunk cited in a RAG response. Its fields are flattened so citations keep the shape of a chunk.
+#[derive(Serialize, Debug)]
+pub struct CitationChunk {
+    #[serde(flatten)]
+    pub chunk: ChunkMetadataWithFileData,
+    /// Where the search query of the response matched the content of the chunk.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlights: Option<ChunkHighlights>,
+}
+
 #[allow(clippy::too_many_arguments)]
 #[tracing::instrument(skip(pool))]
 pub async fn stream_response(
@@ -688,14 +697,11 @@ pub async fn stream_response(
     let search_chunk_query_results = retrieve_qdrant_points_query(
         VectorType::Dense(embedding_vector),
         1,
+        None,
         n_retrievals_to_include.try_into().unwrap(),
         None,
         None,
-        ParsedQuery {
-            query: query.to_string(),
-            quote_words: None,
-            negated_words: None,
-        },
+        ParsedQuery::from_text(query.to_string()),
         dataset.id,
         pool.clone(),
         config,
@@ -719,28 +725,22 @@ pub async fn stream_response(
 
     let citation_chunks: Vec<ChunkMetadataWithFileData> = metadata_chunks.to_vec();
 
-    let highlighted_citation_chunks = if highlight_citations.unwrap_or(true) {
-        citation_chunks
-            .iter()
-            .map(|chunk| {
-                find_relevant_sentence(
-                    chunk.clone(),
-                    query.to_string(),
-                    highlight_delimiters.clone().unwrap_or(vec![
-                        ".".to_string(),
-                        "!".to_string(),
-                        "?".to_string(),
-                        "\n".to_string(),
-                        "\t".to_string(),
-                        ",".to_string(),
-                    ]),
-                )
-                .unwrap_or(chunk.clone())
-            })
-            .collect::<Vec<ChunkMetadataWithFileData>>()
-    } else {
-        citation_chunks.clone()
-    };
+    let highlighter = Highlighter::new(
+        &query,
+        highlight_citations,
+        highlight_delimiters,
+        None,
+        None,
+    );
+    let highlighted_citation_chunks = citation_chunks
+        .iter()
+        .map(|chunk| CitationChunk {
+            chunk: chunk.clone(),
+            highlights: highlighter
+                .as_ref()
+                .and_then(|highlighter| highlighter.highlight(&chunk.content)),
+        })
+        .collect::<Vec<CitationChunk>>();
 
     citation_chunks_stringified = serde_json::to_string(&highlighted_citation_chunks)
         .expect("Failed to serialize citation chunks");
@@ -0,0 +1,45 @@
+use crate::{
+    errors::ServiceError,
+    operators::metrics_operator::{gather_metrics, metrics_content_type},
+};
+use actix_web::{HttpRequest, HttpResponse};
+
+/// Metrics are only served to scrapers which send the METRICS_TOKEN as a bearer token. Without a METRICS_TOKEN the metrics are not served at all.
+fn is_metrics_request_authorized(req: &HttpRequest) -> bool {
+    let metrics_token = match std::env::var("METRICS_TOKEN") {
+        Ok(metrics_token) if !metrics_token.is_empty() => metrics_token,
+        _ => return false,
+    };
+
+    req.headers()
+        .get("Authorization")
+        .and_then(|authorization| authorization.to_str().ok())
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        .is_some_and(|token| {
+            token.len() == metrics_token.len()
+                && openssl::memcmp::eq(token.as_bytes(), metrics_token.as_bytes())
+        })
+}
+
+/// Metrics
+///
+/// Metrics of the server in the Prometheus text format. Includes request counts and latencies per route, the latency and errors of calls to the embedding, SPLADE, and reranker servers, and the latency of calls to Qdrant. The METRICS_TOKEN of the server must be sent as a bearer token in the Authorization header.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "metrics",
+    responses(
+        (status = 200, description = "Metrics of the server in the Prometheus text format", body = String),
+        (status = 401, description = "The METRICS_TOKEN of the server was not sent or the server has none"),
+    ),
+)]
+#[tracing::instrument(skip(req))]
+pub async fn get_metrics(req: HttpRequest) -> Result<HttpResponse, actix_web::Error> {
+    if !is_metrics_request_authorized(&req) {
+        return Err(ServiceError::Unauthorized.into());
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(metrics_content_type())
+        .body(gather_metrics()))
+}
@@ -1,8 +1,9 @@
-use super::auth_handler::{AdminOnly, LoggedUser, OwnerOnly};
+use super::auth_handler::{AdminOnly, AuditActor, LoggedUser, OwnerOnly};
 use crate::{
-    data::models::{Pool, RedisPool, UserOrganization, UserRole},
+    data::models::{AuditAction, AuditLog, Pool, RedisPool, UserOrganization, UserRole},
     errors::ServiceError,
     operators::{
+        audit_operator::log_audit_actions,
         organization_operator::{
             create_organization_query, delete_organization_query, get_org_usage_by_id_query,
             get_org_users_by_id_query, get_organization_by_key_query, update_organization_query,
@@ -127,6 +128,7 @@ pub async fn update_organization(
     pool: web::Data<Pool>,
     redis_pool: web::Data<RedisPool>,
     _user: OwnerOnly,
+    actor: AuditActor,
 ) -> Result<HttpResponse, actix_web::Error> {
     let organization_update_data = organization.into_inner();
     let old_organization = get_organization_by_key_query(
@@ -141,14 +143,31 @@ pub async fn update_organization(
         organization_update_data.organization_id,
         organization_update_data
             .name
-            .unwrap_or(old_organization.name)
+            .unwrap_or(old_organization.name.clone())
             .as_str(),
         redis_pool,
-        pool,
+        pool.clone(),
     )
     .await
     .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
 
+    log_audit_actions(
+        vec![AuditLog::from_details(
+            updated_organization.id,
+            None,
+            actor.user_id,
+            actor.api_key_id,
+            AuditAction::OrganizationUpdated,
+            Some(updated_organization.id.to_string()),
+            serde_json::json!({
+                "old_name": old_organization.name,
+                "new_name": updated_organization.name,
+            }),
+        )],
+        pool,
+    )
+    .await;
+
     Ok(HttpResponse::Ok().json(updated_organization))
 }
 
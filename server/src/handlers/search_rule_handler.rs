@@ -0,0 +1,253 @@
+use super::auth_handler::AdminOnly;
+use crate::{
+    data::models::{
+        DatasetAndOrgWithSubAndPlan, Pool, RedisPool, SearchRule, SearchRuleActions,
+        SearchRuleMatchType,
+    },
+    errors::ServiceError,
+    operators::{
+        cache_operator::bump_search_cache_generation,
+        search_rule_operator::{
+            create_search_rule_query, delete_search_rule_query, get_search_rules_query,
+            update_search_rule_query,
+        },
+    },
+};
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[schema(example = json!({
+    "query_pattern": "carbon tax",
+    "match_type": "contains",
+    "actions": {
+        "pin": ["d290f1ee-6c54-4b01-90e6-d701748f0851"],
+        "bury": ["e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3"],
+        "boost": [{"tag": "canonical", "factor": 2.0}]
+    }
+}))]
+pub struct CreateSearchRuleData {
+    /// The pattern the search query is matched against. Matching ignores case and extra whitespace.
+    pub query_pattern: String,
+    /// How the query is matched against query_pattern. Can be "exact", "contains", or "regex".
+    pub match_type: SearchRuleMatchType,
+    /// What the rule does to the results of a matching search.
+    pub actions: SearchRuleActions,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[schema(example = json!({
+    "search_rule_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "query_pattern": "carbon tax",
+    "match_type": "exact",
+    "actions": {
+        "pin": ["d290f1ee-6c54-4b01-90e6-d701748f0851"]
+    }
+}))]
+pub struct UpdateSearchRuleData {
+    /// Id of the search rule to update.
+    pub search_rule_id: uuid::Uuid,
+    /// The pattern the search query is matched against. Matching ignores case and extra whitespace.
+    pub query_pattern: String,
+    /// How the query is matched against query_pattern. Can be "exact", "contains", or "regex".
+    pub match_type: SearchRuleMatchType,
+    /// What the rule does to the results of a matching search. Replaces the previous actions of the rule.
+    pub actions: SearchRuleActions,
+}
+
+fn validate_search_rule(
+    query_pattern: &str,
+    match_type: SearchRuleMatchType,
+    actions: &SearchRuleActions,
+) -> Result<(), ServiceError> {
+    if query_pattern.trim().is_empty() {
+        return Err(ServiceError::BadRequest(
+            "query_pattern must not be empty".to_string(),
+        ));
+    }
+    if match_type == SearchRuleMatchType::Regex && regex::Regex::new(query_pattern).is_err() {
+        return Err(ServiceError::BadRequest(
+            "query_pattern is not a valid regular expression".to_string(),
+        ));
+    }
+    if actions
+        .boost
+        .iter()
+        .any(|boost| !boost.factor.is_finite() || boost.factor <= 0.0)
+    {
+        return Err(ServiceError::BadRequest(
+            "Boost factors must be greater than 0".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Create Search Rule
+///
+/// Create a rule which pins, buries, or boosts chunks in the results of searches whose query matches its pattern. Rules apply to every search mode and reorder the whole ranking, so searches with a matching rule page through a window of results read from the first one, the same way hybrid searches do. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    post,
+    path = "/search_rule",
+    context_path = "/api",
+    tag = "search_rule",
+    request_body(content = CreateSearchRuleData, description = "JSON request payload to create a search rule", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The created search rule", body = SearchRule),
+        (status = 400, description = "Service error relating to creating the search rule", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool, redis_pool))]
+pub async fn create_search_rule(
+    data: web::Json<CreateSearchRuleData>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let data = data.into_inner();
+    validate_search_rule(&data.query_pattern, data.match_type, &data.actions)?;
+
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+    let search_rule = create_search_rule_query(
+        SearchRule::from_details(
+            dataset_id,
+            data.query_pattern,
+            data.match_type,
+            data.actions,
+        ),
+        pool,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    bump_search_cache_generation(dataset_id, redis_pool).await?;
+
+    Ok(HttpResponse::Ok().json(search_rule))
+}
+
+/// Get Search Rules
+///
+/// Get every search rule of the dataset, oldest first. When several rules match a query, their pins are ordered the same way.
+#[utoipa::path(
+    get,
+    path = "/search_rule",
+    context_path = "/api",
+    tag = "search_rule",
+    responses(
+        (status = 200, description = "The search rules of the dataset", body = Vec<SearchRule>),
+        (status = 400, description = "Service error relating to getting the search rules", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn get_search_rules(
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let search_rules = get_search_rules_query(dataset_org_plan_sub.dataset.id, pool)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(search_rules))
+}
+
+/// Update Search Rule
+///
+/// Replace the pattern, match type, and actions of a search rule. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    put,
+    path = "/search_rule",
+    context_path = "/api",
+    tag = "search_rule",
+    request_body(content = UpdateSearchRuleData, description = "JSON request payload to update a search rule", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The updated search rule", body = SearchRule),
+        (status = 400, description = "Service error relating to updating the search rule", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool, redis_pool))]
+pub async fn update_search_rule(
+    data: web::Json<UpdateSearchRuleData>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let data = data.into_inner();
+    validate_search_rule(&data.query_pattern, data.match_type, &data.actions)?;
+
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+    let mut search_rule = SearchRule::from_details(
+        dataset_id,
+        data.query_pattern,
+        data.match_type,
+        data.actions,
+    );
+    search_rule.id = data.search_rule_id;
+
+    let search_rule = update_search_rule_query(search_rule, pool)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    bump_search_cache_generation(dataset_id, redis_pool).await?;
+
+    Ok(HttpResponse::Ok().json(search_rule))
+}
+
+/// Delete Search Rule
+///
+/// Delete a search rule. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    delete,
+    path = "/search_rule/{search_rule_id}",
+    context_path = "/api",
+    tag = "search_rule",
+    responses(
+        (status = 204, description = "Confirmation that the search rule was deleted"),
+        (status = 400, description = "Service error relating to deleting the search rule", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+        ("search_rule_id" = uuid::Uuid, description = "Id of the search rule to delete"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool, redis_pool))]
+pub async fn delete_search_rule(
+    search_rule_id: web::Path<uuid::Uuid>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let dataset_id = dataset_org_plan_sub.dataset.id;
+    delete_search_rule_query(search_rule_id.into_inner(), dataset_id, pool)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    bump_search_cache_generation(dataset_id, redis_pool).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
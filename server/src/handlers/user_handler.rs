@@ -1,10 +1,13 @@
-use super::auth_handler::LoggedUser;
+use super::auth_handler::{AuditActor, LoggedUser};
 use crate::{
-    data::models::{Pool, SlimUser},
+    data::models::{AuditAction, AuditLog, Pool, SlimUser},
     errors::{DefaultError, ServiceError},
-    operators::user_operator::{
-        delete_user_api_keys_query, get_user_api_keys_query, get_user_by_id_query,
-        set_user_api_key_query, update_user_query,
+    operators::{
+        audit_operator::log_audit_actions,
+        user_operator::{
+            delete_user_api_keys_query, get_user_api_keys_query, get_user_by_id_query,
+            set_user_api_key_query, update_user_query,
+        },
     },
 };
 use actix_web::{web, HttpResponse};
@@ -58,6 +61,7 @@ pub struct GetUserWithChunksData {
 pub async fn update_user(
     data: web::Json<UpdateUserData>,
     mut user: LoggedUser,
+    actor: AuditActor,
     pool: web::Data<Pool>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let update_user_data = data.into_inner();
@@ -116,6 +120,11 @@ pub async fn update_user(
         }));
     }
 
+    let old_role = user
+        .user_orgs
+        .iter()
+        .find(|user_org| user_org.organization_id == update_user_data.organization_id)
+        .map(|user_org| user_org.role);
     let new_role = update_user_data.role.map(|role| role.into());
 
     let user_result = update_user_query(
@@ -125,12 +134,30 @@ pub async fn update_user(
         &update_user_data.website.or(user.website),
         new_role,
         update_user_data.visible_email.unwrap_or(user.visible_email),
-        pool,
+        pool.clone(),
     )
     .await;
 
     match user_result {
-        Ok(slim_user) => Ok(HttpResponse::Ok().json(slim_user)),
+        Ok(slim_user) => {
+            if let Some(role) = update_user_data.role.filter(|role| Some(*role) != old_role) {
+                log_audit_actions(
+                    vec![AuditLog::from_details(
+                        update_user_data.organization_id,
+                        None,
+                        actor.user_id,
+                        actor.api_key_id,
+                        AuditAction::UserRoleChanged,
+                        Some(slim_user.id.to_string()),
+                        serde_json::json!({ "role": role, "old_role": old_role }),
+                    )],
+                    pool,
+                )
+                .await;
+            }
+
+            Ok(HttpResponse::Ok().json(slim_user))
+        }
         Err(e) => Ok(HttpResponse::BadRequest().json(e)),
     }
 }
@@ -169,14 +196,38 @@ pub struct SetUserApiKeyResponse {
 #[tracing::instrument(skip(pool))]
 pub async fn set_user_api_key(
     user: LoggedUser,
+    actor: AuditActor,
     data: web::Json<SetUserApiKeyRequest>,
     pool: web::Data<Pool>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let role = data.role;
 
-    let new_api_key = set_user_api_key_query(user.id, data.name.clone(), role.into(), pool)
-        .await
-        .map_err(|_err| ServiceError::BadRequest("Failed to set new API key for user".into()))?;
+    let (new_api_key, user_api_key) =
+        set_user_api_key_query(user.id, data.name.clone(), role.into(), pool.clone())
+            .await
+            .map_err(|_err| {
+                ServiceError::BadRequest("Failed to set new API key for user".into())
+            })?;
+
+    // Api keys belong to the user rather than an organization, so the key is recorded in the log of every organization it can act in
+    log_audit_actions(
+        user.user_orgs
+            .iter()
+            .map(|user_org| {
+                AuditLog::from_details(
+                    user_org.organization_id,
+                    None,
+                    actor.user_id,
+                    actor.api_key_id,
+                    AuditAction::ApiKeyCreated,
+                    Some(user_api_key.id.to_string()),
+                    serde_json::json!({ "name": user_api_key.name, "role": role }),
+                )
+            })
+            .collect(),
+        pool,
+    )
+    .await;
 
     Ok(HttpResponse::Ok().json(SetUserApiKeyResponse {
         api_key: new_api_key,
@@ -237,12 +288,32 @@ pub struct DeleteUserApiKeyRequest {
 #[tracing::instrument(skip(pool))]
 pub async fn delete_user_api_key(
     user: LoggedUser,
+    actor: AuditActor,
     data: web::Json<DeleteUserApiKeyRequest>,
     pool: web::Data<Pool>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    delete_user_api_keys_query(user.id, data.api_key_id, pool)
+    delete_user_api_keys_query(user.id, data.api_key_id, pool.clone())
         .await
         .map_err(|_err| ServiceError::BadRequest("Failed to get API keys for user".into()))?;
 
+    log_audit_actions(
+        user.user_orgs
+            .iter()
+            .map(|user_org| {
+                AuditLog::from_details(
+                    user_org.organization_id,
+                    None,
+                    actor.user_id,
+                    actor.api_key_id,
+                    AuditAction::ApiKeyDeleted,
+                    Some(data.api_key_id.to_string()),
+                    serde_json::json!({}),
+                )
+            })
+            .collect(),
+        pool,
+    )
+    .await;
+
     Ok(HttpResponse::NoContent().finish())
 }
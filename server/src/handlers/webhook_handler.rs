@@ -0,0 +1,266 @@
+use super::auth_handler::AdminOnly;
+use crate::{
+    data::models::{DatasetAndOrgWithSubAndPlan, EventType, Pool, Webhook, WebhookDTO},
+    errors::ServiceError,
+    operators::webhook_operator::{
+        create_webhook_query, delete_webhook_query, get_webhook_by_id_query,
+        get_webhook_deliveries_query, get_webhook_delivery_with_event_query, get_webhooks_query,
+        resolve_webhook_url, start_webhook_delivery,
+    },
+};
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[schema(example = json!({
+    "url": "https://example.com/trieve/webhook",
+    "event_types": ["card_action_failed"]
+}))]
+pub struct CreateWebhookData {
+    /// The http or https url events are POSTed to. Urls whose host resolves to a loopback, private, link-local, or metadata address are rejected. Redirects are not followed.
+    pub url: String,
+    /// The types of events to deliver. Any combination of file_uploaded, card_uploaded, card_action_failed, or card_updated. Leave undefined to deliver every event.
+    pub event_types: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct GetWebhookDeliveriesQuery {
+    /// The page number to get. Default is 1.
+    pub page: Option<i64>,
+    /// The number of items per page. Default is 10 and cannot exceed 100.
+    pub page_size: Option<i64>,
+}
+
+/// Checks the url and event types of a webhook. The host of the url must only resolve to public addresses, which is checked again
+/// before every delivery.
+async fn validate_webhook(url: &str, event_types: &[String]) -> Result<(), ServiceError> {
+    resolve_webhook_url(url)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    let all_event_types = EventType::get_all_event_types();
+    if let Some(event_type) = event_types
+        .iter()
+        .find(|event_type| !all_event_types.contains(event_type))
+    {
+        return Err(ServiceError::BadRequest(format!(
+            "{} is not an event type",
+            event_type
+        )));
+    }
+
+    Ok(())
+}
+
+/// Create Webhook
+///
+/// Create a webhook which is sent every event of the dataset of the given types as it happens. Each delivery is a POST of the event with an X-Trieve-Signature header holding `sha256=` followed by the hex HMAC-SHA256 of `{X-Trieve-Timestamp}.{body}` keyed with the secret of the webhook. Failed deliveries are retried with exponential backoff. The secret is only returned here. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    post,
+    path = "/webhook",
+    context_path = "/api",
+    tag = "webhook",
+    request_body(content = CreateWebhookData, description = "JSON request payload to create a webhook", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The created webhook along with its secret", body = Webhook),
+        (status = 400, description = "Service error relating to creating the webhook", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn create_webhook(
+    data: web::Json<CreateWebhookData>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let data = data.into_inner();
+    let event_types = data.event_types.unwrap_or_default();
+    validate_webhook(&data.url, &event_types).await?;
+
+    let webhook = create_webhook_query(
+        Webhook::from_details(dataset_org_plan_sub.dataset.id, data.url, event_types),
+        pool,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(webhook))
+}
+
+/// Get Webhooks
+///
+/// Get every webhook of the dataset, oldest first. Secrets are not included.
+#[utoipa::path(
+    get,
+    path = "/webhook",
+    context_path = "/api",
+    tag = "webhook",
+    responses(
+        (status = 200, description = "The webhooks of the dataset", body = Vec<WebhookDTO>),
+        (status = 400, description = "Service error relating to getting the webhooks", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn get_webhooks(
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let webhooks = get_webhooks_query(dataset_org_plan_sub.dataset.id, pool)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(
+        webhooks
+            .into_iter()
+            .map(WebhookDTO::from)
+            .collect::<Vec<WebhookDTO>>(),
+    ))
+}
+
+/// Delete Webhook
+///
+/// Delete a webhook along with its deliveries. Deliveries which are waiting for a retry are never attempted again. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    delete,
+    path = "/webhook/{webhook_id}",
+    context_path = "/api",
+    tag = "webhook",
+    responses(
+        (status = 204, description = "Confirmation that the webhook was deleted"),
+        (status = 400, description = "Service error relating to deleting the webhook", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+        ("webhook_id" = uuid::Uuid, description = "Id of the webhook to delete"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn delete_webhook(
+    webhook_id: web::Path<uuid::Uuid>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    delete_webhook_query(
+        webhook_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Get Webhook Deliveries
+///
+/// Get the deliveries of a webhook, newest first. Each delivery records how many attempts were made and how the last one went.
+#[utoipa::path(
+    get,
+    path = "/webhook/{webhook_id}/deliveries",
+    context_path = "/api",
+    tag = "webhook",
+    responses(
+        (status = 200, description = "The deliveries of the webhook", body = Vec<WebhookDelivery>),
+        (status = 400, description = "Service error relating to getting the deliveries", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+        ("webhook_id" = uuid::Uuid, description = "Id of the webhook to get the deliveries of"),
+        ("page" = Option<i64>, Query, description = "The page number to get. Default is 1."),
+        ("page_size" = Option<i64>, Query, description = "The number of items per page. Default is 10 and cannot exceed 100."),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn get_webhook_deliveries(
+    webhook_id: web::Path<uuid::Uuid>,
+    data: web::Query<GetWebhookDeliveriesQuery>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let page = data.page.unwrap_or(1).max(1);
+    let page_size = data.page_size.unwrap_or(10);
+    if !(1..=100).contains(&page_size) {
+        return Err(
+            ServiceError::BadRequest("page_size must be between 1 and 100".to_string()).into(),
+        );
+    }
+
+    let webhook = get_webhook_by_id_query(
+        webhook_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool.clone(),
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    let webhook_deliveries =
+        get_webhook_deliveries_query(webhook.id, webhook.dataset_id, page, page_size, pool)
+            .await
+            .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(webhook_deliveries))
+}
+
+/// Replay Webhook Delivery
+///
+/// Send the event of a delivery to its webhook again. The replay is a new delivery with its own attempts and retries, the original delivery is left as is. The auth'ed user must be an admin or owner of the organization.
+#[utoipa::path(
+    post,
+    path = "/webhook/delivery/{webhook_delivery_id}/replay",
+    context_path = "/api",
+    tag = "webhook",
+    responses(
+        (status = 200, description = "The new delivery of the event", body = WebhookDelivery),
+        (status = 400, description = "Service error relating to replaying the delivery", body = ErrorResponseBody),
+    ),
+    params(
+        ("TR-Dataset" = String, Header, description = "The dataset id to use for the request"),
+        ("webhook_delivery_id" = uuid::Uuid, description = "Id of the delivery to replay"),
+    ),
+    security(
+        ("ApiKey" = ["admin"]),
+    )
+)]
+#[tracing::instrument(skip(pool))]
+pub async fn replay_webhook_delivery(
+    webhook_delivery_id: web::Path<uuid::Uuid>,
+    _user: AdminOnly,
+    dataset_org_plan_sub: DatasetAndOrgWithSubAndPlan,
+    pool: web::Data<Pool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (_, webhook, event) = get_webhook_delivery_with_event_query(
+        webhook_delivery_id.into_inner(),
+        dataset_org_plan_sub.dataset.id,
+        pool.clone(),
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    let webhook_delivery = start_webhook_delivery(webhook, event, pool)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    Ok(HttpResponse::Ok().json(webhook_delivery))
+}
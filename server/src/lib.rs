@@ -12,7 +12,9 @@ use crate::{
     errors::ServiceError,
     handlers::auth_handler::build_oidc_client,
     operators::{
+        full_text_operator::reindex_fulltext_datasets,
         qdrant_operator::create_new_qdrant_collection_query, user_operator::create_default_user,
+        webhook_operator::sweep_webhook_deliveries,
     },
 };
 use actix_cors::Cors;
@@ -20,6 +22,7 @@ use actix_identity::IdentityMiddleware;
 use actix_session::{config::PersistentSession, storage::RedisSessionStore, SessionMiddleware};
 use actix_web::{
     cookie::{Key, SameSite},
+    dev::Service,
     middleware,
     web::{self, PayloadConfig},
     App, HttpServer,
@@ -144,6 +147,7 @@ impl Modify for SecurityAddon {
         handlers::auth_handler::get_me,
         handlers::auth_handler::callback,
         handlers::auth_handler::health_check,
+        handlers::metrics_handler::get_metrics,
         handlers::topic_handler::create_topic,
         handlers::topic_handler::delete_topic,
         handlers::topic_handler::update_topic,
@@ -159,6 +163,8 @@ impl Modify for SecurityAddon {
         handlers::chunk_handler::get_recommended_chunks,
         handlers::chunk_handler::update_chunk_by_tracking_id,
         handlers::chunk_handler::search_chunk,
+        handlers::chunk_handler::search_chunk_batch,
+        handlers::chunk_handler::search_chunks_federated,
         handlers::chunk_handler::generate_off_chunks,
         handlers::chunk_handler::get_chunk_by_tracking_id,
         handlers::chunk_handler::delete_chunk_by_tracking_id,
@@ -188,12 +194,34 @@ impl Modify for SecurityAddon {
         handlers::file_handler::get_file_handler,
         handlers::file_handler::delete_file_handler,
         handlers::event_handler::get_events,
+        handlers::event_handler::stream_events,
+        handlers::search_rule_handler::create_search_rule,
+        handlers::search_rule_handler::get_search_rules,
+        handlers::search_rule_handler::update_search_rule,
+        handlers::search_rule_handler::delete_search_rule,
+        handlers::webhook_handler::create_webhook,
+        handlers::webhook_handler::get_webhooks,
+        handlers::webhook_handler::delete_webhook,
+        handlers::webhook_handler::get_webhook_deliveries,
+        handlers::webhook_handler::replay_webhook_delivery,
+        handlers::ingestion_handler::get_dead_lettered_messages,
+        handlers::ingestion_handler::requeue_dead_lettered_messages,
+        handlers::ingestion_handler::purge_dead_lettered_messages,
+        handlers::ingestion_handler::get_chunk_ingestion_status,
+        handlers::ingestion_handler::get_chunk_ingestion_status_by_tracking_id,
+        handlers::ingestion_handler::get_batch_ingestion_status,
+        handlers::analytics_handler::get_top_queries,
+        handlers::analytics_handler::get_zero_result_queries,
+        handlers::analytics_handler::get_search_latency,
+        handlers::analytics_handler::record_search_click,
+        handlers::analytics_handler::record_search_feedback,
         handlers::organization_handler::create_organization,
         handlers::organization_handler::get_organization_by_id,
         handlers::organization_handler::update_organization,
         handlers::organization_handler::delete_organization_by_id,
         handlers::organization_handler::get_organization_usage,
         handlers::organization_handler::get_organization_users,
+        handlers::audit_handler::get_audit_logs,
         handlers::dataset_handler::create_dataset,
         handlers::dataset_handler::update_dataset,
         handlers::dataset_handler::delete_dataset,
@@ -231,14 +259,27 @@ impl Modify for SecurityAddon {
             handlers::chunk_handler::GenerateChunksRequest,
             handlers::chunk_handler::SearchChunkData,
             handlers::chunk_handler::ScoreChunkDTO,
+            handlers::chunk_handler::SearchChunkBatchData,
+            handlers::chunk_handler::SearchChunkBatchResponseBody,
+            handlers::chunk_handler::FederatedSearchData,
+            handlers::chunk_handler::FederatedScoreChunkDTO,
+            handlers::chunk_handler::FederatedSearchResponseBody,
             handlers::group_handler::SearchWithinGroupData,
             handlers::group_handler::SearchOverGroupsData,
+            handlers::group_handler::GroupScoreOptions,
+            handlers::group_handler::GroupScoreAggregation,
             handlers::group_handler::SearchGroupsResult,
             handlers::chunk_handler::SearchChunkQueryResponseBody,
             handlers::chunk_handler::ChunkFilter,
             handlers::chunk_handler::FieldCondition,
             handlers::chunk_handler::Range,
             handlers::chunk_handler::MatchCondition,
+            handlers::chunk_handler::FusionStrategy,
+            handlers::chunk_handler::ScoreNormalization,
+            handlers::chunk_handler::RewriteQueryMode,
+            handlers::chunk_handler::DiversifyOptions,
+            handlers::chunk_handler::ScoreExplanation,
+            handlers::chunk_handler::RetrievalSource,
             handlers::user_handler::UpdateUserData,
             handlers::user_handler::SetUserApiKeyRequest,
             handlers::user_handler::SetUserApiKeyResponse,
@@ -256,11 +297,37 @@ impl Modify for SecurityAddon {
             handlers::file_handler::UploadFileResult,
             handlers::invitation_handler::InvitationData,
             handlers::event_handler::GetEventsData,
+            handlers::event_handler::StreamEventsQuery,
+            handlers::search_rule_handler::CreateSearchRuleData,
+            handlers::search_rule_handler::UpdateSearchRuleData,
+            handlers::webhook_handler::CreateWebhookData,
+            handlers::webhook_handler::GetWebhookDeliveriesQuery,
+            handlers::ingestion_handler::GetDeadLetteredMessagesQuery,
+            handlers::ingestion_handler::DeadLetteredMessagesData,
+            handlers::ingestion_handler::DeadLetteredMessagesCount,
+            operators::ingestion_queue_operator::DeadLetteredIngestionMessage,
+            operators::ingestion_status_operator::BatchIngestionStatus,
+            data::models::ChunkIngestionStatus,
+            data::models::IngestionStatus,
             handlers::organization_handler::CreateOrganizationData,
             handlers::organization_handler::UpdateOrganizationData,
+            handlers::audit_handler::GetAuditLogQuery,
             operators::event_operator::EventReturn,
+            operators::analytics_operator::SearchQueryCount,
+            operators::analytics_operator::SearchLatencyStats,
+            operators::audit_operator::AuditLogPage,
+            handlers::analytics_handler::SearchClickData,
+            handlers::analytics_handler::SearchFeedbackData,
+            data::models::SearchFeedback,
+            data::models::SearchFeedbackType,
             operators::search_operator::SearchOverGroupsResponseBody,
             operators::search_operator::GroupScoreChunkDTO,
+            operators::facet_operator::FacetResult,
+            operators::facet_operator::FacetBucket,
+            operators::highlight_operator::ChunkHighlights,
+            operators::highlight_operator::HighlightSpan,
+            operators::highlight_operator::HighlightSnippet,
+            operators::highlight_operator::HighlightKind,
             handlers::dataset_handler::CreateDatasetRequest,
             handlers::dataset_handler::UpdateDatasetRequest,
             handlers::dataset_handler::DeleteDatasetRequest,
@@ -285,6 +352,18 @@ impl Modify for SecurityAddon {
             data::models::DatasetDTO,
             data::models::DatasetUsageCount,
             data::models::ClientDatasetConfiguration,
+            data::models::RecencyDecay,
+            data::models::DecayFunction,
+            data::models::SearchRule,
+            data::models::SearchRuleMatchType,
+            data::models::SearchRuleActions,
+            data::models::TagBoost,
+            data::models::Webhook,
+            data::models::WebhookDTO,
+            data::models::WebhookDelivery,
+            data::models::WebhookDeliveryStatus,
+            data::models::AuditLog,
+            data::models::AuditAction,
             data::models::StripePlan,
             errors::ErrorResponseBody,
         )
@@ -293,16 +372,21 @@ impl Modify for SecurityAddon {
         (name = "invitation", description = "Invitation endpoint. Exists to invite users to an organization."),
         (name = "auth", description = "Authentication endpoint. Serves to register and authenticate users."),
         (name = "user", description = "User endpoint. Enables you to modify user roles and information."),
-        (name = "organization", description = "Organization endpoint. Enables you to modify organization roles and information."),
+        (name = "organization", description = "Organization endpoint. Enables you to modify organization roles and information and to view the audit log of administrative actions."),
         (name = "dataset", description = "Dataset endpoint. Datasets belong to organizations and hold configuration information for both client and server. Datasets contain chunks and chunk groups."),
         (name = "chunk", description = "Chunk endpoint. Think of chunks as individual searchable units of information. The majority of your integration will likely be with the Chunk endpoint."),
         (name = "chunk_group", description = "Chunk groups endpoint. Think of a chunk_group as a bookmark folder within the dataset."),
         (name = "file", description = "File endpoint. When files are uploaded, they are stored in S3 and broken up into chunks with text extraction from Apache Tika. You can upload files of pretty much any type up to 1GB in size. See chunking algorithm details at `docs.trieve.ai` for more information on how chunking works. Improved default chunking is on our roadmap."),
+        (name = "search_rule", description = "Search rule endpoint. Search rules let dataset admins pin, bury, or boost chunks in the results of searches whose query matches a pattern."),
+        (name = "analytics", description = "Analytics endpoint. Every search is recorded so that dataset admins can see what users search for, which searches come up empty, and how long searches take. Clicks and ratings are recorded against the search_id returned by each search."),
+        (name = "webhook", description = "Webhook endpoint. Webhooks push the events of a dataset to a url as they happen, so that clients do not have to poll the events endpoint."),
+        (name = "ingestion", description = "Ingestion endpoint. Chunks are embedded by workers which retry transient failures with backoff. Messages which fail permanently or exhaust their retries are dead-lettered so that dataset admins can inspect, requeue, or purge them. The ingestion status of every queued chunk can be fetched by chunk id, tracking_id, or batch id."),
         (name = "events", description = "Notifications endpoint. Files are uploaded asynchronously and events are sent to the user when the upload is complete. Soon, chunk creation will work in the same way."),
         (name = "topic", description = "Topic chat endpoint. Think of topics as the storage system for gen-ai chat memory. Gen AI messages belong to topics."),
         (name = "message", description = "Message chat endpoint. Messages are units belonging to a topic in the context of a chat with a LLM. There are system, user, and assistant messages."),
         (name = "stripe", description = "Stripe endpoint. Used for the managed SaaS version of this app. Eventually this will become a micro-service. Reach out to the team using contact info found at `docs.trieve.ai` for more information."),
         (name = "health", description = "Health check endpoint. Used to check if the server is up and running."),
+        (name = "metrics", description = "Metrics endpoint. Exposes metrics of the server in the Prometheus text format to scrapers which send the METRICS_TOKEN of the server."),
     ),
 )]
 pub struct ApiDoc;
@@ -404,6 +488,9 @@ pub async fn main() -> std::io::Result<()> {
         });
     }
 
+    tokio::spawn(sweep_webhook_deliveries(web::Data::new(pool.clone())));
+    tokio::spawn(reindex_fulltext_datasets(web::Data::new(pool.clone())));
+
     HttpServer::new(move || {
         App::new()
             .app_data(PayloadConfig::new(134200000))
@@ -450,9 +537,34 @@ pub async fn main() -> std::io::Result<()> {
                 .cookie_path("/".to_owned())
                 .build(),
             )
+            .wrap_fn(|req, srv| {
+                let start = std::time::Instant::now();
+                let method = req.method().to_string();
+                srv.call(req).map(move |res| {
+                    match &res {
+                        Ok(res) => operators::metrics_operator::observe_http_request(
+                            res.request().match_pattern().as_deref().unwrap_or("unmatched"),
+                            &method,
+                            res.status().as_u16(),
+                            start.elapsed(),
+                        ),
+                        Err(err) => operators::metrics_operator::observe_http_request(
+                            "unmatched",
+                            &method,
+                            err.as_response_error().status_code().as_u16(),
+                            start.elapsed(),
+                        ),
+                    }
+                    res
+                })
+            })
             // enable logger
             .wrap(middleware::Logger::default())
             .service(Redoc::with_url("/redoc", ApiDoc::openapi()))
+            .service(
+                web::resource("/metrics")
+                    .route(web::get().to(handlers::metrics_handler::get_metrics)),
+            )
             .service(
                 SwaggerUi::new("/swagger-ui/{_:.*}")
                     .url("/api-docs/openapi.json", ApiDoc::openapi())
@@ -556,6 +668,12 @@ pub async fn main() -> std::io::Result<()> {
                                 web::resource("/search")
                                     .route(web::post().to(handlers::chunk_handler::search_chunk)),
                             )
+                            .service(web::resource("/search/batch").route(
+                                web::post().to(handlers::chunk_handler::search_chunk_batch),
+                            ))
+                            .service(web::resource("/search/federated").route(
+                                web::post().to(handlers::chunk_handler::search_chunks_federated),
+                            ))
                             .service(web::resource("/gen_suggestions").route(
                                 web::post().to(
                                     handlers::message_handler::create_suggested_queries_handler,
@@ -716,10 +834,108 @@ pub async fn main() -> std::io::Result<()> {
                     )
                     
                     .service(
-                        web::scope("/events").service(
-                            web::resource("")
-                                .route(web::post().to(handlers::event_handler::get_events)),
-                        ),
+                        web::scope("/search_rule")
+                            .service(
+                                web::resource("")
+                                    .route(web::post().to(handlers::search_rule_handler::create_search_rule))
+                                    .route(web::get().to(handlers::search_rule_handler::get_search_rules))
+                                    .route(web::put().to(handlers::search_rule_handler::update_search_rule)),
+                            )
+                            .service(
+                                web::resource("/{search_rule_id}").route(
+                                    web::delete().to(handlers::search_rule_handler::delete_search_rule),
+                                ),
+                            ),
+                    )
+                    .service(
+                        web::scope("/webhook")
+                            .service(
+                                web::resource("")
+                                    .route(web::post().to(handlers::webhook_handler::create_webhook))
+                                    .route(web::get().to(handlers::webhook_handler::get_webhooks)),
+                            )
+                            .service(
+                                web::resource("/delivery/{webhook_delivery_id}/replay").route(
+                                    web::post().to(handlers::webhook_handler::replay_webhook_delivery),
+                                ),
+                            )
+                            .service(
+                                web::resource("/{webhook_id}").route(
+                                    web::delete().to(handlers::webhook_handler::delete_webhook),
+                                ),
+                            )
+                            .service(
+                                web::resource("/{webhook_id}/deliveries").route(
+                                    web::get().to(handlers::webhook_handler::get_webhook_deliveries),
+                                ),
+                            ),
+                    )
+                    .service(
+                        web::scope("/ingestion")
+                            .service(
+                                web::resource("/dead_letter")
+                                    .route(web::get().to(
+                                        handlers::ingestion_handler::get_dead_lettered_messages,
+                                    ))
+                                    .route(web::delete().to(
+                                        handlers::ingestion_handler::purge_dead_lettered_messages,
+                                    )),
+                            )
+                            .service(web::resource("/dead_letter/requeue").route(web::post().to(
+                                handlers::ingestion_handler::requeue_dead_lettered_messages,
+                            )))
+                            .service(
+                                web::resource("/status/tracking_id/{tracking_id}").route(
+                                    web::get().to(
+                                        handlers::ingestion_handler::get_chunk_ingestion_status_by_tracking_id,
+                                    ),
+                                ),
+                            )
+                            .service(
+                                web::resource("/status/batch/{batch_id}").route(web::get().to(
+                                    handlers::ingestion_handler::get_batch_ingestion_status,
+                                )),
+                            )
+                            .service(
+                                web::resource("/status/{chunk_id}").route(web::get().to(
+                                    handlers::ingestion_handler::get_chunk_ingestion_status,
+                                )),
+                            ),
+                    )
+                    .service(
+                        web::scope("/analytics")
+                            .service(
+                                web::resource("/top_queries")
+                                    .route(web::get().to(handlers::analytics_handler::get_top_queries)),
+                            )
+                            .service(
+                                web::resource("/zero_result_queries").route(
+                                    web::get().to(handlers::analytics_handler::get_zero_result_queries),
+                                ),
+                            )
+                            .service(
+                                web::resource("/latency")
+                                    .route(web::get().to(handlers::analytics_handler::get_search_latency)),
+                            )
+                            .service(
+                                web::resource("/click")
+                                    .route(web::post().to(handlers::analytics_handler::record_search_click)),
+                            )
+                            .service(
+                                web::resource("/feedback")
+                                    .route(web::post().to(handlers::analytics_handler::record_search_feedback)),
+                            ),
+                    )
+                    .service(
+                        web::scope("/events")
+                            .service(
+                                web::resource("")
+                                    .route(web::post().to(handlers::event_handler::get_events)),
+                            )
+                            .service(
+                                web::resource("/stream")
+                                    .route(web::get().to(handlers::event_handler::stream_events)),
+                            ),
                     )
                     .service(
                         web::resource("/health")
@@ -739,6 +955,10 @@ pub async fn main() -> std::io::Result<()> {
                                         handlers::organization_handler::get_organization_users,
                                     )),
                             )
+                            .service(
+                                web::resource("/audit_log/{organization_id}")
+                                    .route(web::get().to(handlers::audit_handler::get_audit_logs)),
+                            )
                             .service(
                                 web::resource("/{organization_id}")
                                     .route(
@@ -0,0 +1,261 @@
+use super::cache_operator::sha256_hex;
+use crate::{
+    data::models::{Pool, SearchFeedback, SearchQuery},
+    errors::DefaultError,
+    handlers::chunk_handler::ChunkFilter,
+};
+use actix_web::web;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use simple_server_timing_header::Timer;
+use utoipa::ToSchema;
+
+/// The timer only exposes its measurements through the Server-Timing header, so they are summed from there. This is the time from the creation of the timer to its last measurement.
+pub fn get_timer_latency_ms(timer: &Timer) -> i32 {
+    let latency_ms: u64 = timer
+        .header_value()
+        .split(", ")
+        .filter_map(|timing| timing.rsplit_once(";dur="))
+        .filter_map(|(_, duration)| duration.parse::<u64>().ok())
+        .sum();
+
+    latency_ms.try_into().unwrap_or(i32::MAX)
+}
+
+pub fn get_filters_hash(filters: &Option<ChunkFilter>) -> Option<String> {
+    filters
+        .as_ref()
+        .and_then(|filters| serde_json::to_string(filters).ok())
+        .map(|filters| sha256_hex(&filters))
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn create_search_query_record(
+    search_query: SearchQuery,
+    pool: web::Data<Pool>,
+) -> Result<(), DefaultError> {
+    use crate::data::schema::search_queries::dsl as search_queries_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    diesel::insert_into(search_queries_columns::search_queries)
+        .values(&search_query)
+        .execute(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to record search query {:?}", err);
+            DefaultError {
+                message: "Failed to record search query",
+            }
+        })?;
+
+    Ok(())
+}
+
+/// Records a search before its id is returned, so that clicks and feedback for the id always find the search. A failure to record it is logged and never fails the search itself. Returns the id of the search.
+pub async fn log_search_query(
+    dataset_id: uuid::Uuid,
+    search_type: &str,
+    query: &str,
+    filters: &Option<ChunkFilter>,
+    scores: impl Iterator<Item = f64>,
+    timer: &Timer,
+    pool: web::Data<Pool>,
+) -> uuid::Uuid {
+    let scores = scores.collect::<Vec<f64>>();
+    let search_query = SearchQuery::from_details(
+        dataset_id,
+        search_type.to_string(),
+        query.to_string(),
+        get_filters_hash(filters),
+        scores.len().try_into().unwrap_or(i32::MAX),
+        scores.into_iter().reduce(f64::max),
+        get_timer_latency_ms(timer),
+    );
+    let search_id = search_query.id;
+
+    let _ = create_search_query_record(search_query, pool).await;
+
+    search_id
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_search_query_by_id_query(
+    search_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<SearchQuery, DefaultError> {
+    use crate::data::schema::search_queries::dsl as search_queries_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    search_queries_columns::search_queries
+        .filter(search_queries_columns::id.eq(search_id))
+        .filter(search_queries_columns::dataset_id.eq(dataset_id))
+        .select(SearchQuery::as_select())
+        .first::<SearchQuery>(&mut conn)
+        .await
+        .map_err(|_| DefaultError {
+            message: "Search not found, search_id must be the search_id of a search response of this dataset",
+        })
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn create_search_feedback_query(
+    search_feedback: SearchFeedback,
+    pool: web::Data<Pool>,
+) -> Result<SearchFeedback, DefaultError> {
+    use crate::data::schema::search_feedback::dsl as search_feedback_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    diesel::insert_into(search_feedback_columns::search_feedback)
+        .values(&search_feedback)
+        .execute(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to record search feedback {:?}", err);
+            DefaultError {
+                message: "Failed to record search feedback",
+            }
+        })?;
+
+    Ok(search_feedback)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[schema(example = json!({
+    "query": "carbon tax",
+    "count": 42
+}))]
+pub struct SearchQueryCount {
+    pub query: String,
+    /// Number of searches for the query in the time range.
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[schema(example = json!({
+    "search_count": 1200,
+    "p50_latency_ms": 84.0,
+    "p95_latency_ms": 310.5
+}))]
+pub struct SearchLatencyStats {
+    /// Number of searches in the time range.
+    pub search_count: i64,
+    /// Median latency of the searches. Null if there were none.
+    pub p50_latency_ms: Option<f64>,
+    /// 95th percentile latency of the searches. Null if there were none.
+    pub p95_latency_ms: Option<f64>,
+}
+
+/// Most frequent queries of the dataset between from and to. Set zero_results_only to only count searches which returned nothing.
+#[tracing::instrument(skip(pool))]
+pub async fn get_query_counts_query(
+    dataset_id: uuid::Uuid,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+    zero_results_only: bool,
+    limit: i64,
+    pool: web::Data<Pool>,
+) -> Result<Vec<SearchQueryCount>, DefaultError> {
+    use crate::data::schema::search_queries::dsl as search_queries_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    let mut query_counts = search_queries_columns::search_queries
+        .filter(search_queries_columns::dataset_id.eq(dataset_id))
+        .filter(search_queries_columns::created_at.between(from, to))
+        .group_by(search_queries_columns::query)
+        .select((search_queries_columns::query, diesel::dsl::count_star()))
+        .order((
+            diesel::dsl::count_star().desc(),
+            search_queries_columns::query.asc(),
+        ))
+        .limit(limit)
+        .into_boxed();
+
+    if zero_results_only {
+        query_counts = query_counts.filter(search_queries_columns::result_count.eq(0));
+    }
+
+    let query_counts = query_counts
+        .load::<(String, i64)>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get query counts {:?}", err);
+            DefaultError {
+                message: "Failed to get query counts",
+            }
+        })?;
+
+    Ok(query_counts
+        .into_iter()
+        .map(|(query, count)| SearchQueryCount { query, count })
+        .collect())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_search_latency_query(
+    dataset_id: uuid::Uuid,
+    from: chrono::NaiveDateTime,
+    to: chrono::NaiveDateTime,
+    search_type: Option<String>,
+    pool: web::Data<Pool>,
+) -> Result<SearchLatencyStats, DefaultError> {
+    use crate::data::schema::search_queries::dsl as search_queries_columns;
+    use diesel::dsl::sql;
+    use diesel::sql_types::{Double, Nullable};
+
+    let mut conn = pool.get().await.unwrap();
+
+    let mut latency_query = search_queries_columns::search_queries
+        .filter(search_queries_columns::dataset_id.eq(dataset_id))
+        .filter(search_queries_columns::created_at.between(from, to))
+        .into_boxed();
+
+    if let Some(search_type) = search_type {
+        latency_query = latency_query.filter(search_queries_columns::search_type.eq(search_type));
+    }
+
+    let (search_count, p50_latency_ms, p95_latency_ms) = latency_query
+        .select((
+            diesel::dsl::count_star(),
+            sql::<Nullable<Double>>("percentile_cont(0.5) WITHIN GROUP (ORDER BY latency_ms)"),
+            sql::<Nullable<Double>>("percentile_cont(0.95) WITHIN GROUP (ORDER BY latency_ms)"),
+        ))
+        .first::<(i64, Option<f64>, Option<f64>)>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get search latency {:?}", err);
+            DefaultError {
+                message: "Failed to get search latency",
+            }
+        })?;
+
+    Ok(SearchLatencyStats {
+        search_count,
+        p50_latency_ms,
+        p95_latency_ms,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_timer_latency_sums_measurements() {
+        let mut timer = Timer::new();
+        assert_eq!(get_timer_latency_ms(&timer), 0);
+
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        timer.add("Create embedding, query 1");
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        timer.add("Search");
+
+        let latency_ms = get_timer_latency_ms(&timer);
+        assert!((10..1000).contains(&latency_ms));
+    }
+}
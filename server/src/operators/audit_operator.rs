@@ -0,0 +1,146 @@
+use crate::{
+    data::models::{AuditLog, Pool},
+    errors::DefaultError,
+};
+use actix_web::web;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[tracing::instrument(skip(pool))]
+pub async fn create_audit_logs_query(
+    audit_logs: Vec<AuditLog>,
+    pool: web::Data<Pool>,
+) -> Result<(), DefaultError> {
+    use crate::data::schema::audit_log::dsl as audit_log_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    diesel::insert_into(audit_log_columns::audit_log)
+        .values(&audit_logs)
+        .execute(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to create audit log {:?}", err);
+            DefaultError {
+                message: "Failed to create audit log",
+            }
+        })?;
+
+    Ok(())
+}
+
+/// Records actions which have already happened. A failure to record them is logged rather than returned so that it never fails the request which took them.
+pub async fn log_audit_actions(audit_logs: Vec<AuditLog>, pool: web::Data<Pool>) {
+    if audit_logs.is_empty() {
+        return;
+    }
+
+    if let Err(err) = create_audit_logs_query(audit_logs.clone(), pool).await {
+        log::error!(
+            "{}, the unrecorded actions are {:?}",
+            err.message,
+            audit_logs
+        );
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct AuditLogFilter {
+    /// Only include entries with this action, e.g. "dataset_deleted".
+    pub action: Option<String>,
+    /// Only include entries for this kind of resource, e.g. "dataset".
+    pub resource_type: Option<String>,
+    /// Only include actions taken by this user.
+    pub user_id: Option<uuid::Uuid>,
+    /// Only include actions taken with this api key.
+    pub api_key_id: Option<uuid::Uuid>,
+    /// Only include actions taken in this dataset.
+    pub dataset_id: Option<uuid::Uuid>,
+    /// Start of the time range, e.g. 2024-03-01T00:00:00.
+    pub from: Option<chrono::NaiveDateTime>,
+    /// End of the time range.
+    pub to: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct AuditLogPage {
+    pub audit_logs: Vec<AuditLog>,
+    /// Number of pages of entries matching the filter.
+    pub page_count: i64,
+}
+
+/// Entries of the audit log of the organization which match the filter, newest first.
+#[tracing::instrument(skip(pool))]
+pub async fn get_audit_logs_query(
+    organization_id: uuid::Uuid,
+    filter: AuditLogFilter,
+    page: i64,
+    page_size: i64,
+    pool: web::Data<Pool>,
+) -> Result<AuditLogPage, DefaultError> {
+    use crate::data::schema::audit_log::dsl as audit_log_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    let filtered_audit_log = || {
+        let mut query = audit_log_columns::audit_log
+            .filter(audit_log_columns::organization_id.eq(organization_id))
+            .into_boxed();
+
+        if let Some(action) = filter.action.clone() {
+            query = query.filter(audit_log_columns::action.eq(action));
+        }
+        if let Some(resource_type) = filter.resource_type.clone() {
+            query = query.filter(audit_log_columns::resource_type.eq(resource_type));
+        }
+        if let Some(user_id) = filter.user_id {
+            query = query.filter(audit_log_columns::user_id.eq(user_id));
+        }
+        if let Some(api_key_id) = filter.api_key_id {
+            query = query.filter(audit_log_columns::api_key_id.eq(api_key_id));
+        }
+        if let Some(dataset_id) = filter.dataset_id {
+            query = query.filter(audit_log_columns::dataset_id.eq(dataset_id));
+        }
+        if let Some(from) = filter.from {
+            query = query.filter(audit_log_columns::created_at.ge(from));
+        }
+        if let Some(to) = filter.to {
+            query = query.filter(audit_log_columns::created_at.le(to));
+        }
+
+        query
+    };
+
+    let audit_log_count = filtered_audit_log()
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to count audit log {:?}", err);
+            DefaultError {
+                message: "Failed to get audit log",
+            }
+        })?;
+
+    let audit_logs = filtered_audit_log()
+        .order(audit_log_columns::created_at.desc())
+        .limit(page_size)
+        .offset((page - 1) * page_size)
+        .select(AuditLog::as_select())
+        .load::<AuditLog>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to load audit log {:?}", err);
+            DefaultError {
+                message: "Failed to get audit log",
+            }
+        })?;
+
+    Ok(AuditLogPage {
+        audit_logs,
+        page_count: (audit_log_count as f64 / page_size as f64).ceil() as i64,
+    })
+}
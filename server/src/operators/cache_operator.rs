@@ -0,0 +1,378 @@
+use super::model_operator::create_embeddings;
+use crate::{
+    data::models::{RedisPool, ServerDatasetConfiguration},
+    errors::ServiceError,
+};
+use actix_web::web;
+use itertools::Itertools;
+use serde::Serialize;
+
+pub fn sha256_hex(input: &str) -> String {
+    openssl::sha::sha256(input.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .join("")
+}
+
+fn search_cache_generation_key(dataset_id: uuid::Uuid) -> String {
+    format!("search_cache_generation:{}", dataset_id)
+}
+
+/// Queries which only differ in case or whitespace share one cached embedding.
+fn normalize_query(query: &str) -> String {
+    query.split_whitespace().join(" ").to_lowercase()
+}
+
+/// Embeddings only depend on the model which created them, so datasets sharing a model share the cache entry.
+fn query_embedding_key(query: &str, config: &ServerDatasetConfiguration) -> String {
+    format!(
+        "query_embedding:{}",
+        sha256_hex(&format!(
+            "{}\n{}\n{}\n{}",
+            config.EMBEDDING_BASE_URL,
+            config.EMBEDDING_MODEL_NAME,
+            config.EMBEDDING_QUERY_PREFIX,
+            normalize_query(query)
+        ))
+    )
+}
+
+async fn get_cache_value(key: &str, redis_pool: web::Data<RedisPool>) -> Option<String> {
+    let mut redis_conn = match redis_pool.get().await {
+        Ok(redis_conn) => redis_conn,
+        Err(err) => {
+            log::error!(
+                "Failed to get redis connection for the search cache {:?}",
+                err
+            );
+            return None;
+        }
+    };
+
+    redis::cmd("GET")
+        .arg(key)
+        .query_async::<_, Option<String>>(&mut *redis_conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to read {} from the search cache {:?}", key, err);
+        })
+        .ok()
+        .flatten()
+}
+
+async fn set_cache_value(key: &str, value: String, ttl: u64, redis_pool: web::Data<RedisPool>) {
+    let mut redis_conn = match redis_pool.get().await {
+        Ok(redis_conn) => redis_conn,
+        Err(err) => {
+            log::error!(
+                "Failed to get redis connection for the search cache {:?}",
+                err
+            );
+            return;
+        }
+    };
+
+    let _ = redis::cmd("SET")
+        .arg(key)
+        .arg(value)
+        .arg("EX")
+        .arg(ttl)
+        .query_async::<_, ()>(&mut *redis_conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to write {} to the search cache {:?}", key, err);
+        });
+}
+
+/// Marks every cached search response of the dataset as stale. Called whenever the chunks or the configuration of a dataset change.
+#[tracing::instrument(skip(redis_pool))]
+pub async fn bump_search_cache_generation(
+    dataset_id: uuid::Uuid,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), ServiceError> {
+    let mut redis_conn = redis_pool.get().await.map_err(|err| {
+        log::error!(
+            "Failed to get redis connection to bump search cache generation {:?}",
+            err
+        );
+        ServiceError::InternalServerError("Failed to get redis connection".to_string())
+    })?;
+
+    redis::cmd("INCR")
+        .arg(search_cache_generation_key(dataset_id))
+        .query_async::<_, u64>(&mut *redis_conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to bump search cache generation {:?}", err);
+            ServiceError::InternalServerError("Failed to bump search cache generation".to_string())
+        })?;
+
+    Ok(())
+}
+
+/// Returns the key which a response to the request is cached under, or None if SEARCH_CACHE_ENABLED is off for the dataset.
+/// The key includes the current cache generation of the dataset so that responses cached before a change are never read.
+#[tracing::instrument(skip(request, config, redis_pool))]
+pub async fn get_search_cache_key<T: Serialize>(
+    route: &str,
+    request: &T,
+    dataset_id: uuid::Uuid,
+    config: &ServerDatasetConfiguration,
+    redis_pool: web::Data<RedisPool>,
+) -> Option<String> {
+    if !config.SEARCH_CACHE_ENABLED {
+        return None;
+    }
+
+    let generation = get_cache_value(&search_cache_generation_key(dataset_id), redis_pool)
+        .await
+        .and_then(|generation| generation.parse::<u64>().ok())
+        .unwrap_or(0);
+
+    search_cache_key(route, request, dataset_id, generation)
+}
+
+/// Every field of the request is part of the key, so requests which differ in anything but the order of their fields
+/// are cached apart.
+fn search_cache_key<T: Serialize>(
+    route: &str,
+    request: &T,
+    dataset_id: uuid::Uuid,
+    generation: u64,
+) -> Option<String> {
+    let request = serde_json::to_string(request)
+        .map_err(|err| {
+            log::error!("Failed to serialize search request for the cache {:?}", err);
+        })
+        .ok()?;
+
+    Some(format!(
+        "search_cache:{}:{}:{}",
+        dataset_id,
+        generation,
+        sha256_hex(&format!("{}\n{}", route, request))
+    ))
+}
+
+/// Returns the serialized response cached under the key.
+#[tracing::instrument(skip(redis_pool))]
+pub async fn get_cached_search_response(
+    key: &str,
+    redis_pool: web::Data<RedisPool>,
+) -> Option<String> {
+    get_cache_value(key, redis_pool).await
+}
+
+/// Caches the response under the key for SEARCH_CACHE_TTL seconds.
+#[tracing::instrument(skip(response, config, redis_pool))]
+pub async fn set_cached_search_response<T: Serialize>(
+    key: &str,
+    response: &T,
+    config: &ServerDatasetConfiguration,
+    redis_pool: web::Data<RedisPool>,
+) {
+    match serde_json::to_string(response) {
+        Ok(response) => {
+            set_cache_value(key, response, config.SEARCH_CACHE_TTL, redis_pool).await;
+        }
+        Err(err) => log::error!(
+            "Failed to serialize search response for the cache {:?}",
+            err
+        ),
+    }
+}
+
+/// Returns the cached embedding of the query, if there is one.
+#[tracing::instrument(skip(config, redis_pool))]
+pub async fn get_cached_query_embedding(
+    query: &str,
+    config: &ServerDatasetConfiguration,
+    redis_pool: web::Data<RedisPool>,
+) -> Option<Vec<f32>> {
+    if !config.SEARCH_CACHE_ENABLED {
+        return None;
+    }
+
+    get_cache_value(&query_embedding_key(query, config), redis_pool)
+        .await
+        .and_then(|embedding| serde_json::from_str::<Vec<f32>>(&embedding).ok())
+}
+
+/// Caches the embedding of the query for QUERY_EMBEDDING_CACHE_TTL seconds, one day by default.
+#[tracing::instrument(skip(embedding, config, redis_pool))]
+pub async fn set_cached_query_embedding(
+    query: &str,
+    embedding: &[f32],
+    config: &ServerDatasetConfiguration,
+    redis_pool: web::Data<RedisPool>,
+) {
+    if !config.SEARCH_CACHE_ENABLED {
+        return;
+    }
+
+    let ttl: u64 = std::env::var("QUERY_EMBEDDING_CACHE_TTL")
+        .unwrap_or("86400".to_string())
+        .parse()
+        .unwrap_or(86400);
+
+    match serde_json::to_string(embedding) {
+        Ok(embedding) => {
+            set_cache_value(
+                &query_embedding_key(query, config),
+                embedding,
+                ttl,
+                redis_pool,
+            )
+            .await;
+        }
+        Err(err) => log::error!(
+            "Failed to serialize query embedding for the cache {:?}",
+            err
+        ),
+    }
+}
+
+/// Embeds the queries, reusing cached embeddings and caching new ones. Every query missing from the cache is embedded in one request.
+#[tracing::instrument(skip(config, redis_pool))]
+pub async fn create_query_embeddings_with_cache(
+    queries: Vec<String>,
+    config: &ServerDatasetConfiguration,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<Vec<Vec<f32>>, actix_web::Error> {
+    let mut embeddings: Vec<Option<Vec<f32>>> = vec![];
+    for query in queries.iter() {
+        embeddings.push(get_cached_query_embedding(query, config, redis_pool.clone()).await);
+    }
+
+    let missing_positions = embeddings
+        .iter()
+        .positions(|embedding| embedding.is_none())
+        .collect_vec();
+    if !missing_positions.is_empty() {
+        let created_embeddings = create_embeddings(
+            missing_positions
+                .iter()
+                .map(|position| queries[*position].clone())
+                .collect(),
+            "query",
+            config.clone(),
+        )
+        .await?;
+
+        if created_embeddings.len() != missing_positions.len() {
+            return Err(ServiceError::BadRequest(
+                "Failed to get an embedding vector for every query".to_string(),
+            )
+            .into());
+        }
+
+        for (position, embedding) in missing_positions.into_iter().zip(created_embeddings) {
+            set_cached_query_embedding(&queries[position], &embedding, config, redis_pool.clone())
+                .await;
+            embeddings[position] = Some(embedding);
+        }
+    }
+
+    Ok(embeddings.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::handlers::chunk_handler::SearchChunkData;
+    use serde_json::json;
+
+    fn search_request(request: serde_json::Value) -> SearchChunkData {
+        serde_json::from_value(request).unwrap()
+    }
+
+    fn chunk_search_key(request: serde_json::Value) -> String {
+        search_cache_key(
+            "chunk_search",
+            &search_request(request),
+            uuid::Uuid::nil(),
+            0,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_queries_differing_in_case_or_whitespace_share_an_embedding() {
+        let config = ServerDatasetConfiguration::from_json(json!({}));
+
+        assert_eq!(
+            normalize_query("  How do\tCats   SLEEP \n"),
+            "how do cats sleep"
+        );
+        assert_eq!(
+            query_embedding_key("  How do\tCats   SLEEP \n", &config),
+            query_embedding_key("how do cats sleep", &config)
+        );
+        assert_ne!(
+            query_embedding_key("how do cats sleep", &config),
+            query_embedding_key("how do dogs sleep", &config)
+        );
+        assert_ne!(
+            query_embedding_key(
+                "how do cats sleep",
+                &ServerDatasetConfiguration::from_json(
+                    json!({ "EMBEDDING_MODEL_NAME": "another-model" })
+                )
+            ),
+            query_embedding_key("how do cats sleep", &config)
+        );
+    }
+
+    #[test]
+    fn test_identical_searches_share_a_key() {
+        let request = json!({ "search_type": "semantic", "query": "cats", "page": 2 });
+
+        assert_eq!(chunk_search_key(request.clone()), chunk_search_key(request));
+    }
+
+    #[test]
+    fn test_searches_differing_in_any_parameter_are_cached_apart() {
+        let base = json!({ "search_type": "semantic", "query": "cats" });
+        let with = |key: &str, value: serde_json::Value| {
+            let mut request = base.clone();
+            request[key] = value;
+            chunk_search_key(request)
+        };
+
+        let keys = vec![
+            chunk_search_key(base.clone()),
+            with("search_type", json!("hybrid")),
+            with("query", json!("Cats")),
+            with("page", json!(2)),
+            with("page_size", json!(20)),
+            with(
+                "filters",
+                json!({ "must": [{ "field": "tag_set", "match": ["news"] }] }),
+            ),
+            with(
+                "filters",
+                json!({ "must": [{ "field": "tag_set", "match": ["sports"] }] }),
+            ),
+        ];
+
+        assert_eq!(keys.iter().unique().count(), keys.len());
+    }
+
+    #[test]
+    fn test_keys_are_scoped_to_route_dataset_and_generation() {
+        let request = search_request(json!({ "search_type": "semantic", "query": "cats" }));
+        let key = |route: &str, dataset_id: uuid::Uuid, generation: u64| {
+            search_cache_key(route, &request, dataset_id, generation).unwrap()
+        };
+        let dataset_id = uuid::Uuid::new_v4();
+
+        let keys = vec![
+            key("chunk_search", dataset_id, 0),
+            key("group_search", dataset_id, 0),
+            key("chunk_search", uuid::Uuid::new_v4(), 0),
+            key("chunk_search", dataset_id, 1),
+        ];
+
+        assert_eq!(keys.iter().unique().count(), keys.len());
+    }
+}
@@ -16,7 +16,6 @@ use diesel_async::scoped_futures::ScopedFutureExt;
 use diesel_async::{AsyncConnection, RunQueryDsl};
 use itertools::Itertools;
 use qdrant_client::qdrant::{PointId, PointVectors};
-use simsearch::SimSearch;
 
 #[tracing::instrument(skip(pool))]
 pub async fn get_metadata_from_point_ids(
@@ -420,6 +419,7 @@ pub async fn insert_chunk_metadata_query(
 
     let inserted_chunk = diesel::insert_into(chunk_metadata)
         .values(&chunk_data)
+        .returning(ChunkMetadata::as_returning())
         .get_result::<ChunkMetadata>(&mut conn)
         .await
         .map_err(|e| {
@@ -498,6 +498,7 @@ pub async fn insert_duplicate_chunk_metadata_query(
             async {
                 let inserted_chunk = diesel::insert_into(chunk_metadata)
                     .values(&chunk_data)
+                    .returning(ChunkMetadata::as_returning())
                     .get_result::<ChunkMetadata>(conn)
                     .await?;
 
@@ -577,6 +578,7 @@ pub async fn update_chunk_metadata_query(
                     chunk_metadata_columns::tag_set.eq(chunk_data.tag_set),
                     chunk_metadata_columns::weight.eq(chunk_data.weight),
                 ))
+                .returning(ChunkMetadata::as_returning())
                 .get_result::<ChunkMetadata>(conn)
                 .await?;
 
@@ -936,49 +938,6 @@ pub async fn get_qdrant_id_from_chunk_id_query(
     }
 }
 
-#[tracing::instrument]
-pub fn find_relevant_sentence(
-    input: ChunkMetadataWithFileData,
-    query: String,
-    split_chars: Vec<String>,
-) -> Result<ChunkMetadataWithFileData, DefaultError> {
-    let content = &input.chunk_html.clone().unwrap_or(input.content.clone());
-    let mut engine: SimSearch<String> = SimSearch::new();
-    let mut split_content = content
-        .split_inclusive(|c: char| split_chars.contains(&c.to_string()))
-        .map(|x| x.to_string())
-        .collect::<Vec<String>>();
-
-    //insert all sentences into the engine
-    split_content
-        .iter()
-        .enumerate()
-        .for_each(|(idx, sentence)| {
-            engine.insert(
-                format!("{:?}¬{}", idx, &sentence.clone()),
-                &sentence.clone(),
-            );
-        });
-
-    let mut new_output = input;
-
-    //search for the query
-    let results = engine.search(&query);
-    let amount = if split_content.len() < 5 { 2 } else { 3 };
-    for x in results.iter().take(amount) {
-        let split_x: Vec<&str> = x.split('¬').collect();
-        if split_x.len() < 2 {
-            continue;
-        }
-        let sentence_index = split_x[0].parse::<usize>().unwrap();
-        let highlighted_sentence = format!("{}{}{}", "<b>", split_x[1], "</b>");
-        split_content[sentence_index] = highlighted_sentence;
-    }
-
-    new_output.chunk_html = Some(split_content.iter().join(""));
-    Ok(new_output)
-}
-
 #[tracing::instrument(skip(pool))]
 pub async fn get_row_count_for_dataset_id_query(
     dataset_id: uuid::Uuid,
@@ -1,7 +1,8 @@
 use crate::data::models::{
-    DatasetAndUsage, DatasetUsageCount, RedisPool, ServerDatasetConfiguration,
+    DatasetAndUsage, DatasetUsageCount, RecencyDecay, RedisPool, ServerDatasetConfiguration,
 };
 use crate::operators::qdrant_operator::get_qdrant_connection;
+use crate::operators::search_operator::RecencyDecayCurve;
 use crate::{
     data::models::{Dataset, Pool},
     errors::ServiceError,
@@ -160,6 +161,58 @@ pub async fn delete_dataset_by_id_query(
     Ok(())
 }
 
+/// Rejects server configurations which searches could not run with, so a bad value is reported when the dataset is saved
+/// instead of on every search. FULLTEXT_LANGUAGE must name one of the text search configurations installed in Postgres
+/// and RECENCY_DECAY must be a valid recency decay.
+#[tracing::instrument(skip(pool))]
+pub async fn validate_server_configuration_query(
+    server_configuration: &serde_json::Value,
+    pool: web::Data<Pool>,
+) -> Result<(), ServiceError> {
+    use diesel::dsl::sql;
+    use diesel::sql_types::{Bool, Text};
+
+    if let Some(recency_decay) = server_configuration.get("RECENCY_DECAY") {
+        let recency_decay: RecencyDecay = serde_json::from_value(recency_decay.clone())
+            .map_err(|err| ServiceError::BadRequest(format!("Invalid RECENCY_DECAY: {}", err)))?;
+        RecencyDecayCurve::from_decay(recency_decay)?;
+    }
+
+    let language = match server_configuration
+        .get("FULLTEXT_LANGUAGE")
+        .and_then(|language| language.as_str())
+    {
+        Some(language) if !language.is_empty() => language.to_string(),
+        _ => return Ok(()),
+    };
+
+    let mut conn = pool
+        .get()
+        .await
+        .map_err(|_| ServiceError::BadRequest("Could not get database connection".to_string()))?;
+
+    let language_exists: bool = diesel::select(
+        sql::<Bool>("EXISTS (SELECT 1 FROM pg_ts_config WHERE cfgname = ")
+            .bind::<Text, _>(language.clone())
+            .sql(")"),
+    )
+    .get_result(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to check FULLTEXT_LANGUAGE {:?}", err);
+        ServiceError::InternalServerError("Failed to check FULLTEXT_LANGUAGE".to_string())
+    })?;
+
+    if !language_exists {
+        return Err(ServiceError::BadRequest(format!(
+            "FULLTEXT_LANGUAGE {} is not a Postgres text search configuration, use one of the names in pg_ts_config such as english or simple",
+            language
+        )));
+    }
+
+    Ok(())
+}
+
 #[tracing::instrument(skip(redis_pool, pool))]
 pub async fn update_dataset_query(
     id: uuid::Uuid,
@@ -0,0 +1,335 @@
+use super::math_operator::cosine_similarity;
+use super::qdrant_operator::get_qdrant_point_vectors_query;
+use super::search_operator::{get_rank_offset, GroupScoreChunkDTO, RankedResult, SearchCursor};
+use crate::{
+    data::models::{ChunkMetadataWithFileData, ServerDatasetConfiguration},
+    errors::ServiceError,
+    handlers::chunk_handler::{DiversifyOptions, ScoreChunkDTO},
+};
+use std::collections::HashMap;
+
+/// Number of candidates fetched for each result a diversified search has to return.
+const DIVERSITY_CANDIDATES_PER_RESULT: u64 = 4;
+
+/// A search result which can be diversified. Its vector, link, and file are those of its representative chunk.
+pub trait DiverseResult: RankedResult {
+    fn representative_chunk(&self) -> Option<&ChunkMetadataWithFileData>;
+}
+
+impl DiverseResult for ScoreChunkDTO {
+    fn representative_chunk(&self) -> Option<&ChunkMetadataWithFileData> {
+        self.metadata.first()
+    }
+}
+
+impl DiverseResult for GroupScoreChunkDTO {
+    fn representative_chunk(&self) -> Option<&ChunkMetadataWithFileData> {
+        self.metadata
+            .first()
+            .and_then(|score_chunk| score_chunk.metadata.first())
+    }
+}
+
+/// Deepest result a diversified search can page to. Every page re-diversifies from the first candidate, so this bounds
+/// both the candidates fetched and the work done to pick them.
+const MAX_DIVERSIFIED_RESULTS: u64 = 250;
+
+/// Returns the page size to request from the retrievers so that a diversified page can be picked from enough candidates.
+/// Candidates always start at the first result, so the page has to be requested as page 1 without a cursor.
+/// Pages past MAX_DIVERSIFIED_RESULTS are rejected.
+pub fn get_diversity_candidate_limit(
+    page: u64,
+    limit: u64,
+    cursor: &Option<SearchCursor>,
+) -> Result<u64, ServiceError> {
+    let needed = get_rank_offset(page, limit, cursor) + limit;
+    if needed > MAX_DIVERSIFIED_RESULTS {
+        return Err(ServiceError::BadRequest(format!(
+            "Diversified search can only page through its top {} results",
+            MAX_DIVERSIFIED_RESULTS
+        )));
+    }
+
+    Ok(needed * DIVERSITY_CANDIDATES_PER_RESULT)
+}
+
+/// Scales the page count of the candidate search to the page size of the request. This is an upper bound since caps may drop results.
+pub fn get_diversity_total_pages(candidate_pages: i64, candidate_limit: u64, limit: u64) -> i64 {
+    (candidate_pages * candidate_limit as i64 + limit as i64 - 1) / (limit.max(1) as i64)
+}
+
+#[derive(Default)]
+struct DiversityCaps {
+    link_counts: HashMap<String, u32>,
+    file_counts: HashMap<uuid::Uuid, u32>,
+}
+
+impl DiversityCaps {
+    fn is_full<T: DiverseResult>(&self, result: &T, options: &DiversifyOptions) -> bool {
+        let chunk = match result.representative_chunk() {
+            Some(chunk) => chunk,
+            None => return false,
+        };
+
+        let link_full = match (options.max_per_link, chunk.link.as_ref()) {
+            (Some(max_per_link), Some(link)) if !link.is_empty() => {
+                self.link_counts.get(link).copied().unwrap_or(0) >= max_per_link
+            }
+            _ => false,
+        };
+        let file_full = match (options.max_per_file, chunk.file_id) {
+            (Some(max_per_file), Some(file_id)) => {
+                self.file_counts.get(&file_id).copied().unwrap_or(0) >= max_per_file
+            }
+            _ => false,
+        };
+
+        link_full || file_full
+    }
+
+    fn add<T: DiverseResult>(&mut self, result: &T) {
+        if let Some(chunk) = result.representative_chunk() {
+            if let Some(link) = chunk.link.as_ref().filter(|link| !link.is_empty()) {
+                *self.link_counts.entry(link.clone()).or_insert(0) += 1;
+            }
+            if let Some(file_id) = chunk.file_id {
+                *self.file_counts.entry(file_id).or_insert(0) += 1;
+            }
+        }
+    }
+}
+
+/// Greedily picks up to `needed` candidates. Each step takes the candidate with the best maximal marginal relevance,
+/// lambda * relevance - (1 - lambda) * highest similarity to a picked result, skipping candidates whose link or file is at its cap.
+/// Relevance is the min-max normalized score so that it is on the same scale as the cosine similarity.
+/// Also returns whether any eligible candidates were left over.
+pub async fn diversify_results<T: DiverseResult>(
+    candidates: Vec<T>,
+    options: &DiversifyOptions,
+    needed: usize,
+    config: ServerDatasetConfiguration,
+) -> Result<(Vec<T>, bool), ServiceError> {
+    let mmr_lambda: f64 = options.mmr_lambda.unwrap_or(1.0).into();
+    if !(0.0..=1.0).contains(&mmr_lambda) {
+        return Err(ServiceError::BadRequest(
+            "diversify.mmr_lambda must be between 0 and 1".to_string(),
+        ));
+    }
+
+    let vectors = if mmr_lambda < 1.0 {
+        get_qdrant_point_vectors_query(
+            candidates
+                .iter()
+                .filter_map(|candidate| candidate.representative_chunk())
+                .map(|chunk| chunk.qdrant_point_id)
+                .collect(),
+            config,
+        )
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?
+    } else {
+        HashMap::new()
+    };
+
+    Ok(select_diverse_results(
+        candidates, options, needed, mmr_lambda, &vectors,
+    ))
+}
+
+/// The selection step of diversify_results, given the dense vectors of the candidates by qdrant point id.
+/// The highest similarity of each candidate to the picked results is updated as results are picked, so every step
+/// only compares the remaining candidates against the newest pick.
+fn select_diverse_results<T: DiverseResult>(
+    candidates: Vec<T>,
+    options: &DiversifyOptions,
+    needed: usize,
+    mmr_lambda: f64,
+    vectors: &HashMap<uuid::Uuid, Vec<f32>>,
+) -> (Vec<T>, bool) {
+    let vector_of = |result: &T| {
+        result
+            .representative_chunk()
+            .and_then(|chunk| vectors.get(&chunk.qdrant_point_id))
+    };
+
+    let (min_score, max_score) = candidates
+        .iter()
+        .map(|candidate| candidate.rank_score())
+        .fold((f64::MAX, f64::MIN), |(min, max), score| {
+            (min.min(score), max.max(score))
+        });
+    let relevance = |result: &T| {
+        if max_score > min_score {
+            (result.rank_score() - min_score) / (max_score - min_score)
+        } else {
+            1.0
+        }
+    };
+
+    let mut remaining: Vec<(T, f64)> = candidates
+        .into_iter()
+        .map(|candidate| (candidate, 0.0))
+        .collect();
+    let mut selected: Vec<T> = vec![];
+    let mut caps = DiversityCaps::default();
+
+    while selected.len() < needed {
+        remaining.retain(|(candidate, _)| !caps.is_full(candidate, options));
+
+        let mut best: Option<(usize, f64)> = None;
+        for (index, (candidate, max_similarity)) in remaining.iter().enumerate() {
+            let mmr_score = mmr_lambda * relevance(candidate) - (1.0 - mmr_lambda) * max_similarity;
+
+            // Strictly greater so that ties keep the relevance order
+            if best.map_or(true, |(_, best_score)| mmr_score > best_score) {
+                best = Some((index, mmr_score));
+            }
+        }
+
+        let (result, _) = match best {
+            Some((index, _)) => remaining.remove(index),
+            None => break,
+        };
+
+        if let Some(vector) = vector_of(&result) {
+            for (candidate, max_similarity) in remaining.iter_mut() {
+                if let Some(candidate_vector) = vector_of(candidate) {
+                    *max_similarity =
+                        max_similarity.max(cosine_similarity(candidate_vector, vector));
+                }
+            }
+        }
+        caps.add(&result);
+        selected.push(result);
+    }
+
+    remaining.retain(|(candidate, _)| !caps.is_full(candidate, options));
+
+    (selected, !remaining.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn candidate(score: f64, link: &str, file_id: Option<uuid::Uuid>) -> ScoreChunkDTO {
+        ScoreChunkDTO {
+            metadata: vec![ChunkMetadataWithFileData {
+                id: uuid::Uuid::new_v4(),
+                content: "".to_string(),
+                chunk_html: None,
+                link: Some(link.to_string()),
+                qdrant_point_id: uuid::Uuid::new_v4(),
+                created_at: chrono::Utc::now().naive_local(),
+                updated_at: chrono::Utc::now().naive_local(),
+                tag_set: None,
+                file_id,
+                file_name: None,
+                metadata: None,
+                tracking_id: None,
+                time_stamp: None,
+                weight: 1.0,
+            }],
+            score,
+            explain: None,
+            highlights: None,
+        }
+    }
+
+    fn options(
+        mmr_lambda: Option<f32>,
+        max_per_link: Option<u32>,
+        max_per_file: Option<u32>,
+    ) -> DiversifyOptions {
+        DiversifyOptions {
+            mmr_lambda,
+            max_per_link,
+            max_per_file,
+        }
+    }
+
+    fn scores(results: &[ScoreChunkDTO]) -> Vec<f64> {
+        results.iter().map(|result| result.score).collect()
+    }
+
+    #[test]
+    fn test_mmr_picks_past_near_duplicates() {
+        let candidates = vec![
+            candidate(0.9, "a", None),
+            candidate(0.85, "b", None),
+            candidate(0.5, "c", None),
+        ];
+        let vectors = HashMap::from([
+            (candidates[0].metadata[0].qdrant_point_id, vec![1.0, 0.0]),
+            (candidates[1].metadata[0].qdrant_point_id, vec![0.99, 0.1]),
+            (candidates[2].metadata[0].qdrant_point_id, vec![0.0, 1.0]),
+        ]);
+
+        let (relevance_order, _) = select_diverse_results(
+            candidates.clone(),
+            &options(Some(1.0), None, None),
+            3,
+            1.0,
+            &vectors,
+        );
+        assert_eq!(scores(&relevance_order), vec![0.9, 0.85, 0.5]);
+
+        let (diversified, has_leftovers) = select_diverse_results(
+            candidates,
+            &options(Some(0.5), None, None),
+            2,
+            0.5,
+            &vectors,
+        );
+        assert_eq!(scores(&diversified), vec![0.9, 0.5]);
+        assert!(has_leftovers);
+    }
+
+    #[test]
+    fn test_caps_drop_results_past_the_limit_per_link_and_file() {
+        let file_id = uuid::Uuid::new_v4();
+        let candidates = vec![
+            candidate(0.9, "a", None),
+            candidate(0.8, "a", None),
+            candidate(0.7, "a", None),
+            candidate(0.6, "b", Some(file_id)),
+            candidate(0.5, "c", Some(file_id)),
+            candidate(0.4, "d", None),
+        ];
+
+        let (by_link, _) = select_diverse_results(
+            candidates.clone(),
+            &options(None, Some(2), None),
+            10,
+            1.0,
+            &HashMap::new(),
+        );
+        assert_eq!(scores(&by_link), vec![0.9, 0.8, 0.6, 0.5, 0.4]);
+
+        let (by_file, _) = select_diverse_results(
+            candidates.clone(),
+            &options(None, None, Some(1)),
+            10,
+            1.0,
+            &HashMap::new(),
+        );
+        assert_eq!(scores(&by_file), vec![0.9, 0.8, 0.7, 0.6, 0.4]);
+
+        let (both, has_leftovers) = select_diverse_results(
+            candidates,
+            &options(None, Some(1), Some(1)),
+            3,
+            1.0,
+            &HashMap::new(),
+        );
+        assert_eq!(scores(&both), vec![0.9, 0.6, 0.4]);
+        assert!(!has_leftovers);
+    }
+
+    #[test]
+    fn test_candidate_limit_is_capped() {
+        assert_eq!(get_diversity_candidate_limit(1, 10, &None).unwrap(), 40);
+        assert_eq!(get_diversity_candidate_limit(25, 10, &None).unwrap(), 1000);
+        assert!(get_diversity_candidate_limit(26, 10, &None).is_err());
+    }
+}
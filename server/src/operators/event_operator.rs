@@ -1,6 +1,8 @@
+use super::webhook_operator::dispatch_event_to_webhooks;
 use crate::{
-    data::models::{Event, Pool},
+    data::models::{Event, Pool, RedisPool},
     errors::DefaultError,
+    get_env,
 };
 use actix_web::web;
 use diesel::prelude::*;
@@ -8,8 +10,45 @@ use diesel_async::RunQueryDsl;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-#[tracing::instrument(skip(pool))]
-pub async fn create_event_query(event: Event, pool: web::Data<Pool>) -> Result<(), DefaultError> {
+/// Redis channel the events of a dataset are published on as they are created.
+pub fn dataset_events_channel(dataset_id: uuid::Uuid) -> String {
+    format!("events:{}", dataset_id)
+}
+
+/// Publishes the event to the subscribers of its dataset on every server instance. Failures are only logged since subscribers can catch up through Last-Event-ID.
+async fn publish_event(event: &Event, redis_pool: web::Data<RedisPool>) {
+    let payload = match serde_json::to_string(event) {
+        Ok(payload) => payload,
+        Err(err) => {
+            log::error!("Failed to serialize event for publishing {:?}", err);
+            return;
+        }
+    };
+
+    let mut redis_conn = match redis_pool.get().await {
+        Ok(redis_conn) => redis_conn,
+        Err(err) => {
+            log::error!("Failed to get redis connection to publish event {:?}", err);
+            return;
+        }
+    };
+
+    let _ = redis::cmd("PUBLISH")
+        .arg(dataset_events_channel(event.dataset_id))
+        .arg(payload)
+        .query_async::<_, i64>(&mut *redis_conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to publish event {:?}", err);
+        });
+}
+
+#[tracing::instrument(skip(pool, redis_pool))]
+pub async fn create_event_query(
+    event: Event,
+    pool: web::Data<Pool>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), DefaultError> {
     use crate::data::schema::events::dsl as events_columns;
 
     let mut conn = pool.get().await.unwrap();
@@ -25,8 +64,102 @@ pub async fn create_event_query(event: Event, pool: web::Data<Pool>) -> Result<(
             }
         })?;
 
+    publish_event(&event, redis_pool).await;
+    dispatch_event_to_webhooks(event, pool);
+
     Ok(())
 }
+
+/// Subscribes to the events of the dataset which are created from now on.
+pub async fn subscribe_to_dataset_events(
+    dataset_id: uuid::Uuid,
+) -> Result<redis::aio::PubSub, DefaultError> {
+    let redis_url = get_env!("REDIS_URL", "REDIS_URL is not set");
+
+    let mut pubsub = redis::Client::open(redis_url)
+        .map_err(|_| DefaultError {
+            message: "Failed to create redis client",
+        })?
+        .get_async_pubsub()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get redis pubsub connection {:?}", err);
+            DefaultError {
+                message: "Failed to subscribe to events",
+            }
+        })?;
+
+    pubsub
+        .subscribe(dataset_events_channel(dataset_id))
+        .await
+        .map_err(|err| {
+            log::error!("Failed to subscribe to events {:?}", err);
+            DefaultError {
+                message: "Failed to subscribe to events",
+            }
+        })?;
+
+    Ok(pubsub)
+}
+
+/// Creation time of the event of the dataset with id last_event_id. Used to resume an event stream after it.
+#[tracing::instrument(skip(pool))]
+pub async fn get_event_created_at_query(
+    dataset_id: uuid::Uuid,
+    last_event_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<chrono::NaiveDateTime, DefaultError> {
+    use crate::data::schema::events::dsl as events_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    events_columns::events
+        .filter(events_columns::id.eq(last_event_id))
+        .filter(events_columns::dataset_id.eq(dataset_id))
+        .select(events_columns::created_at)
+        .first::<chrono::NaiveDateTime>(&mut conn)
+        .await
+        .map_err(|_| DefaultError {
+            message: "Last-Event-ID must be the id of an event of this dataset",
+        })
+}
+
+/// Events of the dataset which come after the event created at last_event_created_at with id last_event_id, ordered by creation time and then id. Events created at the same time as the last event are only skipped if they were already sent before it. Used to resume an event stream.
+#[tracing::instrument(skip(pool))]
+pub async fn get_events_after_query(
+    dataset_id: uuid::Uuid,
+    last_event_created_at: chrono::NaiveDateTime,
+    last_event_id: uuid::Uuid,
+    event_types: Vec<String>,
+    limit: i64,
+    pool: web::Data<Pool>,
+) -> Result<Vec<Event>, DefaultError> {
+    use crate::data::schema::events::dsl as events_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    events_columns::events
+        .filter(events_columns::dataset_id.eq(dataset_id))
+        .filter(
+            events_columns::created_at
+                .gt(last_event_created_at)
+                .or(events_columns::created_at
+                    .eq(last_event_created_at)
+                    .and(events_columns::id.gt(last_event_id))),
+        )
+        .filter(events_columns::event_type.eq_any(event_types))
+        .order((events_columns::created_at.asc(), events_columns::id.asc()))
+        .limit(limit)
+        .select(Event::as_select())
+        .load::<Event>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get events after {:?}", err);
+            DefaultError {
+                message: "Failed to get events",
+            }
+        })
+}
 #[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
 pub struct EventReturn {
     pub events: Vec<Event>,
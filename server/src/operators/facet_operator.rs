@@ -0,0 +1,310 @@
+use super::qdrant_operator::scroll_qdrant_payloads_query;
+use super::search_operator::assemble_qdrant_filter;
+use crate::{
+    data::models::{Pool, ServerDatasetConfiguration},
+    errors::ServiceError,
+    handlers::chunk_handler::{ChunkFilter, ParsedQuery},
+};
+use actix_web::web;
+use chrono::Datelike;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// Maximum number of buckets returned for a single facet. Time facets are not truncated.
+const MAX_FACET_BUCKETS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeInterval {
+    Day,
+    Month,
+    Year,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FacetField {
+    TagSet,
+    Link,
+    Metadata(Vec<String>),
+    TimeStamp(TimeInterval),
+}
+
+impl FacetField {
+    pub fn parse(facet: &str) -> Result<Self, ServiceError> {
+        match facet.split_once(':') {
+            Some(("time_stamp", "day")) => Ok(FacetField::TimeStamp(TimeInterval::Day)),
+            Some(("time_stamp", "month")) => Ok(FacetField::TimeStamp(TimeInterval::Month)),
+            Some(("time_stamp", "year")) => Ok(FacetField::TimeStamp(TimeInterval::Year)),
+            Some(_) => Err(ServiceError::BadRequest(format!(
+                "Invalid facet {}. Only time_stamp facets accept an interval of day, month, or year",
+                facet
+            ))),
+            None => match facet {
+                "tag_set" => Ok(FacetField::TagSet),
+                "link" => Ok(FacetField::Link),
+                "time_stamp" => Ok(FacetField::TimeStamp(TimeInterval::Month)),
+                _ => match facet.strip_prefix("metadata.") {
+                    Some(path) if !path.is_empty() => Ok(FacetField::Metadata(
+                        path.split('.').map(|key| key.to_string()).collect(),
+                    )),
+                    _ => Err(ServiceError::BadRequest(format!(
+                        "Invalid facet {}. Facets must be tag_set, link, time_stamp, or metadata.<key>",
+                        facet
+                    ))),
+                },
+            },
+        }
+    }
+
+    fn payload_field(&self) -> String {
+        match self {
+            FacetField::TagSet => "tag_set".to_string(),
+            FacetField::Link => "link".to_string(),
+            FacetField::Metadata(path) => format!("metadata.{}", path.join(".")),
+            FacetField::TimeStamp(_) => "time_stamp".to_string(),
+        }
+    }
+
+    fn values_from_payload(&self, payload: &serde_json::Value) -> Vec<String> {
+        match self {
+            FacetField::TagSet => string_values(&payload["tag_set"]),
+            FacetField::Link => string_values(&payload["link"]),
+            FacetField::Metadata(path) => {
+                let value = path
+                    .iter()
+                    .fold(&payload["metadata"], |value, key| &value[key.as_str()]);
+                string_values(value)
+            }
+            FacetField::TimeStamp(interval) => payload["time_stamp"]
+                .as_i64()
+                .filter(|time_stamp| *time_stamp != 0)
+                .and_then(|time_stamp| chrono::NaiveDateTime::from_timestamp_opt(time_stamp, 0))
+                .map(|time_stamp| match interval {
+                    TimeInterval::Day => time_stamp.format("%Y-%m-%d").to_string(),
+                    TimeInterval::Month => {
+                        format!("{:04}-{:02}", time_stamp.year(), time_stamp.month())
+                    }
+                    TimeInterval::Year => format!("{:04}", time_stamp.year()),
+                })
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+fn string_values(value: &serde_json::Value) -> Vec<String> {
+    match value {
+        serde_json::Value::String(value) if !value.is_empty() => vec![value.clone()],
+        serde_json::Value::Number(value) => vec![value.to_string()],
+        serde_json::Value::Bool(value) => vec![value.to_string()],
+        serde_json::Value::Array(values) => values.iter().flat_map(string_values).collect(),
+        _ => vec![],
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct FacetBucket {
+    /// The value of the field. Time stamps are formatted as YYYY, YYYY-MM, or YYYY-MM-DD depending on the interval.
+    pub value: String,
+    /// Number of chunks in the filtered result set which have this value.
+    pub count: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+#[schema(example = json!({
+    "field": "tag_set",
+    "buckets": [
+        {"value": "news", "count": 42},
+        {"value": "sports", "count": 7}
+    ],
+    "partial": false
+}))]
+pub struct FacetResult {
+    /// The facet as it was requested.
+    pub field: String,
+    /// Buckets sorted by count descending, or chronologically for time_stamp facets.
+    pub buckets: Vec<FacetBucket>,
+    /// True if the filtered result set was larger than FACET_SCAN_LIMIT and the counts only cover part of it.
+    pub partial: bool,
+}
+
+/// Counts the chunks which have each value of the field. A chunk counts once per distinct value, so a tag repeated within its tag_set is not counted twice. Ties in count are broken by value so that buckets are stable across requests.
+fn count_facet_buckets(
+    facet_field: &FacetField,
+    payloads: &[serde_json::Value],
+) -> Vec<FacetBucket> {
+    let mut counts: HashMap<String, u64> = HashMap::new();
+    for payload in payloads.iter() {
+        for value in facet_field
+            .values_from_payload(payload)
+            .into_iter()
+            .unique()
+        {
+            *counts.entry(value).or_insert(0) += 1;
+        }
+    }
+
+    let mut buckets = counts
+        .into_iter()
+        .map(|(value, count)| FacetBucket { value, count })
+        .collect_vec();
+
+    match facet_field {
+        FacetField::TimeStamp(_) => buckets.sort_by(|a, b| a.value.cmp(&b.value)),
+        _ => {
+            buckets.sort_by(|a, b| b.count.cmp(&a.count).then(a.value.cmp(&b.value)));
+            buckets.truncate(MAX_FACET_BUCKETS);
+        }
+    }
+
+    buckets
+}
+
+/// Computes value counts for each requested facet over every chunk matching the filters of a search.
+#[tracing::instrument(skip(pool))]
+pub async fn get_facet_counts_query(
+    facets: Option<Vec<String>>,
+    filters: Option<ChunkFilter>,
+    parsed_query: ParsedQuery,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+    config: ServerDatasetConfiguration,
+) -> Result<Option<Vec<FacetResult>>, ServiceError> {
+    let facets = match facets {
+        Some(facets) if !facets.is_empty() => facets,
+        _ => return Ok(None),
+    };
+
+    let facet_fields = facets
+        .iter()
+        .map(|facet| FacetField::parse(facet))
+        .collect::<Result<Vec<FacetField>, ServiceError>>()?;
+
+    let scan_limit: u64 = std::env::var("FACET_SCAN_LIMIT")
+        .unwrap_or("10000".to_string())
+        .parse()
+        .unwrap_or(10000);
+
+    let filter = assemble_qdrant_filter(filters, Some(parsed_query), dataset_id, Some(pool))
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    let payloads = scroll_qdrant_payloads_query(
+        filter,
+        facet_fields
+            .iter()
+            .map(|facet_field| facet_field.payload_field())
+            .unique()
+            .collect(),
+        scan_limit,
+        config,
+    )
+    .await
+    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    let partial = payloads.len() as u64 >= scan_limit;
+
+    let facet_results = facets
+        .into_iter()
+        .zip(facet_fields)
+        .map(|(facet, facet_field)| FacetResult {
+            field: facet,
+            buckets: count_facet_buckets(&facet_field, &payloads),
+            partial,
+        })
+        .collect_vec();
+
+    Ok(Some(facet_results))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn buckets(facet: &str, payloads: Vec<serde_json::Value>) -> Vec<(String, u64)> {
+        count_facet_buckets(&FacetField::parse(facet).unwrap(), &payloads)
+            .into_iter()
+            .map(|bucket| (bucket.value, bucket.count))
+            .collect()
+    }
+
+    #[test]
+    fn test_array_scalar_and_missing_values_are_bucketed() {
+        let payloads = vec![
+            json!({"tag_set": ["news", "sports", "news"], "metadata": {"source": {"name": "wire"}}}),
+            json!({"tag_set": "news", "metadata": {"source": {"name": ["wire", "blog"]}}}),
+            json!({"tag_set": [], "metadata": {"source": {"name": 3}}}),
+            json!({"tag_set": "", "metadata": {"source": true}}),
+            json!({}),
+        ];
+
+        assert_eq!(
+            buckets("tag_set", payloads.clone()),
+            vec![("news".to_string(), 2), ("sports".to_string(), 1)]
+        );
+        assert_eq!(
+            buckets("metadata.source.name", payloads),
+            vec![
+                ("wire".to_string(), 2),
+                ("3".to_string(), 1),
+                ("blog".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_buckets_are_ordered_by_count_then_value() {
+        let payloads = vec![
+            json!({"link": "b"}),
+            json!({"link": "c"}),
+            json!({"link": "a"}),
+            json!({"link": "c"}),
+        ];
+
+        assert_eq!(
+            buckets("link", payloads),
+            vec![
+                ("c".to_string(), 2),
+                ("a".to_string(), 1),
+                ("b".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_time_stamp_buckets_are_chronological_and_skip_unset_time_stamps() {
+        let payloads = vec![
+            json!({"time_stamp": 1_704_067_200}),
+            json!({"time_stamp": 1_672_531_200}),
+            json!({"time_stamp": 1_704_153_600}),
+            json!({"time_stamp": 0}),
+            json!({}),
+        ];
+
+        assert_eq!(
+            buckets("time_stamp", payloads.clone()),
+            vec![("2023-01".to_string(), 1), ("2024-01".to_string(), 2)]
+        );
+        assert_eq!(
+            buckets("time_stamp:day", payloads),
+            vec![
+                ("2023-01-01".to_string(), 1),
+                ("2024-01-01".to_string(), 1),
+                ("2024-01-02".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_buckets_are_capped() {
+        let payloads = (0..MAX_FACET_BUCKETS + 5)
+            .map(|i| json!({ "link": format!("{:03}", i) }))
+            .collect();
+
+        let buckets = buckets("link", payloads);
+        assert_eq!(buckets.len(), MAX_FACET_BUCKETS);
+        assert_eq!(buckets[0].0, "000");
+    }
+}
@@ -359,6 +359,7 @@ pub async fn create_chunks_with_handler(
                         ReturnQueuedChunk::Single(SingleQueuedChunkResponse {
                             chunk_metadata,
                             pos_in_queue: _,
+                            batch_id: _,
                         }) => chunk_ids.push(chunk_metadata.id),
                         _ => unreachable!("Only uploaded 1 chunk but multiple chunks returned"),
                     }
@@ -379,6 +380,7 @@ pub async fn create_chunks_with_handler(
             },
         ),
         pool,
+        redis_pool,
     )
     .await
     .map_err(|_| DefaultError {
@@ -0,0 +1,499 @@
+use super::model_operator::get_splade_embedding;
+use super::qdrant_operator::{scroll_qdrant_point_ids_query, GroupSearchResults, VectorType};
+use super::search_operator::{sort_ranked_results, SearchResult};
+use crate::{
+    data::models::{FullTextBackend, Pool, ServerDatasetConfiguration},
+    errors::{DefaultError, ServiceError},
+};
+use actix_web::web;
+use itertools::Itertools;
+use qdrant_client::qdrant::{
+    condition::ConditionOneOf::HasId, Condition, Filter, HasIdCondition, PointId,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Builds the full-text query for the backend configured on the dataset. SPLADE queries are embedded into a sparse vector
+/// while Postgres queries are kept as text and searched against the chunk_metadata tsvector column.
+#[tracing::instrument]
+pub async fn get_full_text_vector(
+    query: &str,
+    config: &ServerDatasetConfiguration,
+) -> Result<VectorType, ServiceError> {
+    match config.FULLTEXT_BACKEND {
+        FullTextBackend::Splade => Ok(VectorType::Sparse(
+            get_splade_embedding(query, "query").await?,
+        )),
+        FullTextBackend::Postgres => {
+            if query.trim().is_empty() {
+                return Err(ServiceError::BadRequest(
+                    "Cannot search with an empty query".to_string(),
+                ));
+            }
+            Ok(VectorType::FullText(query.to_string()))
+        }
+    }
+}
+
+/// Rewrites the query as a websearch_to_tsquery expression which matches chunks containing all of its words. The query
+/// language was already parsed out of the query, so quotes, leading dashes, and the word or are dropped instead of being
+/// read as phrase, negation, and OR operators.
+fn all_words_tsquery(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| word.trim_start_matches('-').replace('"', ""))
+        .filter(|word| !word.is_empty() && !word.eq_ignore_ascii_case("or"))
+        .join(" ")
+}
+
+/// Ranks one window of the chunks of a dataset which match the query with ts_rank_cd, best first.
+async fn get_ranked_chunk_window_query(
+    tsquery: &str,
+    dataset_id: uuid::Uuid,
+    offset: i64,
+    limit: i64,
+    pool: web::Data<Pool>,
+) -> Result<Vec<SearchResult>, DefaultError> {
+    use crate::data::schema::chunk_metadata::dsl as chunk_metadata_columns;
+    use diesel::dsl::sql;
+    use diesel::prelude::*;
+    use diesel::sql_types::{Bool, Float, Text, Uuid as SqlUuid};
+    use diesel_async::RunQueryDsl;
+
+    // dataset_fulltext_language is what the tsvector trigger indexes with, so queries always match the indexed language
+    // and an unknown FULLTEXT_LANGUAGE falls back to simple instead of failing the cast
+    let rank = || {
+        sql::<Float>(
+            "ts_rank_cd(chunk_metadata.chunk_metadata_tsvector, websearch_to_tsquery(dataset_fulltext_language(",
+        )
+        .bind::<SqlUuid, _>(dataset_id)
+        .sql("), ")
+        .bind::<Text, _>(tsquery.to_string())
+        .sql("), 32)")
+    };
+    let matches_query = sql::<Bool>(
+        "chunk_metadata.chunk_metadata_tsvector @@ websearch_to_tsquery(dataset_fulltext_language(",
+    )
+    .bind::<SqlUuid, _>(dataset_id)
+    .sql("), ")
+    .bind::<Text, _>(tsquery.to_string())
+    .sql(")");
+
+    let mut conn = pool.get().await.unwrap();
+
+    let ranked_chunks: Vec<(Option<uuid::Uuid>, f32)> = chunk_metadata_columns::chunk_metadata
+        .select((chunk_metadata_columns::qdrant_point_id, rank()))
+        .filter(chunk_metadata_columns::dataset_id.eq(dataset_id))
+        .filter(chunk_metadata_columns::qdrant_point_id.is_not_null())
+        .filter(matches_query)
+        .order((rank().desc(), chunk_metadata_columns::qdrant_point_id.asc()))
+        .offset(offset)
+        .limit(limit)
+        .load(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to run Postgres full-text search {:?}", err);
+            DefaultError {
+                message: "Failed to run Postgres full-text search",
+            }
+        })?;
+
+    Ok(ranked_chunks
+        .into_iter()
+        .filter_map(|(point_id, score)| {
+            Some(SearchResult {
+                score,
+                point_id: point_id?,
+            })
+        })
+        .collect_vec())
+}
+
+/// Keeps the ranked chunks whose points match the search filter, in order.
+async fn filter_ranked_chunks_query(
+    ranked_chunks: Vec<SearchResult>,
+    filter: Filter,
+    config: ServerDatasetConfiguration,
+) -> Result<Vec<SearchResult>, DefaultError> {
+    if ranked_chunks.is_empty() {
+        return Ok(ranked_chunks);
+    }
+
+    let mut filter = filter;
+    filter.must.push(Condition {
+        condition_one_of: Some(HasId(HasIdCondition {
+            has_id: ranked_chunks
+                .iter()
+                .map(|result| result.point_id.to_string().into())
+                .collect::<Vec<PointId>>(),
+        })),
+    });
+
+    let matching_point_ids: HashSet<uuid::Uuid> =
+        scroll_qdrant_point_ids_query(filter, ranked_chunks.len() as u64, config)
+            .await?
+            .into_iter()
+            .collect();
+
+    Ok(ranked_chunks
+        .into_iter()
+        .filter(|result| matching_point_ids.contains(&result.point_id))
+        .collect())
+}
+
+/// Ranks the chunks of a dataset against the query with ts_rank_cd, keeping only those which also match the search filter.
+/// At most FULLTEXT_CANDIDATE_LIMIT matching chunks are ranked. The filter lives in Qdrant, so candidates are ranked a
+/// window at a time and filtered until enough of them match or every candidate has been seen.
+async fn get_ranked_chunks_query(
+    query: String,
+    score_threshold: Option<f32>,
+    filter: Filter,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+    config: ServerDatasetConfiguration,
+) -> Result<Vec<SearchResult>, DefaultError> {
+    let candidate_limit: i64 = std::env::var("FULLTEXT_CANDIDATE_LIMIT")
+        .unwrap_or("1000".to_string())
+        .parse()
+        .unwrap_or(1000);
+    let score_threshold = score_threshold.unwrap_or(0.0);
+    let tsquery = all_words_tsquery(&query);
+
+    // assemble_qdrant_filter always adds the dataset_id condition, which the ranking query already applies
+    let has_other_conditions =
+        filter.must.len() > 1 || !filter.should.is_empty() || !filter.must_not.is_empty();
+
+    let mut ranked_chunks: Vec<SearchResult> = vec![];
+    let mut offset = 0;
+    loop {
+        let window = get_ranked_chunk_window_query(
+            &tsquery,
+            dataset_id,
+            offset,
+            candidate_limit,
+            pool.clone(),
+        )
+        .await?;
+        let window_len = window.len() as i64;
+        offset += candidate_limit;
+
+        // The window is ranked best first, so once a score falls below the threshold every later candidate does too
+        let above_threshold = window
+            .into_iter()
+            .take_while(|result| result.score >= score_threshold)
+            .collect_vec();
+        let reached_threshold = (above_threshold.len() as i64) < window_len;
+
+        if has_other_conditions {
+            ranked_chunks.extend(
+                filter_ranked_chunks_query(above_threshold, filter.clone(), config.clone()).await?,
+            );
+        } else {
+            ranked_chunks.extend(above_threshold);
+        }
+
+        if reached_threshold
+            || window_len < candidate_limit
+            || ranked_chunks.len() as i64 >= candidate_limit
+        {
+            break;
+        }
+    }
+
+    ranked_chunks.truncate(candidate_limit.max(0) as usize);
+    sort_ranked_results(&mut ranked_chunks);
+
+    Ok(ranked_chunks)
+}
+
+/// Postgres counterpart to search_qdrant_query. Returns the window of ranked chunks along with the number of chunks which
+/// matched both the query and the filter.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(pool))]
+pub async fn search_postgres_full_text_query(
+    query: String,
+    offset: u64,
+    limit: u64,
+    score_threshold: Option<f32>,
+    filter: Filter,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+    config: ServerDatasetConfiguration,
+) -> Result<(Vec<SearchResult>, u64), DefaultError> {
+    let ranked_chunks =
+        get_ranked_chunks_query(query, score_threshold, filter, dataset_id, pool, config).await?;
+    let count = ranked_chunks.len() as u64;
+
+    Ok((
+        ranked_chunks
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect(),
+        count,
+    ))
+}
+
+/// Postgres counterpart to search_over_groups_query. Groups are ranked by their best matching chunk and hold at most group_size chunks.
+/// Returns the window of groups along with the number of chunks which matched both the query and the filter.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(pool))]
+pub async fn search_over_groups_postgres_full_text_query(
+    query: String,
+    offset: u32,
+    limit: u32,
+    score_threshold: Option<f32>,
+    group_size: u32,
+    filter: Filter,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+    config: ServerDatasetConfiguration,
+) -> Result<(Vec<GroupSearchResults>, u64), DefaultError> {
+    use crate::data::schema::chunk_group_bookmarks::dsl as chunk_group_bookmarks_columns;
+    use crate::data::schema::chunk_metadata::dsl as chunk_metadata_columns;
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+
+    let ranked_chunks = get_ranked_chunks_query(
+        query,
+        score_threshold,
+        filter,
+        dataset_id,
+        pool.clone(),
+        config,
+    )
+    .await?;
+    let count = ranked_chunks.len() as u64;
+
+    let mut conn = pool.get().await.unwrap();
+
+    let group_memberships: Vec<(uuid::Uuid, Option<uuid::Uuid>)> =
+        chunk_group_bookmarks_columns::chunk_group_bookmarks
+            .inner_join(chunk_metadata_columns::chunk_metadata)
+            .select((
+                chunk_group_bookmarks_columns::group_id,
+                chunk_metadata_columns::qdrant_point_id,
+            ))
+            .filter(
+                chunk_metadata_columns::qdrant_point_id.eq_any(
+                    ranked_chunks
+                        .iter()
+                        .map(|result| result.point_id)
+                        .collect::<Vec<uuid::Uuid>>(),
+                ),
+            )
+            .load(&mut conn)
+            .await
+            .map_err(|err| {
+                log::error!("Failed to load groups for full-text search {:?}", err);
+                DefaultError {
+                    message: "Failed to load groups for full-text search",
+                }
+            })?;
+
+    let mut group_ids_by_point_id: HashMap<uuid::Uuid, Vec<uuid::Uuid>> = HashMap::new();
+    for (group_id, point_id) in group_memberships {
+        if let Some(point_id) = point_id {
+            group_ids_by_point_id
+                .entry(point_id)
+                .or_default()
+                .push(group_id);
+        }
+    }
+
+    let mut groups: Vec<GroupSearchResults> = vec![];
+    let mut group_positions: HashMap<uuid::Uuid, usize> = HashMap::new();
+    for result in ranked_chunks {
+        for group_id in group_ids_by_point_id
+            .get(&result.point_id)
+            .cloned()
+            .unwrap_or_default()
+        {
+            let position = *group_positions.entry(group_id).or_insert_with(|| {
+                groups.push(GroupSearchResults {
+                    group_id,
+                    hits: vec![],
+                });
+                groups.len() - 1
+            });
+
+            if groups[position].hits.len() < group_size as usize {
+                groups[position].hits.push(result.clone());
+            }
+        }
+    }
+    sort_ranked_results(&mut groups);
+
+    Ok((
+        groups
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect(),
+        count,
+    ))
+}
+
+/// Number of chunks whose tsvector is recomputed per statement when a dataset is reindexed.
+const FULLTEXT_REINDEX_BATCH_SIZE: i64 = 1000;
+
+/// The dataset whose full-text reindex was requested first, along with when it was requested.
+async fn get_pending_fulltext_reindex_query(
+    pool: web::Data<Pool>,
+) -> Result<Option<(uuid::Uuid, chrono::NaiveDateTime)>, DefaultError> {
+    use crate::data::schema::dataset_fulltext_reindexes::dsl as dataset_fulltext_reindexes_columns;
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+
+    let mut conn = pool.get().await.unwrap();
+
+    dataset_fulltext_reindexes_columns::dataset_fulltext_reindexes
+        .select((
+            dataset_fulltext_reindexes_columns::dataset_id,
+            dataset_fulltext_reindexes_columns::requested_at,
+        ))
+        .order(dataset_fulltext_reindexes_columns::requested_at.asc())
+        .first(&mut conn)
+        .await
+        .optional()
+        .map_err(|err| {
+            log::error!("Failed to get pending full-text reindex {:?}", err);
+            DefaultError {
+                message: "Failed to get pending full-text reindex",
+            }
+        })
+}
+
+/// Recomputes the tsvector of every chunk of the dataset in batches, so that a change of FULLTEXT_LANGUAGE never rewrites a
+/// whole dataset in one statement. Chunks which are not reindexed yet keep matching queries parsed the way their old
+/// language parses them.
+#[tracing::instrument(skip(pool))]
+async fn reindex_dataset_chunk_metadata_tsvector_query(
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<(), DefaultError> {
+    use crate::data::schema::chunk_metadata::dsl as chunk_metadata_columns;
+    use diesel::prelude::*;
+    use diesel::sql_types::{Array, Uuid as SqlUuid};
+    use diesel_async::RunQueryDsl;
+
+    let mut conn = pool.get().await.unwrap();
+
+    let mut last_chunk_id: Option<uuid::Uuid> = None;
+    loop {
+        let mut chunk_ids_query = chunk_metadata_columns::chunk_metadata
+            .select(chunk_metadata_columns::id)
+            .filter(chunk_metadata_columns::dataset_id.eq(dataset_id))
+            .order(chunk_metadata_columns::id.asc())
+            .limit(FULLTEXT_REINDEX_BATCH_SIZE)
+            .into_boxed();
+        if let Some(last_chunk_id) = last_chunk_id {
+            chunk_ids_query = chunk_ids_query.filter(chunk_metadata_columns::id.gt(last_chunk_id));
+        }
+
+        let chunk_ids: Vec<uuid::Uuid> = chunk_ids_query.load(&mut conn).await.map_err(|err| {
+            log::error!("Failed to load chunks to reindex {:?}", err);
+            DefaultError {
+                message: "Failed to load chunks to reindex",
+            }
+        })?;
+        let batch_len = chunk_ids.len() as i64;
+        last_chunk_id = match chunk_ids.last() {
+            Some(chunk_id) => Some(*chunk_id),
+            None => return Ok(()),
+        };
+
+        diesel::sql_query(
+            "UPDATE chunk_metadata SET chunk_metadata_tsvector = to_tsvector(dataset_fulltext_language($1), content) WHERE id = ANY($2)",
+        )
+        .bind::<SqlUuid, _>(dataset_id)
+        .bind::<Array<SqlUuid>, _>(chunk_ids)
+        .execute(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to reindex chunks {:?}", err);
+            DefaultError {
+                message: "Failed to reindex chunks",
+            }
+        })?;
+
+        if batch_len < FULLTEXT_REINDEX_BATCH_SIZE {
+            return Ok(());
+        }
+    }
+}
+
+/// Clears the reindex request of the dataset unless it was requested again while the reindex ran.
+async fn finish_fulltext_reindex_query(
+    dataset_id: uuid::Uuid,
+    requested_at: chrono::NaiveDateTime,
+    pool: web::Data<Pool>,
+) -> Result<(), DefaultError> {
+    use crate::data::schema::dataset_fulltext_reindexes::dsl as dataset_fulltext_reindexes_columns;
+    use diesel::prelude::*;
+    use diesel_async::RunQueryDsl;
+
+    let mut conn = pool.get().await.unwrap();
+
+    diesel::delete(
+        dataset_fulltext_reindexes_columns::dataset_fulltext_reindexes
+            .filter(dataset_fulltext_reindexes_columns::dataset_id.eq(dataset_id))
+            .filter(dataset_fulltext_reindexes_columns::requested_at.eq(requested_at)),
+    )
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to finish full-text reindex {:?}", err);
+        DefaultError {
+            message: "Failed to finish full-text reindex",
+        }
+    })?;
+
+    Ok(())
+}
+
+/// Reindexes the datasets whose FULLTEXT_LANGUAGE changed, and the datasets which existed before the tsvector column was
+/// added, one at a time. Every server runs it, and a dataset reindexed by two of them at once just gets the same tsvectors
+/// twice.
+#[tracing::instrument(skip(pool))]
+pub async fn reindex_fulltext_datasets(pool: web::Data<Pool>) {
+    loop {
+        match get_pending_fulltext_reindex_query(pool.clone()).await {
+            Ok(Some((dataset_id, requested_at))) => {
+                match reindex_dataset_chunk_metadata_tsvector_query(dataset_id, pool.clone()).await
+                {
+                    Ok(()) => {
+                        let _ =
+                            finish_fulltext_reindex_query(dataset_id, requested_at, pool.clone())
+                                .await;
+                        continue;
+                    }
+                    Err(err) => log::error!("Failed to reindex dataset {}: {:?}", dataset_id, err),
+                }
+            }
+            Ok(None) => {}
+            Err(err) => log::error!("Failed to check for full-text reindexes: {:?}", err),
+        }
+
+        tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tsquery_requires_every_word() {
+        assert_eq!(all_words_tsquery("how do  cats sleep"), "how do cats sleep");
+        assert!(!all_words_tsquery("cats dogs").contains(" or "));
+    }
+
+    #[test]
+    fn test_tsquery_drops_websearch_operators() {
+        assert_eq!(
+            all_words_tsquery("cats OR dogs -birds \"pet food\""),
+            "cats dogs birds pet food"
+        );
+        assert_eq!(all_words_tsquery("or - \"\""), "");
+    }
+}
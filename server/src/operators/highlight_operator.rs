@@ -0,0 +1,552 @@
+use super::query_operator::{parse_query_ast, QueryNode};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use simsearch::SimSearch;
+use utoipa::ToSchema;
+
+/// Words which are too common to be worth highlighting on their own. They are still matched inside phrases.
+const STOP_WORDS: [&str; 32] = [
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "do", "for", "from", "how", "if", "in",
+    "is", "it", "of", "on", "or", "so", "that", "the", "their", "there", "this", "to", "was",
+    "what", "when", "who", "with",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HighlightKind {
+    /// A single query term.
+    Term,
+    /// A quoted phrase from the query.
+    Phrase,
+    /// A word starting with a `prefix*` term from the query.
+    Prefix,
+    /// A whole sentence which was fuzzily similar to the query. Only used when no term matched.
+    Sentence,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct HighlightSpan {
+    /// Character offset into content where the match starts.
+    pub start: usize,
+    /// Character offset into content where the match ends, exclusive.
+    pub end: usize,
+    pub kind: HighlightKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct HighlightSnippet {
+    /// Character offset into content where the snippet starts.
+    pub start: usize,
+    /// Character offset into content where the snippet ends, exclusive.
+    pub end: usize,
+    /// The text of content between start and end.
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+#[schema(example = json!({
+    "spans": [{"start": 4, "end": 15, "kind": "phrase"}],
+    "snippets": [{"start": 0, "end": 32, "text": "The carbon tax was passed in May"}]
+}))]
+pub struct ChunkHighlights {
+    /// Every match in content, sorted by start and never overlapping.
+    pub spans: Vec<HighlightSpan>,
+    /// Windows of content around the best matches, in the order they appear in content.
+    pub snippets: Vec<HighlightSnippet>,
+}
+
+/// How highlights are computed for the chunks of a search.
+#[derive(Debug, Clone)]
+pub struct HighlightOptions {
+    /// Strings which end a sentence for the fuzzy sentence fallback.
+    pub delimiters: Vec<String>,
+    /// Number of words of context kept on each side of a match in a snippet.
+    pub window: usize,
+    /// Maximum number of snippets returned per chunk.
+    pub max_snippets: usize,
+}
+
+impl HighlightOptions {
+    pub fn new(
+        delimiters: Option<Vec<String>>,
+        window: Option<u32>,
+        max_snippets: Option<u32>,
+    ) -> Self {
+        HighlightOptions {
+            delimiters: delimiters.unwrap_or(vec![
+                ".".to_string(),
+                "!".to_string(),
+                "?".to_string(),
+                "\n".to_string(),
+                "\t".to_string(),
+                ",".to_string(),
+            ]),
+            window: window.unwrap_or(10) as usize,
+            max_snippets: max_snippets.unwrap_or(3) as usize,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum HighlightTerm {
+    Term(String),
+    Phrase(Vec<String>),
+    Prefix(String),
+}
+
+/// The terms, phrases, and prefixes of a search query which are highlighted in its results.
+#[derive(Debug, Clone)]
+pub struct HighlightQuery {
+    text: String,
+    terms: Vec<HighlightTerm>,
+}
+
+impl HighlightQuery {
+    /// Negated clauses and field filters are not highlighted. Queries which fail to parse are highlighted word by word.
+    pub fn parse(query: &str) -> Self {
+        let mut terms = vec![];
+        match parse_query_ast(query) {
+            Ok(Some(root)) => collect_terms(&root, &mut terms),
+            Ok(None) => {}
+            Err(_) => words_as_terms(query, &mut terms),
+        }
+
+        HighlightQuery {
+            text: query.to_string(),
+            terms: terms
+                .into_iter()
+                .unique_by(|term| format!("{:?}", term))
+                .collect(),
+        }
+    }
+}
+
+fn words_as_terms(text: &str, terms: &mut Vec<HighlightTerm>) {
+    terms.extend(
+        tokenize(text)
+            .into_iter()
+            .map(|token| token.text)
+            .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+            .map(HighlightTerm::Term),
+    );
+}
+
+fn collect_terms(node: &QueryNode, terms: &mut Vec<HighlightTerm>) {
+    match node {
+        QueryNode::Term(text) => words_as_terms(text, terms),
+        QueryNode::Phrase(text) => {
+            let words = tokenize(text)
+                .into_iter()
+                .map(|token| token.text)
+                .collect_vec();
+            match words.len() {
+                0 => {}
+                1 => words_as_terms(text, terms),
+                _ => terms.push(HighlightTerm::Phrase(words)),
+            }
+        }
+        QueryNode::Prefix(prefix) => {
+            if let Some(token) = tokenize(prefix).into_iter().next() {
+                terms.push(HighlightTerm::Prefix(token.text));
+            }
+        }
+        QueryNode::And { clauses, .. } | QueryNode::Or(clauses) => clauses
+            .iter()
+            .for_each(|clause| collect_terms(clause, terms)),
+        QueryNode::Group(inner) => collect_terms(inner, terms),
+        QueryNode::Field { .. } | QueryNode::Not(_) => {}
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    /// Lowercased text of the word.
+    text: String,
+    start: usize,
+    end: usize,
+}
+
+/// Splits text into runs of alphanumeric characters. Offsets are in characters, not bytes.
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut current: Option<Token> = None;
+
+    for (index, c) in text.chars().enumerate() {
+        if c.is_alphanumeric() {
+            let token = current.get_or_insert(Token {
+                text: String::new(),
+                start: index,
+                end: index,
+            });
+            token.text.extend(c.to_lowercase());
+            token.end = index + 1;
+        } else if let Some(token) = current.take() {
+            tokens.push(token);
+        }
+    }
+    tokens.extend(current);
+
+    tokens
+}
+
+/// A light stemmer so that plurals and simple inflections of a query term still match.
+fn stem(word: &str) -> &str {
+    ["ing", "ed", "es", "s"]
+        .iter()
+        .find_map(|suffix| {
+            word.strip_suffix(suffix)
+                .filter(|stem| stem.chars().count() >= 3)
+        })
+        .unwrap_or(word)
+}
+
+fn words_match(token: &str, word: &str) -> bool {
+    token == word || stem(token) == stem(word)
+}
+
+/// A match as a range of token indices, inclusive.
+#[derive(Debug, Clone)]
+struct TokenMatch {
+    first: usize,
+    last: usize,
+    kind: HighlightKind,
+    term_index: usize,
+}
+
+fn find_matches(tokens: &[Token], terms: &[HighlightTerm]) -> Vec<TokenMatch> {
+    let mut matches = vec![];
+
+    for (term_index, term) in terms.iter().enumerate() {
+        for (index, token) in tokens.iter().enumerate() {
+            let (last, kind) = match term {
+                HighlightTerm::Term(word) if words_match(&token.text, word) => {
+                    (index, HighlightKind::Term)
+                }
+                HighlightTerm::Prefix(prefix) if token.text.starts_with(prefix.as_str()) => {
+                    (index, HighlightKind::Prefix)
+                }
+                HighlightTerm::Phrase(words)
+                    if tokens.len() - index >= words.len()
+                        && tokens[index..index + words.len()]
+                            .iter()
+                            .zip(words.iter())
+                            .all(|(token, word)| words_match(&token.text, word)) =>
+                {
+                    (index + words.len() - 1, HighlightKind::Phrase)
+                }
+                _ => continue,
+            };
+
+            matches.push(TokenMatch {
+                first: index,
+                last,
+                kind,
+                term_index,
+            });
+        }
+    }
+
+    // Longer matches win over the shorter ones they overlap, so phrases take precedence over their own words
+    matches.sort_by(|a, b| a.first.cmp(&b.first).then(b.last.cmp(&a.last)));
+    let mut kept: Vec<TokenMatch> = vec![];
+    for token_match in matches {
+        match kept.last() {
+            Some(previous) if token_match.first <= previous.last => {}
+            _ => kept.push(token_match),
+        }
+    }
+
+    kept
+}
+
+fn char_slice(text: &str, start: usize, end: usize) -> String {
+    text.chars().skip(start).take(end - start).collect()
+}
+
+/// Groups the matches into windows of `options.window` words on each side, merging windows which overlap,
+/// and keeps the ones covering the most distinct terms.
+fn build_snippets(
+    content: &str,
+    tokens: &[Token],
+    matches: &[TokenMatch],
+    options: &HighlightOptions,
+) -> Vec<HighlightSnippet> {
+    let mut windows: Vec<(usize, usize, Vec<usize>)> = vec![];
+    for token_match in matches {
+        let first = token_match.first.saturating_sub(options.window);
+        let last = (token_match.last + options.window).min(tokens.len() - 1);
+
+        match windows.last_mut() {
+            Some(window) if first <= window.1 + 1 => {
+                window.1 = window.1.max(last);
+                window.2.push(token_match.term_index);
+            }
+            _ => windows.push((first, last, vec![token_match.term_index])),
+        }
+    }
+
+    windows
+        .into_iter()
+        .enumerate()
+        .sorted_by(|(a_index, a), (b_index, b)| {
+            let distinct_terms =
+                |window: &(usize, usize, Vec<usize>)| window.2.iter().unique().count();
+            distinct_terms(b)
+                .cmp(&distinct_terms(a))
+                .then(b.2.len().cmp(&a.2.len()))
+                .then(a_index.cmp(b_index))
+        })
+        .take(options.max_snippets)
+        .sorted_by_key(|(index, _)| *index)
+        .map(|(_, (first, last, _))| {
+            let (start, end) = (tokens[first].start, tokens[last].end);
+            HighlightSnippet {
+                start,
+                end,
+                text: char_slice(content, start, end),
+            }
+        })
+        .collect()
+}
+
+/// Fallback for when none of the query terms appear in content, e.g. for purely semantic matches.
+/// Highlights the sentences which are most similar to the query.
+fn highlight_sentences(
+    content: &str,
+    query: &str,
+    options: &HighlightOptions,
+) -> Option<ChunkHighlights> {
+    // Longer delimiters are tried first so that one which contains another still ends the sentence after its last character
+    let delimiters = options
+        .delimiters
+        .iter()
+        .filter(|delimiter| !delimiter.is_empty())
+        .map(|delimiter| delimiter.chars().collect_vec())
+        .sorted_by_key(|delimiter| std::cmp::Reverse(delimiter.len()))
+        .collect_vec();
+    let chars = content.chars().collect_vec();
+
+    let mut sentences: Vec<(usize, usize)> = vec![];
+    let mut sentence_start = 0;
+    let mut index = 0;
+    while index < chars.len() {
+        match delimiters
+            .iter()
+            .find(|delimiter| chars[index..].starts_with(delimiter))
+        {
+            Some(delimiter) => {
+                index += delimiter.len();
+                sentences.push((sentence_start, index));
+                sentence_start = index;
+            }
+            None => index += 1,
+        }
+    }
+    let content_length = chars.len();
+    if sentence_start < content_length {
+        sentences.push((sentence_start, content_length));
+    }
+
+    // Trim the surrounding whitespace off of each sentence
+    let sentences = sentences
+        .into_iter()
+        .filter_map(|(start, end)| {
+            let text = char_slice(content, start, end);
+            let leading = text.chars().take_while(|c| c.is_whitespace()).count();
+            let trimmed_length = text.trim().chars().count();
+            (trimmed_length > 0).then_some((start + leading, start + leading + trimmed_length))
+        })
+        .collect_vec();
+
+    let mut engine: SimSearch<usize> = SimSearch::new();
+    for (index, (start, end)) in sentences.iter().enumerate() {
+        engine.insert(index, &char_slice(content, *start, *end));
+    }
+
+    let amount = if sentences.len() < 5 { 2 } else { 3 };
+    let mut matched_sentences = engine.search(query);
+    matched_sentences.truncate(amount);
+    matched_sentences.sort();
+
+    if matched_sentences.is_empty() {
+        return None;
+    }
+
+    Some(ChunkHighlights {
+        spans: matched_sentences
+            .iter()
+            .map(|index| HighlightSpan {
+                start: sentences[*index].0,
+                end: sentences[*index].1,
+                kind: HighlightKind::Sentence,
+            })
+            .collect(),
+        snippets: matched_sentences
+            .iter()
+            .take(options.max_snippets)
+            .map(|index| {
+                let (start, end) = sentences[*index];
+                HighlightSnippet {
+                    start,
+                    end,
+                    text: char_slice(content, start, end),
+                }
+            })
+            .collect(),
+    })
+}
+
+/// The parsed query and options a search highlights its chunks with.
+#[derive(Debug, Clone)]
+pub struct Highlighter {
+    query: HighlightQuery,
+    options: HighlightOptions,
+}
+
+impl Highlighter {
+    /// Returns None when highlight_results is false, so that no chunk is highlighted.
+    pub fn new(
+        query: &str,
+        highlight_results: Option<bool>,
+        delimiters: Option<Vec<String>>,
+        window: Option<u32>,
+        max_snippets: Option<u32>,
+    ) -> Option<Self> {
+        highlight_results.unwrap_or(true).then(|| Highlighter {
+            query: HighlightQuery::parse(query),
+            options: HighlightOptions::new(delimiters, window, max_snippets),
+        })
+    }
+
+    pub fn highlight(&self, content: &str) -> Option<ChunkHighlights> {
+        get_highlights(content, &self.query, &self.options)
+    }
+}
+
+/// Finds where the query matches the content of a chunk. The content is left untouched; callers get offsets instead.
+pub fn get_highlights(
+    content: &str,
+    query: &HighlightQuery,
+    options: &HighlightOptions,
+) -> Option<ChunkHighlights> {
+    if query.text.trim().is_empty() || content.trim().is_empty() {
+        return None;
+    }
+
+    let tokens = tokenize(content);
+    let matches = find_matches(&tokens, &query.terms);
+    if matches.is_empty() {
+        return highlight_sentences(content, &query.text, options);
+    }
+
+    Some(ChunkHighlights {
+        spans: matches
+            .iter()
+            .map(|token_match| HighlightSpan {
+                start: tokens[token_match.first].start,
+                end: tokens[token_match.last].end,
+                kind: token_match.kind,
+            })
+            .collect(),
+        snippets: build_snippets(content, &tokens, &matches, options),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn highlight(content: &str, query: &str, window: u32) -> ChunkHighlights {
+        get_highlights(
+            content,
+            &HighlightQuery::parse(query),
+            &HighlightOptions::new(None, Some(window), None),
+        )
+        .unwrap()
+    }
+
+    fn span_texts(content: &str, highlights: &ChunkHighlights) -> Vec<String> {
+        highlights
+            .spans
+            .iter()
+            .map(|span| char_slice(content, span.start, span.end))
+            .collect()
+    }
+
+    #[test]
+    pub fn test_terms_match_inflections_and_skip_stop_words() {
+        let content = "The tax on carbon. Taxes were raised for the ports.";
+        let highlights = highlight(content, "the tax", 2);
+        assert_eq!(span_texts(content, &highlights), vec!["tax", "Taxes"]);
+        assert!(highlights
+            .spans
+            .iter()
+            .all(|span| span.kind == HighlightKind::Term));
+    }
+
+    #[test]
+    pub fn test_phrases_take_precedence_over_their_words() {
+        let content = "A carbon tax is not a tax on carbon.";
+        let highlights = highlight(content, "\"carbon tax\" carbon", 1);
+        assert_eq!(
+            span_texts(content, &highlights),
+            vec!["carbon tax", "carbon"]
+        );
+        assert_eq!(highlights.spans[0].kind, HighlightKind::Phrase);
+        assert_eq!(highlights.spans[1].kind, HighlightKind::Term);
+    }
+
+    #[test]
+    pub fn test_negated_terms_and_fields_are_not_highlighted() {
+        let content = "Dogs and cats sleep all day";
+        let highlights = highlight(content, "cats -dogs tag_set:pets", 0);
+        assert_eq!(span_texts(content, &highlights), vec!["cats"]);
+    }
+
+    #[test]
+    pub fn test_offsets_are_in_characters() {
+        let content = "Café déjà vu: the naïve tax";
+        let highlights = highlight(content, "naive naïve", 0);
+        assert_eq!(span_texts(content, &highlights), vec!["naïve"]);
+        assert_eq!(highlights.spans[0].start, 18);
+    }
+
+    #[test]
+    pub fn test_snippets_merge_nearby_matches_and_rank_by_distinct_terms() {
+        let content = "one two alpha three four five six seven eight nine alpha beta ten";
+        let highlights = get_highlights(
+            content,
+            &HighlightQuery::parse("alpha beta"),
+            &HighlightOptions::new(None, Some(1), Some(1)),
+        )
+        .unwrap();
+        assert_eq!(highlights.spans.len(), 3);
+        assert_eq!(highlights.snippets.len(), 1);
+        assert_eq!(highlights.snippets[0].text, "nine alpha beta ten");
+    }
+
+    #[test]
+    pub fn test_sentences_split_on_multi_character_delimiters() {
+        let content = "Ports closed early || Tariffs on steel rose || Rain fell";
+        let highlights = highlight_sentences(
+            content,
+            "steel tariffs",
+            &HighlightOptions::new(Some(vec!["||".to_string()]), None, None),
+        )
+        .unwrap();
+        let texts = span_texts(content, &highlights);
+
+        assert!(texts.contains(&"Tariffs on steel rose ||".to_string()));
+        assert!(texts
+            .iter()
+            .all(|text| !text.trim_end_matches("||").contains("||")));
+    }
+
+    #[test]
+    pub fn test_html_in_content_is_left_intact() {
+        let content = "<p>Rising <b>sea</b> levels</p>";
+        let highlights = highlight(content, "sea", 0);
+        assert_eq!(span_texts(content, &highlights), vec!["sea"]);
+        assert_eq!(highlights.snippets[0].text, "sea");
+    }
+}
@@ -0,0 +1,737 @@
+use crate::{data::models::RedisPool, errors::DefaultError};
+use actix_web::web;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// List producers LPUSH ingestion messages onto and workers take them from the other end of.
+pub const INGESTION_QUEUE: &str = "ingestion";
+/// List holding the messages workers are processing. A message stays here until it is acked, retried, or dead-lettered.
+const INGESTION_PROCESSING_QUEUE: &str = "ingestion_processing";
+/// Sorted set of the messages in the processing list scored by the unix time their visibility timeout expires at.
+const INGESTION_LEASES: &str = "ingestion_leases";
+/// Sorted set of failed messages scored by the unix time they are due to be retried at.
+const INGESTION_RETRIES: &str = "ingestion_retries";
+/// Hash of how many attempts have failed for each message which has not succeeded yet.
+const INGESTION_ATTEMPTS: &str = "ingestion_attempts";
+
+lazy_static! {
+    /// Takes a message off the processing list and either schedules its retry or moves it to a dead-letter list in one step, so that a crash can never lose it in between. Messages which are no longer being processed are left alone if only_if_processing is set.
+    static ref FAIL_INGESTION_MESSAGE_SCRIPT: redis::Script = redis::Script::new(
+        r"
+        local removed = redis.call('LREM', KEYS[1], 1, ARGV[1])
+        redis.call('ZREM', KEYS[2], ARGV[1])
+        if removed == 0 and ARGV[2] == '1' then
+            return {0, 0}
+        end
+
+        local attempt_count = redis.call('HINCRBY', KEYS[3], ARGV[1], 1)
+        if ARGV[3] == '1' and attempt_count < tonumber(ARGV[9]) then
+            redis.call('ZADD', KEYS[4], tonumber(ARGV[4]) + tonumber(ARGV[9 + attempt_count]), ARGV[1])
+            return {1, attempt_count}
+        end
+
+        local dataset_id = cjson.null
+        if ARGV[8] ~= '' then
+            dataset_id = ARGV[8]
+        end
+        redis.call('LPUSH', KEYS[5], cjson.encode({
+            id = ARGV[5],
+            dataset_id = dataset_id,
+            payload = ARGV[1],
+            error = ARGV[7],
+            attempt_count = attempt_count,
+            dead_lettered_at = ARGV[6],
+        }))
+        redis.call('HDEL', KEYS[3], ARGV[1])
+        return {2, attempt_count}
+        ",
+    );
+    /// Moves the retries which are due onto the queue in one step.
+    static ref QUEUE_DUE_INGESTION_RETRIES_SCRIPT: redis::Script = redis::Script::new(
+        r"
+        local due_retries = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1], 'LIMIT', 0, 1000)
+        for _, payload in ipairs(due_retries) do
+            redis.call('ZREM', KEYS[1], payload)
+            redis.call('RPUSH', KEYS[2], payload)
+        end
+        return #due_retries
+        ",
+    );
+    /// Moves a dead-lettered message back onto the queue in one step. Only the request which removes the message queues it.
+    static ref REQUEUE_DEAD_LETTERED_INGESTION_MESSAGE_SCRIPT: redis::Script = redis::Script::new(
+        r"
+        if redis.call('LREM', KEYS[1], 1, ARGV[1]) == 1 then
+            redis.call('RPUSH', KEYS[2], ARGV[2])
+            return 1
+        end
+        return 0
+        ",
+    );
+}
+
+/// Redis list the messages of the dataset which exhausted their attempts are moved to. Messages which could not be parsed, and so have no dataset, go to a shared list which is only inspected through the logs.
+pub fn dataset_dead_letter_queue(dataset_id: Option<uuid::Uuid>) -> String {
+    match dataset_id {
+        Some(dataset_id) => format!("ingestion_dead_letter:{}", dataset_id),
+        None => "ingestion_dead_letter".to_string(),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[schema(example = json!({
+    "id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "dataset_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "payload": "{\"chunk_metadata\":{...},\"dataset_id\":\"e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3\"}",
+    "error": "Failed to create embedding: connection refused",
+    "attempt_count": 5,
+    "dead_lettered_at": "2021-01-01T00:00:00",
+}))]
+pub struct DeadLetteredIngestionMessage {
+    pub id: uuid::Uuid,
+    pub dataset_id: Option<uuid::Uuid>,
+    /// The ingestion message exactly as it was queued. Requeuing it queues this payload again.
+    pub payload: String,
+    /// The error of the last attempt.
+    pub error: String,
+    pub attempt_count: i32,
+    pub dead_lettered_at: chrono::NaiveDateTime,
+}
+
+/// What happened to a message whose attempt failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IngestionFailure {
+    Retrying {
+        attempt_count: i32,
+        delay: std::time::Duration,
+    },
+    DeadLettered {
+        attempt_count: i32,
+    },
+}
+
+pub fn get_ingestion_max_attempts() -> i32 {
+    std::env::var("INGESTION_MAX_ATTEMPTS")
+        .ok()
+        .and_then(|max_attempts| max_attempts.parse().ok())
+        .unwrap_or(5)
+}
+
+/// How long a worker has to process a message before it is considered lost and handed to another worker.
+fn get_ingestion_visibility_timeout() -> i64 {
+    std::env::var("INGESTION_VISIBILITY_TIMEOUT_SECS")
+        .ok()
+        .and_then(|timeout| timeout.parse().ok())
+        .unwrap_or(300)
+}
+
+/// Seconds to wait before retrying a message after its nth failed attempt. Doubles with every attempt up to a minute.
+pub fn get_ingestion_retry_delay(attempt_count: i32) -> std::time::Duration {
+    let exponent = attempt_count.saturating_sub(1).clamp(0, 16) as u32;
+
+    std::time::Duration::from_secs(2u64.pow(exponent).min(60))
+}
+
+/// The dataset_id field which both upload and update messages carry, if the payload has one.
+pub fn get_ingestion_message_dataset_id(payload: &str) -> Option<uuid::Uuid> {
+    serde_json::from_str::<serde_json::Value>(payload)
+        .ok()?
+        .get("dataset_id")?
+        .as_str()?
+        .parse()
+        .ok()
+}
+
+/// Id of the chunk the upload or update message is for, if the payload has one.
+pub fn get_ingestion_message_chunk_id(payload: &str) -> Option<uuid::Uuid> {
+    serde_json::from_str::<serde_json::Value>(payload)
+        .ok()?
+        .get("chunk_metadata")?
+        .get("id")?
+        .as_str()?
+        .parse()
+        .ok()
+}
+
+/// How many messages a worker claims at once so that their embeddings can be created and their points upserted in bulk.
+pub fn get_ingestion_batch_size() -> usize {
+    std::env::var("INGESTION_BATCH_SIZE")
+        .ok()
+        .and_then(|batch_size| batch_size.parse().ok())
+        .filter(|batch_size| *batch_size > 0)
+        .unwrap_or(50)
+}
+
+fn now_timestamp() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Pushes the messages onto the queue. Returns the length of the queue after the push.
+pub async fn queue_ingestion_messages(
+    payloads: &[String],
+    redis_pool: web::Data<RedisPool>,
+) -> Result<i32, DefaultError> {
+    let mut redis_conn = redis_pool.get().await.map_err(|_| DefaultError {
+        message: "Failed to get redis connection",
+    })?;
+
+    redis::cmd("LPUSH")
+        .arg(INGESTION_QUEUE)
+        .arg(payloads)
+        .query_async(&mut *redis_conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to queue ingestion messages {:?}", err);
+            DefaultError {
+                message: "Failed to queue ingestion messages",
+            }
+        })
+}
+
+/// Moves the next message onto the processing list and leases it to the caller for the visibility timeout. Waits up to a second for a message. The connection is blocked while waiting so it should not be shared.
+pub async fn claim_ingestion_message<C: redis::aio::ConnectionLike>(
+    redis_connection: &mut C,
+) -> Result<Option<String>, redis::RedisError> {
+    let payload: Option<String> = redis::cmd("BLMOVE")
+        .arg(INGESTION_QUEUE)
+        .arg(INGESTION_PROCESSING_QUEUE)
+        .arg("RIGHT")
+        .arg("LEFT")
+        .arg(1.0)
+        .query_async(redis_connection)
+        .await?;
+
+    if let Some(payload) = &payload {
+        redis::cmd("ZADD")
+            .arg(INGESTION_LEASES)
+            .arg(now_timestamp() + get_ingestion_visibility_timeout())
+            .arg(payload)
+            .query_async::<_, i64>(redis_connection)
+            .await?;
+    }
+
+    Ok(payload)
+}
+
+/// Claims up to batch_size messages. Waits up to a second for the first message and then takes whichever others are already queued without waiting for more.
+pub async fn claim_ingestion_messages<C: redis::aio::ConnectionLike>(
+    redis_connection: &mut C,
+    batch_size: usize,
+) -> Result<Vec<String>, redis::RedisError> {
+    let mut payloads = match claim_ingestion_message(redis_connection).await? {
+        Some(payload) => vec![payload],
+        None => return Ok(vec![]),
+    };
+
+    while payloads.len() < batch_size {
+        let payload: Option<String> = redis::cmd("LMOVE")
+            .arg(INGESTION_QUEUE)
+            .arg(INGESTION_PROCESSING_QUEUE)
+            .arg("RIGHT")
+            .arg("LEFT")
+            .query_async(redis_connection)
+            .await?;
+
+        match payload {
+            Some(payload) => {
+                redis::cmd("ZADD")
+                    .arg(INGESTION_LEASES)
+                    .arg(now_timestamp() + get_ingestion_visibility_timeout())
+                    .arg(&payload)
+                    .query_async::<_, i64>(redis_connection)
+                    .await?;
+                payloads.push(payload);
+            }
+            None => break,
+        }
+    }
+
+    Ok(payloads)
+}
+
+/// How often a worker extends the leases of the messages it is processing, so that a batch which takes longer than the visibility timeout is not handed to another worker while it is still being processed.
+pub fn get_ingestion_lease_renewal_interval() -> std::time::Duration {
+    std::time::Duration::from_secs((get_ingestion_visibility_timeout() / 3).max(1) as u64)
+}
+
+/// Extends the leases of messages which are still being processed by another visibility timeout. Messages which were acked, retried, or dead-lettered in the meantime are not leased again.
+pub async fn extend_ingestion_leases(
+    payloads: &[String],
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), DefaultError> {
+    let mut redis_conn = redis_pool.get().await.map_err(|_| DefaultError {
+        message: "Failed to get redis connection",
+    })?;
+
+    let lease_expires_at = now_timestamp() + get_ingestion_visibility_timeout();
+    let mut pipe = redis::pipe();
+    for payload in payloads {
+        pipe.cmd("ZADD")
+            .arg(INGESTION_LEASES)
+            .arg("XX")
+            .arg(lease_expires_at)
+            .arg(payload)
+            .ignore();
+    }
+
+    pipe.query_async::<_, ()>(&mut *redis_conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to extend ingestion leases {:?}", err);
+            DefaultError {
+                message: "Failed to extend ingestion leases",
+            }
+        })?;
+
+    Ok(())
+}
+
+/// Removes a message which was processed from the processing list.
+pub async fn ack_ingestion_message(
+    payload: &str,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<(), DefaultError> {
+    let mut redis_conn = redis_pool.get().await.map_err(|_| DefaultError {
+        message: "Failed to get redis connection",
+    })?;
+
+    redis::pipe()
+        .atomic()
+        .cmd("LREM")
+        .arg(INGESTION_PROCESSING_QUEUE)
+        .arg(1)
+        .arg(payload)
+        .ignore()
+        .cmd("ZREM")
+        .arg(INGESTION_LEASES)
+        .arg(payload)
+        .ignore()
+        .cmd("HDEL")
+        .arg(INGESTION_ATTEMPTS)
+        .arg(payload)
+        .ignore()
+        .query_async::<_, ()>(&mut *redis_conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to ack ingestion message {:?}", err);
+            DefaultError {
+                message: "Failed to ack ingestion message",
+            }
+        })?;
+
+    Ok(())
+}
+
+/// Records a failed attempt of a message. Retryable failures are retried after a backoff until INGESTION_MAX_ATTEMPTS attempts have failed, after which the message, like one with a permanent failure, is moved to the dead-letter list of its dataset.
+pub async fn fail_ingestion_message(
+    payload: &str,
+    error: String,
+    retryable: bool,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<IngestionFailure, DefaultError> {
+    let mut redis_conn = redis_pool.get().await.map_err(|_| DefaultError {
+        message: "Failed to get redis connection",
+    })?;
+
+    fail_processing_ingestion_message(payload, error, retryable, false, &mut *redis_conn)
+        .await?
+        .ok_or(DefaultError {
+            message: "Failed to record failed ingestion attempt",
+        })
+}
+
+/// Fails the message in one atomic step. Returns None if only_if_processing is set and the message is no longer on the processing list, which happens when it was acked or failed by its worker in the meantime.
+async fn fail_processing_ingestion_message(
+    payload: &str,
+    error: String,
+    retryable: bool,
+    only_if_processing: bool,
+    redis_conn: &mut impl redis::aio::ConnectionLike,
+) -> Result<Option<IngestionFailure>, DefaultError> {
+    let dataset_id = get_ingestion_message_dataset_id(payload);
+    let max_attempts = get_ingestion_max_attempts();
+
+    let mut invocation = FAIL_INGESTION_MESSAGE_SCRIPT.prepare_invoke();
+    invocation
+        .key(INGESTION_PROCESSING_QUEUE)
+        .key(INGESTION_LEASES)
+        .key(INGESTION_ATTEMPTS)
+        .key(INGESTION_RETRIES)
+        .key(dataset_dead_letter_queue(dataset_id))
+        .arg(payload)
+        .arg(if only_if_processing { "1" } else { "0" })
+        .arg(if retryable { "1" } else { "0" })
+        .arg(now_timestamp())
+        .arg(uuid::Uuid::new_v4().to_string())
+        .arg(
+            chrono::Utc::now()
+                .naive_local()
+                .format("%Y-%m-%dT%H:%M:%S%.f")
+                .to_string(),
+        )
+        .arg(error)
+        .arg(
+            dataset_id
+                .map(|dataset_id| dataset_id.to_string())
+                .unwrap_or_default(),
+        )
+        .arg(max_attempts);
+    // The script looks up the delay of the attempt which failed, so the backoff stays defined in one place
+    for attempt_count in 1..max_attempts.max(1) {
+        invocation.arg(get_ingestion_retry_delay(attempt_count).as_secs());
+    }
+
+    let (outcome, attempt_count): (i32, i32) =
+        invocation.invoke_async(redis_conn).await.map_err(|err| {
+            log::error!("Failed to record failed ingestion attempt {:?}", err);
+            DefaultError {
+                message: "Failed to record failed ingestion attempt",
+            }
+        })?;
+
+    Ok(match outcome {
+        0 => None,
+        1 => Some(IngestionFailure::Retrying {
+            attempt_count,
+            delay: get_ingestion_retry_delay(attempt_count),
+        }),
+        _ => Some(IngestionFailure::DeadLettered { attempt_count }),
+    })
+}
+
+/// Error recorded for a message whose visibility timeout expired.
+pub const STALLED_INGESTION_MESSAGE_ERROR: &str =
+    "Visibility timeout expired before the message was processed";
+
+/// Queues the retries which are due and fails the messages whose visibility timeout expired, which happens when the worker processing them crashed. Safe to run from every worker process at once. Returns the payloads of the failed messages along with what happened to them.
+pub async fn requeue_stalled_ingestion_messages(
+    redis_pool: web::Data<RedisPool>,
+) -> Result<Vec<(String, IngestionFailure)>, DefaultError> {
+    let mut redis_conn = redis_pool.get().await.map_err(|_| DefaultError {
+        message: "Failed to get redis connection",
+    })?;
+    let now = now_timestamp();
+
+    QUEUE_DUE_INGESTION_RETRIES_SCRIPT
+        .key(INGESTION_RETRIES)
+        .key(INGESTION_QUEUE)
+        .arg(now)
+        .invoke_async::<_, i64>(&mut *redis_conn)
+        .await
+        .map_err(|_| DefaultError {
+            message: "Failed to queue due ingestion retries",
+        })?;
+
+    // A worker which crashed between taking a message and leasing it leaves the message without a lease, so one is started for it here
+    let processing: Vec<String> = redis::cmd("LRANGE")
+        .arg(INGESTION_PROCESSING_QUEUE)
+        .arg(0)
+        .arg(-1)
+        .query_async(&mut *redis_conn)
+        .await
+        .map_err(|_| DefaultError {
+            message: "Failed to get ingestion messages being processed",
+        })?;
+
+    for payload in processing {
+        redis::cmd("ZADD")
+            .arg(INGESTION_LEASES)
+            .arg("NX")
+            .arg(now + get_ingestion_visibility_timeout())
+            .arg(&payload)
+            .query_async::<_, i64>(&mut *redis_conn)
+            .await
+            .map_err(|_| DefaultError {
+                message: "Failed to lease ingestion message",
+            })?;
+    }
+
+    let expired_leases: Vec<String> = redis::cmd("ZRANGEBYSCORE")
+        .arg(INGESTION_LEASES)
+        .arg("-inf")
+        .arg(now)
+        .query_async(&mut *redis_conn)
+        .await
+        .map_err(|_| DefaultError {
+            message: "Failed to get expired ingestion leases",
+        })?;
+
+    let mut stalled_messages = vec![];
+    for payload in expired_leases {
+        // A message which was acked or failed after its lease expired is no longer being processed and is left alone
+        let failure = fail_processing_ingestion_message(
+            &payload,
+            STALLED_INGESTION_MESSAGE_ERROR.to_string(),
+            true,
+            true,
+            &mut *redis_conn,
+        )
+        .await?;
+
+        if let Some(failure) = failure {
+            stalled_messages.push((payload, failure));
+        }
+    }
+
+    Ok(stalled_messages)
+}
+
+pub async fn get_dead_lettered_ingestion_messages_query(
+    dataset_id: uuid::Uuid,
+    page: i64,
+    page_size: i64,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<Vec<DeadLetteredIngestionMessage>, DefaultError> {
+    let mut redis_conn = redis_pool.get().await.map_err(|_| DefaultError {
+        message: "Failed to get redis connection",
+    })?;
+
+    let dead_lettered_messages: Vec<String> = redis::cmd("LRANGE")
+        .arg(dataset_dead_letter_queue(Some(dataset_id)))
+        .arg((page - 1) * page_size)
+        .arg(page * page_size - 1)
+        .query_async(&mut *redis_conn)
+        .await
+        .map_err(|_| DefaultError {
+            message: "Failed to get dead-lettered ingestion messages",
+        })?;
+
+    Ok(dead_lettered_messages
+        .iter()
+        .filter_map(|message| serde_json::from_str(message).ok())
+        .collect())
+}
+
+/// Removes the dead-lettered messages of the dataset with the given ids, or every one if no ids are given, and returns the removed messages. Requeued messages are moved back onto the queue in the same step as they are removed.
+async fn remove_dead_lettered_ingestion_messages(
+    dataset_id: uuid::Uuid,
+    message_ids: Option<Vec<uuid::Uuid>>,
+    requeue: bool,
+    redis_conn: &mut impl redis::aio::ConnectionLike,
+) -> Result<Vec<DeadLetteredIngestionMessage>, DefaultError> {
+    let dead_letter_queue = dataset_dead_letter_queue(Some(dataset_id));
+
+    let dead_lettered_messages: Vec<String> = redis::cmd("LRANGE")
+        .arg(&dead_letter_queue)
+        .arg(0)
+        .arg(-1)
+        .query_async(redis_conn)
+        .await
+        .map_err(|_| DefaultError {
+            message: "Failed to get dead-lettered ingestion messages",
+        })?;
+
+    let mut removed_messages = vec![];
+    for raw_message in dead_lettered_messages {
+        let message: DeadLetteredIngestionMessage = match serde_json::from_str(&raw_message) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
+        if message_ids
+            .as_ref()
+            .is_some_and(|message_ids| !message_ids.contains(&message.id))
+        {
+            continue;
+        }
+
+        // Only the request which removes the message gets to act on it
+        let removed: i64 = if requeue {
+            REQUEUE_DEAD_LETTERED_INGESTION_MESSAGE_SCRIPT
+                .key(&dead_letter_queue)
+                .key(INGESTION_QUEUE)
+                .arg(&raw_message)
+                .arg(&message.payload)
+                .invoke_async(redis_conn)
+                .await
+        } else {
+            redis::cmd("LREM")
+                .arg(&dead_letter_queue)
+                .arg(1)
+                .arg(&raw_message)
+                .query_async(redis_conn)
+                .await
+        }
+        .map_err(|err| {
+            log::error!("Failed to remove dead-lettered ingestion message {:?}", err);
+            DefaultError {
+                message: "Failed to remove dead-lettered ingestion message",
+            }
+        })?;
+
+        if removed == 1 {
+            removed_messages.push(message);
+        }
+    }
+
+    Ok(removed_messages)
+}
+
+/// Queues the dead-lettered messages of the dataset again with a fresh set of attempts. Returns the requeued messages.
+pub async fn requeue_dead_lettered_ingestion_messages_query(
+    dataset_id: uuid::Uuid,
+    message_ids: Option<Vec<uuid::Uuid>>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<Vec<DeadLetteredIngestionMessage>, DefaultError> {
+    let mut redis_conn = redis_pool.get().await.map_err(|_| DefaultError {
+        message: "Failed to get redis connection",
+    })?;
+
+    let requeued_messages =
+        remove_dead_lettered_ingestion_messages(dataset_id, message_ids, true, &mut *redis_conn)
+            .await?;
+
+    Ok(requeued_messages)
+}
+
+/// Deletes the dead-lettered messages of the dataset. Returns how many were deleted.
+pub async fn purge_dead_lettered_ingestion_messages_query(
+    dataset_id: uuid::Uuid,
+    message_ids: Option<Vec<uuid::Uuid>>,
+    redis_pool: web::Data<RedisPool>,
+) -> Result<usize, DefaultError> {
+    let mut redis_conn = redis_pool.get().await.map_err(|_| DefaultError {
+        message: "Failed to get redis connection",
+    })?;
+
+    let purged_messages =
+        remove_dead_lettered_ingestion_messages(dataset_id, message_ids, false, &mut *redis_conn)
+            .await?;
+
+    Ok(purged_messages.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Serves the queue commands claim_ingestion_messages sends from memory.
+    struct FakeQueueConnection {
+        queued: VecDeque<String>,
+        leased: Vec<String>,
+    }
+
+    impl redis::aio::ConnectionLike for FakeQueueConnection {
+        fn req_packed_command<'a>(
+            &'a mut self,
+            cmd: &'a redis::Cmd,
+        ) -> redis::RedisFuture<'a, redis::Value> {
+            let args: Vec<Vec<u8>> = cmd
+                .args_iter()
+                .filter_map(|arg| match arg {
+                    redis::Arg::Simple(arg) => Some(arg.to_vec()),
+                    redis::Arg::Cursor => None,
+                })
+                .collect();
+
+            let value = match args[0].as_slice() {
+                b"BLMOVE" | b"LMOVE" => match self.queued.pop_back() {
+                    Some(payload) => redis::Value::Data(payload.into_bytes()),
+                    None => redis::Value::Nil,
+                },
+                b"ZADD" => {
+                    let payload = args.last().expect("ZADD must have a member");
+                    self.leased
+                        .push(String::from_utf8(payload.clone()).expect("Payload must be utf8"));
+                    redis::Value::Int(1)
+                }
+                _ => redis::Value::Okay,
+            };
+
+            Box::pin(async move { Ok(value) })
+        }
+
+        fn req_packed_commands<'a>(
+            &'a mut self,
+            _cmd: &'a redis::Pipeline,
+            _offset: usize,
+            _count: usize,
+        ) -> redis::RedisFuture<'a, Vec<redis::Value>> {
+            Box::pin(async move { Ok(vec![]) })
+        }
+
+        fn get_db(&self) -> i64 {
+            0
+        }
+    }
+
+    fn fake_queue(payloads: &[&str]) -> FakeQueueConnection {
+        FakeQueueConnection {
+            // Producers LPUSH, so the oldest message is at the back
+            queued: payloads
+                .iter()
+                .rev()
+                .map(|payload| payload.to_string())
+                .collect(),
+            leased: vec![],
+        }
+    }
+
+    #[test]
+    fn test_claim_takes_at_most_the_batch_size_in_queue_order() {
+        let mut connection = fake_queue(&["a", "b", "c"]);
+
+        let payloads =
+            futures::executor::block_on(claim_ingestion_messages(&mut connection, 2)).unwrap();
+
+        assert_eq!(payloads, vec!["a", "b"]);
+        assert_eq!(connection.queued, VecDeque::from(vec!["c".to_string()]));
+    }
+
+    #[test]
+    fn test_claim_takes_what_is_queued_when_it_is_less_than_the_batch_size() {
+        let mut connection = fake_queue(&["a", "b"]);
+
+        let payloads =
+            futures::executor::block_on(claim_ingestion_messages(&mut connection, 5)).unwrap();
+
+        assert_eq!(payloads, vec!["a", "b"]);
+        assert!(connection.queued.is_empty());
+    }
+
+    #[test]
+    fn test_claim_of_an_empty_queue_is_empty() {
+        let mut connection = fake_queue(&[]);
+
+        let payloads =
+            futures::executor::block_on(claim_ingestion_messages(&mut connection, 5)).unwrap();
+
+        assert!(payloads.is_empty());
+        assert!(connection.leased.is_empty());
+    }
+
+    #[test]
+    fn test_every_claimed_message_is_leased() {
+        let mut connection = fake_queue(&["a", "b", "c"]);
+
+        let payloads =
+            futures::executor::block_on(claim_ingestion_messages(&mut connection, 3)).unwrap();
+
+        assert_eq!(connection.leased, payloads);
+    }
+
+    #[test]
+    fn test_ingestion_retry_delay_doubles_and_is_capped() {
+        assert_eq!(get_ingestion_retry_delay(1).as_secs(), 1);
+        assert_eq!(get_ingestion_retry_delay(3).as_secs(), 4);
+        assert_eq!(get_ingestion_retry_delay(20).as_secs(), 60);
+    }
+
+    #[test]
+    fn test_ingestion_message_dataset_id_is_read_from_payload() {
+        let dataset_id = uuid::Uuid::new_v4();
+
+        assert_eq!(
+            get_ingestion_message_dataset_id(&format!(
+                "{{\"chunk_metadata\":{{}},\"dataset_id\":\"{}\"}}",
+                dataset_id
+            )),
+            Some(dataset_id)
+        );
+        assert_eq!(get_ingestion_message_dataset_id("not json"), None);
+        assert_eq!(
+            dataset_dead_letter_queue(Some(dataset_id)),
+            format!("ingestion_dead_letter:{}", dataset_id)
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{
+    data::models::{ChunkIngestionStatus, IngestionStatus, Pool},
+    errors::DefaultError,
+};
+use actix_web::web;
+use diesel::{prelude::*, upsert::excluded};
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Records the chunks as queued. Chunks which were queued before, such as ones being updated, start over in their new batch.
+#[tracing::instrument(skip(pool))]
+pub async fn create_chunk_ingestion_statuses_query(
+    chunk_ingestion_statuses: Vec<ChunkIngestionStatus>,
+    pool: web::Data<Pool>,
+) -> Result<(), DefaultError> {
+    use crate::data::schema::chunk_ingestion_statuses::dsl as chunk_ingestion_statuses_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    diesel::insert_into(chunk_ingestion_statuses_columns::chunk_ingestion_statuses)
+        .values(&chunk_ingestion_statuses)
+        .on_conflict(chunk_ingestion_statuses_columns::chunk_id)
+        .do_update()
+        .set((
+            chunk_ingestion_statuses_columns::batch_id
+                .eq(excluded(chunk_ingestion_statuses_columns::batch_id)),
+            chunk_ingestion_statuses_columns::tracking_id
+                .eq(excluded(chunk_ingestion_statuses_columns::tracking_id)),
+            chunk_ingestion_statuses_columns::status
+                .eq(excluded(chunk_ingestion_statuses_columns::status)),
+            chunk_ingestion_statuses_columns::error.eq(None::<String>),
+            chunk_ingestion_statuses_columns::collision_chunk_id.eq(None::<uuid::Uuid>),
+            chunk_ingestion_statuses_columns::updated_at
+                .eq(excluded(chunk_ingestion_statuses_columns::updated_at)),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to create chunk ingestion statuses {:?}", err);
+            DefaultError {
+                message: "Failed to create chunk ingestion statuses",
+            }
+        })?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn update_chunk_ingestion_statuses_query(
+    chunk_ids: Vec<uuid::Uuid>,
+    status: IngestionStatus,
+    error: Option<String>,
+    collision_chunk_id: Option<uuid::Uuid>,
+    pool: web::Data<Pool>,
+) -> Result<(), DefaultError> {
+    use crate::data::schema::chunk_ingestion_statuses::dsl as chunk_ingestion_statuses_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    diesel::update(
+        chunk_ingestion_statuses_columns::chunk_ingestion_statuses
+            .filter(chunk_ingestion_statuses_columns::chunk_id.eq_any(chunk_ids)),
+    )
+    .set((
+        chunk_ingestion_statuses_columns::status.eq(status.as_str()),
+        chunk_ingestion_statuses_columns::error.eq(error),
+        chunk_ingestion_statuses_columns::collision_chunk_id.eq(collision_chunk_id),
+        chunk_ingestion_statuses_columns::updated_at.eq(chrono::Utc::now().naive_local()),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to update chunk ingestion statuses {:?}", err);
+        DefaultError {
+            message: "Failed to update chunk ingestion statuses",
+        }
+    })?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_chunk_ingestion_status_query(
+    chunk_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<ChunkIngestionStatus, DefaultError> {
+    use crate::data::schema::chunk_ingestion_statuses::dsl as chunk_ingestion_statuses_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    chunk_ingestion_statuses_columns::chunk_ingestion_statuses
+        .filter(chunk_ingestion_statuses_columns::chunk_id.eq(chunk_id))
+        .filter(chunk_ingestion_statuses_columns::dataset_id.eq(dataset_id))
+        .select(ChunkIngestionStatus::as_select())
+        .first::<ChunkIngestionStatus>(&mut conn)
+        .await
+        .map_err(|_| DefaultError {
+            message: "Chunk ingestion status not found",
+        })
+}
+
+/// The status of the chunk with the tracking_id which was queued most recently.
+#[tracing::instrument(skip(pool))]
+pub async fn get_chunk_ingestion_status_by_tracking_id_query(
+    tracking_id: String,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<ChunkIngestionStatus, DefaultError> {
+    use crate::data::schema::chunk_ingestion_statuses::dsl as chunk_ingestion_statuses_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    chunk_ingestion_statuses_columns::chunk_ingestion_statuses
+        .filter(chunk_ingestion_statuses_columns::tracking_id.eq(tracking_id))
+        .filter(chunk_ingestion_statuses_columns::dataset_id.eq(dataset_id))
+        .order(chunk_ingestion_statuses_columns::updated_at.desc())
+        .select(ChunkIngestionStatus::as_select())
+        .first::<ChunkIngestionStatus>(&mut conn)
+        .await
+        .map_err(|_| DefaultError {
+            message: "Chunk ingestion status not found",
+        })
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+#[schema(example = json!({
+    "batch_id": "e3e3e3e3-e3e3-e3e3-e3e3-e3e3e3e3e3e3",
+    "total": 3,
+    "queued": 0,
+    "processing": 0,
+    "indexed": 2,
+    "collided": 1,
+    "failed": 0,
+    "finished": true,
+    "chunks": [],
+}))]
+pub struct BatchIngestionStatus {
+    pub batch_id: uuid::Uuid,
+    pub total: usize,
+    pub queued: usize,
+    pub processing: usize,
+    pub indexed: usize,
+    pub collided: usize,
+    pub failed: usize,
+    /// True once no chunk of the batch is queued or processing, at which point every chunk which did not fail is searchable.
+    pub finished: bool,
+    pub chunks: Vec<ChunkIngestionStatus>,
+}
+
+impl BatchIngestionStatus {
+    pub fn from_chunks(batch_id: uuid::Uuid, chunks: Vec<ChunkIngestionStatus>) -> Self {
+        let mut batch_status = BatchIngestionStatus {
+            batch_id,
+            total: chunks.len(),
+            ..Default::default()
+        };
+
+        for chunk in chunks.iter() {
+            match IngestionStatus::from(chunk.status.clone()) {
+                IngestionStatus::Queued => batch_status.queued += 1,
+                IngestionStatus::Processing => batch_status.processing += 1,
+                IngestionStatus::Indexed => batch_status.indexed += 1,
+                IngestionStatus::Collided => batch_status.collided += 1,
+                IngestionStatus::Failed => batch_status.failed += 1,
+            }
+        }
+
+        batch_status.finished = batch_status.queued == 0 && batch_status.processing == 0;
+        batch_status.chunks = chunks;
+
+        batch_status
+    }
+}
+
+/// The statuses of the chunks which were last queued in the batch. Chunks which were queued again in a later batch belong to that batch instead.
+#[tracing::instrument(skip(pool))]
+pub async fn get_batch_ingestion_status_query(
+    batch_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<BatchIngestionStatus, DefaultError> {
+    use crate::data::schema::chunk_ingestion_statuses::dsl as chunk_ingestion_statuses_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    let chunks = chunk_ingestion_statuses_columns::chunk_ingestion_statuses
+        .filter(chunk_ingestion_statuses_columns::batch_id.eq(batch_id))
+        .filter(chunk_ingestion_statuses_columns::dataset_id.eq(dataset_id))
+        .order(chunk_ingestion_statuses_columns::created_at.asc())
+        .select(ChunkIngestionStatus::as_select())
+        .load::<ChunkIngestionStatus>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to load batch ingestion status {:?}", err);
+            DefaultError {
+                message: "Failed to get batch ingestion status",
+            }
+        })?;
+
+    if chunks.is_empty() {
+        return Err(DefaultError {
+            message: "Batch not found",
+        });
+    }
+
+    Ok(BatchIngestionStatus::from_chunks(batch_id, chunks))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_batch_is_finished_once_no_chunk_is_queued_or_processing() {
+        let dataset_id = uuid::Uuid::new_v4();
+        let batch_id = uuid::Uuid::new_v4();
+        let chunk_with_status = |status: IngestionStatus| {
+            let mut chunk = ChunkIngestionStatus::from_details(
+                uuid::Uuid::new_v4(),
+                dataset_id,
+                batch_id,
+                None,
+            );
+            chunk.status = status.as_str().to_string();
+            chunk
+        };
+
+        let unfinished = BatchIngestionStatus::from_chunks(
+            batch_id,
+            vec![
+                chunk_with_status(IngestionStatus::Indexed),
+                chunk_with_status(IngestionStatus::Processing),
+            ],
+        );
+        assert_eq!(unfinished.total, 2);
+        assert_eq!(unfinished.processing, 1);
+        assert!(!unfinished.finished);
+
+        let finished = BatchIngestionStatus::from_chunks(
+            batch_id,
+            vec![
+                chunk_with_status(IngestionStatus::Indexed),
+                chunk_with_status(IngestionStatus::Collided),
+                chunk_with_status(IngestionStatus::Failed),
+            ],
+        );
+        assert_eq!(
+            (finished.indexed, finished.collided, finished.failed),
+            (1, 1, 1)
+        );
+        assert!(finished.finished);
+    }
+}
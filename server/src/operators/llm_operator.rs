@@ -0,0 +1,263 @@
+use crate::{
+    data::models::ServerDatasetConfiguration, errors::ServiceError, get_env,
+    handlers::chunk_handler::RewriteQueryMode,
+};
+use itertools::Itertools;
+use openai_dive::v1::{
+    api::Client,
+    resources::chat::{ChatCompletionParameters, ChatMessage, ChatMessageContent, Role},
+};
+use std::future::Future;
+
+/// Maximum number of alternative queries kept from an expansion.
+const MAX_QUERY_EXPANSIONS: usize = 3;
+
+/// Something which completes a single prompt. Search only depends on this so tests can swap in a canned provider.
+pub trait LlmProvider {
+    fn complete(&self, prompt: String)
+        -> impl Future<Output = Result<String, ServiceError>> + Send;
+}
+
+/// Completes prompts with the LLM_BASE_URL and LLM_DEFAULT_MODEL of a dataset.
+pub struct DatasetLlmProvider {
+    client: Client,
+    model: String,
+}
+
+impl DatasetLlmProvider {
+    pub fn from_dataset_config(dataset_config: &ServerDatasetConfiguration) -> Self {
+        let base_url = if dataset_config.LLM_BASE_URL.is_empty() {
+            "https://openrouter.ai/api/v1".to_string()
+        } else {
+            dataset_config.LLM_BASE_URL.clone()
+        };
+
+        let llm_api_key = if base_url.contains("openai.com") {
+            get_env!("OPENAI_API_KEY", "OPENAI_API_KEY for openai should be set").into()
+        } else {
+            get_env!(
+                "LLM_API_KEY",
+                "LLM_API_KEY for openrouter or self-hosted should be set"
+            )
+            .into()
+        };
+
+        DatasetLlmProvider {
+            client: Client {
+                api_key: llm_api_key,
+                http_client: Some(reqwest::Client::new()),
+                base_url,
+                organization: None,
+            },
+            model: dataset_config.LLM_DEFAULT_MODEL.clone(),
+        }
+    }
+}
+
+impl LlmProvider for DatasetLlmProvider {
+    async fn complete(&self, prompt: String) -> Result<String, ServiceError> {
+        let parameters = ChatCompletionParameters {
+            model: self.model.clone(),
+            messages: vec![ChatMessage {
+                role: Role::User,
+                content: ChatMessageContent::Text(prompt),
+                tool_calls: None,
+                name: None,
+                tool_call_id: None,
+            }],
+            stream: Some(false),
+            temperature: None,
+            top_p: None,
+            n: None,
+            stop: None,
+            max_tokens: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            logit_bias: None,
+            user: None,
+            response_format: None,
+            tools: None,
+            tool_choice: None,
+            logprobs: None,
+            top_logprobs: None,
+            seed: None,
+            instance_id: None,
+        };
+
+        let completion = self.client.chat().create(parameters).await.map_err(|err| {
+            ServiceError::BadRequest(format!("Failed to get a completion from the LLM: {}", err))
+        })?;
+
+        match completion
+            .choices
+            .first()
+            .map(|choice| &choice.message.content)
+        {
+            Some(ChatMessageContent::Text(text)) => Ok(text.clone()),
+            _ => Err(ServiceError::BadRequest(
+                "The LLM did not return a text completion".to_string(),
+            )),
+        }
+    }
+}
+
+/// Hypothetical answers reuse the RAG_PROMPT of the dataset, which is what RAG uses to find evidence before answering.
+fn get_rewrite_prompt(query: &str, mode: RewriteQueryMode, rag_prompt: &str) -> String {
+    match mode {
+        RewriteQueryMode::Hyde => format!("{}{}", rag_prompt, query),
+        RewriteQueryMode::Expand => format!(
+            "Write up to {} alternative search queries for the following query. Use synonyms and related terms a relevant document would contain. Respond with one query per line and nothing else.\n\nQuery: {}",
+            MAX_QUERY_EXPANSIONS, query
+        ),
+    }
+}
+
+/// LLMs tend to number or bullet lists even when asked not to.
+fn strip_list_marker(line: &str) -> &str {
+    let line = line.trim();
+    let without_number = line.trim_start_matches(|c: char| c.is_ascii_digit());
+    let line = match without_number.strip_prefix(['.', ')']) {
+        Some(rest) if without_number.len() < line.len() => rest,
+        _ => line.trim_start_matches(['-', '*']),
+    };
+
+    line.trim().trim_matches('"').trim()
+}
+
+/// Expansions are appended to the original query so that its own terms still carry the most weight.
+fn parse_rewrite_completion(
+    query: &str,
+    mode: RewriteQueryMode,
+    completion: &str,
+) -> Result<String, ServiceError> {
+    let rewritten_query = match mode {
+        RewriteQueryMode::Hyde => completion.trim().to_string(),
+        RewriteQueryMode::Expand => {
+            let expansions = completion
+                .lines()
+                .map(strip_list_marker)
+                .filter(|expansion| {
+                    !expansion.is_empty() && !expansion.eq_ignore_ascii_case(query.trim())
+                })
+                .unique_by(|expansion| expansion.to_lowercase())
+                .take(MAX_QUERY_EXPANSIONS)
+                .collect_vec();
+
+            if expansions.is_empty() {
+                String::new()
+            } else {
+                std::iter::once(query.trim()).chain(expansions).join("\n")
+            }
+        }
+    };
+
+    if rewritten_query.is_empty() {
+        return Err(ServiceError::BadRequest(
+            "The LLM returned an empty rewrite of the query".to_string(),
+        ));
+    }
+
+    Ok(rewritten_query)
+}
+
+/// Rewrites a search query into either a hypothetical answer passage (HyDE) or the query followed by its expansions. The result is meant to be embedded in place of the query.
+#[tracing::instrument(skip(provider))]
+pub async fn rewrite_query<P: LlmProvider>(
+    provider: &P,
+    query: &str,
+    mode: RewriteQueryMode,
+    rag_prompt: &str,
+) -> Result<String, ServiceError> {
+    if query.trim().is_empty() {
+        return Err(ServiceError::BadRequest(
+            "rewrite_query requires a text query".to_string(),
+        ));
+    }
+
+    let completion = provider
+        .complete(get_rewrite_prompt(query, mode, rag_prompt))
+        .await?;
+
+    parse_rewrite_completion(query, mode, &completion)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct StubLlmProvider {
+        completion: String,
+        prompts: Mutex<Vec<String>>,
+    }
+
+    impl StubLlmProvider {
+        fn new(completion: &str) -> Self {
+            StubLlmProvider {
+                completion: completion.to_string(),
+                prompts: Mutex::new(vec![]),
+            }
+        }
+    }
+
+    impl LlmProvider for StubLlmProvider {
+        async fn complete(&self, prompt: String) -> Result<String, ServiceError> {
+            self.prompts.lock().unwrap().push(prompt);
+            Ok(self.completion.clone())
+        }
+    }
+
+    #[test]
+    fn test_hyde_uses_rag_prompt() {
+        let provider = StubLlmProvider::new("  Deterrence fails when leaders miscalculate.\n");
+        let rewritten = futures::executor::block_on(rewrite_query(
+            &provider,
+            "why does deterrence fail",
+            RewriteQueryMode::Hyde,
+            "Answer this: ",
+        ))
+        .unwrap();
+
+        assert_eq!(rewritten, "Deterrence fails when leaders miscalculate.");
+        assert_eq!(
+            provider.prompts.lock().unwrap().as_slice(),
+            ["Answer this: why does deterrence fail".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_expansions_follow_query() {
+        let provider = StubLlmProvider::new(
+            "1. nuclear deterrence failure\n2. \"Deterrence Stability\"\n\n- deterrence stability\n3. why does deterrence fail\n4. mutually assured destruction\n5. arms race",
+        );
+        let rewritten = futures::executor::block_on(rewrite_query(
+            &provider,
+            "why does deterrence fail",
+            RewriteQueryMode::Expand,
+            "",
+        ))
+        .unwrap();
+
+        assert_eq!(
+            rewritten,
+            "why does deterrence fail\nnuclear deterrence failure\nDeterrence Stability\nmutually assured destruction"
+        );
+    }
+
+    #[test]
+    fn test_empty_completion_is_an_error() {
+        let provider = StubLlmProvider::new(" \n ");
+        for mode in [RewriteQueryMode::Hyde, RewriteQueryMode::Expand] {
+            assert!(
+                futures::executor::block_on(rewrite_query(&provider, "query", mode, "")).is_err()
+            );
+        }
+        assert!(futures::executor::block_on(rewrite_query(
+            &provider,
+            " ",
+            RewriteQueryMode::Hyde,
+            ""
+        ))
+        .is_err());
+    }
+}
@@ -0,0 +1,29 @@
+/// Cosine similarity of two vectors, or 0 if either has no length.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
+    let (dot, norm_a, norm_b) =
+        a.iter()
+            .zip(b.iter())
+            .fold((0.0, 0.0, 0.0), |(dot, norm_a, norm_b), (x, y)| {
+                let (x, y) = (*x as f64, *y as f64);
+                (dot + x * y, norm_a + x * x, norm_b + y * y)
+            });
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert_eq!(cosine_similarity(&[3.0, 4.0], &[6.0, 8.0]), 1.0);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[-1.0, 0.0]), -1.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+    }
+}
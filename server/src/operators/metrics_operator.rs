@@ -0,0 +1,138 @@
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge, Encoder, HistogramTimer,
+    HistogramVec, IntCounterVec, IntGauge, TextEncoder,
+};
+use std::future::Future;
+
+lazy_static! {
+    static ref HTTP_REQUESTS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "trieve_http_requests_total",
+        "Number of HTTP requests by route pattern, method, and status",
+        &["route", "method", "status"]
+    )
+    .expect("Failed to register trieve_http_requests_total");
+    static ref HTTP_REQUEST_DURATION_SECONDS: HistogramVec = register_histogram_vec!(
+        "trieve_http_request_duration_seconds",
+        "Latency of HTTP requests by route pattern and method",
+        &["route", "method"]
+    )
+    .expect("Failed to register trieve_http_request_duration_seconds");
+    static ref MODEL_CALL_DURATION_SECONDS: HistogramVec = register_histogram_vec!(
+        "trieve_model_call_duration_seconds",
+        "Latency of calls to the embedding, SPLADE, and reranker servers",
+        &["model"]
+    )
+    .expect("Failed to register trieve_model_call_duration_seconds");
+    static ref MODEL_CALL_ERRORS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "trieve_model_call_errors_total",
+        "Number of failed calls to the embedding, SPLADE, and reranker servers",
+        &["model"]
+    )
+    .expect("Failed to register trieve_model_call_errors_total");
+    static ref QDRANT_CALL_DURATION_SECONDS: HistogramVec = register_histogram_vec!(
+        "trieve_qdrant_call_duration_seconds",
+        "Latency of calls to Qdrant by operation",
+        &["operation"]
+    )
+    .expect("Failed to register trieve_qdrant_call_duration_seconds");
+    static ref INGESTION_QUEUE_DEPTH: IntGauge = register_int_gauge!(
+        "trieve_ingestion_queue_depth",
+        "Number of messages waiting in the ingestion redis list"
+    )
+    .expect("Failed to register trieve_ingestion_queue_depth");
+    static ref INGESTION_MESSAGES_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "trieve_ingestion_messages_total",
+        "Number of ingestion messages processed by worker thread, message type, and result",
+        &["thread", "message_type", "result"]
+    )
+    .expect("Failed to register trieve_ingestion_messages_total");
+    static ref INGESTION_MESSAGE_DURATION_SECONDS: HistogramVec = register_histogram_vec!(
+        "trieve_ingestion_message_duration_seconds",
+        "Time taken to store and index an ingestion message by message type, excluding the work shared with the rest of its batch",
+        &["message_type"]
+    )
+    .expect("Failed to register trieve_ingestion_message_duration_seconds");
+    static ref INGESTION_BATCH_DURATION_SECONDS: HistogramVec = register_histogram_vec!(
+        "trieve_ingestion_batch_duration_seconds",
+        "Time taken to process a batch of ingestion messages, including its shared embedding and upsert requests, by message type",
+        &["message_type"]
+    )
+    .expect("Failed to register trieve_ingestion_batch_duration_seconds");
+}
+
+/// Every metric of the process in the Prometheus text format.
+pub fn gather_metrics() -> String {
+    let mut buffer = vec![];
+    if let Err(err) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        log::error!("Failed to encode metrics {:?}", err);
+    }
+
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+/// Content-Type of the response of gather_metrics.
+pub fn metrics_content_type() -> String {
+    TextEncoder::new().format_type().to_string()
+}
+
+/// Records a request. The route is the pattern it matched, e.g. /api/chunk/{id}, so that ids do not create a series per request.
+pub fn observe_http_request(route: &str, method: &str, status: u16, duration: std::time::Duration) {
+    HTTP_REQUESTS_TOTAL
+        .with_label_values(&[route, method, &status.to_string()])
+        .inc();
+    HTTP_REQUEST_DURATION_SECONDS
+        .with_label_values(&[route, method])
+        .observe(duration.as_secs_f64());
+}
+
+/// Records the latency of the call to the model server and whether it failed. The model is one of embedding, splade, or reranker.
+pub async fn observe_model_call<T, E>(
+    model: &str,
+    call: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let timer = MODEL_CALL_DURATION_SECONDS
+        .with_label_values(&[model])
+        .start_timer();
+    let result = call.await;
+    timer.observe_duration();
+
+    if result.is_err() {
+        MODEL_CALL_ERRORS_TOTAL.with_label_values(&[model]).inc();
+    }
+
+    result
+}
+
+/// Records the latency of a Qdrant call when the returned timer is dropped.
+pub fn start_qdrant_timer(operation: &str) -> HistogramTimer {
+    QDRANT_CALL_DURATION_SECONDS
+        .with_label_values(&[operation])
+        .start_timer()
+}
+
+pub fn set_ingestion_queue_depth(depth: i64) {
+    INGESTION_QUEUE_DEPTH.set(depth);
+}
+
+/// Records an ingestion message which was processed by the thread. The result is either succeeded or failed. The duration is the time spent on the message alone.
+pub fn observe_ingestion_message(
+    thread: usize,
+    message_type: &str,
+    result: &str,
+    duration: std::time::Duration,
+) {
+    INGESTION_MESSAGES_TOTAL
+        .with_label_values(&[&thread.to_string(), message_type, result])
+        .inc();
+    INGESTION_MESSAGE_DURATION_SECONDS
+        .with_label_values(&[message_type])
+        .observe(duration.as_secs_f64());
+}
+
+/// Records the time taken to process a batch of ingestion messages of the message type.
+pub fn observe_ingestion_batch(message_type: &str, duration: std::time::Duration) {
+    INGESTION_BATCH_DURATION_SECONDS
+        .with_label_values(&[message_type])
+        .observe(duration.as_secs_f64());
+}
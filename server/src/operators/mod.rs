@@ -1,16 +1,31 @@
+pub mod analytics_operator;
+pub mod audit_operator;
+pub mod cache_operator;
 pub mod chunk_operator;
 pub mod dataset_operator;
+pub mod diversity_operator;
 pub mod email_operator;
 pub mod event_operator;
+pub mod facet_operator;
 pub mod file_operator;
+pub mod full_text_operator;
 pub mod group_operator;
+pub mod highlight_operator;
+pub mod ingestion_queue_operator;
+pub mod ingestion_status_operator;
 pub mod invitation_operator;
+pub mod llm_operator;
+pub mod math_operator;
 pub mod message_operator;
+pub mod metrics_operator;
 pub mod model_operator;
 pub mod organization_operator;
 pub mod parse_operator;
 pub mod qdrant_operator;
+pub mod query_operator;
 pub mod search_operator;
+pub mod search_rule_operator;
 pub mod stripe_operator;
 pub mod topic_operator;
 pub mod user_operator;
+pub mod webhook_operator;
@@ -1,3 +1,4 @@
+use super::metrics_operator::observe_model_call;
 use crate::{
     data::models::ServerDatasetConfiguration, errors::ServiceError, get_env,
     handlers::chunk_handler::ScoreChunkDTO,
@@ -19,11 +20,23 @@ pub struct EmbeddingParameters {
     pub model: String,
 }
 
-#[tracing::instrument]
 pub async fn create_embeddings(
     message: Vec<String>,
     embed_type: &str,
     dataset_config: ServerDatasetConfiguration,
+) -> Result<Vec<Vec<f32>>, actix_web::Error> {
+    observe_model_call(
+        "embedding",
+        request_embeddings(message, embed_type, dataset_config),
+    )
+    .await
+}
+
+#[tracing::instrument]
+async fn request_embeddings(
+    message: Vec<String>,
+    embed_type: &str,
+    dataset_config: ServerDatasetConfiguration,
 ) -> Result<Vec<Vec<f32>>, actix_web::Error> {
     let parent_span = sentry::configure_scope(|scope| scope.get_span());
     let transaction: sentry::TransactionOrSpan = match &parent_span {
@@ -81,6 +94,13 @@ pub async fn create_embeddings(
 
     let input = match embed_type {
         "doc" => EmbeddingInput::StringArray(clipped_messages),
+        // Several queries are embedded in one request, each with the query prefix
+        "query" if clipped_messages.len() > 1 => EmbeddingInput::StringArray(
+            clipped_messages
+                .iter()
+                .map(|msg| format!("{}{}", dataset_config.EMBEDDING_QUERY_PREFIX, msg))
+                .collect(),
+        ),
         "query" => EmbeddingInput::String(
             format!(
                 "{}{}",
@@ -164,7 +184,13 @@ pub struct CustomSparseEmbedData {
     pub truncate: bool,
 }
 
-#[tracing::instrument]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomSparseEmbedBatchData {
+    pub inputs: Vec<String>,
+    pub encode_type: String,
+    pub truncate: bool,
+}
+
 pub async fn get_splade_embedding(
     message: &str,
     embed_type: &str,
@@ -175,6 +201,64 @@ pub async fn get_splade_embedding(
         ));
     }
 
+    observe_model_call(
+        "splade",
+        request_splade_embeddings(
+            CustomSparseEmbedData {
+                inputs: message.to_string(),
+                encode_type: embed_type.to_string(),
+                truncate: true,
+            },
+            embed_type,
+        ),
+    )
+    .await?
+    .into_iter()
+    .next()
+    .ok_or(ServiceError::BadRequest(
+        "Failed getting sparse vector from embedding server".to_string(),
+    ))
+}
+
+/// Embeds every message in one request. The vectors are returned in the order of the messages.
+pub async fn get_splade_embeddings(
+    messages: Vec<String>,
+    embed_type: &str,
+) -> Result<Vec<Vec<(u32, f32)>>, ServiceError> {
+    if messages.iter().any(|message| message.is_empty()) {
+        return Err(ServiceError::BadRequest(
+            "Cannot encode empty query".to_string(),
+        ));
+    }
+
+    let message_count = messages.len();
+    let vectors = observe_model_call(
+        "splade",
+        request_splade_embeddings(
+            CustomSparseEmbedBatchData {
+                inputs: messages,
+                encode_type: embed_type.to_string(),
+                truncate: true,
+            },
+            embed_type,
+        ),
+    )
+    .await?;
+
+    if vectors.len() != message_count {
+        return Err(ServiceError::BadRequest(
+            "Failed getting sparse vector from embedding server".to_string(),
+        ));
+    }
+
+    Ok(vectors)
+}
+
+#[tracing::instrument(skip(data))]
+async fn request_splade_embeddings<T: Serialize>(
+    data: T,
+    embed_type: &str,
+) -> Result<Vec<Vec<(u32, f32)>>, ServiceError> {
     let origin_key = match embed_type {
         "doc" => "SPARSE_SERVER_DOC_ORIGIN",
         "query" => "SPARSE_SERVER_QUERY_ORIGIN",
@@ -200,11 +284,7 @@ pub async fn get_splade_embedding(
                 get_env!("OPENAI_API_KEY", "OPENAI_API should be set")
             ),
         )
-        .send_json(CustomSparseEmbedData {
-            inputs: message.to_string(),
-            encode_type: embed_type.to_string(),
-            truncate: true,
-        })
+        .send_json(data)
         .map_err(|err| {
             log::error!(
                 "Failed parsing response from custom embedding server {:?}",
@@ -223,13 +303,14 @@ pub async fn get_splade_embedding(
             )
         })?;
 
-    let first_vector = resp.get(0).ok_or(ServiceError::BadRequest(
-        "Failed getting sparse vector from embedding server".to_string(),
-    ))?;
-
-    Ok(first_vector
-        .iter()
-        .map(|splade_idx| (splade_idx.index, splade_idx.value))
+    Ok(resp
+        .into_iter()
+        .map(|vector| {
+            vector
+                .iter()
+                .map(|splade_idx| (splade_idx.index, splade_idx.value))
+                .collect()
+        })
         .collect())
 }
 
@@ -246,11 +327,19 @@ pub struct CrossEncoderData {
     pub truncate: bool,
 }
 
-#[tracing::instrument]
 pub async fn cross_encoder(
     query: String,
     page_size: u64,
     results: Vec<ScoreChunkDTO>,
+) -> Result<Vec<ScoreChunkDTO>, actix_web::Error> {
+    observe_model_call("reranker", request_cross_encoder(query, page_size, results)).await
+}
+
+#[tracing::instrument]
+async fn request_cross_encoder(
+    query: String,
+    page_size: u64,
+    results: Vec<ScoreChunkDTO>,
 ) -> Result<Vec<ScoreChunkDTO>, actix_web::Error> {
     let parent_span = sentry::configure_scope(|scope| scope.get_span());
     let transaction: sentry::TransactionOrSpan = match &parent_span {
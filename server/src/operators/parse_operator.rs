@@ -4,7 +4,7 @@ use regex_split::RegexSplit;
 use scraper::Html;
 use std::cmp;
 
-use crate::errors::DefaultError;
+use crate::errors::{DefaultError, ServiceError};
 
 #[tracing::instrument]
 pub fn convert_html_to_text(html: &str) -> String {
@@ -114,6 +114,59 @@ pub fn average_embeddings(embeddings: Vec<Vec<f32>>) -> Result<Vec<f32>, Default
     Ok((arr.sum_axis(ndarray::Axis(0)) / (embeddings.len() as f32)).to_vec())
 }
 
+/// The texts to embed for a chunk. Chunks which are split and averaged have one text per coarse chunk of their content.
+pub fn get_upload_embedding_texts(content: &str, split_avg: bool) -> Vec<String> {
+    if split_avg {
+        coarse_doc_chunker(content.to_string())
+    } else {
+        vec![content.to_string()]
+    }
+}
+
+/// Reduces the embeddings of the texts of a chunk to the embedding of the chunk.
+pub fn get_upload_embedding_vector(
+    embeddings: Vec<Vec<f32>>,
+    split_avg: bool,
+) -> Result<Vec<f32>, ServiceError> {
+    if embeddings.is_empty() {
+        return Err(ServiceError::InternalServerError(
+            "Failed to get first embedding".into(),
+        ));
+    }
+
+    if split_avg {
+        average_embeddings(embeddings).map_err(|err| {
+            ServiceError::InternalServerError(format!(
+                "Failed to average embeddings: {:?}",
+                err.message
+            ))
+        })
+    } else {
+        Ok(embeddings[0].clone())
+    }
+}
+
+/// Hands the embeddings of one batched request out to the chunks whose texts were concatenated into it, in order. Each chunk is given
+/// as its chunk_vector, whether it is split and averaged, and its number of texts. Each chunk takes as many embeddings as it had texts,
+/// and chunks which came with a chunk_vector keep it instead.
+pub fn get_batched_upload_embedding_vectors(
+    chunks: Vec<(Option<Vec<f32>>, bool, usize)>,
+    embeddings: Vec<Vec<f32>>,
+) -> Vec<Result<Vec<f32>, ServiceError>> {
+    let mut embeddings = embeddings.into_iter();
+
+    chunks
+        .into_iter()
+        .map(|(chunk_vector, split_avg, text_count)| match chunk_vector {
+            Some(chunk_vector) => Ok(chunk_vector),
+            None => get_upload_embedding_vector(
+                embeddings.by_ref().take(text_count).collect(),
+                split_avg,
+            ),
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -130,4 +183,68 @@ mod test {
         let result = average_embeddings(embeddings).unwrap();
         assert!(result == vec![2.0, 2.5, 1.0]);
     }
+
+    #[test]
+    fn test_only_split_chunks_embed_more_than_their_content() {
+        let content = format!(
+            "{} {}",
+            "First sentence.".repeat(40),
+            "Second one.".repeat(40)
+        );
+
+        assert_eq!(
+            get_upload_embedding_texts(&content, false),
+            vec![content.clone()]
+        );
+        assert_eq!(
+            get_upload_embedding_texts(&content, true),
+            coarse_doc_chunker(content.clone())
+        );
+    }
+
+    #[test]
+    fn test_split_chunks_average_their_embeddings() {
+        let embeddings = vec![vec![1.0, 0.0], vec![3.0, 2.0]];
+
+        assert_eq!(
+            get_upload_embedding_vector(embeddings.clone(), false).unwrap(),
+            vec![1.0, 0.0]
+        );
+        assert_eq!(
+            get_upload_embedding_vector(embeddings, true).unwrap(),
+            vec![2.0, 1.0]
+        );
+        assert!(get_upload_embedding_vector(vec![], true).is_err());
+    }
+
+    #[test]
+    fn test_batched_embeddings_are_handed_out_in_order() {
+        let vectors = get_batched_upload_embedding_vectors(
+            vec![
+                (None, false, 1),
+                (Some(vec![9.0, 9.0]), false, 0),
+                (None, true, 2),
+                (None, false, 1),
+            ],
+            vec![
+                vec![1.0, 1.0],
+                vec![2.0, 0.0],
+                vec![4.0, 2.0],
+                vec![5.0, 5.0],
+            ],
+        )
+        .into_iter()
+        .map(|vector| vector.unwrap())
+        .collect::<Vec<Vec<f32>>>();
+
+        assert_eq!(
+            vectors,
+            vec![
+                vec![1.0, 1.0],
+                vec![9.0, 9.0],
+                vec![3.0, 1.0],
+                vec![5.0, 5.0]
+            ]
+        );
+    }
 }
@@ -1,3 +1,4 @@
+use super::metrics_operator::start_qdrant_timer;
 use super::search_operator::{assemble_qdrant_filter, SearchResult};
 use crate::{
     data::models::{ChunkMetadata, ServerDatasetConfiguration},
@@ -10,9 +11,10 @@ use qdrant_client::{
     client::{QdrantClient, QdrantClientConfig},
     qdrant::{
         group_id::Kind, point_id::PointIdOptions, quantization_config::Quantization,
-        with_payload_selector::SelectorOptions, BinaryQuantization, CountPoints, CreateCollection,
-        Distance, FieldType, Filter, HnswConfigDiff, PointId, PointStruct, QuantizationConfig,
-        RecommendPointGroups, RecommendPoints, SearchPointGroups, SearchPoints, SparseIndexConfig,
+        vectors::VectorsOptions, with_payload_selector::SelectorOptions, BinaryQuantization,
+        CountPoints, CreateCollection, Distance, FieldType, Filter, HnswConfigDiff,
+        PayloadIncludeSelector, PointId, PointStruct, QuantizationConfig, RecommendPointGroups,
+        RecommendPoints, ScrollPoints, SearchPointGroups, SearchPoints, SparseIndexConfig,
         SparseVectorConfig, SparseVectorParams, Value, Vector, VectorParams, VectorParamsMap,
         VectorsConfig, WithPayloadSelector,
     },
@@ -49,6 +51,8 @@ pub async fn create_new_qdrant_collection_query(
     qdrant_collection: Option<&str>,
     quantize: bool,
 ) -> Result<(), ServiceError> {
+    let _timer = start_qdrant_timer("create_new_qdrant_collection");
+
     let qdrant_collection = qdrant_collection
         .unwrap_or(get_env!(
             "QDRANT_COLLECTION",
@@ -241,21 +245,13 @@ pub async fn create_new_qdrant_collection_query(
     Ok(())
 }
 
-#[tracing::instrument(skip(embedding_vector))]
-pub async fn create_new_qdrant_point_query(
+fn chunk_to_qdrant_point(
     point_id: uuid::Uuid,
     embedding_vector: Vec<f32>,
     chunk_metadata: ChunkMetadata,
     splade_vector: Vec<(u32, f32)>,
     group_ids: Option<Vec<uuid::Uuid>>,
-    config: ServerDatasetConfiguration,
-) -> Result<(), actix_web::Error> {
-    let qdrant_collection = config.QDRANT_COLLECTION_NAME;
-
-    let qdrant = get_qdrant_connection(Some(&config.QDRANT_URL), Some(&config.QDRANT_API_KEY))
-        .await
-        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
-
+) -> Result<PointStruct, ServiceError> {
     let payload = json!({
         "tag_set": chunk_metadata.tag_set.unwrap_or("".to_string()).split(',').collect_vec(),
         "link": chunk_metadata.link.unwrap_or("".to_string()).split(',').collect_vec(),
@@ -273,7 +269,11 @@ pub async fn create_new_qdrant_point_query(
         768 => "768_vectors",
         1024 => "1024_vectors",
         1536 => "1536_vectors",
-        _ => return Err(ServiceError::BadRequest("Invalid embedding vector size".into()).into()),
+        _ => {
+            return Err(ServiceError::BadRequest(
+                "Invalid embedding vector size".into(),
+            ))
+        }
     };
 
     let vector_payload = HashMap::from([
@@ -281,7 +281,37 @@ pub async fn create_new_qdrant_point_query(
         ("sparse_vectors".to_string(), Vector::from(splade_vector)),
     ]);
 
-    let point = PointStruct::new(point_id.clone().to_string(), vector_payload, payload);
+    Ok(PointStruct::new(
+        point_id.clone().to_string(),
+        vector_payload,
+        payload,
+    ))
+}
+
+#[tracing::instrument(skip(embedding_vector))]
+pub async fn create_new_qdrant_point_query(
+    point_id: uuid::Uuid,
+    embedding_vector: Vec<f32>,
+    chunk_metadata: ChunkMetadata,
+    splade_vector: Vec<(u32, f32)>,
+    group_ids: Option<Vec<uuid::Uuid>>,
+    config: ServerDatasetConfiguration,
+) -> Result<(), actix_web::Error> {
+    let _timer = start_qdrant_timer("create_new_qdrant_point");
+
+    let qdrant_collection = config.QDRANT_COLLECTION_NAME;
+
+    let qdrant = get_qdrant_connection(Some(&config.QDRANT_URL), Some(&config.QDRANT_API_KEY))
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    let point = chunk_to_qdrant_point(
+        point_id,
+        embedding_vector,
+        chunk_metadata,
+        splade_vector,
+        group_ids,
+    )?;
 
     qdrant
         .upsert_points_blocking(qdrant_collection, None, vec![point], None)
@@ -295,6 +325,55 @@ pub async fn create_new_qdrant_point_query(
     Ok(())
 }
 
+/// A point of a chunk which is waiting to be upserted in bulk.
+#[derive(Debug, Clone)]
+pub struct NewQdrantPoint {
+    pub point_id: uuid::Uuid,
+    pub embedding_vector: Vec<f32>,
+    pub chunk_metadata: ChunkMetadata,
+    pub splade_vector: Vec<(u32, f32)>,
+    pub group_ids: Option<Vec<uuid::Uuid>>,
+}
+
+/// Upserts every point in one request. Either all of the points are upserted or none of them are.
+#[tracing::instrument(skip(points))]
+pub async fn bulk_create_new_qdrant_points_query(
+    points: Vec<NewQdrantPoint>,
+    config: ServerDatasetConfiguration,
+) -> Result<(), actix_web::Error> {
+    let _timer = start_qdrant_timer("bulk_create_new_qdrant_points");
+
+    let qdrant_collection = config.QDRANT_COLLECTION_NAME;
+
+    let qdrant = get_qdrant_connection(Some(&config.QDRANT_URL), Some(&config.QDRANT_API_KEY))
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+    let points = points
+        .into_iter()
+        .map(|point| {
+            chunk_to_qdrant_point(
+                point.point_id,
+                point.embedding_vector,
+                point.chunk_metadata,
+                point.splade_vector,
+                point.group_ids,
+            )
+        })
+        .collect::<Result<Vec<PointStruct>, ServiceError>>()?;
+
+    qdrant
+        .upsert_points_blocking(qdrant_collection, None, points, None)
+        .await
+        .map_err(|err| {
+            sentry::capture_message(&format!("Error {:?}", err), sentry::Level::Error);
+            log::error!("Failed inserting chunks to qdrant {:?}", err);
+            ServiceError::BadRequest(format!("Failed inserting chunks to qdrant {:?}", err))
+        })?;
+
+    Ok(())
+}
+
 #[tracing::instrument(skip(updated_vector))]
 pub async fn update_qdrant_point_query(
     metadata: Option<ChunkMetadata>,
@@ -305,6 +384,8 @@ pub async fn update_qdrant_point_query(
     splade_vector: Vec<(u32, f32)>,
     config: ServerDatasetConfiguration,
 ) -> Result<(), actix_web::Error> {
+    let _timer = start_qdrant_timer("update_qdrant_point");
+
     let qdrant_point_id: Vec<PointId> = vec![point_id.to_string().into()];
 
     let qdrant_collection = config.QDRANT_COLLECTION_NAME;
@@ -420,12 +501,100 @@ pub async fn update_qdrant_point_query(
     Ok(())
 }
 
+/// Returns the dense vector stored for a point, for searches which use an existing chunk as the query.
+#[tracing::instrument]
+pub async fn get_qdrant_point_vector_query(
+    point_id: uuid::Uuid,
+    config: ServerDatasetConfiguration,
+) -> Result<Vec<f32>, DefaultError> {
+    let _timer = start_qdrant_timer("get_qdrant_point_vector");
+
+    get_qdrant_point_vectors_query(vec![point_id], config)
+        .await?
+        .remove(&point_id)
+        .ok_or(DefaultError {
+            message: "Chunk does not have a vector to search with",
+        })
+}
+
+/// Returns the dense vectors stored for the points. Points which do not exist or have no dense vector are left out.
+#[tracing::instrument]
+pub async fn get_qdrant_point_vectors_query(
+    point_ids: Vec<uuid::Uuid>,
+    config: ServerDatasetConfiguration,
+) -> Result<HashMap<uuid::Uuid, Vec<f32>>, DefaultError> {
+    let _timer = start_qdrant_timer("get_qdrant_point_vectors");
+
+    let qdrant_collection = config.QDRANT_COLLECTION_NAME;
+
+    let vector_name = match config.EMBEDDING_SIZE {
+        384 => "384_vectors",
+        512 => "512_vectors",
+        768 => "768_vectors",
+        1024 => "1024_vectors",
+        1536 => "1536_vectors",
+        _ => {
+            return Err(DefaultError {
+                message: "Invalid embedding vector size",
+            })
+        }
+    };
+
+    if point_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let qdrant =
+        get_qdrant_connection(Some(&config.QDRANT_URL), Some(&config.QDRANT_API_KEY)).await?;
+
+    let qdrant_point_ids: Vec<PointId> = point_ids.iter().map(|id| id.to_string().into()).collect();
+
+    let points = qdrant
+        .get_points(
+            qdrant_collection,
+            None,
+            &qdrant_point_ids,
+            true.into(),
+            false.into(),
+            None,
+        )
+        .await
+        .map_err(|err| {
+            log::error!("Failed to get point vectors from qdrant: {:?}", err);
+            DefaultError {
+                message: "Failed to get point vectors from qdrant",
+            }
+        })?
+        .result;
+
+    Ok(points
+        .into_iter()
+        .filter_map(|point| {
+            let point_id = match point.id?.point_id_options? {
+                PointIdOptions::Uuid(id) => uuid::Uuid::from_str(&id).ok()?,
+                PointIdOptions::Num(_) => return None,
+            };
+
+            let vector = match point.vectors?.vectors_options? {
+                VectorsOptions::Vectors(named_vectors) => {
+                    named_vectors.vectors.get(vector_name)?.data.clone()
+                }
+                VectorsOptions::Vector(vector) => vector.data,
+            };
+
+            Some((point_id, vector))
+        })
+        .collect())
+}
+
 #[tracing::instrument]
 pub async fn add_bookmark_to_qdrant_query(
     point_id: uuid::Uuid,
     group_id: uuid::Uuid,
     config: ServerDatasetConfiguration,
 ) -> Result<(), DefaultError> {
+    let _timer = start_qdrant_timer("add_bookmark_to_qdrant");
+
     let qdrant_collection = config.QDRANT_COLLECTION_NAME;
 
     let qdrant =
@@ -513,6 +682,8 @@ pub async fn remove_bookmark_from_qdrant_query(
     group_id: uuid::Uuid,
     config: ServerDatasetConfiguration,
 ) -> Result<(), DefaultError> {
+    let _timer = start_qdrant_timer("remove_bookmark_from_qdrant");
+
     let qdrant_collection = config.QDRANT_COLLECTION_NAME;
 
     let qdrant =
@@ -604,11 +775,13 @@ pub struct GroupSearchResults {
 pub enum VectorType {
     Sparse(Vec<(u32, f32)>),
     Dense(Vec<f32>),
+    /// Query text for the Postgres full-text backend. It is searched in Postgres instead of being sent to Qdrant.
+    FullText(String),
 }
 
 #[tracing::instrument]
 pub async fn search_over_groups_query(
-    page: u64,
+    offset: u32,
     filter: Filter,
     limit: u32,
     score_threshold: Option<f32>,
@@ -616,6 +789,8 @@ pub async fn search_over_groups_query(
     vector: VectorType,
     config: ServerDatasetConfiguration,
 ) -> Result<Vec<GroupSearchResults>, DefaultError> {
+    let _timer = start_qdrant_timer("search_over_groups");
+
     let qdrant_collection = config.QDRANT_COLLECTION_NAME;
 
     let qdrant =
@@ -635,6 +810,11 @@ pub async fn search_over_groups_query(
                 })
             }
         },
+        VectorType::FullText(_) => {
+            return Err(DefaultError {
+                message: "Postgres full-text queries cannot be run against Qdrant",
+            })
+        }
     };
 
     let data = match vector {
@@ -644,7 +824,7 @@ pub async fn search_over_groups_query(
                     collection_name: qdrant_collection.to_string(),
                     vector: embedding_vector,
                     vector_name: Some(vector_name.to_string()),
-                    limit: (limit * page as u32),
+                    limit: offset + limit,
                     score_threshold,
                     with_payload: None,
                     filter: Some(filter),
@@ -663,7 +843,7 @@ pub async fn search_over_groups_query(
                     vector: sparse_vector.data,
                     sparse_indices: sparse_vector.indices,
                     vector_name: Some(vector_name.to_string()),
-                    limit: (limit * page as u32),
+                    limit: offset + limit,
                     score_threshold,
                     with_payload: None,
                     filter: Some(filter),
@@ -673,6 +853,12 @@ pub async fn search_over_groups_query(
                 })
                 .await
         }
+
+        VectorType::FullText(_) => {
+            return Err(DefaultError {
+                message: "Postgres full-text queries cannot be run against Qdrant",
+            })
+        }
     }
     .map_err(|e| {
         log::error!("Failed to search points on Qdrant {:?}", e);
@@ -681,11 +867,13 @@ pub async fn search_over_groups_query(
         }
     })?;
 
+    // Qdrant does not support offsets for grouped search, so skip the groups before the offset here
     let point_ids: Vec<GroupSearchResults> = data
         .result
         .unwrap()
         .groups
         .iter()
+        .skip(offset as usize)
         .filter_map(|point| {
             let group_id = match &point.id.clone()?.kind? {
                 Kind::StringValue(id) => uuid::Uuid::from_str(id).unwrap_or_default(),
@@ -715,13 +903,15 @@ pub async fn search_over_groups_query(
 
 #[tracing::instrument]
 pub async fn search_qdrant_query(
-    page: u64,
+    offset: u64,
     filter: Filter,
     limit: u64,
     score_threshold: Option<f32>,
     vector: VectorType,
     config: ServerDatasetConfiguration,
 ) -> Result<Vec<SearchResult>, DefaultError> {
+    let _timer = start_qdrant_timer("search_qdrant");
+
     let qdrant_collection = config.QDRANT_COLLECTION_NAME;
 
     let qdrant =
@@ -741,6 +931,11 @@ pub async fn search_qdrant_query(
                 })
             }
         },
+        VectorType::FullText(_) => {
+            return Err(DefaultError {
+                message: "Postgres full-text queries cannot be run against Qdrant",
+            })
+        }
     };
 
     let data = match vector {
@@ -752,7 +947,7 @@ pub async fn search_qdrant_query(
                     vector_name: Some(vector_name.to_string()),
                     limit,
                     score_threshold,
-                    offset: Some((page - 1) * 10),
+                    offset: Some(offset),
                     with_payload: None,
                     filter: Some(filter),
                     ..Default::default()
@@ -770,13 +965,19 @@ pub async fn search_qdrant_query(
                     vector_name: Some(vector_name.to_string()),
                     limit,
                     score_threshold,
-                    offset: Some((page - 1) * 10),
+                    offset: Some(offset),
                     with_payload: None,
                     filter: Some(filter),
                     ..Default::default()
                 })
                 .await
         }
+
+        VectorType::FullText(_) => {
+            return Err(DefaultError {
+                message: "Postgres full-text queries cannot be run against Qdrant",
+            })
+        }
     }
     .map_err(|e| {
         log::error!("Failed to search points on Qdrant {:?}", e);
@@ -809,9 +1010,11 @@ pub async fn recommend_qdrant_query(
     dataset_id: uuid::Uuid,
     config: ServerDatasetConfiguration,
 ) -> Result<Vec<uuid::Uuid>, DefaultError> {
+    let _timer = start_qdrant_timer("recommend_qdrant");
+
     let qdrant_collection = config.QDRANT_COLLECTION_NAME;
 
-    let filter = assemble_qdrant_filter(filters, None, None, dataset_id, None).await?;
+    let filter = assemble_qdrant_filter(filters, None, dataset_id, None).await?;
 
     let qdrant =
         get_qdrant_connection(Some(&config.QDRANT_URL), Some(&config.QDRANT_API_KEY)).await?;
@@ -890,12 +1093,14 @@ pub async fn recommend_qdrant_groups_query(
     dataset_id: uuid::Uuid,
     config: ServerDatasetConfiguration,
 ) -> Result<Vec<GroupSearchResults>, DefaultError> {
+    let _timer = start_qdrant_timer("recommend_qdrant_groups");
+
     let qdrant_collection = config.QDRANT_COLLECTION_NAME;
 
     let qdrant =
         get_qdrant_connection(Some(&config.QDRANT_URL), Some(&config.QDRANT_API_KEY)).await?;
 
-    let filters = assemble_qdrant_filter(filter, None, None, dataset_id, None).await?;
+    let filters = assemble_qdrant_filter(filter, None, dataset_id, None).await?;
 
     let positive_point_ids: Vec<PointId> = positive_ids
         .iter()
@@ -987,6 +1192,8 @@ pub async fn get_point_count_qdrant_query(
     filters: Filter,
     config: ServerDatasetConfiguration,
 ) -> Result<u64, DefaultError> {
+    let _timer = start_qdrant_timer("get_point_count_qdrant");
+
     let qdrant_collection = config.QDRANT_COLLECTION_NAME;
 
     let qdrant =
@@ -1010,3 +1217,110 @@ pub async fn get_point_count_qdrant_query(
 
     Ok(data.result.expect("Failed to get result from qdrant").count)
 }
+
+/// Returns the ids of up to max_points points matching the filter, without their payloads or vectors.
+#[tracing::instrument]
+pub async fn scroll_qdrant_point_ids_query(
+    filter: Filter,
+    max_points: u64,
+    config: ServerDatasetConfiguration,
+) -> Result<Vec<uuid::Uuid>, DefaultError> {
+    let _timer = start_qdrant_timer("scroll_qdrant_point_ids");
+
+    let qdrant_collection = config.QDRANT_COLLECTION_NAME;
+
+    let qdrant =
+        get_qdrant_connection(Some(&config.QDRANT_URL), Some(&config.QDRANT_API_KEY)).await?;
+
+    let mut point_ids: Vec<uuid::Uuid> = vec![];
+    let mut offset: Option<PointId> = None;
+
+    while (point_ids.len() as u64) < max_points {
+        let data = qdrant
+            .scroll(&ScrollPoints {
+                collection_name: qdrant_collection.clone(),
+                filter: Some(filter.clone()),
+                offset: offset.clone(),
+                limit: Some(std::cmp::min(1000, max_points - point_ids.len() as u64) as u32),
+                with_payload: Some(false.into()),
+                with_vectors: Some(false.into()),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| {
+                log::error!("Failed to scroll points from qdrant: {:?}", err);
+                DefaultError {
+                    message: "Failed to scroll points from qdrant",
+                }
+            })?;
+
+        point_ids.extend(data.result.iter().filter_map(|point| {
+            match point.id.clone()?.point_id_options? {
+                PointIdOptions::Uuid(id) => uuid::Uuid::parse_str(&id).ok(),
+                PointIdOptions::Num(_) => None,
+            }
+        }));
+
+        offset = data.next_page_offset;
+        if offset.is_none() {
+            break;
+        }
+    }
+
+    Ok(point_ids)
+}
+
+#[tracing::instrument]
+pub async fn scroll_qdrant_payloads_query(
+    filter: Filter,
+    payload_fields: Vec<String>,
+    max_points: u64,
+    config: ServerDatasetConfiguration,
+) -> Result<Vec<serde_json::Value>, DefaultError> {
+    let _timer = start_qdrant_timer("scroll_qdrant_payloads");
+
+    let qdrant_collection = config.QDRANT_COLLECTION_NAME;
+
+    let qdrant =
+        get_qdrant_connection(Some(&config.QDRANT_URL), Some(&config.QDRANT_API_KEY)).await?;
+
+    let mut payloads: Vec<serde_json::Value> = vec![];
+    let mut offset: Option<PointId> = None;
+
+    while (payloads.len() as u64) < max_points {
+        let data = qdrant
+            .scroll(&ScrollPoints {
+                collection_name: qdrant_collection.clone(),
+                filter: Some(filter.clone()),
+                offset: offset.clone(),
+                limit: Some(std::cmp::min(1000, max_points - payloads.len() as u64) as u32),
+                with_payload: Some(WithPayloadSelector {
+                    selector_options: Some(SelectorOptions::Include(PayloadIncludeSelector {
+                        fields: payload_fields.clone(),
+                    })),
+                }),
+                with_vectors: Some(false.into()),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| {
+                log::error!("Failed to scroll points from qdrant: {:?}", err);
+                DefaultError {
+                    message: "Failed to scroll points from qdrant",
+                }
+            })?;
+
+        payloads.extend(
+            data.result.iter().map(|point| {
+                serde_json::to_value(&point.payload).unwrap_or(serde_json::Value::Null)
+            }),
+        );
+
+        offset = data.next_page_offset;
+        if offset.is_none() {
+            break;
+        }
+    }
+
+    Ok(payloads)
+}
@@ -0,0 +1,854 @@
+use crate::handlers::chunk_handler::{ChunkFilter, FieldCondition, MatchCondition, Range};
+use chrono::{Datelike, NaiveDate};
+use itertools::Itertools;
+
+/// A syntax or compile error in a search query. The position is the 1-based column of the
+/// offending character when the error can be tied to one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuerySyntaxError {
+    pub message: String,
+    pub position: Option<usize>,
+}
+
+impl QuerySyntaxError {
+    fn at(position: usize, message: impl Into<String>) -> Self {
+        QuerySyntaxError {
+            message: message.into(),
+            position: Some(position + 1),
+        }
+    }
+
+    fn new(message: impl Into<String>) -> Self {
+        QuerySyntaxError {
+            message: message.into(),
+            position: None,
+        }
+    }
+}
+
+impl std::fmt::Display for QuerySyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some(position) => write!(
+                f,
+                "Syntax error in query at column {}: {}",
+                position, self.message
+            ),
+            None => write!(f, "Syntax error in query: {}", self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RangeOperator {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    /// An unquoted value, e.g. `tag_set:news`.
+    Word(String),
+    /// A quoted value, e.g. `metadata.author:"Jane Doe"`. Always matched as text.
+    Phrase(String),
+    /// A comparison, e.g. `metadata.price:>=10` or `time_stamp:<2024-01`.
+    Range(RangeOperator, String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryNode {
+    Term(String),
+    Phrase(String),
+    Prefix(String),
+    Field {
+        field: String,
+        value: FieldValue,
+    },
+    /// `explicit` is false when the clauses were only separated by whitespace.
+    And {
+        clauses: Vec<QueryNode>,
+        explicit: bool,
+    },
+    Or(Vec<QueryNode>),
+    Not(Box<QueryNode>),
+    Group(Box<QueryNode>),
+}
+
+/// Constraint on the text of a chunk which is checked against chunk_html in Postgres.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextFilter {
+    Contains(String),
+    Prefix(String),
+    And(Vec<TextFilter>),
+    Or(Vec<TextFilter>),
+    Not(Box<TextFilter>),
+}
+
+/// The result of compiling a query. `text` is what gets embedded, `filters` must all match,
+/// and `text_filter` restricts the candidate chunks by their content.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledQuery {
+    pub text: String,
+    pub filters: Vec<ChunkFilter>,
+    pub text_filter: Option<TextFilter>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word(String),
+    Prefix(String),
+    Phrase(String),
+    Field { field: String, value: FieldValue },
+    And,
+    Or,
+    Not,
+    Minus,
+    LParen,
+    RParen,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+fn is_field_name(name: &str) -> bool {
+    match name {
+        "tag_set" | "link" | "time_stamp" => true,
+        _ => name
+            .strip_prefix("metadata.")
+            .map(|path| !path.is_empty() && path.split('.').all(|key| !key.is_empty()))
+            .unwrap_or(false),
+    }
+}
+
+fn is_word_boundary(c: char) -> bool {
+    c.is_whitespace() || c == '(' || c == ')' || c == '"'
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Lexer {
+    fn new(query: &str) -> Self {
+        Lexer {
+            chars: query.chars().collect(),
+            position: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn read_phrase(&mut self) -> Result<String, QuerySyntaxError> {
+        let start = self.position;
+        self.position += 1;
+        let mut phrase = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(QuerySyntaxError::at(start, "Unterminated quoted phrase")),
+                Some('\\') if self.chars.get(self.position + 1).is_some() => {
+                    phrase.push(self.chars[self.position + 1]);
+                    self.position += 2;
+                }
+                Some('"') => {
+                    self.position += 1;
+                    break;
+                }
+                Some(c) => {
+                    phrase.push(c);
+                    self.position += 1;
+                }
+            }
+        }
+
+        let phrase = phrase.split_whitespace().join(" ");
+        if phrase.is_empty() {
+            return Err(QuerySyntaxError::at(start, "Quoted phrase is empty"));
+        }
+        Ok(phrase)
+    }
+
+    fn read_word(&mut self) -> String {
+        let start = self.position;
+        while self.peek().map(|c| !is_word_boundary(c)).unwrap_or(false) {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    fn field_value(
+        &mut self,
+        field: &str,
+        raw_value: &str,
+        start: usize,
+    ) -> Result<FieldValue, QuerySyntaxError> {
+        if raw_value.is_empty() {
+            if self.peek() == Some('"') {
+                return Ok(FieldValue::Phrase(self.read_phrase()?));
+            }
+            return Err(QuerySyntaxError::at(
+                start,
+                format!("Expected a value after {}:", field),
+            ));
+        }
+
+        let (operator, operand) = if let Some(operand) = raw_value.strip_prefix(">=") {
+            (Some(RangeOperator::Gte), operand)
+        } else if let Some(operand) = raw_value.strip_prefix("<=") {
+            (Some(RangeOperator::Lte), operand)
+        } else if let Some(operand) = raw_value.strip_prefix('>') {
+            (Some(RangeOperator::Gt), operand)
+        } else if let Some(operand) = raw_value.strip_prefix('<') {
+            (Some(RangeOperator::Lt), operand)
+        } else {
+            (None, raw_value)
+        };
+
+        match operator {
+            Some(_) if operand.is_empty() => Err(QuerySyntaxError::at(
+                start,
+                format!(
+                    "Expected a value after the comparison in {}:{}",
+                    field, raw_value
+                ),
+            )),
+            Some(operator) => Ok(FieldValue::Range(operator, operand.to_string())),
+            None => Ok(FieldValue::Word(operand.to_string())),
+        }
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>, QuerySyntaxError> {
+        let mut tokens = vec![];
+
+        while let Some(c) = self.peek() {
+            let position = self.position;
+            let kind = match c {
+                c if c.is_whitespace() => {
+                    self.position += 1;
+                    continue;
+                }
+                '(' => {
+                    self.position += 1;
+                    TokenKind::LParen
+                }
+                ')' => {
+                    self.position += 1;
+                    TokenKind::RParen
+                }
+                '"' => TokenKind::Phrase(self.read_phrase()?),
+                '-' if self
+                    .chars
+                    .get(self.position + 1)
+                    .map(|next| !next.is_whitespace() && *next != ')' && *next != '-')
+                    .unwrap_or(false) =>
+                {
+                    self.position += 1;
+                    TokenKind::Minus
+                }
+                _ => {
+                    let word = self.read_word();
+                    match word.as_str() {
+                        "AND" => TokenKind::And,
+                        "OR" => TokenKind::Or,
+                        "NOT" => TokenKind::Not,
+                        _ => match word.split_once(':') {
+                            Some((field, raw_value)) if is_field_name(field) => TokenKind::Field {
+                                field: field.to_string(),
+                                value: self.field_value(field, raw_value, position)?,
+                            },
+                            _ => match word.strip_suffix('*') {
+                                Some(prefix) if !prefix.is_empty() && !prefix.ends_with('*') => {
+                                    TokenKind::Prefix(prefix.to_string())
+                                }
+                                _ => TokenKind::Word(word),
+                            },
+                        },
+                    }
+                }
+            };
+            tokens.push(Token { kind, position });
+        }
+
+        Ok(tokens)
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.position).map(|token| &token.kind)
+    }
+
+    fn current_position(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map(|token| token.position)
+            .unwrap_or(self.end)
+    }
+
+    fn starts_operand(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(
+                TokenKind::Word(_)
+                    | TokenKind::Prefix(_)
+                    | TokenKind::Phrase(_)
+                    | TokenKind::Field { .. }
+                    | TokenKind::Not
+                    | TokenKind::Minus
+                    | TokenKind::LParen
+            )
+        )
+    }
+
+    fn expect_operand(&self, after: &str) -> Result<(), QuerySyntaxError> {
+        if self.starts_operand() {
+            return Ok(());
+        }
+        Err(QuerySyntaxError::at(
+            self.current_position(),
+            format!("Expected a search term after {}", after),
+        ))
+    }
+
+    fn parse_or(&mut self) -> Result<QueryNode, QuerySyntaxError> {
+        let mut clauses = vec![self.parse_and()?];
+        while self.peek() == Some(&TokenKind::Or) {
+            self.position += 1;
+            self.expect_operand("OR")?;
+            clauses.push(self.parse_and()?);
+        }
+
+        if clauses.len() == 1 {
+            return Ok(clauses.remove(0));
+        }
+        Ok(QueryNode::Or(clauses))
+    }
+
+    /// Parses terms separated by whitespace. An explicit AND binds tighter, so it only joins the terms on either side of it.
+    fn parse_and(&mut self) -> Result<QueryNode, QuerySyntaxError> {
+        let mut clauses = vec![self.parse_explicit_and()?];
+        while self.starts_operand() {
+            clauses.push(self.parse_explicit_and()?);
+        }
+
+        if clauses.len() == 1 {
+            return Ok(clauses.remove(0));
+        }
+        Ok(QueryNode::And {
+            clauses,
+            explicit: false,
+        })
+    }
+
+    fn parse_explicit_and(&mut self) -> Result<QueryNode, QuerySyntaxError> {
+        let mut clauses = vec![self.parse_unary()?];
+        while self.peek() == Some(&TokenKind::And) {
+            self.position += 1;
+            self.expect_operand("AND")?;
+            clauses.push(self.parse_unary()?);
+        }
+
+        if clauses.len() == 1 {
+            return Ok(clauses.remove(0));
+        }
+        Ok(QueryNode::And {
+            clauses,
+            explicit: true,
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<QueryNode, QuerySyntaxError> {
+        match self.peek() {
+            Some(TokenKind::Not) => {
+                self.position += 1;
+                self.expect_operand("NOT")?;
+                Ok(QueryNode::Not(Box::new(self.parse_unary()?)))
+            }
+            Some(TokenKind::Minus) => {
+                self.position += 1;
+                self.expect_operand("-")?;
+                Ok(QueryNode::Not(Box::new(self.parse_unary()?)))
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<QueryNode, QuerySyntaxError> {
+        let position = self.current_position();
+        let token = match self.tokens.get(self.position) {
+            Some(token) => token.kind.clone(),
+            None => return Err(QuerySyntaxError::at(position, "Expected a search term")),
+        };
+        self.position += 1;
+
+        match token {
+            TokenKind::Word(word) => Ok(QueryNode::Term(word)),
+            TokenKind::Prefix(prefix) => Ok(QueryNode::Prefix(prefix)),
+            TokenKind::Phrase(phrase) => Ok(QueryNode::Phrase(phrase)),
+            TokenKind::Field { field, value } => Ok(QueryNode::Field { field, value }),
+            TokenKind::LParen => {
+                if self.peek() == Some(&TokenKind::RParen) {
+                    return Err(QuerySyntaxError::at(position, "Parentheses are empty"));
+                }
+                let inner = self.parse_or()?;
+                if self.peek() != Some(&TokenKind::RParen) {
+                    return Err(QuerySyntaxError::at(
+                        position,
+                        "Missing closing parenthesis for '('",
+                    ));
+                }
+                self.position += 1;
+                Ok(QueryNode::Group(Box::new(inner)))
+            }
+            TokenKind::RParen => Err(QuerySyntaxError::at(position, "Unexpected ')'")),
+            TokenKind::And => Err(QuerySyntaxError::at(
+                position,
+                "Expected a search term before AND",
+            )),
+            TokenKind::Or => Err(QuerySyntaxError::at(
+                position,
+                "Expected a search term before OR",
+            )),
+            TokenKind::Not | TokenKind::Minus => {
+                unreachable!("negations are handled by parse_unary")
+            }
+        }
+    }
+}
+
+/// Parses a search query into an AST. Returns None for a query with no terms.
+///
+/// Bare words are separated by whitespace and can be combined with `AND`, `OR`, `NOT` (or a leading `-`) and parentheses.
+/// Double quotes match a phrase, a trailing `*` matches a word prefix, and `field:value` filters on `tag_set`, `link`,
+/// `time_stamp`, or `metadata.<key>`. Field values may be quoted or compared with `>`, `>=`, `<`, or `<=`.
+pub fn parse_query_ast(query: &str) -> Result<Option<QueryNode>, QuerySyntaxError> {
+    let tokens = Lexer::new(query).tokenize()?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        end: query.chars().count(),
+    };
+    let root = parser.parse_or()?;
+    if parser.position < parser.tokens.len() {
+        return Err(QuerySyntaxError::at(
+            parser.current_position(),
+            "Unexpected ')'",
+        ));
+    }
+
+    Ok(Some(root))
+}
+
+fn contains_field(node: &QueryNode) -> bool {
+    match node {
+        QueryNode::Field { .. } => true,
+        QueryNode::And { clauses, .. } | QueryNode::Or(clauses) => {
+            clauses.iter().any(contains_field)
+        }
+        QueryNode::Not(inner) | QueryNode::Group(inner) => contains_field(inner),
+        _ => false,
+    }
+}
+
+fn embedded_terms(node: &QueryNode, terms: &mut Vec<String>) {
+    match node {
+        QueryNode::Term(text) | QueryNode::Phrase(text) | QueryNode::Prefix(text) => {
+            terms.push(text.clone())
+        }
+        QueryNode::And { clauses, .. } | QueryNode::Or(clauses) => clauses
+            .iter()
+            .for_each(|clause| embedded_terms(clause, terms)),
+        QueryNode::Group(inner) => embedded_terms(inner, terms),
+        QueryNode::Field { .. } | QueryNode::Not(_) => {}
+    }
+}
+
+fn conjuncts(node: QueryNode, constrained: bool, out: &mut Vec<(QueryNode, bool)>) {
+    match node {
+        QueryNode::And { clauses, explicit } => clauses
+            .into_iter()
+            .for_each(|clause| conjuncts(clause, constrained || explicit, out)),
+        QueryNode::Group(inner) => conjuncts(*inner, true, out),
+        node => out.push((node, constrained)),
+    }
+}
+
+fn text_filter(node: QueryNode) -> TextFilter {
+    match node {
+        QueryNode::Term(text) | QueryNode::Phrase(text) => TextFilter::Contains(text),
+        QueryNode::Prefix(prefix) => TextFilter::Prefix(prefix),
+        QueryNode::And { clauses, .. } => {
+            TextFilter::And(clauses.into_iter().map(text_filter).collect())
+        }
+        QueryNode::Or(clauses) => TextFilter::Or(clauses.into_iter().map(text_filter).collect()),
+        QueryNode::Not(inner) => TextFilter::Not(Box::new(text_filter(*inner))),
+        QueryNode::Group(inner) => text_filter(*inner),
+        QueryNode::Field { .. } => unreachable!("field filters are compiled separately"),
+    }
+}
+
+/// Flattens a field filter or an OR of field filters into the conditions it is made of.
+fn field_alternatives(node: QueryNode) -> Result<Vec<FieldCondition>, QuerySyntaxError> {
+    match node {
+        QueryNode::Field { field, value } => Ok(vec![field_condition(field, value)?]),
+        QueryNode::Or(clauses) => Ok(clauses
+            .into_iter()
+            .map(field_alternatives)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect()),
+        QueryNode::Group(inner) => field_alternatives(*inner),
+        _ => Err(QuerySyntaxError::new(
+            "Field filters can only be combined with OR when every alternative is a field filter",
+        )),
+    }
+}
+
+/// Returns the start and exclusive end of a time_stamp value as unix seconds. Accepts YYYY, YYYY-MM, YYYY-MM-DD, or a unix time stamp.
+fn time_stamp_bounds(value: &str) -> Result<(f64, f64), QuerySyntaxError> {
+    let start_of_day = |date: NaiveDate| {
+        date.and_hms_opt(0, 0, 0)
+            .expect("Midnight is always valid")
+            .timestamp() as f64
+    };
+    let invalid = || {
+        QuerySyntaxError::new(format!(
+            "Invalid time_stamp value {}. Use YYYY, YYYY-MM, YYYY-MM-DD, or a unix time stamp",
+            value
+        ))
+    };
+
+    if let Ok(time_stamp) = value.parse::<i64>() {
+        if value.len() != 4 {
+            return Ok((time_stamp as f64, (time_stamp + 1) as f64));
+        }
+    }
+
+    let parts = value
+        .split('-')
+        .map(|part| part.parse::<u32>().map_err(|_| invalid()))
+        .collect::<Result<Vec<u32>, _>>()?;
+
+    let (start, end) = match parts.as_slice() {
+        [year] => {
+            let start = NaiveDate::from_ymd_opt(*year as i32, 1, 1).ok_or_else(invalid)?;
+            let end = NaiveDate::from_ymd_opt(*year as i32 + 1, 1, 1).ok_or_else(invalid)?;
+            (start, end)
+        }
+        [year, month] => {
+            let start = NaiveDate::from_ymd_opt(*year as i32, *month, 1).ok_or_else(invalid)?;
+            let end = if start.month() == 12 {
+                NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)
+            } else {
+                NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1)
+            }
+            .ok_or_else(invalid)?;
+            (start, end)
+        }
+        [year, month, day] => {
+            let start = NaiveDate::from_ymd_opt(*year as i32, *month, *day).ok_or_else(invalid)?;
+            let end = start.succ_opt().ok_or_else(invalid)?;
+            (start, end)
+        }
+        _ => return Err(invalid()),
+    };
+
+    Ok((start_of_day(start), start_of_day(end)))
+}
+
+fn range_condition(field: String, range: Range) -> FieldCondition {
+    FieldCondition {
+        field,
+        r#match: None,
+        range: Some(range),
+    }
+}
+
+fn field_condition(field: String, value: FieldValue) -> Result<FieldCondition, QuerySyntaxError> {
+    let empty_range = Range {
+        gte: None,
+        lte: None,
+        gt: None,
+        lt: None,
+    };
+
+    match (field.as_str(), value) {
+        ("time_stamp", FieldValue::Word(value)) | ("time_stamp", FieldValue::Phrase(value)) => {
+            let (start, end) = time_stamp_bounds(&value)?;
+            Ok(range_condition(
+                field,
+                Range {
+                    gte: Some(start),
+                    lt: Some(end),
+                    ..empty_range
+                },
+            ))
+        }
+        ("time_stamp", FieldValue::Range(operator, value)) => {
+            let (start, end) = time_stamp_bounds(&value)?;
+            let range = match operator {
+                RangeOperator::Gt => Range {
+                    gte: Some(end),
+                    ..empty_range
+                },
+                RangeOperator::Gte => Range {
+                    gte: Some(start),
+                    ..empty_range
+                },
+                RangeOperator::Lt => Range {
+                    lt: Some(start),
+                    ..empty_range
+                },
+                RangeOperator::Lte => Range {
+                    lt: Some(end),
+                    ..empty_range
+                },
+            };
+            Ok(range_condition(field, range))
+        }
+        (_, FieldValue::Range(operator, value)) => {
+            if !field.starts_with("metadata.") {
+                return Err(QuerySyntaxError::new(format!(
+                    "Comparisons are only supported on metadata and time_stamp fields, not {}",
+                    field
+                )));
+            }
+            let value = value.parse::<f64>().map_err(|_| {
+                QuerySyntaxError::new(format!(
+                    "Expected a number to compare {} with, got {}",
+                    field, value
+                ))
+            })?;
+            let range = match operator {
+                RangeOperator::Gt => Range {
+                    gt: Some(value),
+                    ..empty_range
+                },
+                RangeOperator::Gte => Range {
+                    gte: Some(value),
+                    ..empty_range
+                },
+                RangeOperator::Lt => Range {
+                    lt: Some(value),
+                    ..empty_range
+                },
+                RangeOperator::Lte => Range {
+                    lte: Some(value),
+                    ..empty_range
+                },
+            };
+            Ok(range_condition(field, range))
+        }
+        (_, FieldValue::Word(value)) if field.starts_with("metadata.") => {
+            let r#match = match value.parse::<i64>() {
+                Ok(integer) => MatchCondition::Integer(integer),
+                Err(_) => MatchCondition::Text(value),
+            };
+            Ok(FieldCondition {
+                field,
+                r#match: Some(vec![r#match]),
+                range: None,
+            })
+        }
+        (_, FieldValue::Word(value)) | (_, FieldValue::Phrase(value)) => Ok(FieldCondition {
+            field,
+            r#match: Some(vec![MatchCondition::Text(value)]),
+            range: None,
+        }),
+    }
+}
+
+fn must_filter(condition: FieldCondition) -> ChunkFilter {
+    ChunkFilter {
+        should: None,
+        must: Some(vec![condition]),
+        must_not: None,
+    }
+}
+
+/// Compiles a query AST into the text to embed, the field filters, and the text constraints.
+///
+/// Bare words separated only by whitespace are used for semantic matching and do not have to appear in a chunk.
+/// Phrases, prefixes, and any terms combined with AND, OR, NOT, or parentheses must match the chunk's text.
+/// Negated terms and field filters are never embedded.
+pub fn compile_query(root: QueryNode) -> Result<CompiledQuery, QuerySyntaxError> {
+    let mut terms = vec![];
+    embedded_terms(&root, &mut terms);
+
+    let mut clauses = vec![];
+    conjuncts(root, false, &mut clauses);
+
+    let mut filters = vec![];
+    let mut text_filters = vec![];
+    for (clause, constrained) in clauses {
+        match clause {
+            QueryNode::Term(_) if !constrained => {}
+            QueryNode::Field { field, value } => {
+                filters.push(must_filter(field_condition(field, value)?));
+            }
+            QueryNode::Not(inner) if contains_field(&inner) => {
+                filters.push(ChunkFilter {
+                    should: None,
+                    must: None,
+                    must_not: Some(field_alternatives(*inner).map_err(|_| {
+                        QuerySyntaxError::new(
+                            "NOT can only be applied to a field filter or an OR of field filters",
+                        )
+                    })?),
+                });
+            }
+            QueryNode::Or(alternatives) if alternatives.iter().any(contains_field) => {
+                filters.push(ChunkFilter {
+                    should: Some(field_alternatives(QueryNode::Or(alternatives))?),
+                    must: None,
+                    must_not: None,
+                });
+            }
+            clause if contains_field(&clause) => {
+                return Err(QuerySyntaxError::new(
+                    "Field filters can only be combined with search terms using AND",
+                ));
+            }
+            clause => text_filters.push(text_filter(clause)),
+        }
+    }
+
+    let text_filter = match text_filters.len() {
+        0 => None,
+        1 => text_filters.pop(),
+        _ => Some(TextFilter::And(text_filters)),
+    };
+
+    Ok(CompiledQuery {
+        text: terms.join(" "),
+        filters,
+        text_filter,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn compile(query: &str) -> CompiledQuery {
+        compile_query(parse_query_ast(query).unwrap().unwrap()).unwrap()
+    }
+
+    #[test]
+    pub fn test_bare_words_are_only_embedded() {
+        let compiled = compile("how do cats sleep");
+        assert_eq!(compiled.text, "how do cats sleep");
+        assert!(compiled.filters.is_empty());
+        assert_eq!(compiled.text_filter, None);
+    }
+
+    #[test]
+    pub fn test_negations_are_not_embedded() {
+        let compiled = compile("cats -dogs NOT \"pet food\"");
+        assert_eq!(compiled.text, "cats");
+        assert_eq!(
+            compiled.text_filter,
+            Some(TextFilter::And(vec![
+                TextFilter::Not(Box::new(TextFilter::Contains("dogs".to_string()))),
+                TextFilter::Not(Box::new(TextFilter::Contains("pet food".to_string()))),
+            ]))
+        );
+    }
+
+    #[test]
+    pub fn test_boolean_operators_and_prefixes() {
+        let compiled = compile("(cat* OR \"big dog\") AND vet");
+        assert_eq!(compiled.text, "cat big dog vet");
+        assert_eq!(
+            compiled.text_filter,
+            Some(TextFilter::And(vec![
+                TextFilter::Or(vec![
+                    TextFilter::Prefix("cat".to_string()),
+                    TextFilter::Contains("big dog".to_string()),
+                ]),
+                TextFilter::Contains("vet".to_string()),
+            ]))
+        );
+    }
+
+    #[test]
+    pub fn test_and_only_requires_its_operands() {
+        let compiled = compile("cats sleep AND purr loudly");
+        assert_eq!(compiled.text, "cats sleep purr loudly");
+        assert_eq!(
+            compiled.text_filter,
+            Some(TextFilter::And(vec![
+                TextFilter::Contains("sleep".to_string()),
+                TextFilter::Contains("purr".to_string()),
+            ]))
+        );
+    }
+
+    #[test]
+    pub fn test_field_filters_compile_to_chunk_filters() {
+        let compiled = compile(
+            "tariffs tag_set:news -link:\"https://example.com\" (metadata.year:>=2020 OR time_stamp:2024-02)",
+        );
+        assert_eq!(compiled.text, "tariffs");
+        assert_eq!(compiled.filters.len(), 3);
+
+        let tag_set = &compiled.filters[0].must.as_ref().unwrap()[0];
+        assert_eq!(tag_set.field, "tag_set");
+        assert_eq!(tag_set.r#match.as_ref().unwrap()[0].to_string(), "news");
+
+        let link = &compiled.filters[1].must_not.as_ref().unwrap()[0];
+        assert_eq!(
+            link.r#match.as_ref().unwrap()[0].to_string(),
+            "https://example.com"
+        );
+
+        let should = compiled.filters[2].should.as_ref().unwrap();
+        assert_eq!(should[0].range.as_ref().unwrap().gte, Some(2020.0));
+        let february = should[1].range.as_ref().unwrap();
+        assert_eq!(february.gte, Some(1706745600.0));
+        assert_eq!(february.lt, Some(1709251200.0));
+    }
+
+    #[test]
+    pub fn test_words_with_colons_are_not_fields() {
+        let compiled = compile("meet at 10:30 on https://example.com");
+        assert_eq!(compiled.text, "meet at 10:30 on https://example.com");
+        assert!(compiled.filters.is_empty());
+    }
+
+    #[test]
+    pub fn test_rejects_malformed_queries() {
+        let error = |query: &str| parse_query_ast(query).unwrap_err();
+
+        assert_eq!(error("\"unterminated").position, Some(1));
+        assert_eq!(error("(cats OR dogs").position, Some(1));
+        assert_eq!(error("cats OR").position, Some(8));
+        assert_eq!(error("cats )").position, Some(6));
+        assert_eq!(
+            error("AND cats").message,
+            "Expected a search term before AND"
+        );
+        assert_eq!(error("tag_set:").message, "Expected a value after tag_set:");
+        assert!(parse_query_ast("()").is_err());
+        assert!(compile_query(parse_query_ast("cats OR tag_set:news").unwrap().unwrap()).is_err());
+        assert!(compile_query(parse_query_ast("link:>5").unwrap().unwrap()).is_err());
+    }
+}
@@ -1,38 +1,52 @@
 use super::chunk_operator::{
-    find_relevant_sentence, get_metadata_and_collided_chunks_from_point_ids_query,
-    get_metadata_from_point_ids,
+    get_metadata_and_collided_chunks_from_point_ids_query, get_metadata_from_id_query,
+    get_metadata_from_point_ids, get_metadata_from_tracking_id_query,
 };
+use super::facet_operator::FacetResult;
+use super::full_text_operator::{
+    get_full_text_vector, search_over_groups_postgres_full_text_query,
+    search_postgres_full_text_query,
+};
+use super::highlight_operator::Highlighter;
+use super::metrics_operator::start_qdrant_timer;
 use super::model_operator::{create_embeddings, cross_encoder};
 use super::qdrant_operator::{
-    get_point_count_qdrant_query, search_over_groups_query, GroupSearchResults, VectorType,
+    get_point_count_qdrant_query, get_qdrant_point_vector_query, search_over_groups_query,
+    GroupSearchResults, VectorType,
 };
+use super::query_operator::TextFilter;
 use crate::data::models::{
-    ChunkFileWithName, ChunkGroup, ChunkMetadataWithFileData, Dataset, FullTextSearchResult,
-    ServerDatasetConfiguration,
+    ChunkFileWithName, ChunkGroup, ChunkMetadataWithFileData, Dataset, DecayFunction,
+    FullTextSearchResult, RecencyDecay, ServerDatasetConfiguration,
 };
 use crate::errors::ServiceError;
 use crate::handlers::chunk_handler::{
-    ChunkFilter, MatchCondition, ParsedQuery, ScoreChunkDTO, SearchChunkData,
-    SearchChunkQueryResponseBody,
+    ChunkFilter, FusionStrategy, MatchCondition, ParsedQuery, RetrievalSource, ScoreChunkDTO,
+    ScoreExplanation, ScoreNormalization, SearchChunkData, SearchChunkQueryResponseBody,
 };
 use crate::handlers::group_handler::{
-    SearchGroupsResult, SearchOverGroupsData, SearchWithinGroupData,
+    GroupScoreOptions, SearchGroupsResult, SearchOverGroupsData, SearchWithinGroupData,
 };
-use crate::operators::model_operator::get_splade_embedding;
 use crate::operators::qdrant_operator::{get_qdrant_connection, search_qdrant_query};
 use crate::{data::models::Pool, errors::DefaultError};
 use actix_web::web;
-use itertools::Itertools;
+use base64::{
+    alphabet,
+    engine::{self, general_purpose},
+    Engine as _,
+};
+use dateparser::DateTimeUtc;
+use itertools::{EitherOrBoth, Itertools};
 use simple_server_timing_header::Timer;
 use utoipa::ToSchema;
 
-use qdrant_client::qdrant::condition::ConditionOneOf::HasId;
+use qdrant_client::qdrant::condition::ConditionOneOf::{self, HasId};
 use qdrant_client::qdrant::{
     point_id::PointIdOptions, Condition, HasIdCondition, PointId, SearchPoints,
 };
 use qdrant_client::qdrant::{Filter, Range};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SearchResult {
@@ -44,143 +58,414 @@ pub struct SearchResult {
 pub struct SearchChunkQueryResult {
     pub search_results: Vec<SearchResult>,
     pub total_chunk_pages: i64,
+    pub next_cursor: Option<SearchCursor>,
 }
 
-#[allow(clippy::too_many_arguments)]
-#[tracing::instrument(skip(pool))]
-pub async fn assemble_qdrant_filter(
-    filters: Option<ChunkFilter>,
-    quote_words: Option<Vec<String>>,
-    negated_words: Option<Vec<String>>,
-    dataset_id: uuid::Uuid,
-    pool: Option<web::Data<Pool>>,
-) -> Result<Filter, DefaultError> {
-    let mut filter = Filter::default();
+/// A search result which has a stable identity and a score. Used to fuse hybrid result lists and to walk search results with a cursor.
+pub trait RankedResult: Clone {
+    fn rank_key(&self) -> uuid::Uuid;
+    fn rank_score(&self) -> f64;
+    fn set_rank_score(&mut self, score: f64);
+}
 
-    filter
-        .must
-        .push(Condition::matches("dataset_id", dataset_id.to_string()));
-    //TODO: fix this after new qdrant rust client gets released
+impl RankedResult for ScoreChunkDTO {
+    fn rank_key(&self) -> uuid::Uuid {
+        self.metadata[0].id
+    }
 
-    if let Some(filters) = filters {
-        if let Some(should_filters) = filters.should {
-            for should_filter in should_filters {
-                if let Some(r#match) = should_filter.r#match {
-                    if r#match.first().is_none() {
-                        return Err(DefaultError {
-                            message: "Must pass a match value for should filter",
-                        });
-                    }
+    fn rank_score(&self) -> f64 {
+        self.score
+    }
 
-                    if let MatchCondition::Text(_) = r#match.first().unwrap() {
-                        filter.should.push(Condition::matches(
-                            should_filter.field.as_str(),
-                            r#match.iter().map(|x| x.to_string()).collect_vec(),
-                        ));
-                    }
+    fn set_rank_score(&mut self, score: f64) {
+        self.score = score;
+    }
+}
 
-                    if let MatchCondition::Integer(_) = r#match.first().unwrap() {
-                        filter.should.push(Condition::matches(
-                            should_filter.field.as_str(),
-                            r#match.iter().map(|x| x.to_i64()).collect_vec(),
-                        ));
-                    }
+impl RankedResult for GroupScoreChunkDTO {
+    fn rank_key(&self) -> uuid::Uuid {
+        self.group_id
+    }
+
+    fn rank_score(&self) -> f64 {
+        self.group_score.unwrap_or_else(|| {
+            self.metadata
+                .first()
+                .map(|chunk| chunk.score)
+                .unwrap_or(0.0)
+        })
+    }
+
+    fn set_rank_score(&mut self, score: f64) {
+        match self.group_score.as_mut() {
+            Some(group_score) => *group_score = score,
+            None => {
+                if let Some(chunk) = self.metadata.first_mut() {
+                    chunk.score = score;
+                }
+            }
+        }
+    }
+}
+
+impl RankedResult for SearchResult {
+    fn rank_key(&self) -> uuid::Uuid {
+        self.point_id
+    }
+
+    fn rank_score(&self) -> f64 {
+        self.score.into()
+    }
+
+    fn set_rank_score(&mut self, score: f64) {
+        self.score = score as f32;
+    }
+}
+
+impl RankedResult for GroupSearchResults {
+    fn rank_key(&self) -> uuid::Uuid {
+        self.group_id
+    }
+
+    fn rank_score(&self) -> f64 {
+        self.hits.first().map(|hit| hit.score.into()).unwrap_or(0.0)
+    }
+
+    fn set_rank_score(&mut self, score: f64) {
+        if let Some(hit) = self.hits.first_mut() {
+            hit.score = score as f32;
+        }
+    }
+}
+
+/// Sorts by score descending and breaks ties by key so that rankings are stable across requests.
+pub fn sort_ranked_results<T: RankedResult>(results: &mut [T]) {
+    results.sort_by(|a, b| {
+        b.rank_score()
+            .total_cmp(&a.rank_score())
+            .then_with(|| a.rank_key().cmp(&b.rank_key()))
+    });
+}
+
+/// Number of results re-fetched before the cursor position so that results with tied scores are not skipped when Qdrant orders them differently between requests.
+const CURSOR_TIE_WINDOW: u64 = 10;
+
+/// Position of the last result returned for a search. Serialized as an opaque url-safe string in search responses and accepted back in the `cursor` field of the next request.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchCursor {
+    pub offset: u64,
+    pub score: f64,
+    pub id: uuid::Uuid,
+    /// Depth each retriever was read to for a ranking which is computed over a fixed window, such as a fused hybrid ranking. Every page of the search reads the same depth so that it slices the same ranking.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<u64>,
+}
+
+impl SearchCursor {
+    pub fn after<T: RankedResult>(offset: u64, last_result: &T) -> Self {
+        SearchCursor {
+            offset,
+            score: last_result.rank_score(),
+            id: last_result.rank_key(),
+            window: None,
+        }
+    }
+
+    pub fn with_window(self, window: u64) -> Self {
+        SearchCursor {
+            window: Some(window),
+            ..self
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let base64_engine =
+            engine::GeneralPurpose::new(&alphabet::URL_SAFE, general_purpose::NO_PAD);
+        base64_engine.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, ServiceError> {
+        let base64_engine =
+            engine::GeneralPurpose::new(&alphabet::URL_SAFE, general_purpose::NO_PAD);
+        let decoded_cursor = base64_engine
+            .decode(cursor)
+            .map_err(|_| ServiceError::BadRequest("Invalid search cursor".to_string()))?;
+
+        serde_json::from_slice(&decoded_cursor)
+            .map_err(|_| ServiceError::BadRequest("Invalid search cursor".to_string()))
+    }
+
+    /// True if the result sorts strictly after the last result of the previous page.
+    pub fn precedes<T: RankedResult>(&self, result: &T) -> bool {
+        let score = result.rank_score();
+        score < self.score || (score == self.score && result.rank_key() > self.id)
+    }
+}
+
+pub fn decode_search_cursor(cursor: &Option<String>) -> Result<Option<SearchCursor>, ServiceError> {
+    cursor.as_deref().map(SearchCursor::decode).transpose()
+}
+
+/// Smallest depth each retriever is read to for a fused ranking.
+const FUSED_RANKING_WINDOW: u64 = 100;
+/// Deepest fused ranking a page or cursor can ask for.
+const MAX_FUSED_RANKING_WINDOW: u64 = 1000;
+
+/// Returns how deep each retriever is read for a ranking which is built from the first result, such as one fused from several retrievers or one reordered by search rules. The first page picks a window which covers it and cursors pin that window, so fused scores do not shift between pages and every page costs the same. Pages past the window are rejected.
+pub fn get_fused_ranking_window(
+    page: u64,
+    limit: u64,
+    cursor: &Option<SearchCursor>,
+) -> Result<u64, ServiceError> {
+    let needed = get_rank_offset(page, limit, cursor) + limit;
+    let window = match cursor.as_ref().and_then(|cursor| cursor.window) {
+        Some(window) => window,
+        None => needed.max(FUSED_RANKING_WINDOW),
+    };
+
+    if window > MAX_FUSED_RANKING_WINDOW {
+        return Err(ServiceError::BadRequest(format!(
+            "Hybrid searches and searches with matching search rules can only page through their top {} results",
+            MAX_FUSED_RANKING_WINDOW
+        )));
+    }
+
+    Ok(window)
+}
+
+/// Number of pages a fused ranking can be sliced into.
+pub fn get_fused_total_pages(ranking_len: usize, limit: u64) -> i64 {
+    ((ranking_len as u64 + limit - 1) / limit.max(1)) as i64
+}
+
+/// Returns the number of results which come before the first result of a page.
+pub fn get_rank_offset(page: u64, limit: u64, cursor: &Option<SearchCursor>) -> u64 {
+    match cursor {
+        Some(cursor) => cursor.offset,
+        None => (page.max(1) - 1) * limit,
+    }
+}
+
+/// Slices one page out of a ranking which starts at the first result, such as a fused hybrid or diversified ranking, and computes the cursor for the following page.
+pub fn page_fused_results<T: RankedResult>(
+    results: Vec<T>,
+    page: u64,
+    limit: u64,
+    cursor: &Option<SearchCursor>,
+    retrievers_have_more: bool,
+) -> (Vec<T>, Option<SearchCursor>) {
+    let page_offset = get_rank_offset(page, limit, cursor);
+    let has_more = retrievers_have_more || results.len() as u64 > page_offset + limit;
+
+    let page_results = results
+        .into_iter()
+        .skip(page_offset as usize)
+        .take(limit as usize)
+        .collect_vec();
+
+    let next_cursor = match page_results.last() {
+        Some(last_result) if has_more => Some(SearchCursor::after(
+            page_offset + page_results.len() as u64,
+            last_result,
+        )),
+        _ => None,
+    };
+
+    (page_results, next_cursor)
+}
+
+/// Returns the offset and limit to request from Qdrant for a page. One extra result is always requested to tell whether another page exists. With a cursor, the window also starts a few results before the cursor position to account for ties.
+pub fn get_cursor_window(page: u64, limit: u64, cursor: &Option<SearchCursor>) -> (u64, u64) {
+    match cursor {
+        Some(cursor) => {
+            let start = cursor.offset.saturating_sub(CURSOR_TIE_WINDOW);
+            (start, limit + (cursor.offset - start) + CURSOR_TIE_WINDOW)
+        }
+        None => ((page.max(1) - 1) * limit, limit + 1),
+    }
+}
+
+/// Orders a window of results deterministically, drops everything already returned before the cursor, and computes the cursor for the following page.
+pub fn page_ranked_results<T: RankedResult>(
+    mut results: Vec<T>,
+    window_offset: u64,
+    limit: u64,
+    cursor: &Option<SearchCursor>,
+) -> (Vec<T>, Option<SearchCursor>) {
+    sort_ranked_results(&mut results);
+
+    let (page_offset, mut page_results) = match cursor {
+        Some(cursor) => (
+            cursor.offset,
+            results
+                .into_iter()
+                .filter(|result| cursor.precedes(result))
+                .collect_vec(),
+        ),
+        None => (window_offset, results),
+    };
+    let has_more = page_results.len() as u64 > limit;
+    page_results.truncate(limit as usize);
+
+    let next_cursor = match page_results.last() {
+        Some(last_result) if has_more => Some(SearchCursor::after(
+            page_offset + page_results.len() as u64,
+            last_result,
+        )),
+        _ => None,
+    };
+
+    (page_results, next_cursor)
+}
+
+fn apply_chunk_filter(filter: &mut Filter, filters: ChunkFilter) -> Result<(), DefaultError> {
+    if let Some(should_filters) = filters.should {
+        for should_filter in should_filters {
+            if let Some(r#match) = should_filter.r#match {
+                if r#match.first().is_none() {
+                    return Err(DefaultError {
+                        message: "Must pass a match value for should filter",
+                    });
+                }
+
+                if let MatchCondition::Text(_) = r#match.first().unwrap() {
+                    filter.should.push(Condition::matches(
+                        should_filter.field.as_str(),
+                        r#match.iter().map(|x| x.to_string()).collect_vec(),
+                    ));
                 }
-                if let Some(range) = should_filter.range {
-                    filter.should.push(Condition::range(
+
+                if let MatchCondition::Integer(_) = r#match.first().unwrap() {
+                    filter.should.push(Condition::matches(
                         should_filter.field.as_str(),
-                        Range {
-                            gt: range.gt,
-                            gte: range.gte,
-                            lt: range.lt,
-                            lte: range.lte,
-                        },
+                        r#match.iter().map(|x| x.to_i64()).collect_vec(),
                     ));
                 }
             }
+            if let Some(range) = should_filter.range {
+                filter.should.push(Condition::range(
+                    should_filter.field.as_str(),
+                    Range {
+                        gt: range.gt,
+                        gte: range.gte,
+                        lt: range.lt,
+                        lte: range.lte,
+                    },
+                ));
+            }
         }
+    }
 
-        if let Some(must_filters) = filters.must {
-            for must_filter in must_filters {
-                if let Some(r#match) = must_filter.r#match {
-                    if r#match.first().is_none() {
-                        return Err(DefaultError {
-                            message: "Must pass a match value for should filter",
-                        });
-                    }
-
-                    if let MatchCondition::Text(_) = r#match.first().unwrap() {
-                        filter.must.push(Condition::matches(
-                            must_filter.field.as_str(),
-                            r#match.iter().map(|x| x.to_string()).collect_vec(),
-                        ));
-                    }
+    if let Some(must_filters) = filters.must {
+        for must_filter in must_filters {
+            if let Some(r#match) = must_filter.r#match {
+                if r#match.first().is_none() {
+                    return Err(DefaultError {
+                        message: "Must pass a match value for should filter",
+                    });
+                }
 
-                    if let MatchCondition::Integer(_) = r#match.first().unwrap() {
-                        filter.must.push(Condition::matches(
-                            must_filter.field.as_str(),
-                            r#match.iter().map(|x| x.to_i64()).collect_vec(),
-                        ));
-                    }
+                if let MatchCondition::Text(_) = r#match.first().unwrap() {
+                    filter.must.push(Condition::matches(
+                        must_filter.field.as_str(),
+                        r#match.iter().map(|x| x.to_string()).collect_vec(),
+                    ));
                 }
-                if let Some(range) = must_filter.range {
-                    filter.must.push(Condition::range(
+
+                if let MatchCondition::Integer(_) = r#match.first().unwrap() {
+                    filter.must.push(Condition::matches(
                         must_filter.field.as_str(),
-                        Range {
-                            gt: range.gt,
-                            gte: range.gte,
-                            lt: range.lt,
-                            lte: range.lte,
-                        },
+                        r#match.iter().map(|x| x.to_i64()).collect_vec(),
                     ));
                 }
             }
+            if let Some(range) = must_filter.range {
+                filter.must.push(Condition::range(
+                    must_filter.field.as_str(),
+                    Range {
+                        gt: range.gt,
+                        gte: range.gte,
+                        lt: range.lt,
+                        lte: range.lte,
+                    },
+                ));
+            }
         }
-        if let Some(must_not_filters) = filters.must_not {
-            for must_not_filter in must_not_filters {
-                if let Some(r#match) = must_not_filter.r#match {
+    }
+    if let Some(must_not_filters) = filters.must_not {
+        for must_not_filter in must_not_filters {
+            if let Some(r#match) = must_not_filter.r#match {
+                filter.must_not.push(Condition::matches(
+                    must_not_filter.field.as_str(),
+                    r#match.iter().map(|x| x.to_string()).collect_vec(),
+                ));
+
+                if let MatchCondition::Text(_) = r#match.first().unwrap() {
                     filter.must_not.push(Condition::matches(
                         must_not_filter.field.as_str(),
                         r#match.iter().map(|x| x.to_string()).collect_vec(),
                     ));
-
-                    if let MatchCondition::Text(_) = r#match.first().unwrap() {
-                        filter.must_not.push(Condition::matches(
-                            must_not_filter.field.as_str(),
-                            r#match.iter().map(|x| x.to_string()).collect_vec(),
-                        ));
-                    }
-
-                    if let MatchCondition::Integer(_) = r#match.first().unwrap() {
-                        filter.must_not.push(Condition::matches(
-                            must_not_filter.field.as_str(),
-                            r#match.iter().map(|x| x.to_i64()).collect_vec(),
-                        ));
-                    }
                 }
-                if let Some(range) = must_not_filter.range {
-                    filter.must_not.push(Condition::range(
+
+                if let MatchCondition::Integer(_) = r#match.first().unwrap() {
+                    filter.must_not.push(Condition::matches(
                         must_not_filter.field.as_str(),
-                        Range {
-                            gt: range.gt,
-                            gte: range.gte,
-                            lt: range.lt,
-                            lte: range.lte,
-                        },
+                        r#match.iter().map(|x| x.to_i64()).collect_vec(),
                     ));
                 }
             }
+            if let Some(range) = must_not_filter.range {
+                filter.must_not.push(Condition::range(
+                    must_not_filter.field.as_str(),
+                    Range {
+                        gt: range.gt,
+                        gte: range.gte,
+                        lt: range.lt,
+                        lte: range.lte,
+                    },
+                ));
+            }
         }
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn assemble_qdrant_filter(
+    filters: Option<ChunkFilter>,
+    parsed_query: Option<ParsedQuery>,
+    dataset_id: uuid::Uuid,
+    pool: Option<web::Data<Pool>>,
+) -> Result<Filter, DefaultError> {
+    let mut filter = Filter::default();
+
+    filter
+        .must
+        .push(Condition::matches("dataset_id", dataset_id.to_string()));
+    //TODO: fix this after new qdrant rust client gets released
+
+    if let Some(filters) = filters {
+        apply_chunk_filter(&mut filter, filters)?;
+    }
+
+    let (query_filters, text_filter) = match parsed_query {
+        Some(parsed_query) => (parsed_query.filters, parsed_query.text_filter),
+        None => (vec![], None),
     };
 
-    if (quote_words.is_some() || negated_words.is_some()) && pool.is_some() {
-        let available_qdrant_ids = get_qdrant_point_ids_from_pg_for_quote_negated_words(
-            quote_words,
-            negated_words,
-            dataset_id,
-            pool.unwrap(),
-        )
-        .await?;
+    // Each filter compiled from the query gets its own nested filter so that its should conditions are not merged with the request's
+    for query_filter in query_filters {
+        let mut nested_filter = Filter::default();
+        apply_chunk_filter(&mut nested_filter, query_filter)?;
+        filter.must.push(Condition {
+            condition_one_of: Some(ConditionOneOf::Filter(nested_filter)),
+        });
+    }
+
+    if let (Some(text_filter), Some(pool)) = (text_filter, pool) {
+        let available_qdrant_ids =
+            get_qdrant_point_ids_from_pg_for_text_filter(text_filter, dataset_id, pool).await?;
 
         let available_point_ids = available_qdrant_ids
             .iter()
@@ -205,6 +490,7 @@ pub async fn assemble_qdrant_filter(
 pub async fn retrieve_qdrant_points_query(
     vector: VectorType,
     page: u64,
+    cursor: Option<SearchCursor>,
     limit: u64,
     score_threshold: Option<f32>,
     filters: Option<ChunkFilter>,
@@ -230,45 +516,66 @@ pub async fn retrieve_qdrant_points_query(
 
     let page = if page == 0 { 1 } else { page };
 
-    let filter = assemble_qdrant_filter(
-        filters,
-        parsed_query.quote_words,
-        parsed_query.negated_words,
-        dataset_id,
-        Some(pool),
-    )
-    .await?;
+    let filter =
+        assemble_qdrant_filter(filters, Some(parsed_query), dataset_id, Some(pool.clone())).await?;
+
+    let (window_offset, window_limit) = get_cursor_window(page, limit, &cursor);
+
+    let (point_ids, count) = match vector {
+        VectorType::FullText(query) => {
+            search_postgres_full_text_query(
+                query,
+                window_offset,
+                window_limit,
+                score_threshold,
+                filter,
+                dataset_id,
+                pool,
+                config,
+            )
+            .await?
+        }
+        vector => {
+            let point_ids_future = search_qdrant_query(
+                window_offset,
+                filter.clone(),
+                window_limit,
+                score_threshold,
+                vector,
+                config.clone(),
+            );
 
-    let point_ids_future = search_qdrant_query(
-        page,
-        filter.clone(),
-        limit,
-        score_threshold,
-        vector,
-        config.clone(),
-    );
+            let count_future = get_point_count_qdrant_query(filter, config);
 
-    let count_future = get_point_count_qdrant_query(filter, config);
+            let (point_ids, count) = futures::join!(point_ids_future, count_future);
+
+            let count = count.map_err(|e| {
+                log::error!("Failed to get search count from Qdrant {:?}", e);
+                DefaultError {
+                    message: "Failed to get point count from Qdrant",
+                }
+            })?;
 
-    let (point_ids, count) = futures::join!(point_ids_future, count_future);
+            let point_ids = point_ids.map_err(|e| {
+                log::error!("Failed to get points from Qdrant {:?}", e);
+                DefaultError {
+                    message: "Failed to get points from Qdrant",
+                }
+            })?;
 
-    let pages = (count.map_err(|e| {
-        log::error!("Failed to get search count from Qdrant {:?}", e);
-        DefaultError {
-            message: "Failed to get point count from Qdrant",
+            (point_ids, count)
         }
-    })? as f64
-        / limit as f64)
-        .ceil() as i64;
+    };
+
+    let pages = (count as f64 / limit as f64).ceil() as i64;
+
+    let (search_results, next_cursor) =
+        page_ranked_results(point_ids, window_offset, limit, &cursor);
 
     Ok(SearchChunkQueryResult {
-        search_results: point_ids.map_err(|e| {
-            log::error!("Failed to get points from Qdrant {:?}", e);
-            DefaultError {
-                message: "Failed to get points from Qdrant",
-            }
-        })?,
+        search_results,
         total_chunk_pages: pages,
+        next_cursor,
     })
 }
 
@@ -276,6 +583,7 @@ pub async fn retrieve_qdrant_points_query(
 pub struct SearchOverGroupsQueryResult {
     pub search_results: Vec<GroupSearchResults>,
     pub total_chunk_pages: i64,
+    pub next_cursor: Option<SearchCursor>,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -283,6 +591,7 @@ pub struct SearchOverGroupsQueryResult {
 pub async fn retrieve_group_qdrant_points_query(
     vector: VectorType,
     page: u64,
+    cursor: Option<SearchCursor>,
     filters: Option<ChunkFilter>,
     limit: u32,
     score_threshold: Option<f32>,
@@ -294,46 +603,68 @@ pub async fn retrieve_group_qdrant_points_query(
 ) -> Result<SearchOverGroupsQueryResult, DefaultError> {
     let page = if page == 0 { 1 } else { page };
 
-    let filter = assemble_qdrant_filter(
-        filters,
-        parsed_query.quote_words,
-        parsed_query.negated_words,
-        dataset_id,
-        Some(pool),
-    )
-    .await?;
+    let filter =
+        assemble_qdrant_filter(filters, Some(parsed_query), dataset_id, Some(pool.clone())).await?;
+
+    let (window_offset, window_limit) = get_cursor_window(page, limit.into(), &cursor);
+
+    let (point_ids, count) = match vector {
+        VectorType::FullText(query) => {
+            search_over_groups_postgres_full_text_query(
+                query,
+                window_offset as u32,
+                window_limit as u32,
+                score_threshold,
+                group_size,
+                filter,
+                dataset_id,
+                pool,
+                config,
+            )
+            .await?
+        }
+        vector => {
+            let point_id_future = search_over_groups_query(
+                window_offset as u32,
+                filter.clone(),
+                window_limit as u32,
+                score_threshold,
+                group_size,
+                vector,
+                config.clone(),
+            );
 
-    let point_id_future = search_over_groups_query(
-        page,
-        filter.clone(),
-        limit,
-        score_threshold,
-        group_size,
-        vector,
-        config.clone(),
-    );
+            let count_future = get_point_count_qdrant_query(filter, config);
 
-    let count_future = get_point_count_qdrant_query(filter, config);
+            let (point_ids, count) = futures::join!(point_id_future, count_future);
 
-    let (point_ids, count) = futures::join!(point_id_future, count_future);
+            let count = count.map_err(|e| {
+                log::error!("Failed to get point count from Qdrant {:?}", e);
+                DefaultError {
+                    message: "Failed to get point count from Qdrant",
+                }
+            })?;
+
+            let point_ids = point_ids.map_err(|e| {
+                log::error!("Failed to get point count from Qdrant {:?}", e);
+                DefaultError {
+                    message: "Failed to get point count from Qdrant",
+                }
+            })?;
 
-    let pages = (count.map_err(|e| {
-        log::error!("Failed to get point count from Qdrant {:?}", e);
-        DefaultError {
-            message: "Failed to get point count from Qdrant",
+            (point_ids, count)
         }
-    })? as f64
-        / limit as f64)
-        .ceil() as i64;
+    };
+
+    let pages = (count as f64 / limit as f64).ceil() as i64;
+
+    let (search_results, next_cursor) =
+        page_ranked_results(point_ids, window_offset, limit.into(), &cursor);
 
     Ok(SearchOverGroupsQueryResult {
-        search_results: point_ids.map_err(|e| {
-            log::error!("Failed to get point count from Qdrant {:?}", e);
-            DefaultError {
-                message: "Failed to get point count from Qdrant",
-            }
-        })?,
+        search_results,
         total_chunk_pages: pages,
+        next_cursor,
     })
 }
 
@@ -343,6 +674,8 @@ pub async fn global_unfiltered_top_match_query(
     dataset_id: uuid::Uuid,
     config: ServerDatasetConfiguration,
 ) -> Result<SearchResult, DefaultError> {
+    let _timer = start_qdrant_timer("global_unfiltered_top_match");
+
     let qdrant_collection = config.QDRANT_COLLECTION_NAME;
 
     let qdrant =
@@ -435,49 +768,65 @@ pub async fn search_within_chunk_group_query(
     config: ServerDatasetConfiguration,
 ) -> Result<SearchChunkQueryResult, DefaultError> {
     let page = if page == 0 { 1 } else { page };
-    let mut filter = assemble_qdrant_filter(
-        filters,
-        parsed_query.quote_words,
-        parsed_query.negated_words,
-        dataset_id,
-        Some(pool),
-    )
-    .await?;
+    let mut filter =
+        assemble_qdrant_filter(filters, Some(parsed_query), dataset_id, Some(pool.clone())).await?;
 
     filter
         .must
         .push(Condition::matches("group_ids", group_id.to_string()));
 
-    let point_ids_future = search_qdrant_query(
-        page,
-        filter.clone(),
-        limit,
-        score_threshold,
-        embedding_vector,
-        config.clone(),
-    );
+    let (point_ids, count) = match embedding_vector {
+        VectorType::FullText(query) => {
+            search_postgres_full_text_query(
+                query,
+                (page - 1) * limit,
+                limit,
+                score_threshold,
+                filter,
+                dataset_id,
+                pool,
+                config,
+            )
+            .await?
+        }
+        embedding_vector => {
+            let point_ids_future = search_qdrant_query(
+                (page - 1) * limit,
+                filter.clone(),
+                limit,
+                score_threshold,
+                embedding_vector,
+                config.clone(),
+            );
+
+            let count_future = get_point_count_qdrant_query(filter, config);
 
-    let count_future = get_point_count_qdrant_query(filter, config);
+            let (point_ids, count) = futures::join!(point_ids_future, count_future);
 
-    let (point_ids, count) = futures::join!(point_ids_future, count_future);
+            let count = count.map_err(|e| {
+                log::error!("Failed to get point count from Qdrant {:?}", e);
+                DefaultError {
+                    message: "Failed to get point count from Qdrant",
+                }
+            })?;
+
+            let point_ids = point_ids.map_err(|e| {
+                log::error!("Failed to get point count from Qdrant {:?}", e);
+                DefaultError {
+                    message: "Failed to get point count from Qdrant",
+                }
+            })?;
 
-    let pages = (count.map_err(|e| {
-        log::error!("Failed to get point count from Qdrant {:?}", e);
-        DefaultError {
-            message: "Failed to get point count from Qdrant",
+            (point_ids, count)
         }
-    })? as f64
-        / limit as f64)
-        .ceil() as i64;
+    };
+
+    let pages = (count as f64 / limit as f64).ceil() as i64;
 
     Ok(SearchChunkQueryResult {
-        search_results: point_ids.map_err(|e| {
-            log::error!("Failed to get point count from Qdrant {:?}", e);
-            DefaultError {
-                message: "Failed to get point count from Qdrant",
-            }
-        })?,
+        search_results: point_ids,
         total_chunk_pages: pages,
+        next_cursor: None,
     })
 }
 
@@ -603,11 +952,19 @@ pub async fn retrieve_chunks_from_point_ids_without_collsions(
         .await
         .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
 
+    let highlighter = Highlighter::new(
+        &data.query,
+        data.highlight_results,
+        data.highlight_delimiters.clone(),
+        data.highlight_window,
+        data.highlight_max_snippets,
+    );
+
     let score_chunks: Vec<ScoreChunkDTO> = search_chunk_query_results
         .search_results
         .iter()
         .map(|search_result| {
-            let mut chunk: ChunkMetadataWithFileData = match metadata_chunks
+            let chunk: ChunkMetadataWithFileData = match metadata_chunks
                 .iter()
                 .find(|metadata_chunk| metadata_chunk.qdrant_point_id == search_result.point_id)
             {
@@ -629,25 +986,15 @@ pub async fn retrieve_chunks_from_point_ids_without_collsions(
                     weight: 1.0,
                 },
             };
-            if data.highlight_results.unwrap_or(true) {
-                chunk = find_relevant_sentence(
-                    chunk.clone(),
-                    data.query.clone(),
-                    data.highlight_delimiters.clone().unwrap_or(vec![
-                        ".".to_string(),
-                        "!".to_string(),
-                        "?".to_string(),
-                        "\n".to_string(),
-                        "\t".to_string(),
-                        ",".to_string(),
-                    ]),
-                )
-                .unwrap_or(chunk);
-            }
+            let highlights = highlighter
+                .as_ref()
+                .and_then(|highlighter| highlighter.highlight(&chunk.content));
 
             ScoreChunkDTO {
                 metadata: vec![chunk],
                 score: search_result.score.into(),
+                explain: None,
+                highlights,
             }
         })
         .collect();
@@ -655,6 +1002,12 @@ pub async fn retrieve_chunks_from_point_ids_without_collsions(
     Ok(SearchChunkQueryResponseBody {
         score_chunks,
         total_chunk_pages: search_chunk_query_results.total_chunk_pages,
+        cursor: search_chunk_query_results
+            .next_cursor
+            .map(|cursor| cursor.encode()),
+        facets: None,
+        rewritten_query: None,
+        search_id: None,
     })
 }
 
@@ -662,12 +1015,21 @@ pub async fn retrieve_chunks_from_point_ids_without_collsions(
 pub struct GroupScoreChunkDTO {
     pub group_id: uuid::Uuid,
     pub metadata: Vec<ScoreChunkDTO>,
+    /// The score the group was ranked by when group_score was specified. Otherwise groups are ranked by the score of their first chunk and this is omitted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_score: Option<f64>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct SearchOverGroupsResponseBody {
     pub group_chunks: Vec<GroupScoreChunkDTO>,
     pub total_chunk_pages: i64,
+    /// Opaque cursor for the next page of groups. Pass it back in the cursor field of the request to continue from where this page ended. Null when there are no more results.
+    pub cursor: Option<String>,
+    /// Value counts for each facet requested in the facets field of the request. Null if no facets were requested.
+    pub facets: Option<Vec<FacetResult>>,
+    /// Id of the search in the search log. Send it to /analytics/click and /analytics/feedback so clicks and ratings can be attributed to this search.
+    pub search_id: Option<uuid::Uuid>,
 }
 
 #[tracing::instrument(skip(pool))]
@@ -690,6 +1052,14 @@ pub async fn retrieve_chunks_for_groups(
     .await
     .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
 
+    let highlighter = Highlighter::new(
+        &data.query,
+        data.highlight_results,
+        data.highlight_delimiters.clone(),
+        data.highlight_window,
+        data.highlight_max_snippets,
+    );
+
     let group_chunks: Vec<GroupScoreChunkDTO> = search_over_groups_query_result
         .search_results
         .iter()
@@ -698,7 +1068,7 @@ pub async fn retrieve_chunks_for_groups(
                 .hits
                 .iter()
                 .map(|search_result| {
-                    let mut chunk: ChunkMetadataWithFileData =
+                    let chunk: ChunkMetadataWithFileData =
                         match metadata_chunks.iter().find(|metadata_chunk| {
                             metadata_chunk.qdrant_point_id == search_result.point_id
                         }) {
@@ -721,21 +1091,9 @@ pub async fn retrieve_chunks_for_groups(
                             },
                         };
 
-                    if data.highlight_results.unwrap_or(true) {
-                        chunk = find_relevant_sentence(
-                            chunk.clone(),
-                            data.query.clone(),
-                            data.highlight_delimiters.clone().unwrap_or(vec![
-                                ".".to_string(),
-                                "!".to_string(),
-                                "?".to_string(),
-                                "\n".to_string(),
-                                "\t".to_string(),
-                                ",".to_string(),
-                            ]),
-                        )
-                        .unwrap_or(chunk);
-                    }
+                    let highlights = highlighter
+                        .as_ref()
+                        .and_then(|highlighter| highlighter.highlight(&chunk.content));
 
                     let mut collided_chunks: Vec<ChunkMetadataWithFileData> = collided_chunks
                         .iter()
@@ -748,6 +1106,8 @@ pub async fn retrieve_chunks_for_groups(
                     ScoreChunkDTO {
                         metadata: collided_chunks,
                         score: search_result.score.into(),
+                        explain: None,
+                        highlights,
                     }
                 })
                 .collect_vec();
@@ -755,6 +1115,7 @@ pub async fn retrieve_chunks_for_groups(
             GroupScoreChunkDTO {
                 group_id: group.group_id,
                 metadata: score_chunk,
+                group_score: None,
             }
         })
         .collect_vec();
@@ -762,6 +1123,11 @@ pub async fn retrieve_chunks_for_groups(
     Ok(SearchOverGroupsResponseBody {
         group_chunks,
         total_chunk_pages: search_over_groups_query_result.total_chunk_pages,
+        cursor: search_over_groups_query_result
+            .next_cursor
+            .map(|cursor| cursor.encode()),
+        facets: None,
+        search_id: None,
     })
 }
 
@@ -826,6 +1192,8 @@ pub async fn get_metadata_from_groups(
                     ScoreChunkDTO {
                         metadata: collided_chunks,
                         score: search_result.score.into(),
+                        explain: None,
+                        highlights: None,
                     }
                 })
                 .collect_vec();
@@ -833,6 +1201,7 @@ pub async fn get_metadata_from_groups(
             GroupScoreChunkDTO {
                 group_id: group.group_id,
                 metadata: score_chunk,
+                group_score: None,
             }
         })
         .collect_vec();
@@ -879,11 +1248,19 @@ pub async fn retrieve_chunks_from_point_ids(
     .await
     .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
 
+    let highlighter = Highlighter::new(
+        &data.query,
+        data.highlight_results,
+        data.highlight_delimiters.clone(),
+        data.highlight_window,
+        data.highlight_max_snippets,
+    );
+
     let score_chunks: Vec<ScoreChunkDTO> = search_chunk_query_results
         .search_results
         .iter()
         .map(|search_result| {
-            let mut chunk: ChunkMetadataWithFileData = match metadata_chunks
+            let chunk: ChunkMetadataWithFileData = match metadata_chunks
                 .iter()
                 .find(|metadata_chunk| metadata_chunk.qdrant_point_id == search_result.point_id)
             {
@@ -906,21 +1283,9 @@ pub async fn retrieve_chunks_from_point_ids(
                 },
             };
 
-            if data.highlight_results.unwrap_or(true) {
-                chunk = find_relevant_sentence(
-                    chunk.clone(),
-                    data.query.clone(),
-                    data.highlight_delimiters.clone().unwrap_or(vec![
-                        ".".to_string(),
-                        "!".to_string(),
-                        "?".to_string(),
-                        "\n".to_string(),
-                        "\t".to_string(),
-                        ",".to_string(),
-                    ]),
-                )
-                .unwrap_or(chunk);
-            }
+            let highlights = highlighter
+                .as_ref()
+                .and_then(|highlighter| highlighter.highlight(&chunk.content));
 
             let mut collided_chunks: Vec<ChunkMetadataWithFileData> = collided_chunks
                 .iter()
@@ -933,6 +1298,8 @@ pub async fn retrieve_chunks_from_point_ids(
             ScoreChunkDTO {
                 metadata: collided_chunks,
                 score: search_result.score.into(),
+                explain: None,
+                highlights,
             }
         })
         .collect();
@@ -942,13 +1309,132 @@ pub async fn retrieve_chunks_from_point_ids(
     Ok(SearchChunkQueryResponseBody {
         score_chunks,
         total_chunk_pages: search_chunk_query_results.total_chunk_pages,
+        cursor: search_chunk_query_results
+            .next_cursor
+            .map(|cursor| cursor.encode()),
+        facets: None,
+        rewritten_query: None,
+        search_id: None,
     })
 }
 
+/// A recency decay with every default filled in and the origin parsed.
+#[derive(Debug, Clone)]
+pub struct RecencyDecayCurve {
+    function: DecayFunction,
+    scale_days: f64,
+    origin: chrono::NaiveDateTime,
+    decay: f64,
+    weight: f64,
+}
+
+impl RecencyDecayCurve {
+    /// Uses the decay from the request, then the RECENCY_DECAY of the dataset, then the default curve if date_bias is true.
+    /// Setting date_bias to false turns recency decay off.
+    pub fn resolve(
+        date_bias: Option<bool>,
+        requested: Option<RecencyDecay>,
+        config: &ServerDatasetConfiguration,
+    ) -> Result<Option<Self>, ServiceError> {
+        if date_bias == Some(false) {
+            return Ok(None);
+        }
+
+        if let Some(requested) = requested {
+            return Self::from_decay(requested).map(Some);
+        }
+
+        // The dataset setting is validated when it is saved, so one which still fails here is ignored instead of failing
+        // every search on the dataset
+        let dataset_decay = config.RECENCY_DECAY.clone().and_then(|recency_decay| {
+            Self::from_decay(recency_decay)
+                .map_err(|err| log::error!("Ignoring invalid RECENCY_DECAY of dataset {:?}", err))
+                .ok()
+        });
+
+        match dataset_decay {
+            Some(curve) => Ok(Some(curve)),
+            None if date_bias == Some(true) => Self::from_decay(RecencyDecay {
+                function: None,
+                scale_days: None,
+                origin: None,
+                decay: None,
+                weight: None,
+            })
+            .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Fills in the defaults of a recency decay and checks that each of its settings is in range.
+    pub fn from_decay(recency_decay: RecencyDecay) -> Result<Self, ServiceError> {
+        let scale_days = recency_decay.scale_days.unwrap_or(365.0);
+        if scale_days <= 0.0 {
+            return Err(ServiceError::BadRequest(
+                "recency_decay.scale_days must be greater than 0".to_string(),
+            ));
+        }
+
+        let decay = recency_decay.decay.unwrap_or(0.5);
+        if decay <= 0.0 || decay >= 1.0 {
+            return Err(ServiceError::BadRequest(
+                "recency_decay.decay must be between 0 and 1".to_string(),
+            ));
+        }
+
+        let weight = recency_decay.weight.unwrap_or(0.5);
+        if !(0.0..=1.0).contains(&weight) {
+            return Err(ServiceError::BadRequest(
+                "recency_decay.weight must be between 0 and 1".to_string(),
+            ));
+        }
+
+        let origin = match recency_decay.origin {
+            Some(origin) => origin
+                .parse::<DateTimeUtc>()
+                .map_err(|_| {
+                    ServiceError::BadRequest("Invalid recency_decay.origin format".to_string())
+                })?
+                .0
+                .with_timezone(&chrono::Local)
+                .naive_local(),
+            None => chrono::Local::now().naive_local(),
+        };
+
+        Ok(RecencyDecayCurve {
+            function: recency_decay.function.unwrap_or(DecayFunction::Exponential),
+            scale_days,
+            origin,
+            decay,
+            weight,
+        })
+    }
+
+    /// Returns the factor to multiply the score of a chunk with the given time stamp by.
+    pub fn score_multiplier(&self, time_stamp: chrono::NaiveDateTime) -> f64 {
+        let age_days = (self.origin - time_stamp).num_seconds().abs() as f64 / 86400.0;
+        let distance = age_days / self.scale_days;
+
+        let curve = match self.function {
+            DecayFunction::Exponential => self.decay.powf(distance),
+            DecayFunction::Linear => (1.0 - (1.0 - self.decay) * distance).max(0.0),
+            DecayFunction::Gaussian => self.decay.powf(distance * distance),
+        };
+
+        (1.0 - self.weight) + self.weight * curve
+    }
+
+    /// Lowers a score by the share the multiplier takes away. Negative scores move further below zero instead of towards
+    /// it, so older chunks never rank above newer ones with the same score.
+    pub fn apply(score: f64, multiplier: f64) -> f64 {
+        score - score.abs() * (1.0 - multiplier)
+    }
+}
+
 #[tracing::instrument]
 pub fn rerank_chunks(
     chunks: Vec<ScoreChunkDTO>,
-    date_bias: Option<bool>,
+    recency_decay: Option<&RecencyDecayCurve>,
     use_weights: Option<bool>,
 ) -> Vec<ScoreChunkDTO> {
     let mut reranked_chunks = Vec::new();
@@ -958,43 +1444,451 @@ pub fn rerank_chunks(
                 chunk.metadata[0].weight = 1.0;
             }
             chunk.score *= chunk.metadata[0].weight;
+            if let Some(explanation) = chunk.explain.as_mut() {
+                explanation.weight = Some(chunk.metadata[0].weight);
+            }
             reranked_chunks.push(chunk);
         });
     } else {
         reranked_chunks = chunks;
     }
 
-    if date_bias.is_some() && date_bias.unwrap() {
-        reranked_chunks.sort_by(|a, b| {
-            if let (Some(time_stamp_a), Some(time_stamp_b)) =
-                (a.metadata[0].time_stamp, b.metadata[0].time_stamp)
-            {
-                return time_stamp_b.timestamp().cmp(&time_stamp_a.timestamp());
+    // Chunks without a time stamp keep their score
+    if let Some(recency_decay) = recency_decay {
+        reranked_chunks.iter_mut().for_each(|chunk| {
+            if let Some(time_stamp) = chunk.metadata[0].time_stamp {
+                let multiplier = recency_decay.score_multiplier(time_stamp);
+                chunk.score = RecencyDecayCurve::apply(chunk.score, multiplier);
+                if let Some(explanation) = chunk.explain.as_mut() {
+                    explanation.recency_adjustment = Some(multiplier);
+                }
             }
-            a.score.total_cmp(&b.score)
-        });
-    } else {
-        reranked_chunks.sort_by(|a, b| {
-            b.score
-                .partial_cmp(&a.score)
-                .unwrap_or(std::cmp::Ordering::Equal)
         });
     }
 
+    reranked_chunks.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
     reranked_chunks
 }
 
-#[tracing::instrument(skip(timer, pool))]
+/// Starts an explanation for each chunk of a retrieval list with its raw score and its rank in that list.
+fn explain_retrieval(
+    score_chunks: &mut [ScoreChunkDTO],
+    source: RetrievalSource,
+    rank_offset: u64,
+) {
+    for (index, chunk) in score_chunks.iter_mut().enumerate() {
+        let rank = Some(rank_offset + index as u64 + 1);
+        let mut explanation = ScoreExplanation {
+            retrieved_from: vec![source],
+            ..Default::default()
+        };
+        match source {
+            RetrievalSource::Semantic => {
+                explanation.semantic_score = Some(chunk.score);
+                explanation.semantic_rank = rank;
+            }
+            RetrievalSource::FullText => {
+                explanation.full_text_score = Some(chunk.score);
+                explanation.full_text_rank = rank;
+            }
+        }
+        chunk.explain = Some(explanation);
+    }
+}
+
+/// Records the final rank of each explained chunk on a page.
+pub fn finish_explanations(score_chunks: &mut [ScoreChunkDTO], rank_offset: u64) {
+    for (index, chunk) in score_chunks.iter_mut().enumerate() {
+        if let Some(explanation) = chunk.explain.as_mut() {
+            explanation.final_rank = rank_offset + index as u64 + 1;
+        }
+    }
+}
+
+/// Combines the retrieval scores a chunk picked up in the semantic and full-text lists of a hybrid search.
+fn merge_retrieval_explanations(
+    semantic_results: &[ScoreChunkDTO],
+    full_text_results: &[ScoreChunkDTO],
+) -> HashMap<uuid::Uuid, ScoreExplanation> {
+    let mut explanations: HashMap<uuid::Uuid, ScoreExplanation> = HashMap::new();
+
+    for result in semantic_results.iter().chain(full_text_results.iter()) {
+        if let Some(explanation) = result.explain.as_ref() {
+            let merged = explanations.entry(result.rank_key()).or_default();
+            merged
+                .retrieved_from
+                .extend(explanation.retrieved_from.iter().copied());
+            merged.semantic_score = merged.semantic_score.or(explanation.semantic_score);
+            merged.semantic_rank = merged.semantic_rank.or(explanation.semantic_rank);
+            merged.full_text_score = merged.full_text_score.or(explanation.full_text_score);
+            merged.full_text_rank = merged.full_text_rank.or(explanation.full_text_rank);
+        }
+    }
+
+    explanations
+}
+
+/// Constant used by reciprocal rank fusion to dampen the contribution of top ranks.
+const RRF_K: f64 = 60.0;
+
+fn collect_fused_results<T: RankedResult>(fused: HashMap<uuid::Uuid, (T, f64)>) -> Vec<T> {
+    let mut results = fused
+        .into_values()
+        .map(|(mut result, score)| {
+            result.set_rank_score(score);
+            result
+        })
+        .collect_vec();
+
+    sort_ranked_results(&mut results);
+    results
+}
+
+pub fn reciprocal_rank_fusion<T: RankedResult>(result_lists: Vec<Vec<T>>) -> Vec<T> {
+    let mut fused: HashMap<uuid::Uuid, (T, f64)> = HashMap::new();
+
+    for results in result_lists {
+        for (rank, result) in results.into_iter().enumerate() {
+            let rrf_score = 1.0 / (RRF_K + (rank + 1) as f64);
+            fused
+                .entry(result.rank_key())
+                .and_modify(|(_, score)| *score += rrf_score)
+                .or_insert((result, rrf_score));
+        }
+    }
+
+    collect_fused_results(fused)
+}
+
+pub fn weighted_linear_fusion<T: RankedResult>(
+    semantic_results: Vec<T>,
+    full_text_results: Vec<T>,
+    alpha: f64,
+) -> Vec<T> {
+    let mut fused: HashMap<uuid::Uuid, (T, f64)> = HashMap::new();
+
+    for (results, weight) in [(semantic_results, alpha), (full_text_results, 1.0 - alpha)] {
+        let (min_score, max_score) = results
+            .iter()
+            .map(|result| result.rank_score())
+            .fold((f64::MAX, f64::MIN), |(min, max), score| {
+                (min.min(score), max.max(score))
+            });
+
+        for result in results {
+            let normalized_score = if max_score > min_score {
+                (result.rank_score() - min_score) / (max_score - min_score)
+            } else {
+                1.0
+            };
+            let weighted_score = weight * normalized_score;
+
+            fused
+                .entry(result.rank_key())
+                .and_modify(|(_, score)| *score += weighted_score)
+                .or_insert((result, weighted_score));
+        }
+    }
+
+    collect_fused_results(fused)
+}
+
+/// Rescales the scores of one dataset's results, given in rank order, so that they can be merged with the results of other datasets.
+pub fn normalize_dataset_scores(scores: &[f64], normalization: ScoreNormalization) -> Vec<f64> {
+    match normalization {
+        ScoreNormalization::MinMax => {
+            let (min_score, max_score) = scores
+                .iter()
+                .fold((f64::MAX, f64::MIN), |(min, max), score| {
+                    (min.min(*score), max.max(*score))
+                });
+
+            scores
+                .iter()
+                .map(|score| {
+                    if max_score > min_score {
+                        (score - min_score) / (max_score - min_score)
+                    } else {
+                        1.0
+                    }
+                })
+                .collect()
+        }
+        ScoreNormalization::Rrf => (0..scores.len())
+            .map(|rank| 1.0 / (RRF_K + (rank + 1) as f64))
+            .collect(),
+    }
+}
+
+/// Deepest result of the merged ranking a federated search can page to. Every dataset is searched this deep for the last page.
+const MAX_FEDERATED_RESULTS: u64 = 1000;
+
+/// Returns how many results each dataset of a federated search is searched for, which is enough for any one dataset to fill
+/// the requested page of the merged ranking on its own. Pages past MAX_FEDERATED_RESULTS are rejected.
+pub fn get_federated_candidate_limit(page: u64, limit: u64) -> Result<u64, ServiceError> {
+    let candidate_limit = page.max(1).saturating_mul(limit);
+    if candidate_limit > MAX_FEDERATED_RESULTS {
+        return Err(ServiceError::BadRequest(format!(
+            "Federated search can only page through its top {} results",
+            MAX_FEDERATED_RESULTS
+        )));
+    }
+
+    Ok(candidate_limit)
+}
+
+/// Number of pages of limit results the merged ranking of a federated search can be sliced into, from the page count and
+/// the number of results each dataset returned when searched for candidate_limit results. A dataset which returned fewer
+/// results than that has no more, while the page count of one which filled its candidates bounds how many it has. Results
+/// past MAX_FEDERATED_RESULTS cannot be paged to, so they are not counted.
+pub fn get_federated_total_pages(
+    dataset_totals: impl IntoIterator<Item = (i64, usize)>,
+    candidate_limit: u64,
+    limit: u64,
+) -> i64 {
+    let limit = limit.max(1);
+    let total_results: u64 = dataset_totals
+        .into_iter()
+        .map(|(total_chunk_pages, returned)| {
+            if (returned as u64) < candidate_limit {
+                returned as u64
+            } else {
+                (total_chunk_pages.max(0) as u64).saturating_mul(candidate_limit)
+            }
+        })
+        .sum();
+
+    (total_results.min(MAX_FEDERATED_RESULTS) + limit - 1) as i64 / limit as i64
+}
+
+/// Interleaves both result lists, keeping the first occurrence of each key. This is the candidate set handed to the cross encoder.
+fn interleave_unique_results<T: RankedResult>(
+    semantic_results: &[T],
+    full_text_results: &[T],
+) -> Vec<T> {
+    semantic_results
+        .iter()
+        .zip_longest(full_text_results.iter())
+        .flat_map(|pair| match pair {
+            EitherOrBoth::Both(x, y) => vec![x.clone(), y.clone()],
+            EitherOrBoth::Left(x) | EitherOrBoth::Right(x) => vec![x.clone()],
+        })
+        .unique_by(|result| result.rank_key())
+        .collect_vec()
+}
+
+/// The cross encoder ranking, or the rrf ranking of both result lists if the cross encoder failed. Returns the strategy which produced the ranking.
+fn cross_encoder_or_rrf<T: RankedResult, E: std::fmt::Debug>(
+    cross_encoder_results: Result<Vec<T>, E>,
+    semantic_results: Vec<T>,
+    full_text_results: Vec<T>,
+) -> (Vec<T>, FusionStrategy) {
+    match cross_encoder_results {
+        Ok(cross_encoder_results) => (cross_encoder_results, FusionStrategy::CrossEncoder),
+        Err(err) => {
+            log::error!("Cross encoder failed, falling back to rrf: {:?}", err);
+            (
+                reciprocal_rank_fusion(vec![semantic_results, full_text_results]),
+                FusionStrategy::Rrf,
+            )
+        }
+    }
+}
+
+fn get_fusion_alpha(fusion_alpha: Option<f32>) -> Result<f64, ServiceError> {
+    let alpha = fusion_alpha.unwrap_or(0.5);
+    if !(0.0..=1.0).contains(&alpha) {
+        return Err(ServiceError::BadRequest(
+            "fusion_alpha must be between 0 and 1".to_string(),
+        ));
+    }
+
+    Ok(alpha.into())
+}
+
+#[tracing::instrument(skip(semantic_results, full_text_results))]
+pub async fn fuse_hybrid_chunks(
+    query: String,
+    semantic_results: Vec<ScoreChunkDTO>,
+    full_text_results: Vec<ScoreChunkDTO>,
+    fusion_strategy: Option<FusionStrategy>,
+    fusion_alpha: Option<f32>,
+) -> Result<Vec<ScoreChunkDTO>, actix_web::Error> {
+    let explanations = merge_retrieval_explanations(&semantic_results, &full_text_results);
+
+    let (mut fused_results, applied_strategy) = match FusionStrategy::resolve(fusion_strategy) {
+        FusionStrategy::Rrf => (
+            reciprocal_rank_fusion(vec![semantic_results, full_text_results]),
+            FusionStrategy::Rrf,
+        ),
+        FusionStrategy::WeightedLinear => (
+            weighted_linear_fusion(
+                semantic_results,
+                full_text_results,
+                get_fusion_alpha(fusion_alpha)?,
+            ),
+            FusionStrategy::WeightedLinear,
+        ),
+        FusionStrategy::CrossEncoder => {
+            let candidates = interleave_unique_results(&semantic_results, &full_text_results);
+
+            cross_encoder_or_rrf(
+                cross_encoder(query, candidates.len() as u64, candidates).await,
+                semantic_results,
+                full_text_results,
+            )
+        }
+    };
+
+    for result in fused_results.iter_mut() {
+        if let Some(mut explanation) = explanations.get(&result.rank_key()).cloned() {
+            explanation.fusion_strategy = Some(applied_strategy);
+            match applied_strategy {
+                FusionStrategy::CrossEncoder => {
+                    explanation.cross_encoder_score = Some(result.score)
+                }
+                _ => explanation.fusion_score = Some(result.score),
+            }
+            result.explain = Some(explanation);
+        }
+    }
+
+    Ok(fused_results)
+}
+
+#[tracing::instrument(skip(semantic_results, full_text_results))]
+pub async fn fuse_hybrid_groups(
+    query: String,
+    semantic_results: Vec<GroupScoreChunkDTO>,
+    full_text_results: Vec<GroupScoreChunkDTO>,
+    fusion_strategy: Option<FusionStrategy>,
+    fusion_alpha: Option<f32>,
+    group_score: Option<&GroupScoreOptions>,
+) -> Result<Vec<GroupScoreChunkDTO>, actix_web::Error> {
+    match FusionStrategy::resolve(fusion_strategy) {
+        FusionStrategy::Rrf => Ok(reciprocal_rank_fusion(vec![
+            semantic_results,
+            full_text_results,
+        ])),
+        FusionStrategy::WeightedLinear => Ok(weighted_linear_fusion(
+            semantic_results,
+            full_text_results,
+            get_fusion_alpha(fusion_alpha)?,
+        )),
+        FusionStrategy::CrossEncoder => {
+            let candidates = interleave_unique_results(&semantic_results, &full_text_results);
+
+            let (fused_results, _) = cross_encoder_or_rrf(
+                cross_encoder_for_groups(query, candidates.len() as u64, candidates, group_score)
+                    .await,
+                semantic_results,
+                full_text_results,
+            );
+            Ok(fused_results)
+        }
+    }
+}
+
+/// Resolves the dense vector for a search which brings its own query_vector or uses an existing chunk as the query.
+/// Returns None if the text of the query should be embedded instead.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(query_vector, pool))]
+pub async fn get_query_vector(
+    search_type: &str,
+    parsed_query: &ParsedQuery,
+    query_vector: Option<Vec<f32>>,
+    query_chunk_id: Option<uuid::Uuid>,
+    query_tracking_id: Option<String>,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+    config: &ServerDatasetConfiguration,
+) -> Result<Option<Vec<f32>>, ServiceError> {
+    let query_inputs = [
+        query_vector.is_some(),
+        query_chunk_id.is_some(),
+        query_tracking_id.is_some(),
+    ]
+    .into_iter()
+    .filter(|is_set| *is_set)
+    .count();
+
+    if query_inputs == 0 {
+        parsed_query.ensure_has_terms()?;
+        return Ok(None);
+    }
+    if query_inputs > 1 {
+        return Err(ServiceError::BadRequest(
+            "Only one of query_vector, query_chunk_id, and query_tracking_id can be set".into(),
+        ));
+    }
+    if search_type == "fulltext" {
+        return Err(ServiceError::BadRequest(
+            "query_vector, query_chunk_id, and query_tracking_id cannot be used with fulltext search".into(),
+        ));
+    }
+    if search_type == "hybrid" && parsed_query.query.trim().is_empty() {
+        return Err(ServiceError::BadRequest(
+            "Hybrid search needs a text query for its full-text results. Use semantic search to search with only a vector or chunk".into(),
+        ));
+    }
+
+    let vector = match query_vector {
+        Some(query_vector) => query_vector,
+        None => {
+            let chunk = match query_chunk_id {
+                Some(chunk_id) => get_metadata_from_id_query(chunk_id, dataset_id, pool).await,
+                None => {
+                    get_metadata_from_tracking_id_query(
+                        query_tracking_id.unwrap_or_default(),
+                        dataset_id,
+                        pool,
+                    )
+                    .await
+                }
+            }
+            .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+            let point_id = chunk.qdrant_point_id.ok_or(ServiceError::BadRequest(
+                "The query chunk has not been indexed yet".into(),
+            ))?;
+
+            get_qdrant_point_vector_query(point_id, config.clone())
+                .await
+                .map_err(|err| ServiceError::BadRequest(err.message.into()))?
+        }
+    };
+
+    if vector.len() != config.EMBEDDING_SIZE {
+        return Err(ServiceError::BadRequest(format!(
+            "The query vector has {} dimensions but the dataset uses {} dimensional embeddings",
+            vector.len(),
+            config.EMBEDDING_SIZE
+        )));
+    }
+
+    Ok(Some(vector))
+}
+
+#[tracing::instrument(skip(timer, pool, embedding_vector))]
 pub async fn search_semantic_chunks(
     data: web::Json<SearchChunkData>,
     parsed_query: ParsedQuery,
+    embedding_vector: Option<Vec<f32>>,
     page: u64,
     pool: web::Data<Pool>,
     dataset: Dataset,
     timer: &mut Timer,
     config: ServerDatasetConfiguration,
 ) -> Result<SearchChunkQueryResponseBody, actix_web::Error> {
+    let recency_decay =
+        RecencyDecayCurve::resolve(data.date_bias, data.recency_decay.clone(), &config)?;
     timer.add("Reached semantic_chunks");
+    let cursor = decode_search_cursor(&data.cursor)?;
     let parent_span = sentry::configure_scope(|scope| scope.get_span());
     let transaction: sentry::TransactionOrSpan = match &parent_span {
         Some(parent) => parent
@@ -1007,24 +1901,36 @@ pub async fn search_semantic_chunks(
     };
     sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone())));
 
-    let dataset_config =
-        ServerDatasetConfiguration::from_json(dataset.server_configuration.clone());
+    let embedding_vector = match embedding_vector {
+        Some(embedding_vector) => embedding_vector,
+        None => {
+            let dataset_config =
+                ServerDatasetConfiguration::from_json(dataset.server_configuration.clone());
 
-    let embedding_vectors =
-        create_embeddings(vec![data.query.clone()], "query", dataset_config.clone()).await?;
-    let embedding_vector = embedding_vectors
-        .first()
-        .ok_or(ServiceError::BadRequest(
-            "Failed to get embedding vector due to empty vec response from create_embedding"
-                .to_string(),
-        ))?
-        .clone();
+            let embedding_vectors = create_embeddings(
+                vec![parsed_query.query.clone()],
+                "query",
+                dataset_config.clone(),
+            )
+            .await?;
+            embedding_vectors
+                .first()
+                .ok_or(ServiceError::BadRequest(
+                    "Failed to get embedding vector due to empty vec response from create_embedding"
+                        .to_string(),
+                ))?
+                .clone()
+        }
+    };
 
     timer.add("Created Embedding vector");
 
+    let rank_offset = get_rank_offset(page, data.page_size.unwrap_or(10), &cursor);
+
     let search_chunk_query_results = retrieve_qdrant_points_query(
         VectorType::Dense(embedding_vector),
         page,
+        cursor,
         data.page_size.unwrap_or(10),
         data.score_threshold,
         data.filters.clone(),
@@ -1042,8 +1948,20 @@ pub async fn search_semantic_chunks(
 
     timer.add("Fetch from postgres");
 
-    result_chunks.score_chunks =
-        rerank_chunks(result_chunks.score_chunks, data.date_bias, data.use_weights);
+    if data.explain.unwrap_or(false) {
+        explain_retrieval(
+            &mut result_chunks.score_chunks,
+            RetrievalSource::Semantic,
+            rank_offset,
+        );
+    }
+
+    result_chunks.score_chunks = rerank_chunks(
+        result_chunks.score_chunks,
+        recency_decay.as_ref(),
+        data.use_weights,
+    );
+    finish_explanations(&mut result_chunks.score_chunks, rank_offset);
     timer.add("Rerank (algo)");
     transaction.finish();
 
@@ -1053,12 +1971,14 @@ pub async fn search_semantic_chunks(
 #[tracing::instrument(skip(pool))]
 pub async fn search_full_text_chunks(
     data: web::Json<SearchChunkData>,
-    mut parsed_query: ParsedQuery,
+    parsed_query: ParsedQuery,
     page: u64,
     pool: web::Data<Pool>,
     dataset: Dataset,
     config: ServerDatasetConfiguration,
 ) -> Result<SearchChunkQueryResponseBody, actix_web::Error> {
+    let recency_decay =
+        RecencyDecayCurve::resolve(data.date_bias, data.recency_decay.clone(), &config)?;
     let parent_span = sentry::configure_scope(|scope| scope.get_span());
     let transaction: sentry::TransactionOrSpan = match &parent_span {
         Some(parent) => parent
@@ -1072,19 +1992,18 @@ pub async fn search_full_text_chunks(
     };
     sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone())));
 
-    parsed_query.query = parsed_query
-        .query
-        .split_whitespace()
-        .join(" AND ")
-        .replace('\"', "");
+    let cursor = decode_search_cursor(&data.cursor)?;
 
-    let embedding_vector = get_splade_embedding(&parsed_query.query, "query")
+    let embedding_vector = get_full_text_vector(&parsed_query.query, &config)
         .await
-        .map_err(|_| ServiceError::BadRequest("Failed to get splade query embedding".into()))?;
+        .map_err(|_| ServiceError::BadRequest("Failed to get full-text query vector".into()))?;
+
+    let rank_offset = get_rank_offset(page, data.page_size.unwrap_or(10), &cursor);
 
     let search_chunk_query_results = retrieve_qdrant_points_query(
-        VectorType::Sparse(embedding_vector),
+        embedding_vector,
         page,
+        cursor,
         data.page_size.unwrap_or(10),
         data.score_threshold,
         data.filters.clone(),
@@ -1099,23 +2018,38 @@ pub async fn search_full_text_chunks(
     let mut result_chunks =
         retrieve_chunks_from_point_ids(search_chunk_query_results, &data, pool).await?;
 
-    result_chunks.score_chunks =
-        rerank_chunks(result_chunks.score_chunks, data.date_bias, data.use_weights);
+    if data.explain.unwrap_or(false) {
+        explain_retrieval(
+            &mut result_chunks.score_chunks,
+            RetrievalSource::FullText,
+            rank_offset,
+        );
+    }
+
+    result_chunks.score_chunks = rerank_chunks(
+        result_chunks.score_chunks,
+        recency_decay.as_ref(),
+        data.use_weights,
+    );
+    finish_explanations(&mut result_chunks.score_chunks, rank_offset);
 
     transaction.finish();
     Ok(result_chunks)
 }
 
 #[allow(clippy::too_many_arguments)]
-#[tracing::instrument(skip(pool))]
+#[tracing::instrument(skip(pool, embedding_vector))]
 pub async fn search_hybrid_chunks(
     data: web::Json<SearchChunkData>,
     parsed_query: ParsedQuery,
+    embedding_vector: Option<Vec<f32>>,
     page: u64,
     pool: web::Data<Pool>,
     dataset: Dataset,
     config: ServerDatasetConfiguration,
 ) -> Result<SearchChunkQueryResponseBody, actix_web::Error> {
+    let recency_decay =
+        RecencyDecayCurve::resolve(data.date_bias, data.recency_decay.clone(), &config)?;
     let parent_span = sentry::configure_scope(|scope| scope.get_span());
     let transaction: sentry::TransactionOrSpan = match &parent_span {
         Some(parent) => parent
@@ -1128,23 +2062,37 @@ pub async fn search_hybrid_chunks(
     };
     sentry::configure_scope(|scope| scope.set_span(Some(transaction.clone())));
 
-    let dataset_config =
-        ServerDatasetConfiguration::from_json(dataset.server_configuration.clone());
+    let embedding_vector = match embedding_vector {
+        Some(embedding_vector) => embedding_vector,
+        None => {
+            let dataset_config =
+                ServerDatasetConfiguration::from_json(dataset.server_configuration.clone());
 
-    let embedding_vectors =
-        create_embeddings(vec![data.query.clone()], "query", dataset_config.clone()).await?;
-    let embedding_vector = embedding_vectors
-        .first()
-        .ok_or(ServiceError::BadRequest(
-            "Failed to get embedding vector due to empty vec response from create_embedding"
-                .to_string(),
-        ))?
-        .clone();
+            let embedding_vectors = create_embeddings(
+                vec![parsed_query.query.clone()],
+                "query",
+                dataset_config.clone(),
+            )
+            .await?;
+            embedding_vectors
+                .first()
+                .ok_or(ServiceError::BadRequest(
+                    "Failed to get embedding vector due to empty vec response from create_embedding"
+                        .to_string(),
+                ))?
+                .clone()
+        }
+    };
+
+    let cursor = decode_search_cursor(&data.cursor)?;
+    let limit = data.page_size.unwrap_or(10);
+    let window = get_fused_ranking_window(page, limit, &cursor)?;
 
     let search_chunk_query_results = retrieve_qdrant_points_query(
         VectorType::Dense(embedding_vector),
-        page,
-        data.page_size.unwrap_or(10),
+        1,
+        None,
+        window,
         data.score_threshold,
         data.filters.clone(),
         parsed_query.clone(),
@@ -1153,10 +2101,18 @@ pub async fn search_hybrid_chunks(
         config.clone(),
     );
 
+    // Weights and recency decay are applied once to the fused ranking, not to the full-text list it is fused from
+    let mut full_text_data = data.clone();
+    full_text_data.page_size = Some(window);
+    full_text_data.cursor = None;
+    full_text_data.date_bias = Some(false);
+    full_text_data.recency_decay = None;
+    full_text_data.use_weights = Some(false);
+
     let full_text_handler_results = search_full_text_chunks(
-        web::Json(data.clone()),
-        parsed_query,
-        page,
+        web::Json(full_text_data),
+        parsed_query.clone(),
+        1,
         pool.clone(),
         dataset,
         config,
@@ -1185,11 +2141,19 @@ pub async fn search_hybrid_chunks(
     .await
     .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
 
-    let semantic_score_chunks: Vec<ScoreChunkDTO> = search_chunk_query_results
+    let highlighter = Highlighter::new(
+        &data.query,
+        data.highlight_results,
+        data.highlight_delimiters.clone(),
+        data.highlight_window,
+        data.highlight_max_snippets,
+    );
+
+    let mut semantic_score_chunks: Vec<ScoreChunkDTO> = search_chunk_query_results
         .search_results
         .iter()
         .map(|search_result| {
-            let mut chunk: ChunkMetadataWithFileData = match metadata_chunks
+            let chunk: ChunkMetadataWithFileData = match metadata_chunks
                 .iter()
                 .find(|metadata_chunk| metadata_chunk.qdrant_point_id == search_result.point_id)
             {
@@ -1212,21 +2176,9 @@ pub async fn search_hybrid_chunks(
                 },
             };
 
-            if data.highlight_results.unwrap_or(true) {
-                chunk = find_relevant_sentence(
-                    chunk.clone(),
-                    data.query.clone(),
-                    data.highlight_delimiters.clone().unwrap_or(vec![
-                        ".".to_string(),
-                        "!".to_string(),
-                        "?".to_string(),
-                        "\n".to_string(),
-                        "\t".to_string(),
-                        ",".to_string(),
-                    ]),
-                )
-                .unwrap_or(chunk);
-            }
+            let highlights = highlighter
+                .as_ref()
+                .and_then(|highlighter| highlighter.highlight(&chunk.content));
 
             let mut collided_chunks: Vec<ChunkMetadataWithFileData> = collided_chunks
                 .iter()
@@ -1238,59 +2190,41 @@ pub async fn search_hybrid_chunks(
 
             ScoreChunkDTO {
                 metadata: collided_chunks,
-                score: search_result.score as f64 * 0.5,
+                score: search_result.score as f64,
+                explain: None,
+                highlights,
             }
         })
         .collect();
 
-    let result_chunks = {
-        let combined_results = semantic_score_chunks
-            .iter()
-            .zip(full_text_handler_results.score_chunks.iter())
-            .flat_map(|(x, y)| vec![x.clone(), y.clone()])
-            .unique_by(|score_chunk| score_chunk.metadata[0].id)
-            .collect::<Vec<ScoreChunkDTO>>();
-
-        let mut reranked_chunks = if combined_results.len() > 20 {
-            let split_results = combined_results
-                .chunks(20)
-                .map(|chunk| chunk.to_vec())
-                .collect::<Vec<Vec<ScoreChunkDTO>>>();
-
-            let cross_encoder_results = cross_encoder(
-                data.query.clone(),
-                data.page_size.unwrap_or(10),
-                split_results
-                    .first()
-                    .expect("Split results must exist")
-                    .to_vec(),
-            )
-            .await?;
-
-            let score_chunks =
-                rerank_chunks(cross_encoder_results, data.date_bias, data.use_weights);
+    if data.explain.unwrap_or(false) {
+        explain_retrieval(&mut semantic_score_chunks, RetrievalSource::Semantic, 0);
+    }
 
-            score_chunks
-                .iter()
-                .chain(split_results.get(1).unwrap().iter())
-                .cloned()
-                .collect::<Vec<ScoreChunkDTO>>()
-        } else {
-            let cross_encoder_results = cross_encoder(
-                data.query.clone(),
-                data.page_size.unwrap_or(10),
-                combined_results,
-            )
-            .await?;
+    let result_chunks = {
+        let fused_chunks = fuse_hybrid_chunks(
+            parsed_query.query.clone(),
+            semantic_score_chunks,
+            full_text_handler_results.score_chunks,
+            data.fusion_strategy,
+            data.fusion_alpha,
+        )
+        .await?;
 
-            rerank_chunks(cross_encoder_results, data.date_bias, data.use_weights)
-        };
+        let reranked_chunks = rerank_chunks(fused_chunks, recency_decay.as_ref(), data.use_weights);
+        let total_chunk_pages = get_fused_total_pages(reranked_chunks.len(), limit);
 
-        reranked_chunks.truncate(data.page_size.unwrap_or(10) as usize);
+        let (mut score_chunks, next_cursor) =
+            page_fused_results(reranked_chunks, page, limit, &cursor, false);
+        finish_explanations(&mut score_chunks, get_rank_offset(page, limit, &cursor));
 
         SearchChunkQueryResponseBody {
-            score_chunks: reranked_chunks,
-            total_chunk_pages: search_chunk_query_results.total_chunk_pages,
+            score_chunks,
+            total_chunk_pages,
+            cursor: next_cursor.map(|cursor| cursor.with_window(window).encode()),
+            facets: None,
+            rewritten_query: None,
+            search_id: None,
         }
     };
 
@@ -1309,11 +2243,17 @@ pub async fn search_semantic_groups(
     dataset: Dataset,
     config: ServerDatasetConfiguration,
 ) -> Result<SearchGroupsResult, actix_web::Error> {
+    let recency_decay =
+        RecencyDecayCurve::resolve(data.date_bias, data.recency_decay.clone(), &config)?;
     let dataset_config =
         ServerDatasetConfiguration::from_json(dataset.server_configuration.clone());
 
-    let embedding_vectors =
-        create_embeddings(vec![data.query.clone()], "query", dataset_config.clone()).await?;
+    let embedding_vectors = create_embeddings(
+        vec![parsed_query.query.clone()],
+        "query",
+        dataset_config.clone(),
+    )
+    .await?;
     let embedding_vector = embedding_vectors
         .first()
         .ok_or(ServiceError::BadRequest(
@@ -1344,13 +2284,17 @@ pub async fn search_semantic_groups(
     )
     .await?;
 
-    result_chunks.score_chunks =
-        rerank_chunks(result_chunks.score_chunks, data.date_bias, data.use_weights);
+    result_chunks.score_chunks = rerank_chunks(
+        result_chunks.score_chunks,
+        recency_decay.as_ref(),
+        data.use_weights,
+    );
 
     Ok(SearchGroupsResult {
         bookmarks: result_chunks.score_chunks,
         group,
         total_pages: result_chunks.total_chunk_pages,
+        search_id: None,
     })
 }
 
@@ -1365,11 +2309,13 @@ pub async fn search_full_text_groups(
     dataset: Dataset,
     config: ServerDatasetConfiguration,
 ) -> Result<SearchGroupsResult, actix_web::Error> {
+    let recency_decay =
+        RecencyDecayCurve::resolve(data.date_bias, data.recency_decay.clone(), &config)?;
     let data_inner = data.clone();
-    let embedding_vector = get_splade_embedding(&data.query, "query").await?;
+    let embedding_vector = get_full_text_vector(&parsed_query.query, &config).await?;
 
     let search_chunk_query_results = search_within_chunk_group_query(
-        VectorType::Sparse(embedding_vector),
+        embedding_vector,
         page,
         pool.clone(),
         data_inner.filters.clone(),
@@ -1390,13 +2336,17 @@ pub async fn search_full_text_groups(
     )
     .await?;
 
-    result_chunks.score_chunks =
-        rerank_chunks(result_chunks.score_chunks, data.date_bias, data.use_weights);
+    result_chunks.score_chunks = rerank_chunks(
+        result_chunks.score_chunks,
+        recency_decay.as_ref(),
+        data.use_weights,
+    );
 
     Ok(SearchGroupsResult {
         bookmarks: result_chunks.score_chunks,
         group,
         total_pages: result_chunks.total_chunk_pages,
+        search_id: None,
     })
 }
 
@@ -1411,12 +2361,18 @@ pub async fn search_hybrid_groups(
     dataset: Dataset,
     config: ServerDatasetConfiguration,
 ) -> Result<SearchGroupsResult, actix_web::Error> {
+    let recency_decay =
+        RecencyDecayCurve::resolve(data.date_bias, data.recency_decay.clone(), &config)?;
     let data_inner = data.clone();
     let dataset_config =
         ServerDatasetConfiguration::from_json(dataset.server_configuration.clone());
 
-    let dense_embedding_vectors =
-        create_embeddings(vec![data.query.clone()], "query", dataset_config.clone()).await?;
+    let dense_embedding_vectors = create_embeddings(
+        vec![parsed_query.query.clone()],
+        "query",
+        dataset_config.clone(),
+    )
+    .await?;
     let dense_embedding_vector = dense_embedding_vectors
         .first()
         .ok_or(ServiceError::BadRequest(
@@ -1425,7 +2381,7 @@ pub async fn search_hybrid_groups(
         ))?
         .clone();
 
-    let sparse_embedding_vector = get_splade_embedding(&data.query, "query").await?;
+    let full_text_vector = get_full_text_vector(&parsed_query.query, &config).await?;
 
     let semantic_future = search_within_chunk_group_query(
         VectorType::Dense(dense_embedding_vector),
@@ -1441,7 +2397,7 @@ pub async fn search_hybrid_groups(
     );
 
     let full_text_future = search_within_chunk_group_query(
-        VectorType::Sparse(sparse_embedding_vector),
+        full_text_vector,
         page,
         pool.clone(),
         data_inner.filters.clone(),
@@ -1461,117 +2417,103 @@ pub async fn search_hybrid_groups(
     let full_text_results =
         full_text_results.map_err(|err| ServiceError::BadRequest(err.to_string()))?;
 
-    let combined_results = semantic_results
-        .clone()
-        .search_results
-        .iter()
-        .zip(full_text_results.search_results.iter())
-        .flat_map(|(x, y)| vec![x.clone(), y.clone()])
-        .unique_by(|chunk| chunk.point_id)
-        .collect::<Vec<SearchResult>>();
+    let semantic_results_len = semantic_results.search_results.len();
 
     let combined_search_chunk_query_results = SearchChunkQueryResult {
-        search_results: combined_results,
+        search_results: semantic_results
+            .search_results
+            .iter()
+            .chain(full_text_results.search_results.iter())
+            .cloned()
+            .collect::<Vec<SearchResult>>(),
         total_chunk_pages: semantic_results.total_chunk_pages,
+        next_cursor: None,
     };
 
-    let combined_result_chunks = retrieve_chunks_from_point_ids_without_collsions(
+    let mut semantic_score_chunks = retrieve_chunks_from_point_ids_without_collsions(
         combined_search_chunk_query_results,
         &web::Json(data.clone().into()),
         pool.clone(),
     )
     .await?;
+    let full_text_score_chunks = semantic_score_chunks
+        .score_chunks
+        .split_off(semantic_results_len);
 
     let result_chunks = {
-        let reranked_chunks = if combined_result_chunks.score_chunks.len() > 20 {
-            let split_results = combined_result_chunks
-                .score_chunks
-                .chunks(20)
-                .map(|chunk| chunk.to_vec())
-                .collect::<Vec<Vec<ScoreChunkDTO>>>();
-
-            let cross_encoder_results = cross_encoder(
-                data.query.clone(),
-                data.page_size.unwrap_or(10),
-                split_results
-                    .first()
-                    .expect("Split results must exist")
-                    .to_vec(),
-            )
-            .await?;
-            let score_chunks =
-                rerank_chunks(cross_encoder_results, data.date_bias, data.use_weights);
+        let fused_chunks = fuse_hybrid_chunks(
+            parsed_query.query.clone(),
+            semantic_score_chunks.score_chunks,
+            full_text_score_chunks,
+            data.fusion_strategy,
+            data.fusion_alpha,
+        )
+        .await?;
 
-            score_chunks
-                .iter()
-                .chain(split_results.get(1).unwrap().iter())
-                .cloned()
-                .collect::<Vec<ScoreChunkDTO>>()
-        } else {
-            let cross_encoder_results = cross_encoder(
-                data.query.clone(),
-                data.page_size.unwrap_or(10),
-                combined_result_chunks.score_chunks.clone(),
-            )
-            .await?;
+        let mut reranked_chunks =
+            rerank_chunks(fused_chunks, recency_decay.as_ref(), data.use_weights);
 
-            rerank_chunks(cross_encoder_results, data.date_bias, data.use_weights)
-        };
+        reranked_chunks.truncate(data.page_size.unwrap_or(10) as usize);
 
         SearchChunkQueryResponseBody {
             score_chunks: reranked_chunks,
-            total_chunk_pages: combined_result_chunks.total_chunk_pages,
+            total_chunk_pages: semantic_score_chunks.total_chunk_pages,
+            cursor: None,
+            facets: None,
+            rewritten_query: None,
+            search_id: None,
         }
     };
 
     Ok(SearchGroupsResult {
         bookmarks: result_chunks.score_chunks,
         group,
-        total_pages: combined_result_chunks.total_chunk_pages,
+        total_pages: result_chunks.total_chunk_pages,
+        search_id: None,
     })
 }
 
-#[tracing::instrument(skip(pool))]
+#[tracing::instrument(skip(pool, embedding_vector))]
 pub async fn semantic_search_over_groups(
     data: web::Json<SearchOverGroupsData>,
     parsed_query: ParsedQuery,
+    embedding_vector: Option<Vec<f32>>,
     page: u64,
     pool: web::Data<Pool>,
     dataset: Dataset,
     config: ServerDatasetConfiguration,
 ) -> Result<SearchOverGroupsResponseBody, actix_web::Error> {
-    let dataset_config =
-        ServerDatasetConfiguration::from_json(dataset.server_configuration.clone());
-    let embedding_vectors =
-        create_embeddings(vec![data.query.clone()], "query", dataset_config.clone()).await?;
-    let embedding_vector = embedding_vectors
-        .first()
-        .ok_or(ServiceError::BadRequest(
-            "Failed to get embedding vector due to empty array from create_embedding".to_string(),
-        ))?
-        .clone();
+    let embedding_vector = match embedding_vector {
+        Some(embedding_vector) => embedding_vector,
+        None => {
+            let dataset_config =
+                ServerDatasetConfiguration::from_json(dataset.server_configuration.clone());
+            let embedding_vectors = create_embeddings(
+                vec![parsed_query.query.clone()],
+                "query",
+                dataset_config.clone(),
+            )
+            .await?;
+            embedding_vectors
+                .first()
+                .ok_or(ServiceError::BadRequest(
+                    "Failed to get embedding vector due to empty array from create_embedding"
+                        .to_string(),
+                ))?
+                .clone()
+        }
+    };
 
-    let search_chunk_query_results = retrieve_group_qdrant_points_query(
+    search_groups_with_retriever(
         VectorType::Dense(embedding_vector),
-        page,
-        data.filters.clone(),
-        data.page_size.unwrap_or(10),
-        data.score_threshold,
-        data.group_size.unwrap_or(3),
+        &data,
         parsed_query,
-        dataset.id,
-        pool.clone(),
+        page,
+        pool,
+        dataset,
         config,
     )
     .await
-    .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
-
-    let result_chunks =
-        retrieve_chunks_for_groups(search_chunk_query_results, &data, pool.clone()).await?;
-
-    //TODO: rerank for groups
-
-    Ok(result_chunks)
 }
 
 #[tracing::instrument(skip(pool))]
@@ -1583,17 +2525,116 @@ pub async fn full_text_search_over_groups(
     dataset: Dataset,
     config: ServerDatasetConfiguration,
 ) -> Result<SearchOverGroupsResponseBody, actix_web::Error> {
-    let embedding_vector = get_splade_embedding(&data.query, "query")
+    let embedding_vector = get_full_text_vector(&parsed_query.query, &config)
         .await
-        .map_err(|_| ServiceError::BadRequest("Failed to get splade query embedding".into()))?;
+        .map_err(|_| ServiceError::BadRequest("Failed to get full-text query vector".into()))?;
 
-    let search_chunk_query_results = retrieve_group_qdrant_points_query(
-        VectorType::Sparse(embedding_vector),
+    search_groups_with_retriever(
+        embedding_vector,
+        &data,
+        parsed_query,
         page,
+        pool,
+        dataset,
+        config,
+    )
+    .await
+}
+
+/// Number of candidate groups fetched for every group on the requested page when groups are ranked by an aggregate of their chunks.
+/// The retrievers order groups by their best chunk, so a group which aggregates well can start out several pages deep.
+const GROUP_SCORE_CANDIDATES_PER_RESULT: u64 = 5;
+
+/// Scores each group by aggregating the scores of its chunks and sorts the groups by that score.
+pub fn aggregate_group_scores(
+    groups: Vec<GroupScoreChunkDTO>,
+    group_score: &GroupScoreOptions,
+) -> Vec<GroupScoreChunkDTO> {
+    let mut groups = groups
+        .into_iter()
+        .map(|mut group| {
+            let scores = group.metadata.iter().map(|chunk| chunk.score).collect_vec();
+            group.group_score = Some(group_score.aggregate(&scores));
+            group
+        })
+        .collect_vec();
+
+    // Counts tie often, so fall back to the best chunk before the group id
+    groups.sort_by(|a, b| {
+        b.rank_score()
+            .total_cmp(&a.rank_score())
+            .then_with(|| {
+                let best_score = |group: &GroupScoreChunkDTO| {
+                    group
+                        .metadata
+                        .first()
+                        .map(|chunk| chunk.score)
+                        .unwrap_or(0.0)
+                };
+                best_score(b).total_cmp(&best_score(a))
+            })
+            .then_with(|| a.rank_key().cmp(&b.rank_key()))
+    });
+
+    groups
+}
+
+/// Groups are fetched with extra chunks for mean_top_k, which are dropped once the groups are ranked.
+fn truncate_group_chunks(groups: &mut [GroupScoreChunkDTO], group_size: u32) {
+    for group in groups.iter_mut() {
+        group.metadata.truncate(group_size as usize);
+    }
+}
+
+/// Runs a group search against a single retriever. When group_score is specified, candidate groups are fetched from the first result,
+/// re-ranked by their aggregated score, and then paged.
+async fn search_groups_with_retriever(
+    vector: VectorType,
+    data: &web::Json<SearchOverGroupsData>,
+    parsed_query: ParsedQuery,
+    page: u64,
+    pool: web::Data<Pool>,
+    dataset: Dataset,
+    config: ServerDatasetConfiguration,
+) -> Result<SearchOverGroupsResponseBody, actix_web::Error> {
+    let cursor = decode_search_cursor(&data.cursor)?;
+    let limit: u64 = data.page_size.unwrap_or(10).into();
+    let group_size = data.group_size.unwrap_or(3);
+
+    let group_score = match GroupScoreOptions::resolve(&data.group_score)? {
+        Some(group_score) => group_score,
+        None => {
+            let search_chunk_query_results = retrieve_group_qdrant_points_query(
+                vector,
+                page,
+                cursor,
+                data.filters.clone(),
+                limit as u32,
+                data.score_threshold,
+                group_size,
+                parsed_query,
+                dataset.id,
+                pool.clone(),
+                config,
+            )
+            .await
+            .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+
+            return retrieve_chunks_for_groups(search_chunk_query_results, data, pool).await;
+        }
+    };
+
+    let candidate_limit =
+        (get_rank_offset(page, limit, &cursor) + limit) * GROUP_SCORE_CANDIDATES_PER_RESULT;
+
+    let candidates = retrieve_group_qdrant_points_query(
+        vector,
+        1,
+        None,
         data.filters.clone(),
-        data.page_size.unwrap_or(10),
+        candidate_limit as u32,
         data.score_threshold,
-        data.group_size.unwrap_or(3),
+        group_score.hits_per_group(group_size),
         parsed_query,
         dataset.id,
         pool.clone(),
@@ -1602,19 +2643,69 @@ pub async fn full_text_search_over_groups(
     .await
     .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
 
-    let result_chunks =
-        retrieve_chunks_for_groups(search_chunk_query_results, &data, pool.clone()).await?;
+    let retriever_has_more = candidates.next_cursor.is_some();
+    // Scale the page count of the candidate window to the page size of the request
+    let total_chunk_pages = (candidates.total_chunk_pages * candidate_limit as i64 + limit as i64
+        - 1)
+        / limit.max(1) as i64;
 
-    //TODO: rerank for groups
+    let candidate_groups = retrieve_chunks_for_groups(candidates, data, pool).await?;
 
-    Ok(result_chunks)
+    let (mut group_chunks, next_cursor) = page_fused_results(
+        aggregate_group_scores(candidate_groups.group_chunks, &group_score),
+        page,
+        limit,
+        &cursor,
+        retriever_has_more,
+    );
+    truncate_group_chunks(&mut group_chunks, group_size);
+
+    Ok(SearchOverGroupsResponseBody {
+        group_chunks,
+        total_chunk_pages,
+        cursor: next_cursor.map(|cursor| cursor.encode()),
+        facets: None,
+        search_id: None,
+    })
 }
 
 async fn cross_encoder_for_groups(
     query: String,
     page_size: u64,
     groups_chunks: Vec<GroupScoreChunkDTO>,
+    group_score: Option<&GroupScoreOptions>,
 ) -> Result<Vec<GroupScoreChunkDTO>, actix_web::Error> {
+    // An aggregated group score needs every chunk of the group re-scored, not just the first one
+    if let Some(group_score) = group_score {
+        let score_chunks = groups_chunks
+            .iter()
+            .flat_map(|group| group.metadata.iter().cloned())
+            .unique_by(|score_chunk| score_chunk.metadata[0].id)
+            .collect_vec();
+
+        let cross_encoder_scores: HashMap<uuid::Uuid, f64> =
+            cross_encoder(query, score_chunks.len() as u64, score_chunks)
+                .await?
+                .into_iter()
+                .map(|score_chunk| (score_chunk.metadata[0].id, score_chunk.score))
+                .collect();
+
+        let groups_chunks = groups_chunks
+            .into_iter()
+            .map(|mut group| {
+                for score_chunk in group.metadata.iter_mut() {
+                    if let Some(score) = cross_encoder_scores.get(&score_chunk.metadata[0].id) {
+                        score_chunk.score = *score;
+                    }
+                }
+                group.metadata.sort_by(|a, b| b.score.total_cmp(&a.score));
+                group
+            })
+            .collect_vec();
+
+        return Ok(aggregate_group_scores(groups_chunks, group_score));
+    }
+
     let score_chunks = groups_chunks
         .iter()
         .map(|group| {
@@ -1657,38 +2748,64 @@ async fn cross_encoder_for_groups(
     Ok(group_results)
 }
 
-#[tracing::instrument(skip(pool))]
+#[tracing::instrument(skip(pool, embedding_vector))]
 pub async fn hybrid_search_over_groups(
     data: web::Json<SearchOverGroupsData>,
     parsed_query: ParsedQuery,
+    embedding_vector: Option<Vec<f32>>,
     page: u64,
     pool: web::Data<Pool>,
     dataset: Dataset,
     config: ServerDatasetConfiguration,
 ) -> Result<SearchOverGroupsResponseBody, actix_web::Error> {
-    let dataset_config =
-        ServerDatasetConfiguration::from_json(dataset.server_configuration.clone());
+    let dense_embedding_vector = match embedding_vector {
+        Some(embedding_vector) => embedding_vector,
+        None => {
+            let dataset_config =
+                ServerDatasetConfiguration::from_json(dataset.server_configuration.clone());
 
-    let dense_embedding_vectors =
-        create_embeddings(vec![data.query.clone()], "query", dataset_config.clone()).await?;
-    let dense_embedding_vector = dense_embedding_vectors
-        .first()
-        .ok_or(ServiceError::BadRequest(
-            "Failed to get embedding vector due to empty array from create_embedding".to_string(),
-        ))?
-        .clone();
+            let dense_embedding_vectors = create_embeddings(
+                vec![parsed_query.query.clone()],
+                "query",
+                dataset_config.clone(),
+            )
+            .await?;
+            dense_embedding_vectors
+                .first()
+                .ok_or(ServiceError::BadRequest(
+                    "Failed to get embedding vector due to empty array from create_embedding"
+                        .to_string(),
+                ))?
+                .clone()
+        }
+    };
 
-    let sparse_embedding_vector = get_splade_embedding(&data.query, "query")
+    let full_text_vector = get_full_text_vector(&parsed_query.query, &config)
         .await
-        .map_err(|_| ServiceError::BadRequest("Failed to get splade query embedding".into()))?;
+        .map_err(|_| ServiceError::BadRequest("Failed to get full-text query vector".into()))?;
+
+    let cursor = decode_search_cursor(&data.cursor)?;
+    let limit: u64 = data.page_size.unwrap_or(10).into();
+    let group_size = data.group_size.unwrap_or(3);
+    let group_score = GroupScoreOptions::resolve(&data.group_score)?;
+    let window = get_fused_ranking_window(page, limit, &cursor)?;
+    let retrieval_limit = match group_score {
+        Some(_) => window * GROUP_SCORE_CANDIDATES_PER_RESULT,
+        None => window,
+    };
+    let hits_per_group = group_score
+        .as_ref()
+        .map(|group_score| group_score.hits_per_group(group_size))
+        .unwrap_or(group_size);
 
     let semantic_future = retrieve_group_qdrant_points_query(
         VectorType::Dense(dense_embedding_vector),
-        page,
+        1,
+        None,
         data.filters.clone(),
-        data.page_size.unwrap_or(10),
+        retrieval_limit as u32,
         data.score_threshold,
-        data.group_size.unwrap_or(3),
+        hits_per_group,
         parsed_query.clone(),
         dataset.id,
         pool.clone(),
@@ -1696,12 +2813,13 @@ pub async fn hybrid_search_over_groups(
     );
 
     let full_text_future = retrieve_group_qdrant_points_query(
-        VectorType::Sparse(sparse_embedding_vector),
-        page,
+        full_text_vector,
+        1,
+        None,
         data.filters.clone(),
-        data.page_size.unwrap_or(10),
+        retrieval_limit as u32,
         data.score_threshold,
-        data.group_size.unwrap_or(3),
+        hits_per_group,
         parsed_query.clone(),
         dataset.id,
         pool.clone(),
@@ -1716,72 +2834,109 @@ pub async fn hybrid_search_over_groups(
     let full_text_results =
         full_text_results.map_err(|err| ServiceError::BadRequest(err.to_string()))?;
 
-    let combined_results = semantic_results
-        .clone()
-        .search_results
-        .iter()
-        .zip(full_text_results.search_results.iter())
-        .flat_map(|(x, y)| vec![x.clone(), y.clone()])
-        .unique_by(|chunk| chunk.group_id)
-        .collect::<Vec<GroupSearchResults>>();
+    let semantic_results_len = semantic_results.search_results.len();
 
     let combined_search_chunk_query_results = SearchOverGroupsQueryResult {
-        search_results: combined_results,
+        search_results: semantic_results
+            .search_results
+            .iter()
+            .chain(full_text_results.search_results.iter())
+            .cloned()
+            .collect::<Vec<GroupSearchResults>>(),
         total_chunk_pages: semantic_results.total_chunk_pages,
+        next_cursor: None,
     };
 
-    let combined_result_chunks = retrieve_chunks_for_groups(
-        combined_search_chunk_query_results.clone(),
-        &data,
-        pool.clone(),
+    let mut semantic_group_chunks =
+        retrieve_chunks_for_groups(combined_search_chunk_query_results, &data, pool.clone())
+            .await?;
+    let full_text_group_chunks = semantic_group_chunks
+        .group_chunks
+        .split_off(semantic_results_len);
+
+    let (semantic_group_chunks, full_text_group_chunks) = match group_score.as_ref() {
+        Some(group_score) => (
+            aggregate_group_scores(semantic_group_chunks.group_chunks, group_score),
+            aggregate_group_scores(full_text_group_chunks, group_score),
+        ),
+        None => (semantic_group_chunks.group_chunks, full_text_group_chunks),
+    };
+
+    let reranked_chunks = fuse_hybrid_groups(
+        parsed_query.query.clone(),
+        semantic_group_chunks,
+        full_text_group_chunks,
+        data.fusion_strategy,
+        data.fusion_alpha,
+        group_score.as_ref(),
     )
     .await?;
 
-    let reranked_chunks = if combined_result_chunks.group_chunks.len() > 20 {
-        let split_results = combined_result_chunks
-            .group_chunks
-            .chunks(20)
-            .map(|chunk| chunk.to_vec())
-            .collect::<Vec<Vec<GroupScoreChunkDTO>>>();
-
-        let cross_encoder_results = cross_encoder_for_groups(
-            data.query.clone(),
-            data.page_size.unwrap_or(10).into(),
-            split_results
-                .first()
-                .expect("Split results must exist")
-                .to_vec(),
-        )
-        .await?;
-
-        cross_encoder_results
-            .iter()
-            .chain(split_results.get(1).unwrap().iter())
-            .cloned()
-            .collect::<Vec<GroupScoreChunkDTO>>()
-    } else {
-        cross_encoder_for_groups(
-            data.query.clone(),
-            data.page_size.unwrap_or(10).into(),
-            combined_result_chunks.group_chunks.clone(),
-        )
-        .await?
-    };
+    let total_chunk_pages = get_fused_total_pages(reranked_chunks.len(), limit);
+    let (mut group_chunks, next_cursor) =
+        page_fused_results(reranked_chunks, page, limit, &cursor, false);
+    truncate_group_chunks(&mut group_chunks, group_size);
 
     let result_chunks = SearchOverGroupsResponseBody {
-        group_chunks: reranked_chunks,
-        total_chunk_pages: combined_search_chunk_query_results.total_chunk_pages,
+        group_chunks,
+        total_chunk_pages,
+        cursor: next_cursor.map(|cursor| cursor.with_window(window).encode()),
+        facets: None,
+        search_id: None,
     };
 
-    //TODO: rerank for groups
-
     Ok(result_chunks)
 }
 
+type ChunkHtmlPredicate = Box<
+    dyn diesel::BoxableExpression<
+        crate::data::schema::chunk_metadata::table,
+        diesel::pg::Pg,
+        SqlType = diesel::sql_types::Bool,
+    >,
+>;
+
+/// Escapes the wildcards of an ILIKE pattern so that the text is matched literally.
+fn escape_like_pattern(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+fn text_filter_predicate(text_filter: TextFilter) -> ChunkHtmlPredicate {
+    use crate::data::schema::chunk_metadata::dsl as chunk_metadata_columns;
+    use diesel::prelude::*;
+
+    match text_filter {
+        TextFilter::Contains(text) => Box::new(
+            chunk_metadata_columns::chunk_html
+                .assume_not_null()
+                .ilike(format!("%{}%", escape_like_pattern(&text))),
+        ),
+        // \m anchors the pattern to the start of a word
+        TextFilter::Prefix(prefix) => Box::new(
+            diesel::dsl::sql::<diesel::sql_types::Bool>("chunk_metadata.chunk_html ~* ")
+                .bind::<diesel::sql_types::Text, _>(format!("\\m{}", regex::escape(&prefix))),
+        ),
+        TextFilter::And(text_filters) => text_filters
+            .into_iter()
+            .map(text_filter_predicate)
+            .reduce(|acc, predicate| Box::new(acc.and(predicate)))
+            .unwrap_or_else(|| Box::new(diesel::dsl::sql::<diesel::sql_types::Bool>("TRUE"))),
+        TextFilter::Or(text_filters) => text_filters
+            .into_iter()
+            .map(text_filter_predicate)
+            .reduce(|acc, predicate| Box::new(acc.or(predicate)))
+            .unwrap_or_else(|| Box::new(diesel::dsl::sql::<diesel::sql_types::Bool>("FALSE"))),
+        TextFilter::Not(text_filter) => {
+            Box::new(diesel::dsl::not(text_filter_predicate(*text_filter)))
+        }
+    }
+}
+
 #[tracing::instrument(skip(pool))]
-pub async fn get_qdrant_point_ids_from_pg_for_quote_negated_words(
-    quote_words: Option<Vec<String>>,
-    negated_words: Option<Vec<String>>,
+pub async fn get_qdrant_point_ids_from_pg_for_text_filter(
+    text_filter: TextFilter,
     dataset_id: uuid::Uuid,
     pool: web::Data<Pool>,
 ) -> Result<Vec<uuid::Uuid>, DefaultError> {
@@ -1790,31 +2945,13 @@ pub async fn get_qdrant_point_ids_from_pg_for_quote_negated_words(
     use diesel_async::RunQueryDsl;
 
     let mut conn = pool.get().await.unwrap();
-    let mut query = chunk_metadata_columns::chunk_metadata
+    let query = chunk_metadata_columns::chunk_metadata
         .select(chunk_metadata_columns::qdrant_point_id)
         .filter(chunk_metadata_columns::qdrant_point_id.is_not_null())
         .filter(chunk_metadata_columns::dataset_id.eq(dataset_id))
+        .filter(text_filter_predicate(text_filter))
         .into_boxed();
 
-    if let Some(quote_words) = quote_words {
-        for word in quote_words.iter() {
-            let word_without_quotes = word.trim_matches('\"');
-            query = query.filter(
-                chunk_metadata_columns::chunk_html.ilike(format!("%{}%", word_without_quotes)),
-            );
-        }
-    }
-
-    if let Some(negated_words) = negated_words {
-        for word in negated_words.iter() {
-            let word_without_negation = word.trim_matches('-');
-            query = query.filter(
-                chunk_metadata_columns::chunk_html
-                    .not_ilike(format!("%{}%", word_without_negation)),
-            );
-        }
-    }
-
     let matching_qdrant_point_ids: Vec<Option<uuid::Uuid>> =
         query.load(&mut conn).await.map_err(|_| DefaultError {
             message: "Failed to load full-text searched chunks",
@@ -1827,3 +2964,364 @@ pub async fn get_qdrant_point_ids_from_pg_for_quote_negated_words(
 
     Ok(matching_qdrant_point_ids)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::handlers::group_handler::GroupScoreAggregation;
+
+    fn search_result(score: f32) -> SearchResult {
+        SearchResult {
+            score,
+            point_id: uuid::Uuid::new_v4(),
+        }
+    }
+
+    fn ids(results: &[SearchResult]) -> Vec<uuid::Uuid> {
+        results.iter().map(|result| result.point_id).collect_vec()
+    }
+
+    #[test]
+    fn test_rrf_ranks_results_found_by_both_retrievers_first() {
+        let semantic_results = (0..3).map(|i| search_result(3.0 - i as f32)).collect_vec();
+        let mut full_text_results = vec![search_result(9.0), semantic_results[2].clone()];
+        full_text_results[1].score = 8.0;
+
+        let fused =
+            reciprocal_rank_fusion(vec![semantic_results.clone(), full_text_results.clone()]);
+
+        assert_eq!(fused.len(), 4);
+        assert_eq!(fused[0].point_id, semantic_results[2].point_id);
+        assert!((fused[0].rank_score() - (1.0 / (RRF_K + 3.0) + 1.0 / (RRF_K + 2.0))).abs() < 1e-6);
+        assert!(fused
+            .windows(2)
+            .all(|pair| pair[0].rank_score() >= pair[1].rank_score()));
+    }
+
+    #[test]
+    fn test_rrf_breaks_ties_by_key() {
+        let semantic_results = vec![search_result(1.0)];
+        let full_text_results = vec![search_result(5.0)];
+
+        let fused =
+            reciprocal_rank_fusion(vec![semantic_results.clone(), full_text_results.clone()]);
+        let mut expected = vec![semantic_results[0].point_id, full_text_results[0].point_id];
+        expected.sort();
+
+        assert_eq!(fused[0].rank_score(), fused[1].rank_score());
+        assert_eq!(ids(&fused), expected);
+        assert_eq!(
+            ids(&reciprocal_rank_fusion(vec![
+                full_text_results,
+                semantic_results
+            ])),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_weighted_linear_fusion_with_one_empty_list_keeps_the_other_ranking() {
+        let semantic_results = vec![search_result(4.0), search_result(2.0), search_result(0.0)];
+
+        let fused = weighted_linear_fusion(semantic_results.clone(), vec![], 0.25);
+
+        assert_eq!(ids(&fused), ids(&semantic_results));
+        assert_eq!(
+            fused.iter().map(|result| result.rank_score()).collect_vec(),
+            vec![0.25, 0.125, 0.0]
+        );
+
+        let fused = weighted_linear_fusion(vec![], semantic_results.clone(), 0.25);
+        assert_eq!(ids(&fused), ids(&semantic_results));
+        assert_eq!(fused[0].rank_score(), 0.75);
+    }
+
+    #[test]
+    fn test_weighted_linear_fusion_gives_a_single_result_list_full_weight() {
+        let fused = weighted_linear_fusion(vec![search_result(0.3)], vec![search_result(7.0)], 0.5);
+
+        assert_eq!(
+            fused.iter().map(|result| result.rank_score()).collect_vec(),
+            vec![0.5, 0.5]
+        );
+    }
+
+    #[test]
+    fn test_failed_cross_encoder_falls_back_to_rrf() {
+        let semantic_results = vec![search_result(0.9), search_result(0.5)];
+        let full_text_results = vec![search_result(3.0), semantic_results[1].clone()];
+
+        let (fused, strategy) = cross_encoder_or_rrf::<SearchResult, &str>(
+            Err("reranker unreachable"),
+            semantic_results.clone(),
+            full_text_results.clone(),
+        );
+        assert_eq!(strategy, FusionStrategy::Rrf);
+        assert_eq!(
+            ids(&fused),
+            ids(&reciprocal_rank_fusion(vec![
+                semantic_results.clone(),
+                full_text_results.clone()
+            ]))
+        );
+
+        let reranked = vec![full_text_results[0].clone()];
+        let (fused, strategy) = cross_encoder_or_rrf::<SearchResult, &str>(
+            Ok(reranked.clone()),
+            semantic_results,
+            full_text_results,
+        );
+        assert_eq!(strategy, FusionStrategy::CrossEncoder);
+        assert_eq!(ids(&fused), ids(&reranked));
+    }
+
+    #[test]
+    fn test_cursor_pins_fused_ranking_window() {
+        assert_eq!(get_fused_ranking_window(1, 10, &None).unwrap(), 100);
+        assert_eq!(get_fused_ranking_window(30, 10, &None).unwrap(), 300);
+        assert!(get_fused_ranking_window(200, 10, &None).is_err());
+
+        let mut ranking = (0..40).map(|i| search_result(i as f32)).collect_vec();
+        sort_ranked_results(&mut ranking);
+        let (_, cursor) = page_fused_results(ranking, 1, 10, &None, false);
+        let cursor = cursor.unwrap().with_window(100);
+        let decoded = SearchCursor::decode(&cursor.encode()).unwrap();
+
+        assert_eq!(decoded.window, Some(100));
+        assert_eq!(
+            get_fused_ranking_window(1, 10, &Some(decoded)).unwrap(),
+            100
+        );
+    }
+
+    #[test]
+    fn test_fused_pages_walk_a_fixed_ranking_without_gaps_or_repeats() {
+        let mut ranking = (0..25).map(|i| search_result((i % 5) as f32)).collect_vec();
+        sort_ranked_results(&mut ranking);
+
+        let mut walked = vec![];
+        let mut cursor = None;
+        loop {
+            let (page, next_cursor) = page_fused_results(ranking.clone(), 1, 10, &cursor, false);
+            walked.extend(page.iter().map(|result| result.point_id));
+            match next_cursor {
+                Some(next_cursor) => cursor = Some(next_cursor.with_window(100)),
+                None => break,
+            }
+        }
+
+        assert_eq!(
+            walked,
+            ranking.iter().map(|result| result.point_id).collect_vec()
+        );
+        assert_eq!(get_fused_total_pages(ranking.len(), 10), 3);
+    }
+
+    #[test]
+    fn test_fused_pages_without_a_cursor_start_at_the_page_offset() {
+        let mut ranking = (0..25).map(|i| search_result(i as f32)).collect_vec();
+        sort_ranked_results(&mut ranking);
+
+        let (page, cursor) = page_fused_results(ranking.clone(), 2, 10, &None, false);
+        assert_eq!(
+            page.iter().map(|result| result.point_id).collect_vec(),
+            ranking[10..20]
+                .iter()
+                .map(|result| result.point_id)
+                .collect_vec()
+        );
+        assert_eq!(cursor.unwrap().offset, 20);
+
+        let (page, cursor) = page_fused_results(ranking, 3, 10, &None, false);
+        assert_eq!(page.len(), 5);
+        assert!(cursor.is_none());
+    }
+
+    #[test]
+    fn test_recency_decay_never_raises_old_chunks() {
+        let curve = RecencyDecayCurve::from_decay(RecencyDecay {
+            function: None,
+            scale_days: Some(10.0),
+            origin: Some("2024-01-31".to_string()),
+            decay: Some(0.5),
+            weight: Some(1.0),
+        })
+        .unwrap();
+        let new = curve.score_multiplier(curve.origin);
+        let old = curve.score_multiplier(curve.origin - chrono::Duration::days(10));
+
+        assert!((old - 0.5).abs() < 1e-9);
+        assert_eq!(RecencyDecayCurve::apply(0.8, new), 0.8);
+        assert!((RecencyDecayCurve::apply(0.8, old) - 0.4).abs() < 1e-9);
+        assert!((RecencyDecayCurve::apply(-0.2, old) + 0.3).abs() < 1e-9);
+        assert!(RecencyDecayCurve::apply(-0.2, old) < RecencyDecayCurve::apply(-0.2, new));
+    }
+
+    #[test]
+    fn test_recency_decay_rejects_out_of_range_settings() {
+        let recency_decay = |decay: f64, weight: f64| RecencyDecay {
+            function: None,
+            scale_days: None,
+            origin: None,
+            decay: Some(decay),
+            weight: Some(weight),
+        };
+
+        assert!(RecencyDecayCurve::from_decay(recency_decay(0.5, 0.5)).is_ok());
+        assert!(RecencyDecayCurve::from_decay(recency_decay(1.5, 0.5)).is_err());
+        assert!(RecencyDecayCurve::from_decay(recency_decay(0.5, -0.1)).is_err());
+    }
+
+    #[test]
+    fn test_min_max_normalization_rescales_each_dataset_to_unit_range() {
+        assert_eq!(
+            normalize_dataset_scores(&[3.0, 2.0, 1.0], ScoreNormalization::MinMax),
+            vec![1.0, 0.5, 0.0]
+        );
+        assert_eq!(
+            normalize_dataset_scores(&[-2.0, -4.0], ScoreNormalization::MinMax),
+            vec![1.0, 0.0]
+        );
+        assert_eq!(
+            normalize_dataset_scores(&[0.3, 0.3], ScoreNormalization::MinMax),
+            vec![1.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn test_rrf_normalization_only_uses_rank() {
+        assert_eq!(
+            normalize_dataset_scores(&[100.0, 0.1], ScoreNormalization::Rrf),
+            vec![1.0 / (RRF_K + 1.0), 1.0 / (RRF_K + 2.0)]
+        );
+    }
+
+    #[test]
+    fn test_normalization_handles_empty_and_single_results() {
+        for normalization in [ScoreNormalization::MinMax, ScoreNormalization::Rrf] {
+            assert!(normalize_dataset_scores(&[], normalization).is_empty());
+        }
+        assert_eq!(
+            normalize_dataset_scores(&[0.42], ScoreNormalization::MinMax),
+            vec![1.0]
+        );
+        assert_eq!(
+            normalize_dataset_scores(&[0.42], ScoreNormalization::Rrf),
+            vec![1.0 / (RRF_K + 1.0)]
+        );
+    }
+
+    #[test]
+    fn test_federated_depth_is_capped() {
+        assert_eq!(get_federated_candidate_limit(0, 10).unwrap(), 10);
+        assert_eq!(get_federated_candidate_limit(100, 10).unwrap(), 1000);
+        assert!(get_federated_candidate_limit(101, 10).is_err());
+        assert!(get_federated_candidate_limit(10000, 10).is_err());
+        assert!(get_federated_candidate_limit(u64::MAX, 10).is_err());
+    }
+
+    #[test]
+    fn test_federated_total_pages_count_results_at_the_page_size() {
+        // Datasets which returned fewer results than asked for are counted exactly
+        assert_eq!(get_federated_total_pages([(1, 3), (1, 5)], 10, 10), 1);
+        assert_eq!(get_federated_total_pages([(1, 3), (1, 5)], 10, 3), 3);
+        assert_eq!(get_federated_total_pages([(0, 0), (0, 0)], 10, 10), 0);
+
+        // A dataset which filled its candidates is bounded by its page count at the candidate size
+        assert_eq!(get_federated_total_pages([(5, 20), (1, 4)], 20, 10), 11);
+
+        // Results past the deepest page are not counted
+        assert_eq!(
+            get_federated_total_pages([(500, 10), (500, 10)], 10, 10),
+            100
+        );
+    }
+
+    fn group(scores: &[f64]) -> GroupScoreChunkDTO {
+        GroupScoreChunkDTO {
+            group_id: uuid::Uuid::new_v4(),
+            metadata: scores
+                .iter()
+                .map(|score| ScoreChunkDTO {
+                    metadata: vec![],
+                    score: *score,
+                    explain: None,
+                    highlights: None,
+                })
+                .collect(),
+            group_score: None,
+        }
+    }
+
+    fn group_score(
+        aggregation: GroupScoreAggregation,
+        k: Option<u32>,
+        threshold: Option<f32>,
+    ) -> GroupScoreOptions {
+        GroupScoreOptions {
+            aggregation,
+            k,
+            threshold,
+        }
+    }
+
+    #[test]
+    fn test_group_score_aggregations() {
+        let scores = [0.25, 1.0, 0.5, 0.75];
+
+        assert_eq!(
+            group_score(GroupScoreAggregation::Max, None, None).aggregate(&scores),
+            1.0
+        );
+        assert_eq!(
+            group_score(GroupScoreAggregation::MeanTopK, Some(2), None).aggregate(&scores),
+            0.875
+        );
+        assert_eq!(
+            group_score(GroupScoreAggregation::MeanTopK, Some(10), None).aggregate(&scores),
+            0.625
+        );
+        assert_eq!(
+            group_score(GroupScoreAggregation::Sum, None, None).aggregate(&scores),
+            2.5
+        );
+        assert_eq!(
+            group_score(GroupScoreAggregation::CountAboveThreshold, None, Some(0.5))
+                .aggregate(&scores),
+            3.0
+        );
+        assert_eq!(
+            group_score(GroupScoreAggregation::MeanTopK, Some(3), None).aggregate(&[]),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_groups_rank_by_aggregate_then_best_chunk() {
+        let one_strong = group(&[0.9]);
+        let many_good = group(&[0.75, 0.75, 0.5]);
+        let many_weak = group(&[0.5, 0.5, 0.5]);
+        let ids =
+            |groups: &[GroupScoreChunkDTO]| groups.iter().map(|group| group.group_id).collect_vec();
+
+        let by_sum = aggregate_group_scores(
+            vec![one_strong.clone(), many_good.clone(), many_weak.clone()],
+            &group_score(GroupScoreAggregation::Sum, None, None),
+        );
+        assert_eq!(
+            ids(&by_sum),
+            vec![many_good.group_id, many_weak.group_id, one_strong.group_id]
+        );
+        assert_eq!(by_sum[0].group_score, Some(2.0));
+
+        // many_good and many_weak both count three chunks, so their best chunk breaks the tie
+        let by_count = aggregate_group_scores(
+            vec![many_weak.clone(), one_strong.clone(), many_good.clone()],
+            &group_score(GroupScoreAggregation::CountAboveThreshold, None, Some(0.5)),
+        );
+        assert_eq!(
+            ids(&by_count),
+            vec![many_good.group_id, many_weak.group_id, one_strong.group_id]
+        );
+    }
+}
@@ -0,0 +1,873 @@
+use super::chunk_operator::get_metadata_from_ids_query;
+use super::diversity_operator::diversify_results;
+use super::qdrant_operator::scroll_qdrant_point_ids_query;
+use super::search_operator::{
+    assemble_qdrant_filter, get_rank_offset, page_fused_results, GroupScoreChunkDTO, RankedResult,
+    SearchCursor,
+};
+use crate::{
+    data::models::{
+        Pool, SearchRule, SearchRuleActions, SearchRuleMatchType, ServerDatasetConfiguration,
+    },
+    errors::{DefaultError, ServiceError},
+    handlers::chunk_handler::{ChunkFilter, DiversifyOptions, ParsedQuery, ScoreChunkDTO},
+};
+use actix_web::web;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use itertools::Itertools;
+use qdrant_client::qdrant::{condition::ConditionOneOf::HasId, Condition, HasIdCondition, PointId};
+use std::collections::{HashMap, HashSet};
+
+#[tracing::instrument(skip(pool))]
+pub async fn create_search_rule_query(
+    search_rule: SearchRule,
+    pool: web::Data<Pool>,
+) -> Result<SearchRule, DefaultError> {
+    use crate::data::schema::search_rules::dsl as search_rules_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    diesel::insert_into(search_rules_columns::search_rules)
+        .values(&search_rule)
+        .execute(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to create search rule {:?}", err);
+            DefaultError {
+                message: "Failed to create search rule",
+            }
+        })?;
+
+    Ok(search_rule)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_search_rules_query(
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<SearchRule>, DefaultError> {
+    use crate::data::schema::search_rules::dsl as search_rules_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    search_rules_columns::search_rules
+        .filter(search_rules_columns::dataset_id.eq(dataset_id))
+        .order(search_rules_columns::created_at.asc())
+        .select(SearchRule::as_select())
+        .load::<SearchRule>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to load search rules {:?}", err);
+            DefaultError {
+                message: "Failed to load search rules",
+            }
+        })
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn update_search_rule_query(
+    search_rule: SearchRule,
+    pool: web::Data<Pool>,
+) -> Result<SearchRule, DefaultError> {
+    use crate::data::schema::search_rules::dsl as search_rules_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    diesel::update(
+        search_rules_columns::search_rules
+            .filter(search_rules_columns::id.eq(search_rule.id))
+            .filter(search_rules_columns::dataset_id.eq(search_rule.dataset_id)),
+    )
+    .set((
+        search_rules_columns::query_pattern.eq(search_rule.query_pattern),
+        search_rules_columns::match_type.eq(search_rule.match_type),
+        search_rules_columns::actions.eq(search_rule.actions),
+        search_rules_columns::updated_at.eq(diesel::dsl::now),
+    ))
+    .get_result::<SearchRule>(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to update search rule {:?}", err);
+        DefaultError {
+            message: "Failed to update search rule, it may not exist",
+        }
+    })
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn delete_search_rule_query(
+    search_rule_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<(), DefaultError> {
+    use crate::data::schema::search_rules::dsl as search_rules_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    let deleted = diesel::delete(
+        search_rules_columns::search_rules
+            .filter(search_rules_columns::id.eq(search_rule_id))
+            .filter(search_rules_columns::dataset_id.eq(dataset_id)),
+    )
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to delete search rule {:?}", err);
+        DefaultError {
+            message: "Failed to delete search rule",
+        }
+    })?;
+
+    if deleted == 0 {
+        return Err(DefaultError {
+            message: "Search rule not found",
+        });
+    }
+
+    Ok(())
+}
+
+/// Rules are matched case-insensitively and without regard to spacing.
+fn normalize_rule_query(query: &str) -> String {
+    query.split_whitespace().join(" ").to_lowercase()
+}
+
+pub fn search_rule_matches(search_rule: &SearchRule, query: &str) -> bool {
+    let query = normalize_rule_query(query);
+    let pattern = normalize_rule_query(&search_rule.query_pattern);
+    if query.is_empty() {
+        return false;
+    }
+
+    match SearchRuleMatchType::parse(&search_rule.match_type) {
+        Some(SearchRuleMatchType::Exact) => query == pattern,
+        Some(SearchRuleMatchType::Contains) => {
+            !pattern.is_empty() && format!(" {} ", query).contains(&format!(" {} ", pattern))
+        }
+        Some(SearchRuleMatchType::Regex) => regex::RegexBuilder::new(&search_rule.query_pattern)
+            .case_insensitive(true)
+            .build()
+            .map(|pattern| pattern.is_match(&query))
+            .unwrap_or(false),
+        None => false,
+    }
+}
+
+fn chunk_id(score_chunk: &ScoreChunkDTO) -> uuid::Uuid {
+    score_chunk.metadata[0].id
+}
+
+fn chunk_has_tag(score_chunk: &ScoreChunkDTO, tag: &str) -> bool {
+    score_chunk.metadata[0]
+        .tag_set
+        .as_ref()
+        .is_some_and(|tag_set| tag_set.split(',').any(|chunk_tag| chunk_tag.trim() == tag))
+}
+
+fn note_search_rule(score_chunk: &mut ScoreChunkDTO, search_rule_id: uuid::Uuid) {
+    if let Some(explanation) = score_chunk.explain.as_mut() {
+        let search_rules = explanation.search_rules.get_or_insert_with(Vec::new);
+        if !search_rules.contains(&search_rule_id) {
+            search_rules.push(search_rule_id);
+        }
+    }
+}
+
+fn get_rule_actions(matching_rules: &[SearchRule]) -> Vec<(uuid::Uuid, SearchRuleActions)> {
+    matching_rules
+        .iter()
+        .map(|search_rule| {
+            (
+                search_rule.id,
+                SearchRuleActions::from_json(search_rule.actions.clone()),
+            )
+        })
+        .collect_vec()
+}
+
+/// Scales the magnitude of a score by the factor of a boost. Adding to the score instead of multiplying it means a boost above 1
+/// raises negative scores too, rather than pushing them further below zero.
+fn boost_score(score: f64, factor: f64) -> f64 {
+    score + score.abs() * (factor - 1.0)
+}
+
+/// Loads the rules of the dataset which match the query.
+#[tracing::instrument(skip(pool))]
+pub async fn get_matching_search_rules_query(
+    query: &str,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<SearchRule>, ServiceError> {
+    Ok(get_search_rules_query(dataset_id, pool)
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?
+        .into_iter()
+        .filter(|search_rule| search_rule_matches(search_rule, query))
+        .collect_vec())
+}
+
+/// Applies the tag boosts of the matching rules to a ranking and re-sorts it by the boosted scores. Chunks with equal scores keep
+/// their order, and a ranking which no boost applies to is returned as is.
+fn boost_search_rule_chunks(
+    score_chunks: Vec<ScoreChunkDTO>,
+    matching_rules: &[SearchRule],
+) -> Vec<ScoreChunkDTO> {
+    let rule_actions = get_rule_actions(matching_rules);
+    if rule_actions
+        .iter()
+        .all(|(_, actions)| actions.boost.is_empty())
+    {
+        return score_chunks;
+    }
+
+    let mut score_chunks = score_chunks;
+    for score_chunk in score_chunks.iter_mut() {
+        for (search_rule_id, actions) in rule_actions.iter() {
+            for boost in actions.boost.iter() {
+                if chunk_has_tag(score_chunk, &boost.tag) {
+                    score_chunk.score = boost_score(score_chunk.score, boost.factor);
+                    note_search_rule(score_chunk, *search_rule_id);
+                }
+            }
+        }
+    }
+    score_chunks.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    score_chunks
+}
+
+/// Splits a ranking into the chunks pinned by the matching rules, in the order they were pinned, the chunks the rules leave in place,
+/// and the chunks they bury. Pins take precedence over buries, and chunks keep their relative order within each part so that
+/// diversified rankings stay diversified. Missing_pinned_chunks are the pinned chunks which the search did not find.
+fn partition_search_rule_chunks(
+    score_chunks: Vec<ScoreChunkDTO>,
+    matching_rules: &[SearchRule],
+    missing_pinned_chunks: Vec<ScoreChunkDTO>,
+) -> (Vec<ScoreChunkDTO>, Vec<ScoreChunkDTO>, Vec<ScoreChunkDTO>) {
+    let rule_actions = get_rule_actions(matching_rules);
+
+    let mut pinned_ids: Vec<(uuid::Uuid, uuid::Uuid)> = vec![];
+    let mut buried_ids: HashMap<uuid::Uuid, uuid::Uuid> = HashMap::new();
+    for (search_rule_id, actions) in rule_actions.iter() {
+        for chunk_id in actions.pin.iter() {
+            if !pinned_ids
+                .iter()
+                .any(|(pinned_id, _)| pinned_id == chunk_id)
+            {
+                pinned_ids.push((*chunk_id, *search_rule_id));
+            }
+        }
+        for chunk_id in actions.bury.iter() {
+            buried_ids.entry(*chunk_id).or_insert(*search_rule_id);
+        }
+    }
+
+    let (mut pinned_chunks, unpinned_chunks): (Vec<ScoreChunkDTO>, Vec<ScoreChunkDTO>) =
+        score_chunks
+            .into_iter()
+            .chain(missing_pinned_chunks)
+            .unique_by(chunk_id)
+            .partition(|score_chunk| {
+                pinned_ids
+                    .iter()
+                    .any(|(pinned_id, _)| *pinned_id == chunk_id(score_chunk))
+            });
+    pinned_chunks.sort_by_key(|score_chunk| {
+        pinned_ids
+            .iter()
+            .position(|(pinned_id, _)| *pinned_id == chunk_id(score_chunk))
+    });
+    for score_chunk in pinned_chunks.iter_mut() {
+        if let Some((_, search_rule_id)) = pinned_ids
+            .iter()
+            .find(|(pinned_id, _)| *pinned_id == chunk_id(score_chunk))
+        {
+            note_search_rule(score_chunk, *search_rule_id);
+        }
+    }
+
+    let (mut buried_chunks, kept_chunks): (Vec<ScoreChunkDTO>, Vec<ScoreChunkDTO>) =
+        unpinned_chunks
+            .into_iter()
+            .partition(|score_chunk| buried_ids.contains_key(&chunk_id(score_chunk)));
+    for score_chunk in buried_chunks.iter_mut() {
+        if let Some(search_rule_id) = buried_ids.get(&chunk_id(score_chunk)) {
+            note_search_rule(score_chunk, *search_rule_id);
+        }
+    }
+
+    (pinned_chunks, kept_chunks, buried_chunks)
+}
+
+/// Applies the actions of the matching rules to a ranking which starts at the first result. Boosts are applied first and re-sort the
+/// chunks, then pinned chunks are moved to the top and buried chunks below everything else.
+pub fn apply_search_rules(
+    score_chunks: Vec<ScoreChunkDTO>,
+    matching_rules: &[SearchRule],
+    missing_pinned_chunks: Vec<ScoreChunkDTO>,
+) -> Vec<ScoreChunkDTO> {
+    if matching_rules.is_empty() {
+        return score_chunks;
+    }
+
+    let (pinned_chunks, kept_chunks, buried_chunks) = partition_search_rule_chunks(
+        boost_search_rule_chunks(score_chunks, matching_rules),
+        matching_rules,
+        missing_pinned_chunks,
+    );
+
+    pinned_chunks
+        .into_iter()
+        .chain(kept_chunks)
+        .chain(buried_chunks)
+        .collect()
+}
+
+/// Diversifies the candidates of a search which matched search rules and slices out the requested page. Boosts apply before
+/// diversifying so that maximal marginal relevance weighs the boosted scores. Pinned chunks lead the ranking without being
+/// diversified, and buried chunks only follow once every other candidate has been ranked.
+#[allow(clippy::too_many_arguments)]
+pub async fn diversify_search_rule_page(
+    candidates: Vec<ScoreChunkDTO>,
+    candidates_have_more: bool,
+    options: &DiversifyOptions,
+    matching_rules: &[SearchRule],
+    missing_pinned_chunks: Vec<ScoreChunkDTO>,
+    page: u64,
+    limit: u64,
+    cursor: &Option<SearchCursor>,
+    config: ServerDatasetConfiguration,
+) -> Result<(Vec<ScoreChunkDTO>, Option<SearchCursor>), ServiceError> {
+    let (pinned_chunks, kept_chunks, buried_chunks) = partition_search_rule_chunks(
+        boost_search_rule_chunks(candidates, matching_rules),
+        matching_rules,
+        missing_pinned_chunks,
+    );
+
+    let needed =
+        (get_rank_offset(page, limit, cursor) + limit).saturating_sub(pinned_chunks.len() as u64);
+    let (diversified, has_leftovers) =
+        diversify_results(kept_chunks, options, needed as usize, config).await?;
+    let has_more = candidates_have_more || has_leftovers;
+
+    let ranking = pinned_chunks
+        .into_iter()
+        .chain(diversified)
+        .chain(if has_more { vec![] } else { buried_chunks })
+        .collect_vec();
+
+    Ok(page_fused_results(ranking, page, limit, cursor, has_more))
+}
+
+/// Applies the tag boosts of the matching rules to the chunks of each group and re-sorts the groups. A group ranked by group_score has
+/// that score boosted once for every boost which matches one of its chunks, otherwise it is ranked by its best chunk after boosting.
+fn boost_search_rule_groups(
+    groups: Vec<GroupScoreChunkDTO>,
+    matching_rules: &[SearchRule],
+) -> Vec<GroupScoreChunkDTO> {
+    let rule_actions = get_rule_actions(matching_rules);
+    if rule_actions
+        .iter()
+        .all(|(_, actions)| actions.boost.is_empty())
+    {
+        return groups;
+    }
+
+    let mut groups = groups;
+    for group in groups.iter_mut() {
+        if let Some(group_score) = group.group_score.as_mut() {
+            for boost in rule_actions
+                .iter()
+                .flat_map(|(_, actions)| actions.boost.iter())
+            {
+                if group
+                    .metadata
+                    .iter()
+                    .any(|score_chunk| chunk_has_tag(score_chunk, &boost.tag))
+                {
+                    *group_score = boost_score(*group_score, boost.factor);
+                }
+            }
+        }
+        group.metadata =
+            boost_search_rule_chunks(std::mem::take(&mut group.metadata), matching_rules);
+    }
+    groups.sort_by(|a, b| b.rank_score().total_cmp(&a.rank_score()));
+
+    groups
+}
+
+/// Splits a group ranking the way partition_search_rule_chunks splits a chunk ranking. Pinned chunks lead their group and buried chunks
+/// trail it. Groups holding a pinned chunk are moved to the top in the order of their earliest pin, and groups whose chunks are all
+/// buried are moved below everything else. Pins only lift groups the search found, since a pinned chunk may belong to any number of groups.
+fn partition_search_rule_groups(
+    groups: Vec<GroupScoreChunkDTO>,
+    matching_rules: &[SearchRule],
+) -> (
+    Vec<GroupScoreChunkDTO>,
+    Vec<GroupScoreChunkDTO>,
+    Vec<GroupScoreChunkDTO>,
+) {
+    let pinned_ids = get_rule_actions(matching_rules)
+        .into_iter()
+        .flat_map(|(_, actions)| actions.pin)
+        .unique()
+        .collect_vec();
+
+    let mut pinned_groups: Vec<(usize, GroupScoreChunkDTO)> = vec![];
+    let mut kept_groups = vec![];
+    let mut buried_groups = vec![];
+    for mut group in groups.into_iter() {
+        let (pinned_chunks, kept_chunks, buried_chunks) = partition_search_rule_chunks(
+            std::mem::take(&mut group.metadata),
+            matching_rules,
+            vec![],
+        );
+        let pin_position = pinned_chunks.first().and_then(|score_chunk| {
+            pinned_ids
+                .iter()
+                .position(|pinned_id| *pinned_id == chunk_id(score_chunk))
+        });
+        let all_buried =
+            pinned_chunks.is_empty() && kept_chunks.is_empty() && !buried_chunks.is_empty();
+
+        group.metadata = pinned_chunks
+            .into_iter()
+            .chain(kept_chunks)
+            .chain(buried_chunks)
+            .collect();
+
+        match pin_position {
+            Some(pin_position) => pinned_groups.push((pin_position, group)),
+            None if all_buried => buried_groups.push(group),
+            None => kept_groups.push(group),
+        }
+    }
+    pinned_groups.sort_by_key(|(pin_position, _)| *pin_position);
+
+    (
+        pinned_groups.into_iter().map(|(_, group)| group).collect(),
+        kept_groups,
+        buried_groups,
+    )
+}
+
+/// Applies the actions of the matching rules to a ranking of groups which starts at the first result. Boosts are applied first and
+/// re-sort the groups, then groups holding a pinned chunk are moved to the top and groups of buried chunks below everything else.
+pub fn apply_search_rules_to_groups(
+    groups: Vec<GroupScoreChunkDTO>,
+    matching_rules: &[SearchRule],
+) -> Vec<GroupScoreChunkDTO> {
+    if matching_rules.is_empty() {
+        return groups;
+    }
+
+    let (pinned_groups, kept_groups, buried_groups) = partition_search_rule_groups(
+        boost_search_rule_groups(groups, matching_rules),
+        matching_rules,
+    );
+
+    pinned_groups
+        .into_iter()
+        .chain(kept_groups)
+        .chain(buried_groups)
+        .collect()
+}
+
+/// Diversifies the candidate groups of a search which matched search rules and slices out the requested page, in the same way
+/// diversify_search_rule_page does for chunks.
+#[allow(clippy::too_many_arguments)]
+pub async fn diversify_search_rule_group_page(
+    candidates: Vec<GroupScoreChunkDTO>,
+    candidates_have_more: bool,
+    options: &DiversifyOptions,
+    matching_rules: &[SearchRule],
+    page: u64,
+    limit: u64,
+    cursor: &Option<SearchCursor>,
+    config: ServerDatasetConfiguration,
+) -> Result<(Vec<GroupScoreChunkDTO>, Option<SearchCursor>), ServiceError> {
+    let (pinned_groups, kept_groups, buried_groups) = partition_search_rule_groups(
+        boost_search_rule_groups(candidates, matching_rules),
+        matching_rules,
+    );
+
+    let needed =
+        (get_rank_offset(page, limit, cursor) + limit).saturating_sub(pinned_groups.len() as u64);
+    let (diversified, has_leftovers) =
+        diversify_results(kept_groups, options, needed as usize, config).await?;
+    let has_more = candidates_have_more || has_leftovers;
+
+    let ranking = pinned_groups
+        .into_iter()
+        .chain(diversified)
+        .chain(if has_more { vec![] } else { buried_groups })
+        .collect_vec();
+
+    Ok(page_fused_results(ranking, page, limit, cursor, has_more))
+}
+
+/// Loads the chunks pinned by the matching rules which are not in the ranking, keeping only those which pass the filters of the
+/// request so that a pin never adds a chunk the search excluded.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(score_chunks, pool))]
+pub async fn get_missing_pinned_chunks_query(
+    score_chunks: &[ScoreChunkDTO],
+    matching_rules: &[SearchRule],
+    filters: Option<ChunkFilter>,
+    parsed_query: ParsedQuery,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+    config: ServerDatasetConfiguration,
+) -> Result<Vec<ScoreChunkDTO>, ServiceError> {
+    let missing_pinned_ids = get_rule_actions(matching_rules)
+        .into_iter()
+        .flat_map(|(_, actions)| actions.pin)
+        .filter(|pinned_id| {
+            !score_chunks
+                .iter()
+                .any(|score_chunk| chunk_id(score_chunk) == *pinned_id)
+        })
+        .unique()
+        .collect_vec();
+
+    if missing_pinned_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let pinned_chunks = get_metadata_from_ids_query(missing_pinned_ids, dataset_id, pool.clone())
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+    if pinned_chunks.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut filter = assemble_qdrant_filter(filters, Some(parsed_query), dataset_id, Some(pool))
+        .await
+        .map_err(|err| ServiceError::BadRequest(err.message.into()))?;
+    filter.must.push(Condition {
+        condition_one_of: Some(HasId(HasIdCondition {
+            has_id: pinned_chunks
+                .iter()
+                .map(|chunk| chunk.qdrant_point_id.to_string().into())
+                .collect::<Vec<PointId>>(),
+        })),
+    });
+    let matching_point_ids: HashSet<uuid::Uuid> =
+        scroll_qdrant_point_ids_query(filter, pinned_chunks.len() as u64, config)
+            .await
+            .map_err(|err| ServiceError::BadRequest(err.message.into()))?
+            .into_iter()
+            .collect();
+
+    Ok(pinned_chunks
+        .into_iter()
+        .filter(|chunk| matching_point_ids.contains(&chunk.qdrant_point_id))
+        .map(|chunk| ScoreChunkDTO {
+            metadata: vec![chunk],
+            score: 0.0,
+            explain: None,
+            highlights: None,
+        })
+        .collect_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::models::{ChunkMetadataWithFileData, TagBoost};
+    use crate::operators::search_operator::get_fused_total_pages;
+    use serde_json::json;
+
+    fn score_chunk(id: u128, score: f64, tag_set: &str) -> ScoreChunkDTO {
+        ScoreChunkDTO {
+            metadata: vec![ChunkMetadataWithFileData {
+                id: uuid::Uuid::from_u128(id),
+                qdrant_point_id: uuid::Uuid::from_u128(id),
+                created_at: chrono::Utc::now().naive_local(),
+                updated_at: chrono::Utc::now().naive_local(),
+                file_id: None,
+                file_name: None,
+                content: "".to_string(),
+                chunk_html: Some("".to_string()),
+                link: None,
+                tag_set: Some(tag_set.to_string()),
+                metadata: None,
+                tracking_id: None,
+                time_stamp: None,
+                weight: 1.0,
+            }],
+            score,
+            explain: None,
+            highlights: None,
+        }
+    }
+
+    fn search_rule(
+        query_pattern: &str,
+        match_type: SearchRuleMatchType,
+        actions: SearchRuleActions,
+    ) -> SearchRule {
+        SearchRule::from_details(
+            uuid::Uuid::nil(),
+            query_pattern.to_string(),
+            match_type,
+            actions,
+        )
+    }
+
+    fn ids(score_chunks: &[ScoreChunkDTO]) -> Vec<u128> {
+        score_chunks
+            .iter()
+            .map(|score_chunk| chunk_id(score_chunk).as_u128())
+            .collect()
+    }
+
+    #[test]
+    pub fn test_rules_match_queries() {
+        let contains = search_rule(
+            "Carbon Tax",
+            SearchRuleMatchType::Contains,
+            SearchRuleActions::default(),
+        );
+        assert!(search_rule_matches(&contains, "the carbon  tax fails"));
+        assert!(!search_rule_matches(&contains, "carbon taxes"));
+
+        let exact = search_rule(
+            "carbon tax",
+            SearchRuleMatchType::Exact,
+            SearchRuleActions::default(),
+        );
+        assert!(search_rule_matches(&exact, " Carbon tax "));
+        assert!(!search_rule_matches(&exact, "carbon tax fails"));
+
+        let regex = search_rule(
+            "^nuclear (war|deterrence)",
+            SearchRuleMatchType::Regex,
+            SearchRuleActions::default(),
+        );
+        assert!(search_rule_matches(&regex, "Nuclear war bad"));
+        assert!(!search_rule_matches(&regex, "no nuclear war"));
+    }
+
+    #[test]
+    pub fn test_boost_then_pin_then_bury() {
+        let rule = search_rule(
+            "tax",
+            SearchRuleMatchType::Exact,
+            SearchRuleActions {
+                pin: vec![uuid::Uuid::from_u128(4), uuid::Uuid::from_u128(3)],
+                bury: vec![uuid::Uuid::from_u128(1), uuid::Uuid::from_u128(3)],
+                boost: vec![TagBoost {
+                    tag: "canonical".to_string(),
+                    factor: 2.0,
+                }],
+            },
+        );
+
+        let score_chunks = vec![
+            score_chunk(1, 0.9, "news"),
+            score_chunk(2, 0.5, "news,canonical"),
+            score_chunk(3, 0.4, ""),
+            score_chunk(5, 0.8, ""),
+        ];
+
+        let ranked = apply_search_rules(score_chunks, &[rule], vec![score_chunk(4, 0.0, "")]);
+        assert_eq!(ids(&ranked), vec![4, 3, 2, 5, 1]);
+        assert_eq!(ranked[2].score, 1.0);
+    }
+
+    #[test]
+    pub fn test_rules_shape_every_page_of_the_ranking() {
+        let rule = search_rule(
+            "tax",
+            SearchRuleMatchType::Exact,
+            SearchRuleActions {
+                pin: vec![uuid::Uuid::from_u128(30)],
+                bury: vec![uuid::Uuid::from_u128(2)],
+                boost: vec![TagBoost {
+                    tag: "canonical".to_string(),
+                    factor: 2.0,
+                }],
+            },
+        );
+        let score_chunks = (1..=25)
+            .map(|id| {
+                let tag_set = if id == 20 { "canonical" } else { "" };
+                score_chunk(id, 1.0 - id as f64 / 100.0, tag_set)
+            })
+            .collect_vec();
+
+        let ranked = apply_search_rules(score_chunks, &[rule], vec![score_chunk(30, 0.0, "")]);
+
+        let mut pages = vec![];
+        let mut cursor = None;
+        loop {
+            let (page, next_cursor) = page_fused_results(ranked.clone(), 1, 10, &cursor, false);
+            pages.push(ids(&page));
+            match next_cursor {
+                Some(next_cursor) => cursor = Some(next_cursor),
+                None => break,
+            }
+        }
+
+        assert_eq!(
+            pages.iter().map(|page| page.len()).collect_vec(),
+            vec![10, 10, 6]
+        );
+        // The pin leads the first page, the boost pulls chunk 20 up from the second page, and the bury drops chunk 2 to the last
+        assert_eq!(pages[0][..3], [30, 20, 1]);
+        assert_eq!(pages[2].last(), Some(&2));
+        let walked = pages.concat();
+        assert_eq!(walked.iter().unique().count(), 26);
+        assert_eq!(get_fused_total_pages(ranked.len(), 10), 3);
+    }
+
+    #[test]
+    pub fn test_boosts_raise_negative_scores() {
+        let rule = search_rule(
+            "tax",
+            SearchRuleMatchType::Exact,
+            SearchRuleActions {
+                pin: vec![],
+                bury: vec![],
+                boost: vec![TagBoost {
+                    tag: "canonical".to_string(),
+                    factor: 2.0,
+                }],
+            },
+        );
+
+        let ranked = apply_search_rules(
+            vec![score_chunk(1, -0.1, ""), score_chunk(2, -0.2, "canonical")],
+            &[rule],
+            vec![],
+        );
+        assert_eq!(ids(&ranked), vec![2, 1]);
+        assert_eq!(ranked[0].score, 0.0);
+    }
+
+    #[test]
+    pub fn test_pins_and_buries_keep_the_order_of_the_rest() {
+        let rule = search_rule(
+            "tax",
+            SearchRuleMatchType::Exact,
+            SearchRuleActions {
+                pin: vec![uuid::Uuid::from_u128(4)],
+                bury: vec![uuid::Uuid::from_u128(2)],
+                boost: vec![],
+            },
+        );
+
+        // A diversified ranking is not sorted by score, and applying pins and buries must not sort it
+        let diversified = vec![
+            score_chunk(1, 0.2, ""),
+            score_chunk(2, 0.9, ""),
+            score_chunk(3, 0.8, ""),
+            score_chunk(4, 0.1, ""),
+            score_chunk(5, 0.5, ""),
+        ];
+        let ranked = apply_search_rules(diversified, &[rule], vec![]);
+        assert_eq!(ids(&ranked), vec![4, 1, 3, 5, 2]);
+    }
+
+    fn group(id: u128, group_score: Option<f64>, chunks: Vec<ScoreChunkDTO>) -> GroupScoreChunkDTO {
+        GroupScoreChunkDTO {
+            group_id: uuid::Uuid::from_u128(id),
+            metadata: chunks,
+            group_score,
+        }
+    }
+
+    fn group_ids(groups: &[GroupScoreChunkDTO]) -> Vec<u128> {
+        groups
+            .iter()
+            .map(|group| group.group_id.as_u128())
+            .collect()
+    }
+
+    #[test]
+    pub fn test_rules_reorder_groups() {
+        let rule = search_rule(
+            "tax",
+            SearchRuleMatchType::Exact,
+            SearchRuleActions {
+                pin: vec![uuid::Uuid::from_u128(5)],
+                bury: vec![uuid::Uuid::from_u128(1), uuid::Uuid::from_u128(3)],
+                boost: vec![TagBoost {
+                    tag: "canonical".to_string(),
+                    factor: 2.0,
+                }],
+            },
+        );
+
+        let groups = vec![
+            group(10, None, vec![score_chunk(1, 0.9, "news")]),
+            group(20, None, vec![score_chunk(6, 0.8, "")]),
+            group(30, None, vec![score_chunk(7, 0.7, "")]),
+            group(
+                40,
+                None,
+                vec![score_chunk(3, 0.6, ""), score_chunk(2, 0.5, "canonical")],
+            ),
+            group(
+                50,
+                None,
+                vec![score_chunk(4, 0.3, ""), score_chunk(5, 0.2, "")],
+            ),
+        ];
+
+        let ranked = apply_search_rules_to_groups(groups, &[rule]);
+        // The pin lifts its group and leads it, the boost lifts group 40 above 20 and 30, the group of only buried chunks comes last,
+        // and a buried chunk in a group which is not buried trails that group
+        assert_eq!(group_ids(&ranked), vec![50, 40, 20, 30, 10]);
+        assert_eq!(ids(&ranked[0].metadata), vec![5, 4]);
+        assert_eq!(ids(&ranked[1].metadata), vec![2, 3]);
+        assert_eq!(ranked[1].metadata[0].score, 1.0);
+    }
+
+    #[test]
+    pub fn test_boosts_scale_group_scores() {
+        let rule = search_rule(
+            "tax",
+            SearchRuleMatchType::Exact,
+            SearchRuleActions {
+                pin: vec![],
+                bury: vec![],
+                boost: vec![TagBoost {
+                    tag: "canonical".to_string(),
+                    factor: 2.0,
+                }],
+            },
+        );
+
+        let groups = vec![
+            group(10, Some(2.0), vec![score_chunk(1, 0.9, "")]),
+            group(
+                20,
+                Some(1.5),
+                vec![score_chunk(2, 0.5, ""), score_chunk(3, 0.4, "canonical")],
+            ),
+        ];
+
+        let ranked = apply_search_rules_to_groups(groups, &[rule]);
+        assert_eq!(group_ids(&ranked), vec![20, 10]);
+        assert_eq!(ranked[0].group_score, Some(3.0));
+        assert_eq!(ids(&ranked[0].metadata), vec![3, 2]);
+    }
+
+    #[test]
+    pub fn test_malformed_actions_are_ignored() {
+        let mut rule = search_rule(
+            "tax",
+            SearchRuleMatchType::Exact,
+            SearchRuleActions::default(),
+        );
+        rule.actions = json!({"pin": "not a list"});
+
+        let score_chunks = vec![score_chunk(1, 0.9, ""), score_chunk(2, 0.5, "")];
+        let ranked = apply_search_rules(score_chunks, &[rule], vec![]);
+        assert_eq!(ids(&ranked), vec![1, 2]);
+    }
+}
@@ -235,7 +235,7 @@ pub async fn set_user_api_key_query(
     name: String,
     role: ApiKeyRole,
     pool: web::Data<Pool>,
-) -> Result<String, DefaultError> {
+) -> Result<(String, UserApiKey), DefaultError> {
     let raw_api_key = generate_api_key();
     let hashed_api_key = hash_password(&raw_api_key)?;
 
@@ -251,14 +251,14 @@ pub async fn set_user_api_key_query(
             message: "Error setting api key",
         })?;
 
-    Ok(raw_api_key)
+    Ok((raw_api_key, api_key_struct))
 }
 
 #[tracing::instrument(skip(pool))]
 pub async fn get_user_from_api_key_query(
     api_key: &str,
     pool: &web::Data<Pool>,
-) -> Result<SlimUser, DefaultError> {
+) -> Result<(SlimUser, UserApiKey), DefaultError> {
     use crate::data::schema::organizations::dsl as organization_columns;
     use crate::data::schema::user_api_key::dsl as user_api_key_columns;
     use crate::data::schema::user_organizations::dsl as user_organizations_columns;
@@ -315,7 +315,10 @@ pub async fn get_user_from_api_key_query(
                 .iter()
                 .map(|user_org_org| user_org_org.2.clone())
                 .collect::<Vec<Organization>>();
-            Ok(SlimUser::from_details(user, user_orgs, orgs))
+            Ok((
+                SlimUser::from_details(user, user_orgs, orgs),
+                first_user_org.3.clone(),
+            ))
         }
         None => Err(DefaultError {
             message: "User not found",
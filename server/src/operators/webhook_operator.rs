@@ -0,0 +1,696 @@
+use crate::{
+    data::models::{Event, Pool, Webhook, WebhookDelivery, WebhookDeliveryStatus},
+    errors::DefaultError,
+};
+use actix_web::web;
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use itertools::Itertools;
+use std::net::{IpAddr, SocketAddr};
+
+#[tracing::instrument(skip(pool))]
+pub async fn create_webhook_query(
+    webhook: Webhook,
+    pool: web::Data<Pool>,
+) -> Result<Webhook, DefaultError> {
+    use crate::data::schema::webhooks::dsl as webhooks_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    diesel::insert_into(webhooks_columns::webhooks)
+        .values(&webhook)
+        .execute(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to create webhook {:?}", err);
+            DefaultError {
+                message: "Failed to create webhook",
+            }
+        })?;
+
+    Ok(webhook)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_webhooks_query(
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Vec<Webhook>, DefaultError> {
+    use crate::data::schema::webhooks::dsl as webhooks_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    webhooks_columns::webhooks
+        .filter(webhooks_columns::dataset_id.eq(dataset_id))
+        .order(webhooks_columns::created_at.asc())
+        .select(Webhook::as_select())
+        .load::<Webhook>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to load webhooks {:?}", err);
+            DefaultError {
+                message: "Failed to load webhooks",
+            }
+        })
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_webhook_by_id_query(
+    webhook_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<Webhook, DefaultError> {
+    use crate::data::schema::webhooks::dsl as webhooks_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    webhooks_columns::webhooks
+        .filter(webhooks_columns::id.eq(webhook_id))
+        .filter(webhooks_columns::dataset_id.eq(dataset_id))
+        .select(Webhook::as_select())
+        .first::<Webhook>(&mut conn)
+        .await
+        .map_err(|_| DefaultError {
+            message: "Webhook not found",
+        })
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn delete_webhook_query(
+    webhook_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<(), DefaultError> {
+    use crate::data::schema::webhooks::dsl as webhooks_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    let deleted = diesel::delete(
+        webhooks_columns::webhooks
+            .filter(webhooks_columns::id.eq(webhook_id))
+            .filter(webhooks_columns::dataset_id.eq(dataset_id)),
+    )
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to delete webhook {:?}", err);
+        DefaultError {
+            message: "Failed to delete webhook",
+        }
+    })?;
+
+    if deleted == 0 {
+        return Err(DefaultError {
+            message: "Webhook not found",
+        });
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn create_webhook_delivery_query(
+    webhook_delivery: WebhookDelivery,
+    pool: web::Data<Pool>,
+) -> Result<WebhookDelivery, DefaultError> {
+    use crate::data::schema::webhook_deliveries::dsl as webhook_deliveries_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    diesel::insert_into(webhook_deliveries_columns::webhook_deliveries)
+        .values(&webhook_delivery)
+        .execute(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to create webhook delivery {:?}", err);
+            DefaultError {
+                message: "Failed to create webhook delivery",
+            }
+        })?;
+
+    Ok(webhook_delivery)
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn update_webhook_delivery_query(
+    webhook_delivery: WebhookDelivery,
+    pool: web::Data<Pool>,
+) -> Result<(), DefaultError> {
+    use crate::data::schema::webhook_deliveries::dsl as webhook_deliveries_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    diesel::update(
+        webhook_deliveries_columns::webhook_deliveries
+            .filter(webhook_deliveries_columns::id.eq(webhook_delivery.id)),
+    )
+    .set((
+        webhook_deliveries_columns::status.eq(webhook_delivery.status),
+        webhook_deliveries_columns::attempt_count.eq(webhook_delivery.attempt_count),
+        webhook_deliveries_columns::response_status.eq(webhook_delivery.response_status),
+        webhook_deliveries_columns::last_error.eq(webhook_delivery.last_error),
+        webhook_deliveries_columns::next_attempt_at.eq(webhook_delivery.next_attempt_at),
+        webhook_deliveries_columns::updated_at.eq(diesel::dsl::now),
+    ))
+    .execute(&mut conn)
+    .await
+    .map_err(|err| {
+        log::error!("Failed to update webhook delivery {:?}", err);
+        DefaultError {
+            message: "Failed to update webhook delivery",
+        }
+    })?;
+
+    Ok(())
+}
+
+/// Claims up to limit pending deliveries whose next attempt is due by moving their next attempt past the claim expiry. Deliveries claimed by another server are skipped, and a claim whose attempt never finishes expires so that the delivery is picked up again.
+#[tracing::instrument(skip(pool))]
+pub async fn claim_due_webhook_deliveries_query(
+    limit: i64,
+    pool: web::Data<Pool>,
+) -> Result<Vec<WebhookDelivery>, DefaultError> {
+    use crate::data::schema::webhook_deliveries::dsl as webhook_deliveries_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        async move {
+            let due_delivery_ids = webhook_deliveries_columns::webhook_deliveries
+                .filter(
+                    webhook_deliveries_columns::status.eq(WebhookDeliveryStatus::Pending.as_str()),
+                )
+                .filter(
+                    webhook_deliveries_columns::next_attempt_at
+                        .le(chrono::Utc::now().naive_local()),
+                )
+                .order(webhook_deliveries_columns::next_attempt_at.asc())
+                .limit(limit)
+                .select(webhook_deliveries_columns::id)
+                .for_update()
+                .skip_locked()
+                .load::<uuid::Uuid>(conn)
+                .await?;
+
+            diesel::update(
+                webhook_deliveries_columns::webhook_deliveries
+                    .filter(webhook_deliveries_columns::id.eq_any(due_delivery_ids)),
+            )
+            .set(webhook_deliveries_columns::next_attempt_at.eq(get_webhook_claim_expiry()))
+            .returning(WebhookDelivery::as_returning())
+            .get_results::<WebhookDelivery>(conn)
+            .await
+        }
+        .scope_boxed()
+    })
+    .await
+    .map_err(|err| {
+        log::error!("Failed to claim webhook deliveries {:?}", err);
+        DefaultError {
+            message: "Failed to claim webhook deliveries",
+        }
+    })
+}
+
+/// The webhook and event of a delivery. Errors if either has since been deleted.
+#[tracing::instrument(skip(pool))]
+pub async fn get_webhook_and_event_query(
+    webhook_id: uuid::Uuid,
+    event_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<(Webhook, Event), DefaultError> {
+    use crate::data::schema::events::dsl as events_columns;
+    use crate::data::schema::webhooks::dsl as webhooks_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    let webhook = webhooks_columns::webhooks
+        .filter(webhooks_columns::id.eq(webhook_id))
+        .select(Webhook::as_select())
+        .first::<Webhook>(&mut conn)
+        .await
+        .map_err(|_| DefaultError {
+            message: "Webhook not found",
+        })?;
+
+    let event = events_columns::events
+        .filter(events_columns::id.eq(event_id))
+        .select(Event::as_select())
+        .first::<Event>(&mut conn)
+        .await
+        .map_err(|_| DefaultError {
+            message: "Event not found",
+        })?;
+
+    Ok((webhook, event))
+}
+
+/// Deliveries of a webhook of the dataset, newest first.
+#[tracing::instrument(skip(pool))]
+pub async fn get_webhook_deliveries_query(
+    webhook_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    page: i64,
+    page_size: i64,
+    pool: web::Data<Pool>,
+) -> Result<Vec<WebhookDelivery>, DefaultError> {
+    use crate::data::schema::webhook_deliveries::dsl as webhook_deliveries_columns;
+    use crate::data::schema::webhooks::dsl as webhooks_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    webhook_deliveries_columns::webhook_deliveries
+        .inner_join(webhooks_columns::webhooks)
+        .filter(webhook_deliveries_columns::webhook_id.eq(webhook_id))
+        .filter(webhooks_columns::dataset_id.eq(dataset_id))
+        .order(webhook_deliveries_columns::created_at.desc())
+        .limit(page_size)
+        .offset((page - 1) * page_size)
+        .select(WebhookDelivery::as_select())
+        .load::<WebhookDelivery>(&mut conn)
+        .await
+        .map_err(|err| {
+            log::error!("Failed to load webhook deliveries {:?}", err);
+            DefaultError {
+                message: "Failed to load webhook deliveries",
+            }
+        })
+}
+
+/// The delivery along with the webhook and event it belongs to. Errors if the webhook is not part of the dataset.
+#[tracing::instrument(skip(pool))]
+pub async fn get_webhook_delivery_with_event_query(
+    webhook_delivery_id: uuid::Uuid,
+    dataset_id: uuid::Uuid,
+    pool: web::Data<Pool>,
+) -> Result<(WebhookDelivery, Webhook, Event), DefaultError> {
+    use crate::data::schema::events::dsl as events_columns;
+    use crate::data::schema::webhook_deliveries::dsl as webhook_deliveries_columns;
+    use crate::data::schema::webhooks::dsl as webhooks_columns;
+
+    let mut conn = pool.get().await.unwrap();
+
+    webhook_deliveries_columns::webhook_deliveries
+        .inner_join(webhooks_columns::webhooks)
+        .inner_join(events_columns::events)
+        .filter(webhook_deliveries_columns::id.eq(webhook_delivery_id))
+        .filter(webhooks_columns::dataset_id.eq(dataset_id))
+        .select((
+            WebhookDelivery::as_select(),
+            Webhook::as_select(),
+            Event::as_select(),
+        ))
+        .first::<(WebhookDelivery, Webhook, Event)>(&mut conn)
+        .await
+        .map_err(|_| DefaultError {
+            message: "Webhook delivery not found",
+        })
+}
+
+/// Hex encoded HMAC-SHA256 of the payload keyed with the secret of the webhook.
+pub fn sign_webhook_payload(secret: &str, payload: &str) -> Result<String, DefaultError> {
+    let key = openssl::pkey::PKey::hmac(secret.as_bytes()).map_err(|_| DefaultError {
+        message: "Failed to create webhook signing key",
+    })?;
+    let mut signer = openssl::sign::Signer::new(openssl::hash::MessageDigest::sha256(), &key)
+        .map_err(|_| DefaultError {
+            message: "Failed to create webhook signer",
+        })?;
+    signer
+        .update(payload.as_bytes())
+        .map_err(|_| DefaultError {
+            message: "Failed to sign webhook payload",
+        })?;
+    let signature = signer.sign_to_vec().map_err(|_| DefaultError {
+        message: "Failed to sign webhook payload",
+    })?;
+
+    Ok(signature
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .join(""))
+}
+
+/// Whether webhooks may be delivered to the address. Loopback, private, link-local, unspecified, shared, broadcast, multicast, and
+/// unique local addresses are refused so that a webhook cannot reach the internal network or the cloud metadata service at
+/// 169.254.169.254. IPv4 addresses mapped into IPv6 are checked as IPv4.
+pub fn is_public_webhook_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || first == 0
+                || (first == 100 && (64..128).contains(&second)))
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            let nat64_ip = match segments {
+                [0x64, 0xff9b, 0, 0, 0, 0, high, low] => {
+                    Some(std::net::Ipv4Addr::from(((high as u32) << 16) | low as u32))
+                }
+                _ => ip.to_ipv4_mapped(),
+            };
+
+            match nat64_ip {
+                Some(ip) => is_public_webhook_ip(IpAddr::V4(ip)),
+                None => {
+                    !(ip.is_loopback()
+                        || ip.is_unspecified()
+                        || ip.is_multicast()
+                        || (segments[0] & 0xfe00) == 0xfc00
+                        || (segments[0] & 0xffc0) == 0xfe80)
+                }
+            }
+        }
+    }
+}
+
+/// Resolves the host of a webhook url and checks that every address it resolves to is public. Returns the host along with the
+/// addresses so that a delivery can connect to exactly the addresses which were checked.
+pub async fn resolve_webhook_url(url: &str) -> Result<(String, Vec<SocketAddr>), DefaultError> {
+    let url = reqwest::Url::parse(url)
+        .ok()
+        .filter(|url| url.scheme() == "http" || url.scheme() == "https")
+        .ok_or(DefaultError {
+            message: "url must be a valid http or https url",
+        })?;
+    let (host, port) = match (url.host_str(), url.port_or_known_default()) {
+        (Some(host), Some(port)) => (
+            host.trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string(),
+            port,
+        ),
+        _ => {
+            return Err(DefaultError {
+                message: "url must be a valid http or https url",
+            })
+        }
+    };
+
+    let addrs = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .map_err(|_| DefaultError {
+            message: "Failed to resolve the host of the webhook url",
+        })?
+        .collect_vec();
+    if addrs.is_empty() {
+        return Err(DefaultError {
+            message: "Failed to resolve the host of the webhook url",
+        });
+    }
+    if addrs.iter().any(|addr| !is_public_webhook_ip(addr.ip())) {
+        return Err(DefaultError {
+            message: "url must not resolve to a loopback, private, link-local, or metadata address",
+        });
+    }
+
+    Ok((host, addrs))
+}
+
+/// Client for one delivery to the webhook url. The host is resolved and checked again for every delivery and the client only connects
+/// to the checked addresses, so a host which starts resolving to an internal address after the webhook was created is never sent to.
+/// Redirects are not followed since they could lead anywhere.
+async fn get_webhook_client(url: &str) -> Result<reqwest::Client, DefaultError> {
+    let (host, addrs) = resolve_webhook_url(url).await?;
+
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(&host, &addrs)
+        .build()
+        .map_err(|err| {
+            log::error!("Failed to create webhook client {:?}", err);
+            DefaultError {
+                message: "Failed to create webhook client",
+            }
+        })
+}
+
+/// Delay before retrying a delivery which failed attempt_count times. Doubles with every attempt starting at 1 second and is capped at 5 minutes.
+pub fn get_webhook_retry_delay(attempt_count: i32) -> std::time::Duration {
+    let exponent = attempt_count.saturating_sub(1).clamp(0, 16) as u32;
+
+    std::time::Duration::from_secs(2u64.pow(exponent).min(300))
+}
+
+fn get_webhook_max_attempts() -> i32 {
+    std::env::var("WEBHOOK_MAX_ATTEMPTS")
+        .ok()
+        .and_then(|max_attempts| max_attempts.parse().ok())
+        .unwrap_or(5)
+}
+
+/// Makes one attempt to POST the event to the webhook. Returns the response status, if any, and why the attempt failed.
+async fn attempt_webhook_delivery(
+    client: &reqwest::Client,
+    webhook: &Webhook,
+    webhook_delivery_id: uuid::Uuid,
+    event: &Event,
+) -> (Option<i32>, Option<String>) {
+    let body = match serde_json::to_string(event) {
+        Ok(body) => body,
+        Err(err) => return (None, Some(format!("Failed to serialize event: {}", err))),
+    };
+    let timestamp = chrono::Utc::now().timestamp();
+    let signature = match sign_webhook_payload(&webhook.secret, &format!("{}.{}", timestamp, body))
+    {
+        Ok(signature) => signature,
+        Err(err) => return (None, Some(err.message.to_string())),
+    };
+
+    let response = client
+        .post(&webhook.url)
+        .header("Content-Type", "application/json")
+        .header("X-Trieve-Event", event.event_type.clone())
+        .header("X-Trieve-Delivery", webhook_delivery_id.to_string())
+        .header("X-Trieve-Timestamp", timestamp.to_string())
+        .header("X-Trieve-Signature", format!("sha256={}", signature))
+        .body(body)
+        .send()
+        .await;
+
+    match response {
+        Ok(response) if response.status().is_success() => {
+            (Some(response.status().as_u16() as i32), None)
+        }
+        Ok(response) if response.status().is_redirection() => (
+            Some(response.status().as_u16() as i32),
+            Some(format!(
+                "Webhook url redirected with {}, redirects are not followed",
+                response.status()
+            )),
+        ),
+        Ok(response) => (
+            Some(response.status().as_u16() as i32),
+            Some(format!("Webhook url responded with {}", response.status())),
+        ),
+        Err(err) => (None, Some(format!("Failed to reach webhook url: {}", err))),
+    }
+}
+
+/// How long a claimed delivery is left to its attempt before another server may claim it. Well above the timeout of an attempt.
+fn get_webhook_claim_expiry() -> chrono::NaiveDateTime {
+    chrono::Utc::now().naive_local() + chrono::Duration::seconds(60)
+}
+
+/// When the delivery is next due after an attempt. Only pending deliveries are attempted again.
+pub fn get_next_webhook_attempt_at(
+    webhook_delivery: &WebhookDelivery,
+    now: chrono::NaiveDateTime,
+) -> Option<chrono::NaiveDateTime> {
+    if webhook_delivery.status != WebhookDeliveryStatus::Pending.as_str() {
+        return None;
+    }
+
+    chrono::Duration::from_std(get_webhook_retry_delay(webhook_delivery.attempt_count))
+        .ok()
+        .map(|delay| now + delay)
+}
+
+/// Makes one attempt of a claimed delivery and records it on the delivery. Redirects and urls which resolve to internal addresses count as failed attempts. A delivery which failed is scheduled again with exponential backoff until WEBHOOK_MAX_ATTEMPTS attempts have been made, so that retries survive restarts. The webhook is loaded again before the attempt, so deleted webhooks are never sent to.
+#[tracing::instrument(skip(pool))]
+pub async fn deliver_webhook_event(mut webhook_delivery: WebhookDelivery, pool: web::Data<Pool>) {
+    // Deleting the webhook or the event also deletes its deliveries
+    let (webhook, event) = match get_webhook_and_event_query(
+        webhook_delivery.webhook_id,
+        webhook_delivery.event_id,
+        pool.clone(),
+    )
+    .await
+    {
+        Ok(webhook_and_event) => webhook_and_event,
+        Err(_) => return,
+    };
+
+    let (response_status, error) = match get_webhook_client(&webhook.url).await {
+        Ok(client) => {
+            attempt_webhook_delivery(&client, &webhook, webhook_delivery.id, &event).await
+        }
+        Err(err) => (None, Some(err.message.to_string())),
+    };
+
+    webhook_delivery.attempt_count += 1;
+    webhook_delivery.response_status = response_status;
+    webhook_delivery.status = match (
+        &error,
+        webhook_delivery.attempt_count >= get_webhook_max_attempts(),
+    ) {
+        (None, _) => WebhookDeliveryStatus::Succeeded,
+        (Some(_), true) => WebhookDeliveryStatus::Failed,
+        (Some(_), false) => WebhookDeliveryStatus::Pending,
+    }
+    .as_str()
+    .to_string();
+    webhook_delivery.last_error = error;
+    webhook_delivery.next_attempt_at =
+        get_next_webhook_attempt_at(&webhook_delivery, chrono::Utc::now().naive_local());
+
+    let _ = update_webhook_delivery_query(webhook_delivery, pool).await;
+}
+
+/// Attempts the due deliveries of every server, including the retries of failed attempts and deliveries whose attempt was cut off by a restart.
+#[tracing::instrument(skip(pool))]
+pub async fn sweep_webhook_deliveries(pool: web::Data<Pool>) {
+    loop {
+        match claim_due_webhook_deliveries_query(100, pool.clone()).await {
+            Ok(webhook_deliveries) => {
+                futures::future::join_all(
+                    webhook_deliveries.into_iter().map(|webhook_delivery| {
+                        deliver_webhook_event(webhook_delivery, pool.clone())
+                    }),
+                )
+                .await;
+            }
+            Err(err) => log::error!("Failed to sweep webhook deliveries: {:?}", err),
+        }
+
+        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+    }
+}
+
+/// Creates a delivery of the event to the webhook and makes its first attempt in the background. The delivery is created already claimed, so the sweeper only picks it up if the attempt fails or never finishes.
+pub async fn start_webhook_delivery(
+    webhook: Webhook,
+    event: Event,
+    pool: web::Data<Pool>,
+) -> Result<WebhookDelivery, DefaultError> {
+    let mut webhook_delivery = WebhookDelivery::from_details(webhook.id, event.id);
+    webhook_delivery.next_attempt_at = Some(get_webhook_claim_expiry());
+
+    let webhook_delivery = create_webhook_delivery_query(webhook_delivery, pool.clone()).await?;
+
+    tokio::spawn(deliver_webhook_event(webhook_delivery.clone(), pool));
+
+    Ok(webhook_delivery)
+}
+
+/// Sends the event to every webhook of its dataset which subscribes to its type. Runs in the background so that a slow or failing webhook never fails or slows down whatever created the event.
+pub fn dispatch_event_to_webhooks(event: Event, pool: web::Data<Pool>) {
+    tokio::spawn(async move {
+        let webhooks = match get_webhooks_query(event.dataset_id, pool.clone()).await {
+            Ok(webhooks) => webhooks,
+            Err(_) => return,
+        };
+
+        for webhook in webhooks
+            .into_iter()
+            .filter(|webhook| webhook.subscribes_to(&event.event_type))
+        {
+            let _ = start_webhook_delivery(webhook, event.clone(), pool.clone()).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sign_webhook_payload_matches_rfc_4231() {
+        assert_eq!(
+            sign_webhook_payload("Jefe", "what do ya want for nothing?").unwrap(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_only_public_addresses_are_sent_to() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.100.100.200",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fe80::1",
+            "fd00:ec2::254",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public_webhook_ip(ip.parse().unwrap()), "{}", ip);
+        }
+
+        for ip in [
+            "93.184.216.34",
+            "8.8.8.8",
+            "2606:4700::1111",
+            "::ffff:8.8.8.8",
+        ] {
+            assert!(is_public_webhook_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_webhook_retry_delay_doubles_and_is_capped() {
+        assert_eq!(get_webhook_retry_delay(1).as_secs(), 1);
+        assert_eq!(get_webhook_retry_delay(2).as_secs(), 2);
+        assert_eq!(get_webhook_retry_delay(4).as_secs(), 8);
+        assert_eq!(get_webhook_retry_delay(30).as_secs(), 300);
+    }
+
+    #[test]
+    fn test_only_pending_deliveries_are_scheduled_again() {
+        let now = chrono::Utc::now().naive_local();
+        let mut webhook_delivery =
+            WebhookDelivery::from_details(uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        webhook_delivery.attempt_count = 3;
+
+        assert_eq!(
+            get_next_webhook_attempt_at(&webhook_delivery, now),
+            Some(now + chrono::Duration::seconds(4))
+        );
+
+        webhook_delivery.status = WebhookDeliveryStatus::Failed.as_str().to_string();
+        assert_eq!(get_next_webhook_attempt_at(&webhook_delivery, now), None);
+
+        webhook_delivery.status = WebhookDeliveryStatus::Succeeded.as_str().to_string();
+        assert_eq!(get_next_webhook_attempt_at(&webhook_delivery, now), None);
+    }
+
+    #[test]
+    fn test_webhook_without_event_types_subscribes_to_every_event() {
+        let dataset_id = uuid::Uuid::new_v4();
+        let every_event = Webhook::from_details(dataset_id, "https://a.com".to_string(), vec![]);
+        let failures_only = Webhook::from_details(
+            dataset_id,
+            "https://a.com".to_string(),
+            vec!["card_action_failed".to_string()],
+        );
+
+        assert!(every_event.subscribes_to("card_uploaded"));
+        assert!(failures_only.subscribes_to("card_action_failed"));
+        assert!(!failures_only.subscribes_to("card_uploaded"));
+    }
+}